@@ -0,0 +1,126 @@
+use std::path::PathBuf;
+
+use crate::CliError;
+
+/// The subcommand to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Decode,
+    Encode,
+    Dissect,
+    Replay,
+    Help,
+}
+
+/// Encoding of binary input or output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Hex,
+    Base64,
+    Raw,
+}
+
+/// The MQTT version given by `--protocol`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolArg {
+    /// Detect the version from the CONNECT packet.
+    Auto,
+    V3,
+    V5,
+}
+
+/// Parsed command line arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Args {
+    pub command: Command,
+    /// Input file, read stdin if `None`.
+    pub input: Option<PathBuf>,
+    /// Binary format, the default depends on the command.
+    pub format: Option<Format>,
+    pub protocol: ProtocolArg,
+    pub json: bool,
+    pub verbose: bool,
+    /// Server ports of the MQTT connections in a pcap capture.
+    pub ports: Vec<u16>,
+}
+
+impl Args {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args, CliError> {
+        let mut args = args.into_iter();
+        let command = match args.next().as_deref() {
+            Some("decode") => Command::Decode,
+            Some("encode") => Command::Encode,
+            Some("dissect") => Command::Dissect,
+            Some("replay") => Command::Replay,
+            Some("help" | "-h" | "--help") | None => Command::Help,
+            Some(other) => return Err(CliError::Usage(format!("unknown command `{other}`"))),
+        };
+        let mut parsed = Args {
+            command,
+            input: None,
+            format: None,
+            protocol: ProtocolArg::Auto,
+            json: false,
+            verbose: false,
+            ports: Vec::new(),
+        };
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name, Some(value.to_owned())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| CliError::Usage(format!("missing value for `{name}`")))
+            };
+            match name {
+                "-f" | "--format" => parsed.format = Some(parse_format(&value()?)?),
+                "-p" | "--protocol" => parsed.protocol = parse_protocol(&value()?)?,
+                "--port" => {
+                    let port = value()?;
+                    let port = port
+                        .parse()
+                        .map_err(|_| CliError::Usage(format!("invalid port `{port}`")))?;
+                    parsed.ports.push(port);
+                }
+                "--json" => parsed.json = true,
+                "-v" | "--verbose" => parsed.verbose = true,
+                "-h" | "--help" => parsed.command = Command::Help,
+                "-" => parsed.set_input(None)?,
+                _ if name.starts_with('-') => {
+                    return Err(CliError::Usage(format!("unknown option `{name}`")))
+                }
+                path => parsed.set_input(Some(path.into()))?,
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn set_input(&mut self, input: Option<PathBuf>) -> Result<(), CliError> {
+        if self.input.is_some() {
+            return Err(CliError::Usage("more than one input given".into()));
+        }
+        self.input = input;
+        Ok(())
+    }
+}
+
+fn parse_format(value: &str) -> Result<Format, CliError> {
+    match value {
+        "hex" => Ok(Format::Hex),
+        "base64" => Ok(Format::Base64),
+        "raw" => Ok(Format::Raw),
+        _ => Err(CliError::Usage(format!("unknown format `{value}`"))),
+    }
+}
+
+fn parse_protocol(value: &str) -> Result<ProtocolArg, CliError> {
+    match value {
+        "auto" => Ok(ProtocolArg::Auto),
+        "v3" | "3" | "3.1" | "3.1.1" => Ok(ProtocolArg::V3),
+        "v5" | "5" | "5.0" => Ok(ProtocolArg::V5),
+        _ => Err(CliError::Usage(format!("unknown protocol `{value}`"))),
+    }
+}
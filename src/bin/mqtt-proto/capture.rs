@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::ExitCode;
+
+use mqtt_proto::pcap::{Direction, Packet, PcapConfig, PcapReader};
+use mqtt_proto::Protocol;
+
+use crate::args::{Args, ProtocolArg};
+use crate::{exit_code, stdout_error, CliError};
+
+/// Whether `data` starts with the magic number of a pcap or pcapng file.
+pub fn is_capture(data: &[u8]) -> bool {
+    matches!(
+        data.get(..4),
+        Some(
+            [0xd4, 0xc3, 0xb2, 0xa1]
+                | [0xa1, 0xb2, 0xc3, 0xd4]
+                | [0x4d, 0x3c, 0xb2, 0xa1]
+                | [0xa1, 0xb2, 0x3c, 0x4d]
+                | [0x0a, 0x0d, 0x0d, 0x0a]
+        )
+    )
+}
+
+pub fn config(args: &Args) -> PcapConfig {
+    let mut config = PcapConfig::default();
+    if !args.ports.is_empty() {
+        config.ports = args.ports.clone();
+    }
+    if args.protocol == ProtocolArg::V5 {
+        config.fallback_protocol = Protocol::V500;
+    }
+    config
+}
+
+/// Replay the MQTT connections of a pcap capture, print every packet if
+/// verbose and the statistics.
+pub fn replay_capture(data: &[u8], args: &Args, mut out: impl Write) -> Result<ExitCode, CliError> {
+    let mut reader = PcapReader::with_config(data, config(args))?;
+    let mut types: BTreeMap<String, usize> = BTreeMap::new();
+    let mut packets = 0;
+    let mut errors = Vec::new();
+    for event in reader.by_ref() {
+        let event = match event {
+            Ok(event) => event,
+            Err(err) => {
+                errors.push(format!("capture file: {err}"));
+                continue;
+            }
+        };
+        let (from, to) = match event.direction {
+            Direction::ClientToServer => (event.client, event.server),
+            Direction::ServerToClient => (event.server, event.client),
+        };
+        let line = match &event.packet {
+            Ok(packet) => {
+                let name = match packet {
+                    Packet::V3(packet) => format!("{:?}", packet.get_type()),
+                    Packet::V5(packet) => format!("{:?}", packet.get_type()),
+                }
+                .to_uppercase();
+                packets += 1;
+                *types.entry(name.clone()).or_default() += 1;
+                name
+            }
+            Err(err) => {
+                let message = format!("{from} -> {to}: {err}");
+                errors.push(message);
+                format!("error: {err}")
+            }
+        };
+        if args.verbose {
+            let ts = event.timestamp;
+            writeln!(
+                out,
+                "{}.{:06} {from} -> {to} {:?} {line}",
+                ts.as_secs(),
+                ts.subsec_micros(),
+                event.protocol
+            )
+            .map_err(stdout_error)?;
+        }
+    }
+    let stats = reader.stats();
+    let mut summary = format!(
+        "frames:      {} ({} unsupported)\nsegments:    {}\nconnections: {}\npackets:     {packets}\n",
+        stats.frames, stats.unsupported_frames, stats.segments, stats.connections
+    );
+    for (name, count) in &types {
+        summary += &format!("  {name:<12} {count:>8}\n");
+    }
+    for error in &errors {
+        summary += &format!("error: {error}\n");
+    }
+    out.write_all(summary.as_bytes()).map_err(stdout_error)?;
+    out.flush().map_err(stdout_error)?;
+    Ok(exit_code(errors.is_empty()))
+}
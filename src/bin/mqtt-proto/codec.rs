@@ -0,0 +1,94 @@
+use std::fmt;
+
+use mqtt_proto::{decode_raw_header, v3, v5, Dissection, Protocol};
+
+use crate::args::ProtocolArg;
+
+/// The codec used to decode a byte stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    V3,
+    V5,
+}
+
+impl Version {
+    /// Resolve `--protocol`, `auto` looks at the CONNECT packet at the start
+    /// of `data`. Return the version and whether it was detected.
+    pub fn select(protocol: ProtocolArg, data: &[u8]) -> (Version, bool) {
+        match protocol {
+            ProtocolArg::V3 => (Version::V3, true),
+            ProtocolArg::V5 => (Version::V5, true),
+            ProtocolArg::Auto => match sniff(data) {
+                Some(version) => (version, true),
+                None => (Version::V3, false),
+            },
+        }
+    }
+
+    pub fn decode(self, data: &[u8]) -> Result<Option<(AnyPacket, usize)>, String> {
+        match self {
+            Version::V3 => v3::Packet::decode_from_slice(data)
+                .map(|result| result.map(|(packet, len)| (AnyPacket::V3(packet), len)))
+                .map_err(|err| err.to_string()),
+            Version::V5 => v5::Packet::decode_from_slice(data)
+                .map(|result| result.map(|(packet, len)| (AnyPacket::V5(Box::new(packet)), len)))
+                .map_err(|err| err.to_string()),
+        }
+    }
+
+    pub fn dissect(self, data: &[u8]) -> Dissection {
+        match self {
+            Version::V3 => v3::dissect(data),
+            Version::V5 => v5::dissect(data),
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Version::V3 => write!(f, "v3.1.1"),
+            Version::V5 => write!(f, "v5.0"),
+        }
+    }
+}
+
+/// Detect the version from the protocol level of a CONNECT packet at the
+/// start of `data`.
+pub fn sniff(data: &[u8]) -> Option<Version> {
+    let (typ, _, var_bytes) = decode_raw_header(data).ok()??;
+    if typ >> 4 != 1 {
+        return None;
+    }
+    let mut offset = 1 + var_bytes;
+    match Protocol::decode(data, &mut offset).ok()? {
+        Protocol::V310 | Protocol::V311 => Some(Version::V3),
+        Protocol::V500 => Some(Version::V5),
+    }
+}
+
+/// A decoded packet of either version.
+#[derive(Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(untagged)]
+pub enum AnyPacket {
+    V3(v3::Packet),
+    V5(Box<v5::Packet>),
+}
+
+impl fmt::Debug for AnyPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnyPacket::V3(packet) => packet.fmt(f),
+            AnyPacket::V5(packet) => packet.fmt(f),
+        }
+    }
+}
+
+impl AnyPacket {
+    pub fn type_name(&self) -> String {
+        match self {
+            AnyPacket::V3(packet) => format!("{:?}", packet.get_type()).to_uppercase(),
+            AnyPacket::V5(packet) => format!("{:?}", packet.get_type()).to_uppercase(),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use base64::Engine;
+
+use crate::args::Format;
+use crate::CliError;
+
+/// Read the whole input file, or stdin if no file is given.
+pub fn read_input(path: Option<&Path>) -> Result<Vec<u8>, CliError> {
+    match path {
+        Some(path) => fs::read(path).map_err(|err| CliError::Io(path.display().to_string(), err)),
+        None => {
+            let mut data = Vec::new();
+            io::stdin()
+                .read_to_end(&mut data)
+                .map_err(|err| CliError::Io("stdin".into(), err))?;
+            Ok(data)
+        }
+    }
+}
+
+/// Convert the input in `format` to the raw bytes. Whitespace is ignored in
+/// hex and base64 input, hex bytes may also be prefixed with `0x`.
+pub fn decode_data(input: &[u8], format: Format) -> Result<Vec<u8>, CliError> {
+    if format == Format::Raw {
+        return Ok(input.to_vec());
+    }
+    let text = core::str::from_utf8(input)
+        .map_err(|_| CliError::Input("input is not valid UTF-8 text".into()))?;
+    match format {
+        Format::Hex => decode_hex(text),
+        Format::Base64 => {
+            let text: String = text.split_whitespace().collect();
+            base64::engine::general_purpose::STANDARD
+                .decode(text)
+                .map_err(|err| CliError::Input(format!("invalid base64: {err}")))
+        }
+        Format::Raw => unreachable!(),
+    }
+}
+
+fn decode_hex(text: &str) -> Result<Vec<u8>, CliError> {
+    let mut digits = Vec::with_capacity(text.len());
+    for word in text.split_whitespace() {
+        let word = word.strip_prefix("0x").unwrap_or(word);
+        for ch in word.chars() {
+            let digit = ch
+                .to_digit(16)
+                .ok_or_else(|| CliError::Input(format!("invalid hex digit `{ch}`")))?;
+            digits.push(digit as u8);
+        }
+    }
+    if digits.len() % 2 != 0 {
+        return Err(CliError::Input("odd number of hex digits".into()));
+    }
+    Ok(digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair[1])
+        .collect())
+}
+
+/// Write `data` to `out` in `format`, hex and base64 end with a newline.
+pub fn write_data(out: &mut impl Write, data: &[u8], format: Format) -> Result<(), CliError> {
+    let result = match format {
+        Format::Hex => {
+            let hex: Vec<String> = data.iter().map(|byte| format!("{byte:02x}")).collect();
+            writeln!(out, "{}", hex.join(" "))
+        }
+        Format::Base64 => {
+            let text = base64::engine::general_purpose::STANDARD.encode(data);
+            writeln!(out, "{text}")
+        }
+        Format::Raw => out.write_all(data),
+    };
+    result.map_err(|err| CliError::Io("stdout".into(), err))
+}
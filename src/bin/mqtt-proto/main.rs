@@ -0,0 +1,280 @@
+//! Command line tool to decode, encode and inspect MQTT packets.
+//!
+//! ```text
+//! echo "c0 00" | mqtt-proto decode
+//! mqtt-proto dissect --format raw capture.bin
+//! ```
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+use mqtt_proto::{v3, v5};
+
+mod args;
+mod capture;
+mod codec;
+mod data;
+
+#[cfg(test)]
+mod tests;
+
+use args::{Args, Command, Format, ProtocolArg};
+use codec::{AnyPacket, Version};
+use data::{decode_data, read_input, write_data};
+
+const USAGE: &str = "\
+Decode, encode and inspect MQTT v3.1/v3.1.1/v5.0 packets.
+
+Usage: mqtt-proto <COMMAND> [OPTIONS] [INPUT]
+
+Commands:
+  decode   Decode packets and print them, pretty or as JSON lines
+  encode   Encode packets given as JSON values
+  dissect  Print an annotated field-by-field dump of the packets
+  replay   Decode a pcap/pcapng capture, or the raw bytes of one direction
+           of a connection, report errors and statistics
+
+Arguments:
+  [INPUT]  Input file, `-` or nothing for stdin
+
+Options:
+  -f, --format <FORMAT>      hex, base64 or raw; the binary input format, or
+                             the output format of `encode` [default: raw for
+                             `replay`, hex otherwise]
+  -p, --protocol <PROTOCOL>  auto, v3 or v5; `auto` detects the version from
+                             the CONNECT packet, else assumes v3 [default: auto].
+                             In pcap captures, the version of the connections
+                             without a CONNECT packet
+      --port <PORT>          MQTT server port in pcap captures, may be repeated
+                             [default: 1883]
+      --json                 Print decoded packets as JSON lines
+  -v, --verbose              Print every packet in `replay`
+  -h, --help                 Print help
+";
+
+#[derive(Debug, thiserror::Error)]
+pub enum CliError {
+    #[error("{0}")]
+    Capture(#[from] mqtt_proto::pcap::Error),
+    #[error("{0}")]
+    Usage(String),
+    #[error("{0}: {1}")]
+    Io(String, std::io::Error),
+    #[error("{0}")]
+    Input(String),
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("encode failed: {0}")]
+    Encode(String),
+    #[error("decode failed at offset {offset:#x}: {message}")]
+    Decode { offset: usize, message: String },
+}
+
+fn main() -> ExitCode {
+    let result = Args::parse(std::env::args().skip(1)).and_then(run);
+    match result {
+        Ok(code) => code,
+        // The output was closed early, e.g. piped to `head`
+        Err(CliError::Io(_, err)) if err.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(err @ CliError::Usage(_)) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<ExitCode, CliError> {
+    if args.command == Command::Help {
+        print!("{USAGE}");
+        return Ok(ExitCode::SUCCESS);
+    }
+    let input = read_input(args.input.as_deref())?;
+    let mut out = io::stdout().lock();
+    let code = match args.command {
+        Command::Decode => {
+            let data = decode_data(&input, args.format.unwrap_or(Format::Hex))?;
+            for packet in decode_all(&data, detect(&args, &data))? {
+                if args.json {
+                    writeln!(out, "{}", serde_json::to_string(&packet)?)
+                } else {
+                    writeln!(out, "{packet:#?}")
+                }
+                .map_err(stdout_error)?;
+            }
+            ExitCode::SUCCESS
+        }
+        Command::Encode => {
+            let data = encode_all(&input, args.protocol)?;
+            write_data(&mut out, &data, args.format.unwrap_or(Format::Hex))?;
+            ExitCode::SUCCESS
+        }
+        Command::Dissect => {
+            let data = decode_data(&input, args.format.unwrap_or(Format::Hex))?;
+            let dissection = detect(&args, &data).dissect(&data);
+            write!(out, "{dissection}").map_err(stdout_error)?;
+            exit_code(dissection.error.is_none())
+        }
+        Command::Replay => {
+            let data = decode_data(&input, args.format.unwrap_or(Format::Raw))?;
+            if capture::is_capture(&data) {
+                return capture::replay_capture(&data, &args, out);
+            }
+            let mut result = Ok(());
+            let stats = replay(&data, detect(&args, &data), |offset, packet, len| {
+                if args.verbose && result.is_ok() {
+                    let name = packet.type_name();
+                    result = writeln!(out, "{offset:08x} {name} {len} bytes");
+                }
+            });
+            result
+                .and_then(|()| write!(out, "{stats}"))
+                .map_err(stdout_error)?;
+            exit_code(stats.error.is_none() && stats.incomplete == 0)
+        }
+        Command::Help => unreachable!(),
+    };
+    out.flush().map_err(stdout_error)?;
+    Ok(code)
+}
+
+fn stdout_error(err: io::Error) -> CliError {
+    CliError::Io("stdout".into(), err)
+}
+
+fn exit_code(success: bool) -> ExitCode {
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Select the version, tell the user when it is only assumed.
+fn detect(args: &Args, data: &[u8]) -> Version {
+    let (version, detected) = Version::select(args.protocol, data);
+    if !detected {
+        eprintln!("note: no CONNECT packet at the start, assuming {version} (see --protocol)");
+    }
+    version
+}
+
+/// Decode all the packets in `data`, a trailing incomplete packet is an error.
+pub fn decode_all(data: &[u8], version: Version) -> Result<Vec<AnyPacket>, CliError> {
+    let mut packets = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        match version.decode(&data[offset..]) {
+            Ok(Some((packet, len))) => {
+                packets.push(packet);
+                offset += len;
+            }
+            Ok(None) => {
+                let message = format!("incomplete packet, {} bytes", data.len() - offset);
+                return Err(CliError::Decode { offset, message });
+            }
+            Err(message) => return Err(CliError::Decode { offset, message }),
+        }
+    }
+    Ok(packets)
+}
+
+/// Encode the packets given as a sequence of JSON values, `auto` means v5.
+pub fn encode_all(json: &[u8], protocol: ProtocolArg) -> Result<Vec<u8>, CliError> {
+    let mut data = Vec::new();
+    if protocol == ProtocolArg::V3 {
+        for packet in serde_json::Deserializer::from_slice(json).into_iter::<v3::Packet>() {
+            let bytes = packet?
+                .encode()
+                .map_err(|err| CliError::Encode(err.to_string()))?;
+            data.extend_from_slice(bytes.as_ref());
+        }
+    } else {
+        for packet in serde_json::Deserializer::from_slice(json).into_iter::<v5::Packet>() {
+            let bytes = packet?
+                .encode()
+                .map_err(|err| CliError::Encode(err.to_string()))?;
+            data.extend_from_slice(bytes.as_ref());
+        }
+    }
+    Ok(data)
+}
+
+/// Statistics of a replayed capture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayStats {
+    pub version: Version,
+    pub packets: usize,
+    pub bytes: usize,
+    /// Count and total size of each packet type.
+    pub types: BTreeMap<String, (usize, usize)>,
+    pub largest: usize,
+    /// Size of the trailing incomplete packet.
+    pub incomplete: usize,
+    /// Offset and message of the first decode error, replaying stops there.
+    pub error: Option<(usize, String)>,
+}
+
+/// Decode the packets in `data` until the first error, `each` is called with
+/// the offset, the packet and its length.
+pub fn replay(
+    data: &[u8],
+    version: Version,
+    mut each: impl FnMut(usize, &AnyPacket, usize),
+) -> ReplayStats {
+    let mut stats = ReplayStats {
+        version,
+        packets: 0,
+        bytes: 0,
+        types: BTreeMap::new(),
+        largest: 0,
+        incomplete: 0,
+        error: None,
+    };
+    let mut offset = 0;
+    while offset < data.len() {
+        match version.decode(&data[offset..]) {
+            Ok(Some((packet, len))) => {
+                each(offset, &packet, len);
+                let entry = stats.types.entry(packet.type_name()).or_default();
+                entry.0 += 1;
+                entry.1 += len;
+                stats.packets += 1;
+                stats.bytes += len;
+                stats.largest = stats.largest.max(len);
+                offset += len;
+            }
+            Ok(None) => {
+                stats.incomplete = data.len() - offset;
+                break;
+            }
+            Err(message) => {
+                stats.error = Some((offset, message));
+                break;
+            }
+        }
+    }
+    stats
+}
+
+impl std::fmt::Display for ReplayStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "protocol: {}", self.version)?;
+        writeln!(f, "packets:  {} ({} bytes)", self.packets, self.bytes)?;
+        for (name, (count, bytes)) in &self.types {
+            writeln!(f, "  {name:<12} {count:>8} {bytes:>10} bytes")?;
+        }
+        writeln!(f, "largest:  {} bytes", self.largest)?;
+        if self.incomplete > 0 {
+            writeln!(f, "incomplete packet at the end: {} bytes", self.incomplete)?;
+        }
+        if let Some((offset, message)) = &self.error {
+            writeln!(f, "error at offset {offset:#x}: {message}")?;
+        }
+        Ok(())
+    }
+}
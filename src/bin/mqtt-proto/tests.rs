@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+
+use mqtt_proto::v3;
+
+use super::args::{Args, Command, Format, ProtocolArg};
+use super::capture::is_capture;
+use super::codec::{sniff, AnyPacket, Version};
+use super::data::decode_data;
+use super::{decode_all, encode_all, replay, CliError};
+
+fn parse(args: &[&str]) -> Result<Args, CliError> {
+    Args::parse(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn test_parse_args() {
+    let args = parse(&["decode", "--format=base64", "-p", "v5", "--json", "in.txt"]).unwrap();
+    assert_eq!(
+        args,
+        Args {
+            command: Command::Decode,
+            input: Some(PathBuf::from("in.txt")),
+            format: Some(Format::Base64),
+            protocol: ProtocolArg::V5,
+            json: true,
+            verbose: false,
+            ports: Vec::new(),
+        }
+    );
+    assert_eq!(parse(&[]).unwrap().command, Command::Help);
+    assert_eq!(parse(&["replay", "-h"]).unwrap().command, Command::Help);
+    assert_eq!(parse(&["dissect", "-"]).unwrap().input, None);
+    let args = parse(&["replay", "--port", "1883", "--port=8883"]).unwrap();
+    assert_eq!(args.ports, [1883, 8883]);
+    for args in [
+        &["send"][..],
+        &["decode", "--format", "bin"],
+        &["decode", "--protocol"],
+        &["decode", "--quiet"],
+        &["decode", "a", "b"],
+        &["replay", "--port", "mqtt"],
+    ] {
+        assert!(matches!(parse(args), Err(CliError::Usage(_))), "{args:?}");
+    }
+}
+
+#[test]
+fn test_decode_data() {
+    assert_eq!(
+        decode_data(b"c0 00\n0xe0 00", Format::Hex).unwrap(),
+        [0xc0, 0x00, 0xe0, 0x00]
+    );
+    assert_eq!(
+        decode_data(b"wAA=\n", Format::Base64).unwrap(),
+        [0xc0, 0x00]
+    );
+    assert_eq!(decode_data(b"c0 00", Format::Raw).unwrap(), b"c0 00");
+    assert!(decode_data(b"c0 0", Format::Hex).is_err());
+    assert!(decode_data(b"c0 zz", Format::Hex).is_err());
+    assert!(decode_data(b"wA*=", Format::Base64).is_err());
+}
+
+#[test]
+fn test_sniff() {
+    let connect = v3::Packet::from(v3::Connect::new("c".into(), 10));
+    let data = connect.encode().unwrap();
+    assert_eq!(sniff(data.as_ref()), Some(Version::V3));
+    let connect = mqtt_proto::v5::Packet::from(mqtt_proto::v5::Connect::new("c".into(), 10));
+    let data = connect.encode().unwrap();
+    assert_eq!(sniff(data.as_ref()), Some(Version::V5));
+    assert_eq!(sniff(&[0xc0, 0x00]), None);
+    assert_eq!(sniff(&[0x10]), None);
+    assert_eq!(
+        Version::select(ProtocolArg::Auto, &[0xc0, 0x00]),
+        (Version::V3, false)
+    );
+}
+
+#[test]
+fn test_decode_encode() {
+    let packets = decode_all(&[0xc0, 0x00, 0xe0, 0x00], Version::V3).unwrap();
+    assert_eq!(
+        packets,
+        [
+            AnyPacket::V3(v3::Packet::Pingreq),
+            AnyPacket::V3(v3::Packet::Disconnect)
+        ]
+    );
+    let json: Vec<String> = packets
+        .iter()
+        .map(|packet| serde_json::to_string(packet).unwrap())
+        .collect();
+    let data = encode_all(json.join("\n").as_bytes(), ProtocolArg::V3).unwrap();
+    assert_eq!(data, [0xc0, 0x00, 0xe0, 0x00]);
+
+    assert!(matches!(
+        decode_all(&[0xc0, 0x00, 0x30], Version::V3),
+        Err(CliError::Decode { offset: 2, .. })
+    ));
+    assert!(matches!(
+        decode_all(&[0x00, 0x00], Version::V5),
+        Err(CliError::Decode { offset: 0, .. })
+    ));
+    assert!(matches!(
+        encode_all(b"{\"Connect\": 1}", ProtocolArg::V5),
+        Err(CliError::Json(_))
+    ));
+}
+
+#[test]
+fn test_replay() {
+    let stats = replay(
+        &[0xc0, 0x00, 0xc0, 0x00, 0xe0, 0x00, 0x30],
+        Version::V5,
+        |_, _, _| (),
+    );
+    assert_eq!(stats.packets, 3);
+    assert_eq!(stats.bytes, 6);
+    assert_eq!(stats.types["PINGREQ"], (2, 4));
+    assert_eq!(stats.incomplete, 1);
+    assert_eq!(stats.error, None);
+    assert_eq!(
+        stats.to_string(),
+        "protocol: v5.0
+packets:  3 (6 bytes)
+  DISCONNECT          1          2 bytes
+  PINGREQ             2          4 bytes
+largest:  2 bytes
+incomplete packet at the end: 1 bytes
+"
+    );
+
+    assert!(is_capture(&[0xd4, 0xc3, 0xb2, 0xa1, 2, 0]));
+    assert!(is_capture(&[0x0a, 0x0d, 0x0d, 0x0a]));
+    assert!(!is_capture(&[0xc0, 0x00, 0xc0, 0x00]));
+
+    let stats = replay(&[0xc0, 0x00, 0xf0, 0x00], Version::V3, |_, _, _| ());
+    assert_eq!(stats.packets, 1);
+    assert_eq!(stats.error.unwrap().0, 2);
+}
@@ -0,0 +1,105 @@
+use core::iter::FusedIterator;
+use core::marker::PhantomData;
+
+use super::{decode_raw_header, new_header, DecodeOptions, Error, PollHeader};
+
+/// Decode one packet from the start of `bytes`, return the packet and the
+/// consumed length, or `Ok(None)` if `bytes` does not hold a whole packet yet.
+pub(crate) fn decode_slice<H: PollHeader>(
+    bytes: &[u8],
+    options: &DecodeOptions,
+) -> Result<Option<(H::Packet, usize)>, H::Error> {
+    let Some((typ, remaining_len, var_bytes)) = decode_raw_header(bytes)? else {
+        return Ok(None);
+    };
+    let header_len = 1 + var_bytes;
+    let total = header_len + remaining_len as usize;
+    // A malformed header fails before the body is buffered, as it does when
+    // polling
+    let header = new_header::<H>(typ, remaining_len, var_bytes, total as u32, options)?;
+    if let Some(packet) = header.build_empty_packet() {
+        return Ok(Some((packet, total)));
+    }
+    if remaining_len == 0 {
+        return Err(Error::InvalidRemainingLength.into());
+    }
+    if bytes.len() < total {
+        return Ok(None);
+    }
+    let mut offset = 0;
+    match header.decode_buffer_with(&bytes[header_len..total], &mut offset, options) {
+        Ok(packet) => Ok(Some((packet, total))),
+        // The whole packet is available, the remaining length is wrong.
+        Err(err) if H::is_eof_error(&err) => Err(Error::InvalidRemainingLength.into()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Iterator over the complete packets in a filled buffer.
+///
+/// The packets are decoded straight from the buffer one after another, it
+/// stops at the first incomplete packet or after the first error. The bytes
+/// of the incomplete packet are reported by [`leftover`](Self::leftover) and
+/// should be kept for the next read.
+#[derive(Debug, Clone)]
+pub struct GenericDecodeBatch<'a, H> {
+    buf: &'a [u8],
+    offset: usize,
+    done: bool,
+    options: DecodeOptions,
+    _header: PhantomData<H>,
+}
+
+impl<'a, H> GenericDecodeBatch<'a, H> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        GenericDecodeBatch {
+            buf,
+            offset: 0,
+            done: false,
+            options: DecodeOptions::default(),
+            _header: PhantomData,
+        }
+    }
+
+    /// Decode the packets with `options`.
+    pub fn with_options(mut self, options: DecodeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The bytes of the packets decoded so far.
+    pub fn consumed(&self) -> usize {
+        self.offset
+    }
+
+    /// The bytes after the last decoded packet.
+    pub fn leftover(&self) -> usize {
+        self.buf.len() - self.offset
+    }
+}
+
+impl<H: PollHeader> Iterator for GenericDecodeBatch<'_, H> {
+    type Item = Result<H::Packet, H::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match decode_slice::<H>(&self.buf[self.offset..], &self.options) {
+            Ok(Some((packet, len))) => {
+                self.offset += len;
+                Some(Ok(packet))
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl<H: PollHeader> FusedIterator for GenericDecodeBatch<'_, H> {}
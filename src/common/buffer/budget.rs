@@ -0,0 +1,506 @@
+use core::future::Future;
+use core::mem::MaybeUninit;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::Error;
+
+use super::lock::Lock;
+use super::{Buffer, BufferHandle, ReadStrategy};
+
+/// Configuration of [`MemoryBudget`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryBudgetConfig {
+    /// Upper bound of the in-flight decode memory of all connections.
+    pub limit: usize,
+    /// Upper bound of the in-flight decode memory of one connection.
+    pub connection_limit: usize,
+    /// Wait for memory to be released when the budget is exhausted, otherwise
+    /// fail with [`Error::MemoryBudgetExceeded`].
+    pub wait: bool,
+}
+
+impl Default for MemoryBudgetConfig {
+    fn default() -> Self {
+        MemoryBudgetConfig {
+            limit: 256 * 1024 * 1024,
+            connection_limit: 16 * 1024 * 1024,
+            wait: true,
+        }
+    }
+}
+
+/// Snapshot of the [`MemoryBudget`] accounting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryBudgetStats {
+    /// Bytes currently granted.
+    pub in_use: usize,
+    /// Highest value of `in_use` so far.
+    pub peak: usize,
+    /// Number of connections sharing the budget.
+    pub connections: usize,
+    /// Number of reservations currently waiting.
+    pub waiting: usize,
+    /// Number of reservations that had to wait.
+    pub throttled: u64,
+    /// Number of reservations that failed.
+    pub rejected: u64,
+}
+
+/// Memory budget shared by the [`Buffer`]s of many connections.
+///
+/// Each connection takes a [`ConnectionBudget`] and wraps its buffer in a
+/// [`BudgetedBuffer`]. A reservation first waits for the connection's own
+/// limit, then queues for the global limit in arrival order, so one busy
+/// connection or a stream of small packets can not starve the others.
+#[derive(Debug, Clone)]
+pub struct MemoryBudget {
+    inner: Arc<BudgetInner>,
+}
+
+#[derive(Debug)]
+struct BudgetInner {
+    config: MemoryBudgetConfig,
+    state: Lock<BudgetState>,
+}
+
+#[derive(Debug, Default)]
+struct BudgetState {
+    in_use: usize,
+    peak: usize,
+    throttled: u64,
+    rejected: u64,
+    next_conn: u64,
+    next_ticket: u64,
+    conns: BTreeMap<u64, ConnState>,
+    // Reservations waiting for the global limit, in arrival order
+    queue: VecDeque<(u64, Waker)>,
+}
+
+#[derive(Debug, Default)]
+struct ConnState {
+    in_use: usize,
+    throttled: u64,
+    waiters: Vec<Waker>,
+}
+
+impl BudgetState {
+    fn grant(&mut self, conn: u64, size: usize) {
+        if let Some(conn) = self.conns.get_mut(&conn) {
+            conn.in_use += size;
+        }
+        self.in_use += size;
+        self.peak = self.peak.max(self.in_use);
+    }
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        Self::new(MemoryBudgetConfig::default())
+    }
+}
+
+impl MemoryBudget {
+    pub fn new(config: MemoryBudgetConfig) -> Self {
+        MemoryBudget {
+            inner: Arc::new(BudgetInner {
+                config,
+                state: Lock::new(BudgetState::default()),
+            }),
+        }
+    }
+
+    pub fn config(&self) -> &MemoryBudgetConfig {
+        &self.inner.config
+    }
+
+    pub fn stats(&self) -> MemoryBudgetStats {
+        self.inner.state.with(|state| MemoryBudgetStats {
+            in_use: state.in_use,
+            peak: state.peak,
+            connections: state.conns.len(),
+            waiting: state.queue.len()
+                + state.conns.values().map(|c| c.waiters.len()).sum::<usize>(),
+            throttled: state.throttled,
+            rejected: state.rejected,
+        })
+    }
+
+    /// Register a new connection.
+    pub fn connection(&self) -> ConnectionBudget {
+        let id = self.inner.state.with(|state| {
+            let id = state.next_conn;
+            state.next_conn += 1;
+            state.conns.insert(id, ConnState::default());
+            id
+        });
+        ConnectionBudget {
+            id,
+            budget: Arc::clone(&self.inner),
+        }
+    }
+}
+
+/// The share of one connection in a [`MemoryBudget`], unregistered on drop.
+#[derive(Debug)]
+pub struct ConnectionBudget {
+    id: u64,
+    budget: Arc<BudgetInner>,
+}
+
+impl ConnectionBudget {
+    /// Reserve `size` bytes, the bytes are given back when the permit drops.
+    pub fn reserve(&self, size: usize) -> MemoryReserve<'_> {
+        MemoryReserve {
+            conn: self,
+            size,
+            waiting: WaitingSlot::Owned(Waiting::default()),
+        }
+    }
+
+    /// Reserve `size` bytes, the queue position is kept in `waiting` when the
+    /// returned future is dropped before it completes.
+    fn reserve_kept<'a>(&'a self, waiting: &'a mut Waiting, size: usize) -> MemoryReserve<'a> {
+        MemoryReserve {
+            conn: self,
+            size,
+            waiting: WaitingSlot::Kept(waiting),
+        }
+    }
+
+    /// Bytes currently granted to this connection.
+    pub fn in_use(&self) -> usize {
+        self.with_conn(|conn| conn.in_use)
+    }
+
+    /// Number of reservations of this connection that had to wait.
+    pub fn throttled(&self) -> u64 {
+        self.with_conn(|conn| conn.throttled)
+    }
+
+    fn with_conn<R: Default>(&self, f: impl FnOnce(&mut ConnState) -> R) -> R {
+        self.budget
+            .state
+            .with(|state| state.conns.get_mut(&self.id).map(f).unwrap_or_default())
+    }
+}
+
+impl Drop for ConnectionBudget {
+    fn drop(&mut self) {
+        self.budget.state.with(|state| state.conns.remove(&self.id));
+    }
+}
+
+/// Future returned by [`ConnectionBudget::reserve`].
+#[derive(Debug)]
+pub struct MemoryReserve<'a> {
+    conn: &'a ConnectionBudget,
+    size: usize,
+    waiting: WaitingSlot<'a>,
+}
+
+#[derive(Debug)]
+enum WaitingSlot<'a> {
+    // Leaves the queue when the future is dropped
+    Owned(Waiting),
+    // Kept by the caller across futures
+    Kept(&'a mut Waiting),
+}
+
+/// Progress of a reservation which had to wait.
+#[derive(Debug, Default)]
+struct Waiting {
+    ticket: Option<Ticket>,
+    throttled: bool,
+}
+
+/// Position in the global queue, the queue is left on drop.
+#[derive(Debug)]
+struct Ticket {
+    id: u64,
+    budget: Arc<BudgetInner>,
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        let id = self.id;
+        let next = self.budget.state.with(|state| {
+            let was_front = state.queue.front().map(|(t, _)| *t) == Some(id);
+            state.queue.retain(|(t, _)| *t != id);
+            if was_front {
+                state.queue.front().map(|(_, w)| w.clone())
+            } else {
+                None
+            }
+        });
+        if let Some(waker) = next {
+            waker.wake();
+        }
+    }
+}
+
+impl Future for MemoryReserve<'_> {
+    type Output = Result<MemoryPermit, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let id = this.conn.id;
+        let size = this.size;
+        let budget = &this.conn.budget;
+        let config = budget.config;
+        let waiting = match &mut this.waiting {
+            WaitingSlot::Owned(waiting) => waiting,
+            WaitingSlot::Kept(waiting) => &mut **waiting,
+        };
+        let (result, wake, left) = budget.state.with(|state| {
+            if size > config.limit || size > config.connection_limit {
+                state.rejected += 1;
+                return (
+                    Poll::Ready(Err(Error::MemoryBudgetExceeded(size))),
+                    None,
+                    None,
+                );
+            }
+            let conn_in_use = state.conns.get(&id).map_or(0, |conn| conn.in_use);
+            let fits_conn = conn_in_use + size <= config.connection_limit;
+
+            let Some(ticket) = waiting.ticket.as_ref().map(|t| t.id) else {
+                // Wait for the connection limit before queueing
+                let fits_global = state.queue.is_empty() && state.in_use + size <= config.limit;
+                if fits_conn && fits_global {
+                    state.grant(id, size);
+                    return (Poll::Ready(Ok(())), None, None);
+                }
+                if !config.wait {
+                    state.rejected += 1;
+                    return (
+                        Poll::Ready(Err(Error::MemoryBudgetExceeded(size))),
+                        None,
+                        None,
+                    );
+                }
+                let first_wait = !waiting.throttled;
+                waiting.throttled = true;
+                if first_wait {
+                    state.throttled += 1;
+                }
+                let conn = state.conns.entry(id).or_default();
+                if first_wait {
+                    conn.throttled += 1;
+                }
+                if !fits_conn {
+                    if !conn.waiters.iter().any(|w| w.will_wake(cx.waker())) {
+                        conn.waiters.push(cx.waker().clone());
+                    }
+                    return (Poll::Pending, None, None);
+                }
+                let ticket = state.next_ticket;
+                state.next_ticket += 1;
+                state.queue.push_back((ticket, cx.waker().clone()));
+                waiting.ticket = Some(Ticket {
+                    id: ticket,
+                    budget: Arc::clone(budget),
+                });
+                return (Poll::Pending, None, None);
+            };
+
+            let is_front = state.queue.front().map(|(t, _)| *t) == Some(ticket);
+            if is_front && state.in_use + size <= config.limit {
+                state.queue.pop_front();
+                // Out of the queue, the next poll queues again if needed
+                let left = waiting.ticket.take();
+                // The next one may fit as well
+                let next = state.queue.front().map(|(_, w)| w.clone());
+                if !fits_conn {
+                    // The connection took more memory while queued, wait for
+                    // it again rather than holding up the queue
+                    if let Some(conn) = state.conns.get_mut(&id) {
+                        conn.waiters.push(cx.waker().clone());
+                    }
+                    return (Poll::Pending, next, left);
+                }
+                state.grant(id, size);
+                return (Poll::Ready(Ok(())), next, left);
+            }
+            if let Some((_, waker)) = state.queue.iter_mut().find(|(t, _)| *t == ticket) {
+                waker.clone_from(cx.waker());
+            }
+            (Poll::Pending, None, None)
+        });
+        // Dropping the ticket only takes the lock again once the lock above
+        // is released
+        drop(left);
+        if result.is_ready() {
+            drop(core::mem::take(waiting));
+        }
+        if let Some(waker) = wake {
+            waker.wake();
+        }
+        result.map_ok(|()| MemoryPermit {
+            conn: id,
+            size,
+            budget: Arc::clone(budget),
+        })
+    }
+}
+
+/// Bytes granted by a [`MemoryBudget`], given back on drop.
+#[derive(Debug)]
+pub struct MemoryPermit {
+    conn: u64,
+    size: usize,
+    budget: Arc<BudgetInner>,
+}
+
+impl MemoryPermit {
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+impl Drop for MemoryPermit {
+    fn drop(&mut self) {
+        let (conn_waiters, next) = self.budget.state.with(|state| {
+            state.in_use -= self.size;
+            let conn_waiters = match state.conns.get_mut(&self.conn) {
+                Some(conn) => {
+                    conn.in_use -= self.size;
+                    core::mem::take(&mut conn.waiters)
+                }
+                None => Vec::new(),
+            };
+            (conn_waiters, state.queue.front().map(|(_, w)| w.clone()))
+        });
+        for waker in conn_waiters.into_iter().chain(next) {
+            waker.wake();
+        }
+    }
+}
+
+/// [`Buffer`] charging every acquired handle to a [`ConnectionBudget`].
+///
+/// Chunked reads of large packets are charged through [`Buffer::reserve`]
+/// for the time the packet is read and decoded.
+#[derive(Debug)]
+pub struct BudgetedBuffer<B> {
+    inner: B,
+    budget: ConnectionBudget,
+    // A reservation interrupted by a dropped decode future keeps its place
+    // in the queue until the next acquire or reserve
+    waiting: Waiting,
+    // Permit of an acquire interrupted while waiting for the inner buffer,
+    // kept so the next acquire does not queue again
+    granted: Option<MemoryPermit>,
+}
+
+impl<B> BudgetedBuffer<B> {
+    pub fn new(inner: B, budget: ConnectionBudget) -> Self {
+        BudgetedBuffer {
+            inner,
+            budget,
+            waiting: Waiting::default(),
+            granted: None,
+        }
+    }
+
+    pub fn budget(&self) -> &ConnectionBudget {
+        &self.budget
+    }
+
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B> Buffer for BudgetedBuffer<B>
+where
+    B: Buffer,
+    B::Error: From<Error>,
+{
+    type Handle = BudgetedHandle<B::Handle>;
+    type Error = B::Error;
+
+    async fn acquire(&mut self, size: usize) -> Result<Self::Handle, Self::Error> {
+        let permit = match self.granted.take() {
+            Some(permit) if permit.size() == size => permit,
+            stale => {
+                drop(stale);
+                self.budget.reserve_kept(&mut self.waiting, size).await?
+            }
+        };
+        self.granted = Some(permit);
+        let handle = self.inner.acquire(size).await;
+        let permit = self.granted.take().expect("permit is kept");
+        Ok(BudgetedHandle {
+            handle: handle?,
+            permit,
+        })
+    }
+
+    async fn release(&mut self, handle: Self::Handle) -> Result<(), Self::Error> {
+        let BudgetedHandle { handle, permit } = handle;
+        let result = self.inner.release(handle).await;
+        drop(permit);
+        result
+    }
+
+    fn read_strategy(&self, packet_size: usize) -> ReadStrategy {
+        self.inner.read_strategy(packet_size)
+    }
+
+    async fn reserve(&mut self, size: usize) -> Result<Option<MemoryPermit>, Self::Error> {
+        Ok(Some(
+            self.budget.reserve_kept(&mut self.waiting, size).await?,
+        ))
+    }
+}
+
+/// Buffer handle of [`BudgetedBuffer`], holds the memory permit.
+#[derive(Debug)]
+pub struct BudgetedHandle<H> {
+    handle: H,
+    permit: MemoryPermit,
+}
+
+impl<H> BudgetedHandle<H> {
+    pub fn permit(&self) -> &MemoryPermit {
+        &self.permit
+    }
+
+    /// The inner handle, the memory permit is given back.
+    pub fn into_inner(self) -> H {
+        self.handle
+    }
+}
+
+impl<H: BufferHandle> BufferHandle for BudgetedHandle<H> {
+    type Error = H::Error;
+
+    fn as_mut_slice(&mut self) -> (&mut [MaybeUninit<u8>], usize) {
+        self.handle.as_mut_slice()
+    }
+
+    fn as_slice(&self, len: usize) -> &[u8] {
+        self.handle.as_slice(len)
+    }
+
+    fn set_len(&mut self, len: usize) {
+        self.handle.set_len(len)
+    }
+
+    fn len(&self) -> usize {
+        self.handle.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.handle.capacity()
+    }
+
+    fn freeze(&mut self) -> Option<bytes::Bytes> {
+        self.handle.freeze()
+    }
+}
@@ -0,0 +1,139 @@
+use core::mem::MaybeUninit;
+
+use bytes::{Bytes, BytesMut};
+
+use crate::Error;
+
+use super::{Buffer, BufferHandle, ReadStrategy};
+
+/// [`Buffer`] backed by [`BytesMut`].
+///
+/// Handles are split from one growing arena, and the arena reclaims the
+/// memory once all the `Bytes` sharing it are dropped. A filled handle can
+/// be frozen into [`Bytes`], so the packet body and the payloads sliced from
+/// it share one allocation.
+#[derive(Debug, Default)]
+pub struct BytesBuffer {
+    arena: BytesMut,
+    reserve_size: usize,
+}
+
+impl BytesBuffer {
+    /// Reserve at least `reserve_size` bytes each time the arena grows.
+    pub fn new(reserve_size: usize) -> Self {
+        BytesBuffer {
+            arena: BytesMut::with_capacity(reserve_size),
+            reserve_size,
+        }
+    }
+}
+
+impl Buffer for BytesBuffer {
+    type Handle = BytesBufferHandle;
+    type Error = Error;
+
+    async fn acquire(&mut self, size: usize) -> Result<Self::Handle, Self::Error> {
+        if self.arena.capacity() < size {
+            self.arena.reserve(size.max(self.reserve_size));
+        }
+        self.arena.resize(size, 0);
+        let data = self.arena.split_to(size);
+        Ok(BytesBufferHandle {
+            data: BytesData::Mut(data),
+            len: 0,
+        })
+    }
+
+    async fn release(&mut self, _handle: Self::Handle) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn read_strategy(&self, _packet_size: usize) -> ReadStrategy {
+        ReadStrategy::Buffer
+    }
+}
+
+/// Buffer handle of [`BytesBuffer`].
+#[derive(Debug, Clone)]
+pub struct BytesBufferHandle {
+    data: BytesData,
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+enum BytesData {
+    Mut(BytesMut),
+    Frozen(Bytes),
+}
+
+impl BytesBufferHandle {
+    /// The filled bytes as [`Bytes`], the allocation is shared.
+    pub fn into_bytes(mut self) -> Bytes {
+        self.share()
+    }
+
+    fn share(&mut self) -> Bytes {
+        let bytes = match &mut self.data {
+            BytesData::Mut(data) => {
+                data.truncate(self.len);
+                core::mem::take(data).freeze()
+            }
+            BytesData::Frozen(bytes) => bytes.slice(..self.len.min(bytes.len())),
+        };
+        self.data = BytesData::Frozen(bytes.clone());
+        bytes
+    }
+}
+
+impl From<BytesBufferHandle> for Bytes {
+    fn from(handle: BytesBufferHandle) -> Bytes {
+        handle.into_bytes()
+    }
+}
+
+impl BufferHandle for BytesBufferHandle {
+    type Error = Error;
+
+    /// The slice is empty once the handle is frozen.
+    fn as_mut_slice(&mut self) -> (&mut [MaybeUninit<u8>], usize) {
+        match &mut self.data {
+            BytesData::Mut(data) => {
+                let capacity = data.len();
+                let ptr = data.as_mut_ptr() as *mut MaybeUninit<u8>;
+                // SAFETY: `u8` and `MaybeUninit<u8>` have the same layout and
+                // the bytes are always initialized.
+                let slice = unsafe { core::slice::from_raw_parts_mut(ptr, capacity) };
+                (slice, capacity)
+            }
+            BytesData::Frozen(_) => (&mut [], 0),
+        }
+    }
+
+    fn as_slice(&self, len: usize) -> &[u8] {
+        let data: &[u8] = match &self.data {
+            BytesData::Mut(data) => data,
+            BytesData::Frozen(bytes) => bytes,
+        };
+        &data[..len.min(data.len())]
+    }
+
+    /// The length is capped at the acquired size.
+    fn set_len(&mut self, len: usize) {
+        self.len = len.min(self.capacity());
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn capacity(&self) -> usize {
+        match &self.data {
+            BytesData::Mut(data) => data.len(),
+            BytesData::Frozen(bytes) => bytes.len(),
+        }
+    }
+
+    fn freeze(&mut self) -> Option<Bytes> {
+        Some(self.share())
+    }
+}
@@ -0,0 +1,37 @@
+/// Short critical sections only, never held across an `.await`.
+#[derive(Debug)]
+pub(super) struct Lock<T> {
+    #[cfg(feature = "std")]
+    inner: std::sync::Mutex<T>,
+    #[cfg(not(feature = "std"))]
+    inner: embassy_sync::blocking_mutex::Mutex<
+        embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex,
+        core::cell::RefCell<T>,
+    >,
+}
+
+impl<T> Lock<T> {
+    pub(super) fn new(value: T) -> Self {
+        Self {
+            #[cfg(feature = "std")]
+            inner: std::sync::Mutex::new(value),
+            #[cfg(not(feature = "std"))]
+            inner: embassy_sync::blocking_mutex::Mutex::new(core::cell::RefCell::new(value)),
+        }
+    }
+
+    pub(super) fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        #[cfg(feature = "std")]
+        {
+            let mut guard = self
+                .inner
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            f(&mut guard)
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            self.inner.lock(|cell| f(&mut cell.borrow_mut()))
+        }
+    }
+}
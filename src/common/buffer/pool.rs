@@ -0,0 +1,291 @@
+use core::future::poll_fn;
+use core::mem::MaybeUninit;
+use core::task::{Poll, Waker};
+
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{Error, IoErrorKind, Metrics, NoopMetrics};
+
+use super::lock::Lock;
+use super::{Buffer, BufferHandle, ReadStrategy};
+
+/// Configuration of [`BufferPool`].
+#[derive(Debug, Clone)]
+pub struct BufferPoolConfig {
+    /// Buffer size of each slab, in ascending order. A request is served from
+    /// the smallest size class that can hold it.
+    pub size_classes: Vec<usize>,
+    /// Upper bound of the bytes owned by the pool, both in use and idle.
+    pub max_memory: usize,
+    /// Chunk size for packets larger than the biggest size class.
+    pub chunk_size: usize,
+}
+
+impl Default for BufferPoolConfig {
+    fn default() -> Self {
+        Self {
+            size_classes: vec![512, 2048, 8192, 32768],
+            max_memory: 4 * 1024 * 1024,
+            chunk_size: 8192,
+        }
+    }
+}
+
+/// Snapshot of the [`BufferPool`] accounting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BufferPoolStats {
+    /// Number of handles currently held by callers.
+    pub in_use: usize,
+    /// Number of buffers waiting in the free lists.
+    pub idle: usize,
+    /// Bytes owned by the pool, both in use and idle.
+    pub allocated_bytes: usize,
+    /// Number of tasks waiting for memory to be released.
+    pub waiting: usize,
+}
+
+/// Size-classed buffer pool with bounded total memory.
+///
+/// Buffers are kept in one free list per size class and reused. When the
+/// memory bound is reached, idle buffers of other size classes are freed
+/// first, then `acquire` waits until a handle is dropped. Handles return
+/// themselves to the pool on drop.
+///
+/// The pool is cheap to clone, all the clones share the same memory.
+#[derive(Debug, Clone)]
+pub struct BufferPool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    config: BufferPoolConfig,
+    state: Lock<PoolState>,
+    metrics: Arc<dyn Metrics>,
+}
+
+impl core::fmt::Debug for PoolInner {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PoolInner")
+            .field("config", &self.config)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+struct PoolState {
+    // Free buffers per size class
+    free: Vec<Vec<Box<[u8]>>>,
+    in_use: usize,
+    allocated_bytes: usize,
+    waiters: VecDeque<Waker>,
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        Self::new(BufferPoolConfig::default())
+    }
+}
+
+impl BufferPool {
+    pub fn new(config: BufferPoolConfig) -> Self {
+        Self::with_metrics(config, Arc::new(NoopMetrics))
+    }
+
+    /// Create the pool reporting hits and misses to `metrics`.
+    pub fn with_metrics(mut config: BufferPoolConfig, metrics: Arc<dyn Metrics>) -> Self {
+        config.size_classes.sort_unstable();
+        config.size_classes.dedup();
+        let state = PoolState {
+            free: config.size_classes.iter().map(|_| Vec::new()).collect(),
+            in_use: 0,
+            allocated_bytes: 0,
+            waiters: VecDeque::new(),
+        };
+        Self {
+            inner: Arc::new(PoolInner {
+                config,
+                state: Lock::new(state),
+                metrics,
+            }),
+        }
+    }
+
+    pub fn config(&self) -> &BufferPoolConfig {
+        &self.inner.config
+    }
+
+    pub fn stats(&self) -> BufferPoolStats {
+        self.inner.state.with(|state| BufferPoolStats {
+            in_use: state.in_use,
+            idle: state.free.iter().map(Vec::len).sum(),
+            allocated_bytes: state.allocated_bytes,
+            waiting: state.waiters.len(),
+        })
+    }
+
+    /// Acquire a buffer without waiting, return `Ok(None)` when the memory
+    /// bound is reached.
+    pub fn try_acquire(&self, size: usize) -> Result<Option<PooledBuffer>, Error> {
+        let class = self.size_class(size)?;
+        let taken = self.inner.state.with(|state| self.take(state, class));
+        Ok(taken.map(|(handle, hit)| self.report(handle, hit)))
+    }
+
+    fn size_class(&self, size: usize) -> Result<usize, Error> {
+        let config = &self.inner.config;
+        config
+            .size_classes
+            .iter()
+            .position(|class_size| *class_size >= size)
+            .filter(|class| config.size_classes[*class] <= config.max_memory)
+            .ok_or(Error::IoError(IoErrorKind::OutOfMemory))
+    }
+
+    fn report(&self, handle: PooledBuffer, hit: bool) -> PooledBuffer {
+        self.inner.metrics.buffer_acquired(handle.capacity(), hit);
+        handle
+    }
+
+    /// Take a buffer of the size class, tell whether it was reused.
+    fn take(&self, state: &mut PoolState, class: usize) -> Option<(PooledBuffer, bool)> {
+        let class_size = self.inner.config.size_classes[class];
+        let (data, hit) = match state.free[class].pop() {
+            Some(data) => (data, true),
+            None => {
+                let max_memory = self.inner.config.max_memory;
+                // Release idle buffers of other size classes to make room
+                while state.allocated_bytes + class_size > max_memory {
+                    let (idx, data) = state
+                        .free
+                        .iter_mut()
+                        .enumerate()
+                        .find_map(|(idx, free)| free.pop().map(|data| (idx, data)))?;
+                    debug_assert_eq!(data.len(), self.inner.config.size_classes[idx]);
+                    state.allocated_bytes -= data.len();
+                }
+                state.allocated_bytes += class_size;
+                (vec![0u8; class_size].into_boxed_slice(), false)
+            }
+        };
+        state.in_use += 1;
+        let handle = PooledBuffer {
+            data: Some(data),
+            len: 0,
+            class,
+            pool: Arc::clone(&self.inner),
+        };
+        Some((handle, hit))
+    }
+}
+
+impl PoolInner {
+    fn give_back(&self, class: usize, data: Box<[u8]>) {
+        let waiters = self.state.with(|state| {
+            state.in_use -= 1;
+            state.free[class].push(data);
+            core::mem::take(&mut state.waiters)
+        });
+        // Every waiter retries, the free buffer may not fit the first one.
+        for waker in waiters {
+            waker.wake();
+        }
+    }
+}
+
+impl Buffer for BufferPool {
+    type Handle = PooledBuffer;
+    type Error = Error;
+
+    async fn acquire(&mut self, size: usize) -> Result<Self::Handle, Self::Error> {
+        let class = self.size_class(size)?;
+        let (handle, hit) = poll_fn(|cx| {
+            self.inner
+                .state
+                .with(|state| match self.take(state, class) {
+                    Some(handle) => Poll::Ready(handle),
+                    None => {
+                        if !state.waiters.iter().any(|w| w.will_wake(cx.waker())) {
+                            state.waiters.push_back(cx.waker().clone());
+                        }
+                        Poll::Pending
+                    }
+                })
+        })
+        .await;
+        Ok(self.report(handle, hit))
+    }
+
+    async fn release(&mut self, handle: Self::Handle) -> Result<(), Self::Error> {
+        drop(handle);
+        Ok(())
+    }
+
+    fn read_strategy(&self, packet_size: usize) -> ReadStrategy {
+        let config = &self.inner.config;
+        match config.size_classes.last() {
+            Some(max_size) if packet_size <= *max_size => ReadStrategy::Buffer,
+            _ => ReadStrategy::Chunk(config.chunk_size),
+        }
+    }
+}
+
+/// Buffer handle of [`BufferPool`], the buffer returns to the pool on drop.
+#[derive(Debug)]
+pub struct PooledBuffer {
+    // Only `None` after drop
+    data: Option<Box<[u8]>>,
+    len: usize,
+    class: usize,
+    pool: Arc<PoolInner>,
+}
+
+impl PooledBuffer {
+    fn data(&self) -> &[u8] {
+        self.data.as_deref().unwrap_or_default()
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        if let Some(data) = self.data.take() {
+            self.pool.give_back(self.class, data);
+        }
+    }
+}
+
+impl BufferHandle for PooledBuffer {
+    type Error = Error;
+
+    fn as_mut_slice(&mut self) -> (&mut [MaybeUninit<u8>], usize) {
+        let data = self.data.as_deref_mut().unwrap_or_default();
+        let capacity = data.len();
+        let ptr = data.as_mut_ptr() as *mut MaybeUninit<u8>;
+        // SAFETY: `u8` and `MaybeUninit<u8>` have the same layout and the
+        // bytes are always initialized.
+        let slice = unsafe { core::slice::from_raw_parts_mut(ptr, capacity) };
+        (slice, capacity)
+    }
+
+    fn as_slice(&self, len: usize) -> &[u8] {
+        let data = self.data();
+        &data[..len.min(data.len())]
+    }
+
+    /// The length is capped at the capacity of the size class.
+    fn set_len(&mut self, len: usize) {
+        self.len = len.min(self.capacity());
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn capacity(&self) -> usize {
+        self.data().len()
+    }
+}
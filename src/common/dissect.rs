@@ -0,0 +1,506 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::Protocol;
+
+/// Bytes shown in the preview of a payload or binary field.
+const PREVIEW_LEN: usize = 16;
+
+/// One field of a dissected packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    /// Offset of the field in the dissected bytes.
+    pub offset: usize,
+    pub len: usize,
+    pub name: &'static str,
+    /// Human-readable value, empty for groups without a value.
+    pub value: String,
+    pub children: Vec<Field>,
+}
+
+/// Where and why dissecting stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DissectError {
+    pub offset: usize,
+    pub message: String,
+}
+
+/// Annotated tree of the packets in a byte slice, returned by
+/// `v3::dissect` and `v5::dissect`.
+///
+/// The fields read before a malformed field are kept, and `error` tells
+/// where decoding failed. The `Display` output has one field per line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dissection {
+    /// One field per packet.
+    pub packets: Vec<Field>,
+    pub error: Option<DissectError>,
+}
+
+impl Dissection {
+    /// Find the first field by name, depth first.
+    pub fn find(&self, name: &str) -> Option<&Field> {
+        fn find_in<'a>(fields: &'a [Field], name: &str) -> Option<&'a Field> {
+            fields.iter().find_map(|field| {
+                if field.name == name {
+                    Some(field)
+                } else {
+                    find_in(&field.children, name)
+                }
+            })
+        }
+        find_in(&self.packets, name)
+    }
+}
+
+impl fmt::Display for Dissection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_field(f: &mut fmt::Formatter<'_>, field: &Field, depth: usize) -> fmt::Result {
+            write!(
+                f,
+                "{:04x} +{:<4} {:indent$}{}",
+                field.offset,
+                field.len,
+                "",
+                field.name,
+                indent = depth * 2
+            )?;
+            if !field.value.is_empty() {
+                write!(f, ": {}", field.value)?;
+            }
+            writeln!(f)?;
+            for child in &field.children {
+                write_field(f, child, depth + 1)?;
+            }
+            Ok(())
+        }
+        for packet in &self.packets {
+            write_field(f, packet, 0)?;
+        }
+        if let Some(err) = &self.error {
+            writeln!(f, "error at {:04x}: {}", err.offset, err.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Dissecting stopped, the error is recorded in the [`Dissector`].
+pub(crate) struct Stop;
+
+pub(crate) type DResult<T> = Result<T, Stop>;
+
+/// Cursor building the field tree of a [`Dissection`].
+pub(crate) struct Dissector<'a> {
+    buf: &'a [u8],
+    offset: usize,
+    /// End of the enclosing length, the remaining length or a property length.
+    end: usize,
+    end_name: &'static str,
+    /// Open groups, the innermost last.
+    stack: Vec<Field>,
+    packets: Vec<Field>,
+    error: Option<DissectError>,
+}
+
+impl<'a> Dissector<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Dissector {
+            buf,
+            offset: 0,
+            // Truncated packets are dissected up to the end of the data
+            end: usize::MAX,
+            end_name: "data",
+            stack: Vec::new(),
+            packets: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub(crate) fn fail<T>(&mut self, offset: usize, message: String) -> DResult<T> {
+        self.error = Some(DissectError { offset, message });
+        Err(Stop)
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Whether the enclosing length has bytes left.
+    pub(crate) fn has_more(&self) -> bool {
+        self.offset < self.end
+    }
+
+    fn take(&mut self, len: usize) -> DResult<&'a [u8]> {
+        let start = self.offset;
+        if start + len > self.end {
+            let message = format!(
+                "field of {len} bytes exceeds the {} by {} bytes",
+                self.end_name,
+                start + len - self.end
+            );
+            return self.fail(start, message);
+        }
+        if start + len > self.buf.len() {
+            let message = format!(
+                "unexpected end of data, {} more bytes needed",
+                start + len - self.buf.len()
+            );
+            return self.fail(self.buf.len(), message);
+        }
+        self.offset += len;
+        Ok(&self.buf[start..start + len])
+    }
+
+    /// Add a field from `offset` to the current offset.
+    pub(crate) fn add(&mut self, offset: usize, name: &'static str, value: String) {
+        self.add_field(Field {
+            offset,
+            len: self.offset - offset,
+            name,
+            value,
+            children: Vec::new(),
+        });
+    }
+
+    fn add_field(&mut self, field: Field) {
+        match self.stack.last_mut() {
+            Some(parent) => parent.children.push(field),
+            None => self.packets.push(field),
+        }
+    }
+
+    /// Add the fields added by `f` to a group, the group is closed even if
+    /// dissecting stops inside it.
+    pub(crate) fn group<T>(
+        &mut self,
+        name: &'static str,
+        value: String,
+        f: impl FnOnce(&mut Self) -> DResult<T>,
+    ) -> DResult<T> {
+        self.stack.push(Field {
+            offset: self.offset,
+            len: 0,
+            name,
+            value,
+            children: Vec::new(),
+        });
+        let result = f(self);
+        let mut group = self.stack.pop().expect("group opened");
+        group.len = self.offset - group.offset;
+        self.add_field(group);
+        result
+    }
+
+    /// Set the value of the innermost open group.
+    pub(crate) fn set_value(&mut self, value: String) {
+        if let Some(group) = self.stack.last_mut() {
+            group.value = value;
+        }
+    }
+
+    /// Run `f` with the enclosing length ending `len` bytes from here.
+    pub(crate) fn limit<T>(
+        &mut self,
+        len: usize,
+        name: &'static str,
+        f: impl FnOnce(&mut Self) -> DResult<T>,
+    ) -> DResult<T> {
+        let end = self.offset + len;
+        if end > self.end {
+            let message = format!(
+                "{name} exceeds the {} by {} bytes",
+                self.end_name,
+                end - self.end
+            );
+            return self.fail(self.offset, message);
+        }
+        let saved = (self.end, self.end_name);
+        self.end = end;
+        self.end_name = name;
+        let result = f(self);
+        (self.end, self.end_name) = saved;
+        result
+    }
+
+    #[cfg(feature = "v5")]
+    pub(crate) fn u8(&mut self, name: &'static str) -> DResult<u8> {
+        self.u8_with(name, |_| Ok(None))
+    }
+
+    /// Read a byte, `describe` names the value or rejects it.
+    pub(crate) fn u8_with(
+        &mut self,
+        name: &'static str,
+        describe: impl FnOnce(u8) -> Result<Option<String>, String>,
+    ) -> DResult<u8> {
+        let offset = self.offset;
+        let value = self.take(1)?[0];
+        match describe(value) {
+            Ok(Some(label)) => self.add(offset, name, format!("{value} ({label})")),
+            Ok(None) => self.add(offset, name, format!("{value}")),
+            Err(message) => {
+                self.add(offset, name, format!("{value} (invalid)"));
+                return self.fail(offset, message);
+            }
+        }
+        Ok(value)
+    }
+
+    /// Read a byte of flags, each `(name, mask)` is added as a child field.
+    /// A non-zero `reserved` bit stops dissecting.
+    pub(crate) fn flags(
+        &mut self,
+        name: &'static str,
+        bits: &[(&'static str, u8)],
+        reserved: u8,
+    ) -> DResult<u8> {
+        let offset = self.offset;
+        let byte = self.take(1)?[0];
+        self.add_flags(offset, name, byte, bits);
+        if byte & reserved != 0 {
+            return self.fail(offset, format!("reserved bits set in {name}: {byte:#04x}"));
+        }
+        Ok(byte)
+    }
+
+    fn add_flags(
+        &mut self,
+        offset: usize,
+        name: &'static str,
+        byte: u8,
+        bits: &[(&'static str, u8)],
+    ) {
+        let children = bits
+            .iter()
+            .map(|(bit_name, mask)| Field {
+                offset,
+                len: 1,
+                name: bit_name,
+                value: format!("{}", (byte & mask) >> mask.trailing_zeros()),
+                children: Vec::new(),
+            })
+            .collect();
+        self.add_field(Field {
+            offset,
+            len: 1,
+            name,
+            value: format!("{byte:#04x}"),
+            children,
+        });
+    }
+
+    pub(crate) fn u16(&mut self, name: &'static str) -> DResult<u16> {
+        let offset = self.offset;
+        let data = self.take(2)?;
+        let value = u16::from_be_bytes([data[0], data[1]]);
+        self.add(offset, name, format!("{value}"));
+        Ok(value)
+    }
+
+    #[cfg(feature = "v5")]
+    pub(crate) fn u32(&mut self, name: &'static str) -> DResult<u32> {
+        let offset = self.offset;
+        let data = self.take(4)?;
+        let value = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+        self.add(offset, name, format!("{value}"));
+        Ok(value)
+    }
+
+    pub(crate) fn var_int(&mut self, name: &'static str) -> DResult<u32> {
+        let offset = self.offset;
+        let mut value: u32 = 0;
+        for idx in 0..4 {
+            let byte = self.take(1)?[0];
+            value |= (u32::from(byte) & 0x7F) << (7 * idx);
+            if byte & 0x80 == 0 {
+                self.add(offset, name, format!("{value}"));
+                return Ok(value);
+            }
+        }
+        self.fail(offset, format!("invalid variable byte integer in {name}"))
+    }
+
+    /// Read a string with its two bytes length.
+    pub(crate) fn string(&mut self, name: &'static str) -> DResult<&'a str> {
+        let offset = self.offset;
+        let data = self.length_prefixed()?;
+        match simdutf8::basic::from_utf8(data) {
+            Ok(value) => {
+                self.add(offset, name, format!("{value:?}"));
+                Ok(value)
+            }
+            Err(_) => {
+                self.add(offset, name, preview(data));
+                self.fail(offset + 2, format!("{name} is not valid UTF-8"))
+            }
+        }
+    }
+
+    /// Read binary data with its two bytes length.
+    pub(crate) fn binary(&mut self, name: &'static str) -> DResult<&'a [u8]> {
+        let offset = self.offset;
+        let data = self.length_prefixed()?;
+        self.add(offset, name, preview(data));
+        Ok(data)
+    }
+
+    fn length_prefixed(&mut self) -> DResult<&'a [u8]> {
+        let data = self.take(2)?;
+        let len = u16::from_be_bytes([data[0], data[1]]) as usize;
+        self.take(len)
+    }
+
+    /// Read the rest of the enclosing length.
+    pub(crate) fn payload(&mut self, name: &'static str) -> DResult<&'a [u8]> {
+        let offset = self.offset;
+        let data = self.take(self.end.saturating_sub(offset))?;
+        self.add(offset, name, preview(data));
+        Ok(data)
+    }
+}
+
+/// Length, leading bytes in hex and the text if it is printable.
+fn preview(data: &[u8]) -> String {
+    let shown = &data[..data.len().min(PREVIEW_LEN)];
+    let mut value = format!("{} bytes", data.len());
+    if data.is_empty() {
+        return value;
+    }
+    value.push_str(" [");
+    for (idx, byte) in shown.iter().enumerate() {
+        if idx > 0 {
+            value.push(' ');
+        }
+        value.push_str(&format!("{byte:02x}"));
+    }
+    if shown.len() < data.len() {
+        value.push_str(" ..");
+    }
+    value.push(']');
+    if shown.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+        let text = core::str::from_utf8(shown).expect("ascii");
+        let ellipsis = if shown.len() < data.len() { ".." } else { "" };
+        value.push_str(&format!(" {text:?}{ellipsis}"));
+    }
+    value
+}
+
+/// Packet type names indexed by the high nibble of the first byte.
+const PACKET_NAMES: [&str; 16] = [
+    "Reserved",
+    "CONNECT",
+    "CONNACK",
+    "PUBLISH",
+    "PUBACK",
+    "PUBREC",
+    "PUBREL",
+    "PUBCOMP",
+    "SUBSCRIBE",
+    "SUBACK",
+    "UNSUBSCRIBE",
+    "UNSUBACK",
+    "PINGREQ",
+    "PINGRESP",
+    "DISCONNECT",
+    "AUTH",
+];
+
+/// Dissect the packets in `buf`. The fixed header is dissected here, then
+/// `body` is called with the first byte to dissect the rest of the packet.
+/// `max_type` is the highest valid packet type.
+pub(crate) fn dissect_packets(
+    buf: &[u8],
+    max_type: u8,
+    body: impl Fn(&mut Dissector<'_>, u8) -> DResult<()>,
+) -> Dissection {
+    let mut d = Dissector::new(buf);
+    while d.offset < buf.len() {
+        let first = buf[d.offset];
+        let typ = first >> 4;
+        let name = PACKET_NAMES[typ as usize];
+        let result = d.group("Packet", name.into(), |d| {
+            let remaining_len = d.group("Fixed header", String::new(), |d| {
+                fixed_header_byte(d, max_type)?;
+                d.var_int("Remaining length")
+            })?;
+            d.limit(remaining_len as usize, "remaining length", |d| {
+                body(d, first)?;
+                if d.has_more() {
+                    let message = format!(
+                        "{} unexpected bytes at the end of the packet",
+                        d.end - d.offset
+                    );
+                    return d.fail(d.offset, message);
+                }
+                Ok(())
+            })
+        });
+        if result.is_err() {
+            break;
+        }
+    }
+    Dissection {
+        packets: d.packets,
+        error: d.error,
+    }
+}
+
+/// Packet type and flags, both in the first byte.
+fn fixed_header_byte(d: &mut Dissector<'_>, max_type: u8) -> DResult<()> {
+    let offset = d.offset;
+    let byte = d.take(1)?[0];
+    let typ = byte >> 4;
+    let name = PACKET_NAMES[typ as usize];
+    d.add(offset, "Packet type", format!("{typ} ({name})"));
+    if !(1..=max_type).contains(&typ) {
+        return d.fail(offset, format!("invalid packet type {typ}"));
+    }
+    let flags = byte & 0x0F;
+    if typ == 3 {
+        let bits = [("DUP", 0b1000), ("QoS", 0b0110), ("Retain", 0b0001)];
+        d.add_flags(offset, "Flags", flags, &bits);
+        if flags & 0b0110 == 0b0110 {
+            return d.fail(offset, "invalid QoS 3".into());
+        }
+    } else {
+        d.add_flags(offset, "Flags", flags, &[]);
+        let expected = if matches!(typ, 6 | 8 | 10) { 0b0010 } else { 0 };
+        if flags != expected {
+            let message = format!("invalid flags {flags:#03x} of {name}, expected {expected:#03x}");
+            return d.fail(offset, message);
+        }
+    }
+    Ok(())
+}
+
+/// Protocol name and level of a CONNECT packet.
+pub(crate) fn dissect_protocol(d: &mut Dissector<'_>) -> DResult<Protocol> {
+    let name = d.string("Protocol name")?;
+    let mut protocol = None;
+    d.u8_with("Protocol level", |level| {
+        let value = Protocol::new(name.as_bytes(), level).map_err(|err| err.to_string())?;
+        protocol = Some(value);
+        Ok(Some(value.to_string()))
+    })?;
+    Ok(protocol.expect("protocol checked"))
+}
+
+/// Connect flags of a CONNECT packet.
+pub(crate) fn dissect_connect_flags(d: &mut Dissector<'_>) -> DResult<u8> {
+    let bits = [
+        ("Username", 0x80),
+        ("Password", 0x40),
+        ("Will retain", 0x20),
+        ("Will QoS", 0x18),
+        ("Will", 0x04),
+        ("Clean session", 0x02),
+        ("Reserved", 0x01),
+    ];
+    let flags = d.flags("Connect flags", &bits, 0x01)?;
+    if flags & 0x18 == 0x18 {
+        return d.fail(d.offset - 1, "invalid will QoS 3".into());
+    }
+    Ok(flags)
+}
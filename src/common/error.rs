@@ -1,3 +1,4 @@
+#[cfg(feature = "alloc")]
 use alloc::sync::Arc;
 
 use thiserror::Error;
@@ -36,6 +37,7 @@ pub enum Error {
     InvalidConnectReturnCode(u8),
 
     /// Invalid protocol.
+    #[cfg(feature = "alloc")]
     #[error("invalid protocol: {0}, {1}")]
     InvalidProtocol(Arc<str>, u8),
 
@@ -51,11 +53,17 @@ pub enum Error {
     #[error("invalid variable byte integer")]
     InvalidVarByteInt,
 
+    /// Variable byte integer not encoded in the minimum number of bytes.
+    #[error("non-minimal variable byte integer: `{0}` in `{1}` bytes")]
+    NonMinimalVarByteInt(u32, usize),
+
     /// Invalid Topic Name
+    #[cfg(feature = "alloc")]
     #[error("invalid topic name: {0}")]
     InvalidTopicName(Arc<str>),
 
     /// Invalid topic filter
+    #[cfg(feature = "alloc")]
     #[error("invalid topic filter: {0}")]
     InvalidTopicFilter(Arc<str>),
 
@@ -63,6 +71,19 @@ pub enum Error {
     #[error("invalid string")]
     InvalidString,
 
+    /// A string holds a code point the [`StringPolicy`](crate::StringPolicy)
+    /// rejects.
+    #[error("disallowed character in string: U+{0:04X}")]
+    DisallowedChar(u32),
+
+    /// A streamed payload is shorter than announced.
+    #[error("payload length mismatch, expected `{0}` bytes")]
+    PayloadLengthMismatch(usize),
+
+    /// The memory budget can not grant the requested bytes.
+    #[error("memory budget exceeded: `{0}` bytes")]
+    MemoryBudgetExceeded(usize),
+
     /// Catch-all error when converting from `io::Error`.
     #[error("io error: {0:?}")]
     IoError(IoErrorKind),
@@ -70,6 +91,7 @@ pub enum Error {
 
 /// IoErrorKind for both std and no-std environments
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum IoErrorKind {
     UnexpectedEof,
     InvalidData,
@@ -97,6 +119,54 @@ impl<E: embedded_io::Error> From<E> for Error {
     }
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for Error {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Error::InvalidRemainingLength => defmt::write!(f, "InvalidRemainingLength"),
+            Error::EmptySubscription => defmt::write!(f, "EmptySubscription"),
+            Error::ZeroPid => defmt::write!(f, "ZeroPid"),
+            Error::InvalidQos(qos) => defmt::write!(f, "InvalidQos({=u8})", qos),
+            Error::InvalidConnectFlags(flags) => {
+                defmt::write!(f, "InvalidConnectFlags({=u8:#010b})", flags)
+            }
+            Error::InvalidConnackFlags(flags) => {
+                defmt::write!(f, "InvalidConnackFlags({=u8:#010b})", flags)
+            }
+            Error::InvalidConnectReturnCode(code) => {
+                defmt::write!(f, "InvalidConnectReturnCode({=u8})", code)
+            }
+            #[cfg(feature = "alloc")]
+            Error::InvalidProtocol(name, level) => {
+                defmt::write!(f, "InvalidProtocol({=str}, {=u8})", name, level)
+            }
+            Error::UnexpectedProtocol(protocol) => {
+                defmt::write!(f, "UnexpectedProtocol({})", protocol)
+            }
+            Error::InvalidHeader => defmt::write!(f, "InvalidHeader"),
+            Error::InvalidVarByteInt => defmt::write!(f, "InvalidVarByteInt"),
+            Error::NonMinimalVarByteInt(value, bytes) => {
+                defmt::write!(f, "NonMinimalVarByteInt({=u32}, {=usize})", value, bytes)
+            }
+            #[cfg(feature = "alloc")]
+            Error::InvalidTopicName(name) => defmt::write!(f, "InvalidTopicName({=str})", name),
+            #[cfg(feature = "alloc")]
+            Error::InvalidTopicFilter(filter) => {
+                defmt::write!(f, "InvalidTopicFilter({=str})", filter)
+            }
+            Error::InvalidString => defmt::write!(f, "InvalidString"),
+            Error::DisallowedChar(code) => defmt::write!(f, "DisallowedChar({=u32:#x})", code),
+            Error::PayloadLengthMismatch(len) => {
+                defmt::write!(f, "PayloadLengthMismatch({=usize})", len)
+            }
+            Error::MemoryBudgetExceeded(size) => {
+                defmt::write!(f, "MemoryBudgetExceeded({=usize})", size)
+            }
+            Error::IoError(kind) => defmt::write!(f, "IoError({})", kind),
+        }
+    }
+}
+
 pub trait ToError {
     fn to_error(self) -> Error;
 }
@@ -0,0 +1,36 @@
+use core::time::Duration;
+
+use super::ReadStrategy;
+
+/// Hook receiving decoder and buffer events.
+///
+/// Every method defaults to a no-op, implement the ones to export. The hook
+/// is called inline on the decode path, so it should only bump counters.
+#[allow(unused_variables)]
+pub trait Metrics: Send + Sync {
+    /// A packet was decoded, `packet_type` is the control packet type (the
+    /// high 4 bits of the fixed header).
+    fn packet_decoded(&self, packet_type: u8, total_len: usize) {}
+
+    /// Bytes were read from the reader.
+    fn bytes_read(&self, len: usize) {}
+
+    /// The buffer chose how to read the body of a packet.
+    fn read_strategy(&self, strategy: ReadStrategy, packet_size: usize) {}
+
+    /// A pooled buffer was handed out, `hit` is false when it was newly
+    /// allocated.
+    fn buffer_acquired(&self, size: usize, hit: bool) {}
+
+    /// A packet body was read in chunks into an owned `Vec`.
+    fn owned_fallback(&self, len: usize) {}
+
+    /// Time spent waiting for a buffer, only measured with the `std` feature.
+    fn acquire_waited(&self, waited: Duration) {}
+}
+
+/// [`Metrics`] ignoring every event.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopMetrics;
+
+impl Metrics for NoopMetrics {}
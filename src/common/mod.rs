@@ -1,10 +1,20 @@
+#[cfg(feature = "alloc")]
+mod batch;
 mod buffer;
+#[cfg(any(feature = "v3", feature = "v5"))]
+mod dissect;
 mod error;
+mod metrics;
+mod options;
+#[cfg(feature = "alloc")]
 mod poll;
+mod stream;
+#[cfg(feature = "alloc")]
+mod topic;
 mod types;
 mod utils;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod tests;
 
 pub(crate) mod future {
@@ -31,26 +41,73 @@ pub(crate) mod io {
 
 pub(crate) use future::block_on;
 pub(crate) use io::{AsyncRead, AsyncWrite, SyncRead, SyncWrite};
+#[cfg(any(feature = "v3", feature = "v5", feature = "sn"))]
+pub(crate) use utils::packet_from;
+#[cfg(any(feature = "v3", feature = "heapless"))]
+pub(crate) use utils::write_string;
+#[cfg(any(feature = "alloc", feature = "heapless"))]
 pub(crate) use utils::{
-    decode_var_int, decode_var_int_async, encode_packet, packet_from, read_bytes, read_bytes_async,
-    read_raw_bytes, read_string, read_string_async, read_u16, read_u16_async, read_u32,
-    read_u32_async, read_u8, read_u8_async, write_bytes, write_string, write_u16, write_u32,
-    write_u8, write_var_int,
+    check_topic_filter, is_invalid_topic_name, read_bytes, read_raw_bytes, read_u16, read_u8,
 };
+#[cfg(feature = "alloc")]
+pub(crate) use utils::{check_var_int, read_bytes_async, read_u16_async};
+pub(crate) use utils::{
+    decode_var_int, decode_var_int_async, read_u8_async, write_bytes, write_u16, write_u8,
+};
+#[cfg(any(feature = "v3", feature = "v5"))]
+pub(crate) use utils::{encode_packet, read_raw_header, read_string_async};
+#[cfg(any(feature = "v3", feature = "v5", feature = "heapless"))]
+pub(crate) use utils::{read_string, write_var_int};
+#[cfg(feature = "v5")]
+pub(crate) use utils::{read_u32, read_u32_async, write_u32};
 
+#[cfg(feature = "alloc")]
+pub(crate) use batch::decode_slice;
+#[cfg(feature = "alloc")]
+pub use batch::GenericDecodeBatch;
+#[cfg(feature = "alloc")]
 pub use buffer::{
-    Buffer, BufferHandle, BufferResult, MockBuffer, MockBufferConfig, MockBufferHandle,
-    ReadStrategy,
+    BudgetedBuffer, BudgetedHandle, BufferPool, BufferPoolConfig, BufferPoolStats, BufferResult,
+    BytesBuffer, BytesBufferHandle, ConnectionBudget, MemoryBudget, MemoryBudgetConfig,
+    MemoryBudgetStats, MemoryPermit, MemoryReserve, PooledBuffer,
+};
+pub use buffer::{Buffer, BufferHandle, ReadStrategy};
+#[cfg(feature = "alloc")]
+#[allow(deprecated)]
+pub use buffer::{MockBuffer, MockBufferConfig, MockBufferHandle};
+#[cfg(any(feature = "v3", feature = "v5"))]
+pub(crate) use dissect::{
+    dissect_connect_flags, dissect_packets, dissect_protocol, DResult, Dissector,
 };
+#[cfg(any(feature = "v3", feature = "v5"))]
+pub use dissect::{DissectError, Dissection, Field};
 pub use error::{Error, IoErrorKind, ToError};
+pub use metrics::{Metrics, NoopMetrics};
+pub use options::{DecodeOptions, Leniencies, Leniency, LenientReport, StringPolicy};
+#[cfg(feature = "alloc")]
+pub(crate) use poll::new_header;
+#[cfg(feature = "alloc")]
 pub use poll::{GenericPollPacket, GenericPollPacketState, PollHeader};
-pub use types::{
-    ClientId, Encodable, Pid, Protocol, QoS, QosPid, TopicFilter, TopicName, Username, VarBytes,
+#[cfg(any(feature = "v3", feature = "v5"))]
+pub(crate) use stream::copy_payload;
+#[cfg(not(feature = "tokio"))]
+pub use stream::PayloadReadError;
+pub use stream::PayloadReader;
+#[cfg(feature = "alloc")]
+pub use topic::{TopicFilter, TopicName};
+#[cfg(feature = "alloc")]
+pub use types::{ClientId, Username, VarBytes};
+pub use types::{Encodable, Pid, Protocol, QoS, QosPid};
+pub use utils::{
+    decode_raw_header, decode_raw_header_async, header_len, remaining_len, total_len, var_int_len,
 };
-pub use utils::{decode_raw_header_async, header_len, remaining_len, total_len, var_int_len};
 
 #[cfg(all(test, feature = "dhat-heap"))]
 pub use tests::MemorySummary;
+#[cfg(all(test, feature = "std", any(feature = "v3", feature = "v5")))]
+pub(crate) use tests::{raw_packets, Conformance, Statement, Status};
+#[cfg(all(test, any(feature = "v3", feature = "v5")))]
+pub(crate) use tests::{trickle_reader, TrickleReader};
 
 /// Character used to separate each level within a topic tree and provide a hierarchical structure.
 pub const LEVEL_SEP: char = '/';
@@ -0,0 +1,319 @@
+use core::hash::{Hash, Hasher};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "alloc")]
+use alloc::sync::Arc;
+
+use super::Error;
+
+/// Options of the packet decoders.
+///
+/// The `*_with` decode functions and the `with_options` builders of the poll
+/// and batch decoders take them, the functions without the suffix use the
+/// default options. Build them from the default with the `with_*` methods,
+/// e.g. `DecodeOptions::default().with_string_policy(StringPolicy::Strict)`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct DecodeOptions {
+    /// Reject variable byte integers not encoded in the minimum number of
+    /// bytes: the remaining length, and for v5.0 the property lengths and the
+    /// subscription identifiers. `None` uses the protocol default, strict for
+    /// v5.0 [MQTT-1.5.5-1] and lenient for v3.1.1.
+    pub strict_var_int: Option<bool>,
+    /// Characters accepted in the client identifier, username, topics, user
+    /// properties and the other string properties.
+    pub string_policy: StringPolicy,
+    /// Violations accepted from non-conforming peers, all rejected by default.
+    pub lenient: Leniencies,
+    /// Counts the violations accepted by [`lenient`](Self::lenient).
+    #[cfg(feature = "alloc")]
+    pub report: Option<Arc<LenientReport>>,
+}
+
+impl DecodeOptions {
+    /// Set [`strict_var_int`](Self::strict_var_int), overriding the protocol
+    /// default.
+    pub fn with_strict_var_int(mut self, strict: bool) -> Self {
+        self.strict_var_int = Some(strict);
+        self
+    }
+
+    /// Set [`string_policy`](Self::string_policy).
+    pub fn with_string_policy(mut self, policy: StringPolicy) -> Self {
+        self.string_policy = policy;
+        self
+    }
+
+    /// Set [`lenient`](Self::lenient).
+    pub fn with_lenient(mut self, lenient: Leniencies) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Set [`report`](Self::report).
+    #[cfg(feature = "alloc")]
+    pub fn with_report(mut self, report: Arc<LenientReport>) -> Self {
+        self.report = Some(report);
+        self
+    }
+
+    /// Whether non-minimal variable byte integers are rejected, `default` is
+    /// the protocol default.
+    pub fn strict_var_int(&self, default: bool) -> bool {
+        self.strict_var_int.unwrap_or(default)
+    }
+
+    /// Check a decoded string against the string policy.
+    #[inline]
+    pub fn check_string(&self, value: &str) -> Result<(), Error> {
+        self.string_policy.check(value)
+    }
+
+    /// Whether a `leniency` violation is accepted, an accepted violation is
+    /// counted in the report.
+    pub fn tolerate(&self, leniency: Leniency) -> bool {
+        let allowed = self.lenient.allows(leniency);
+        #[cfg(feature = "alloc")]
+        if let (true, Some(report)) = (allowed, &self.report) {
+            report.record(leniency);
+        }
+        allowed
+    }
+}
+
+/// A violation of the specification the decoders can accept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Leniency {
+    /// The reserved bit 0 of the CONNECT flags is set [MQTT-3.1.2-3].
+    ConnectReservedFlag = 0,
+    /// The reserved flags of the fixed header are wrong, e.g. a SUBSCRIBE
+    /// without `0010` [MQTT-2.1.3-1]. The flags are ignored.
+    ReservedHeaderFlags = 1,
+    /// A v5.0 property other than User Property appears more than once, the
+    /// first value is kept. Extra Subscription Identifiers in a PUBLISH are
+    /// allowed [3.3.2.3.8], they are dropped but not reported.
+    DuplicatedProperty = 2,
+}
+
+impl Leniency {
+    pub const ALL: [Leniency; 3] = [
+        Leniency::ConnectReservedFlag,
+        Leniency::ReservedHeaderFlags,
+        Leniency::DuplicatedProperty,
+    ];
+}
+
+/// The leniencies enabled in [`DecodeOptions`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Leniencies {
+    pub connect_reserved_flag: bool,
+    pub reserved_header_flags: bool,
+    pub duplicated_property: bool,
+}
+
+impl Leniencies {
+    /// Every leniency enabled.
+    pub fn all() -> Self {
+        Leniencies {
+            connect_reserved_flag: true,
+            reserved_header_flags: true,
+            duplicated_property: true,
+        }
+    }
+
+    pub fn allows(&self, leniency: Leniency) -> bool {
+        match leniency {
+            Leniency::ConnectReservedFlag => self.connect_reserved_flag,
+            Leniency::ReservedHeaderFlags => self.reserved_header_flags,
+            Leniency::DuplicatedProperty => self.duplicated_property,
+        }
+    }
+}
+
+/// Counters of the violations accepted by the leniencies.
+///
+/// Shared with the options through an `Arc`, keep a clone to read the
+/// counts. Reports compare and hash by identity.
+#[derive(Debug, Default)]
+pub struct LenientReport {
+    counts: [AtomicUsize; 3],
+}
+
+impl LenientReport {
+    pub const fn new() -> Self {
+        LenientReport {
+            counts: [
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+            ],
+        }
+    }
+
+    /// Count one accepted `leniency` violation.
+    pub fn record(&self, leniency: Leniency) {
+        self.counts[leniency as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The accepted `leniency` violations so far.
+    pub fn count(&self, leniency: Leniency) -> usize {
+        self.counts[leniency as usize].load(Ordering::Relaxed)
+    }
+
+    /// All the accepted violations so far.
+    pub fn total(&self) -> usize {
+        Leniency::ALL
+            .iter()
+            .map(|leniency| self.count(*leniency))
+            .sum()
+    }
+
+    pub fn reset(&self) {
+        for count in &self.counts {
+            count.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+impl PartialEq for LenientReport {
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::eq(self, other)
+    }
+}
+
+impl Eq for LenientReport {}
+
+impl Hash for LenientReport {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        core::ptr::hash(self, state);
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for LenientReport {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "LenientReport {{ connect_reserved_flag: {=usize}, reserved_header_flags: {=usize}, duplicated_property: {=usize} }}",
+            self.count(Leniency::ConnectReservedFlag),
+            self.count(Leniency::ReservedHeaderFlags),
+            self.count(Leniency::DuplicatedProperty),
+        )
+    }
+}
+
+/// Which code points of well-formed UTF-8 strings are accepted [MQTT-1.5.4].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StringPolicy {
+    /// Any well-formed UTF-8 string without the surrogates.
+    #[default]
+    Lenient,
+    /// Also reject the null character U+0000 [MQTT-1.5.4-2], the control
+    /// characters U+0001 to U+001F and U+007F to U+009F, and the Unicode
+    /// non-characters, the receiver MAY treat those as malformed.
+    Strict,
+}
+
+impl StringPolicy {
+    /// Check the code points of `value` are allowed by the policy.
+    #[inline]
+    pub fn check(self, value: &str) -> Result<(), Error> {
+        match self {
+            StringPolicy::Lenient => Ok(()),
+            StringPolicy::Strict => match value.chars().find(|c| is_disallowed(*c)) {
+                Some(c) => Err(Error::DisallowedChar(c as u32)),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+#[inline]
+fn is_disallowed(c: char) -> bool {
+    let code = c as u32;
+    code <= 0x1F
+        || (0x7F..=0x9F).contains(&code)
+        || (0xFDD0..=0xFDEF).contains(&code)
+        || code & 0xFFFE == 0xFFFE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tolerate() {
+        let mut options = DecodeOptions::default();
+        for leniency in Leniency::ALL {
+            assert!(!options.tolerate(leniency));
+        }
+        options.lenient.duplicated_property = true;
+        assert!(!options.tolerate(Leniency::ConnectReservedFlag));
+        assert!(options.tolerate(Leniency::DuplicatedProperty));
+        options.lenient = Leniencies::all();
+        for leniency in Leniency::ALL {
+            assert!(options.tolerate(leniency));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_tolerate_report() {
+        let report = Arc::new(LenientReport::new());
+        let mut options = DecodeOptions::default().with_report(report.clone());
+        for leniency in Leniency::ALL {
+            assert!(!options.tolerate(leniency));
+        }
+        assert_eq!(report.total(), 0);
+
+        options.lenient.duplicated_property = true;
+        assert!(!options.tolerate(Leniency::ConnectReservedFlag));
+        assert!(options.tolerate(Leniency::DuplicatedProperty));
+        assert!(options.tolerate(Leniency::DuplicatedProperty));
+        assert_eq!(report.count(Leniency::DuplicatedProperty), 2);
+        assert_eq!(report.count(Leniency::ConnectReservedFlag), 0);
+
+        options.lenient = Leniencies::all();
+        for leniency in Leniency::ALL {
+            assert!(options.tolerate(leniency));
+        }
+        assert_eq!(report.total(), 5);
+        report.reset();
+        assert_eq!(report.total(), 0);
+
+        options.report = None;
+        assert!(options.tolerate(Leniency::ReservedHeaderFlags));
+        assert_eq!(report.total(), 0);
+    }
+
+    #[test]
+    fn test_string_policy() {
+        for (value, disallowed) in [
+            ("abc/é/世界/🦀", None),
+            ("\u{feff}bom", None),
+            ("\u{a0}\u{fffd}", None),
+            ("a\0b", Some(0x00)),
+            ("a\u{1}", Some(0x01)),
+            ("\u{1f}", Some(0x1f)),
+            ("del\u{7f}", Some(0x7f)),
+            ("\u{9f}", Some(0x9f)),
+            ("\u{fdd0}", Some(0xfdd0)),
+            ("\u{fdef}", Some(0xfdef)),
+            ("\u{fffe}", Some(0xfffe)),
+            ("\u{ffff}", Some(0xffff)),
+            ("\u{1fffe}", Some(0x1fffe)),
+            ("\u{10ffff}", Some(0x10ffff)),
+        ] {
+            assert_eq!(StringPolicy::Lenient.check(value), Ok(()));
+            let expected = match disallowed {
+                Some(code) => Err(Error::DisallowedChar(code)),
+                None => Ok(()),
+            };
+            assert_eq!(StringPolicy::Strict.check(value), expected, "{value:?}");
+        }
+    }
+}
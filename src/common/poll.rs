@@ -4,11 +4,14 @@ use core::task::{Context, Poll};
 
 use alloc::vec::Vec;
 
+use bytes::Bytes;
+
 #[cfg(feature = "tokio")]
 use tokio::io::AsyncReadExt;
 
 use super::{
-    AsyncRead, Buffer, BufferHandle, BufferResult, Error, IoErrorKind, ReadStrategy, ToError,
+    check_var_int, AsyncRead, Buffer, BufferHandle, BufferResult, DecodeOptions, Error,
+    IoErrorKind, MemoryPermit, Metrics, NoopMetrics, ReadStrategy, ToError,
 };
 
 impl<H: BufferHandle> BufferResult<H> {
@@ -29,18 +32,48 @@ impl<H: BufferHandle> BufferResult<H> {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Convert into [`Bytes`], only copy when the handle can not be frozen.
+    pub fn into_bytes(self) -> Bytes {
+        match self {
+            BufferResult::Pooled(mut handle) => handle
+                .freeze()
+                .unwrap_or_else(|| Bytes::copy_from_slice(handle.as_slice(handle.len()))),
+            BufferResult::Owned(vec) => Bytes::from(vec),
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
-pub enum GenericPollPacketState<H> {
+/// Progress of the packet being read, kept outside of [`GenericPollPacket`]
+/// so a dropped or re-created future continues where the last one stopped.
+///
+/// Every byte read from the reader, the acquired buffer handle and the
+/// memory permit are stored here before the next await point.
+#[derive(Debug)]
+pub enum GenericPollPacketState<H, BH> {
     Header {
         control_byte: Option<u8>,
         var_idx: u8,
         var_int: u32,
     },
+    /// The header is read, waiting for the buffer or the memory permit.
     Body {
         header: H,
+        strategy: ReadStrategy,
+        /// When the wait began, kept across polls for
+        /// [`Metrics::acquire_waited`].
+        #[cfg(feature = "std")]
+        since: std::time::Instant,
+    },
+    /// Reading the body into an acquired buffer handle.
+    Buffer { header: H, handle: BH, idx: usize },
+    /// Reading the body in chunks into an owned `Vec`.
+    Chunk {
+        header: H,
+        chunk_size: usize,
+        acc: Vec<u8>,
         idx: usize,
+        permit: Option<MemoryPermit>,
     },
 }
 
@@ -53,28 +86,112 @@ pub trait PollHeader {
     where
         Self: Sized;
 
+    /// Build the header with the decode `options`, defaults to
+    /// [`new_with`](Self::new_with)
+    fn new_with_options(
+        hd: u8,
+        remaining_len: u32,
+        total_len: u32,
+        _options: &DecodeOptions,
+    ) -> Result<Self, Self::Error>
+    where
+        Self: Sized,
+    {
+        Self::new_with(hd, remaining_len, total_len)
+    }
+
+    /// Whether non-minimal variable byte integers are rejected with `options`
+    fn strict_var_int(&self, options: &DecodeOptions) -> bool {
+        options.strict_var_int(false)
+    }
+
     /// Packet without body is empty packet
     fn build_empty_packet(&self) -> Option<Self::Packet>;
 
     /// Synchronous decode method for direct buffer access
     fn decode_buffer(self, buf: &[u8], offset: &mut usize) -> Result<Self::Packet, Self::Error>;
 
+    /// Decode from shared bytes, payloads may be sliced from `buf` without copy
+    fn decode_bytes(self, buf: &Bytes, offset: &mut usize) -> Result<Self::Packet, Self::Error>
+    where
+        Self: Sized,
+    {
+        self.decode_buffer(buf, offset)
+    }
+
     /// Async decode method for stream-based processing
     async fn decode_stream<T: AsyncRead + Unpin>(
         self,
         reader: &mut T,
     ) -> Result<Self::Packet, Self::Error>;
 
+    /// [`decode_buffer`](Self::decode_buffer) with the decode `options`
+    fn decode_buffer_with(
+        self,
+        buf: &[u8],
+        offset: &mut usize,
+        _options: &DecodeOptions,
+    ) -> Result<Self::Packet, Self::Error>
+    where
+        Self: Sized,
+    {
+        self.decode_buffer(buf, offset)
+    }
+
+    /// [`decode_bytes`](Self::decode_bytes) with the decode `options`
+    fn decode_bytes_with(
+        self,
+        buf: &Bytes,
+        offset: &mut usize,
+        _options: &DecodeOptions,
+    ) -> Result<Self::Packet, Self::Error>
+    where
+        Self: Sized,
+    {
+        self.decode_bytes(buf, offset)
+    }
+
+    /// [`decode_stream`](Self::decode_stream) with the decode `options`
+    async fn decode_stream_with<T: AsyncRead + Unpin>(
+        self,
+        reader: &mut T,
+        _options: &DecodeOptions,
+    ) -> Result<Self::Packet, Self::Error>
+    where
+        Self: Sized,
+    {
+        self.decode_stream(reader).await
+    }
+
     /// The remaining length of the packet to decode
     fn remaining_len(&self) -> usize;
 
     /// The total length of the packet, including the header and the body
     fn total_len(&self) -> usize;
 
+    /// The control packet type, the high 4 bits of the fixed header
+    fn packet_type(&self) -> u8;
+
     fn is_eof_error(err: &Self::Error) -> bool;
 }
 
-impl<H> Default for GenericPollPacketState<H> {
+/// Build the header with `options`, the remaining length was decoded from
+/// `var_bytes` bytes.
+pub(crate) fn new_header<H: PollHeader>(
+    hd: u8,
+    remaining_len: u32,
+    var_bytes: usize,
+    total_len: u32,
+    options: &DecodeOptions,
+) -> Result<H, H::Error> {
+    let header = H::new_with_options(hd, remaining_len, total_len, options)?;
+    if header.strict_var_int(options) {
+        check_var_int(remaining_len, var_bytes)?;
+    }
+    Ok(header)
+}
+
+impl<H, BH> Default for GenericPollPacketState<H, BH> {
     fn default() -> Self {
         GenericPollPacketState::Header {
             control_byte: None,
@@ -88,9 +205,11 @@ pub struct GenericPollPacket<'a, T, H, B>
 where
     B: Buffer,
 {
-    state: &'a mut GenericPollPacketState<H>,
+    state: &'a mut GenericPollPacketState<H, B::Handle>,
     reader: &'a mut T,
     buffer: &'a mut B,
+    metrics: &'a dyn Metrics,
+    options: DecodeOptions,
 }
 
 impl<'a, T, H, B> GenericPollPacket<'a, T, H, B>
@@ -98,7 +217,7 @@ where
     B: Buffer,
 {
     pub fn new(
-        state: &'a mut GenericPollPacketState<H>,
+        state: &'a mut GenericPollPacketState<H, B::Handle>,
         reader: &'a mut T,
         buffer: &'a mut B,
     ) -> Self {
@@ -106,12 +225,28 @@ where
             state,
             reader,
             buffer,
+            metrics: &NoopMetrics,
+            options: DecodeOptions::default(),
         }
     }
+
+    /// Report the decoder and buffer events to `metrics`.
+    pub fn with_metrics(mut self, metrics: &'a dyn Metrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Decode the packets with `options`.
+    pub fn with_options(mut self, options: DecodeOptions) -> Self {
+        self.options = options;
+        self
+    }
 }
 
 async fn poll_packet_header<T, H>(
     reader: &mut T,
+    metrics: &dyn Metrics,
+    options: &DecodeOptions,
     control_byte: &mut Option<u8>,
     var_idx: &mut u8,
     var_int: &mut u32,
@@ -127,6 +262,7 @@ where
             .read_exact(&mut buf)
             .await
             .map_err(ToError::to_error)?;
+        metrics.bytes_read(1);
         *control_byte = Some(buf[0]);
     }
 
@@ -136,6 +272,7 @@ where
             .read_exact(&mut buf)
             .await
             .map_err(ToError::to_error)?;
+        metrics.bytes_read(1);
 
         let byte = buf[0];
         *var_int |= (u32::from(byte) & 0x7F) << (7 * u32::from(*var_idx));
@@ -147,20 +284,22 @@ where
             return Err(Error::InvalidVarByteInt.into());
         }
     }
-    let header = H::new_with(
+    new_header(
         control_byte.unwrap(),
         *var_int,
+        *var_idx as usize + 1,
         1 + 1 + (*var_idx as u32) + *var_int,
-    )?;
-    Ok(header)
+        options,
+    )
 }
 
 async fn poll_packet_buffer_body<T, H, B>(
     reader: &mut T,
+    metrics: &dyn Metrics,
     header: H,
     idx: &mut usize,
-    mut buf: B,
-) -> Result<BufferResult<B>, H::Error>
+    buf: &mut B,
+) -> Result<(), H::Error>
 where
     T: AsyncRead + Unpin,
     H: PollHeader + Copy + Unpin,
@@ -184,69 +323,53 @@ where
             unsafe { core::slice::from_raw_parts_mut(slice.as_mut_ptr() as *mut u8, slice.len()) };
         match reader.read(slice).await {
             Ok(0) => return Err(Error::IoError(IoErrorKind::UnexpectedEof).into()),
-            Ok(n) => *idx += n,
+            Ok(n) => {
+                metrics.bytes_read(n);
+                *idx += n;
+            }
             Err(e) => return Err(Error::from(e).into()),
         }
     }
-
-    // Return BufferHandle directly - zero copy!
-    Ok(BufferResult::Pooled(buf))
+    Ok(())
 }
 
-async fn poll_packet_chunk_body<T, H, B>(
+async fn poll_packet_chunk_body<T, H>(
     reader: &mut T,
+    metrics: &dyn Metrics,
     header: H,
     chunk_size: usize,
     idx: &mut usize,
-    acc: &mut Vec<u8>,
-) -> Result<BufferResult<B::Handle>, H::Error>
+    acc: &mut [u8],
+) -> Result<(), H::Error>
 where
     T: AsyncRead + Unpin,
     H: PollHeader + Copy + Unpin,
     H::Error: From<Error>,
-    B: Buffer,
 {
     let remaining_len = header.remaining_len();
 
-    // Ensure accumulated_data has enough capacity
-    if acc.capacity() < remaining_len {
-        acc.reserve(remaining_len - acc.capacity());
-    }
-
-    // Read in chunks until we have all data
+    // Read in chunks until we have all data, `idx` is updated after every
+    // read so the next poll continues from there
     while *idx < remaining_len {
-        let bytes_to_read = chunk_size.min(remaining_len - *idx);
-
-        // Resize accumulated_data to accommodate new chunk
-        let old_len = acc.len();
-        acc.resize(old_len + bytes_to_read, 0);
-
-        // Get slice for this chunk
-        let chunk_slice = &mut acc[old_len..old_len + bytes_to_read];
-
-        // Read the chunk
-        let mut chunk_bytes_read = 0;
-        while chunk_bytes_read < bytes_to_read {
-            match reader.read(&mut chunk_slice[chunk_bytes_read..]).await {
-                Ok(0) => return Err(Error::IoError(IoErrorKind::UnexpectedEof).into()),
-                Ok(n) => {
-                    chunk_bytes_read += n;
-                    *idx += n;
-                }
-                Err(e) => return Err(Error::from(e).into()),
+        let bytes_to_read = chunk_size.max(1).min(remaining_len - *idx);
+        match reader.read(&mut acc[*idx..*idx + bytes_to_read]).await {
+            Ok(0) => return Err(Error::IoError(IoErrorKind::UnexpectedEof).into()),
+            Ok(n) => {
+                metrics.bytes_read(n);
+                *idx += n;
             }
+            Err(e) => return Err(Error::from(e).into()),
         }
     }
-
-    // Return owned Vec directly - avoid copy by taking ownership
-    let result = core::mem::take(acc); // Move out of acc, leaving empty vec
-    Ok(BufferResult::Owned(result))
+    Ok(())
 }
 
 async fn poll_packet<T, H, B>(
-    state: &mut GenericPollPacketState<H>,
+    state: &mut GenericPollPacketState<H, B::Handle>,
     reader: &mut T,
     buffer: &mut B,
+    metrics: &dyn Metrics,
+    options: &DecodeOptions,
 ) -> Result<(usize, BufferResult<B::Handle>, H::Packet), H::Error>
 where
     T: AsyncRead + Unpin,
@@ -262,63 +385,138 @@ where
                 var_int,
             } => {
                 #[allow(clippy::useless_conversion)]
-                let header: H = poll_packet_header(reader, control_byte, var_idx, var_int)
-                    .await
-                    .map_err(Into::<H::Error>::into)?;
+                let header: H =
+                    poll_packet_header(reader, metrics, options, control_byte, var_idx, var_int)
+                        .await
+                        .map_err(Into::<H::Error>::into)?;
                 if let Some(empty_packet) = header.build_empty_packet() {
+                    *state = GenericPollPacketState::default();
+                    metrics.packet_decoded(header.packet_type(), 2);
                     return Ok((2, BufferResult::Owned(Vec::new()), empty_packet));
                 }
                 if header.remaining_len() == 0 {
+                    *state = GenericPollPacketState::default();
                     return Err(Error::InvalidRemainingLength.into());
                 }
-                *state = GenericPollPacketState::Body { header, idx: 0 };
-            }
-            GenericPollPacketState::Body { header, idx } => {
-                let header_copy = *header;
-                let total_len = header_copy.total_len();
+                let total_len = header.total_len();
                 let strategy = buffer.read_strategy(total_len);
-
-                let buffer_result = match strategy {
+                metrics.read_strategy(strategy, total_len);
+                *state = GenericPollPacketState::Body {
+                    header,
+                    strategy,
+                    #[cfg(feature = "std")]
+                    since: std::time::Instant::now(),
+                };
+            }
+            GenericPollPacketState::Body {
+                header,
+                strategy,
+                #[cfg(feature = "std")]
+                since,
+            } => {
+                let header = *header;
+                let remaining_len = header.remaining_len();
+                // Nothing is read from the reader here, a cancelled acquire
+                // or reserve is started again on the next poll
+                *state = match *strategy {
                     ReadStrategy::Buffer => {
-                        // Acquire buffer and read with zero copy
-                        let remaining_len = header_copy.remaining_len();
-                        let buf = buffer.acquire(remaining_len).await?;
-                        let result = poll_packet_buffer_body(reader, header_copy, idx, buf).await?;
-                        result
+                        // Acquire buffer and read with zero copy, the
+                        // handle is charged for the memory itself
+                        let handle = buffer.acquire(remaining_len).await?;
+                        #[cfg(feature = "std")]
+                        metrics.acquire_waited(since.elapsed());
+                        GenericPollPacketState::Buffer {
+                            header,
+                            handle,
+                            idx: 0,
+                        }
                     }
                     ReadStrategy::Chunk(chunk_size) => {
-                        // Use chunk reading with owned Vec
-                        let mut acc = Vec::new();
-                        poll_packet_chunk_body::<T, H, B>(
-                            reader,
-                            header_copy,
+                        // Use chunk reading with owned Vec, charged to the
+                        // buffer until the packet is decoded
+                        let permit = buffer.reserve(remaining_len).await?;
+                        GenericPollPacketState::Chunk {
+                            header,
                             chunk_size,
-                            idx,
-                            &mut acc,
-                        )
-                        .await?
+                            acc: alloc::vec![0; remaining_len],
+                            idx: 0,
+                            permit,
+                        }
                     }
                 };
-
-                // Decode packet from buffer data
-                let mut offset = 0;
-                let packet = header_copy
-                    .decode_buffer(buffer_result.as_slice(), &mut offset)
-                    .map_err(|e| {
-                        if H::is_eof_error(&e) {
-                            Error::InvalidRemainingLength.into()
-                        } else {
-                            e
-                        }
-                    })?;
-
-                *state = GenericPollPacketState::default(); // Reset
-                return Ok((total_len, buffer_result, packet));
+            }
+            GenericPollPacketState::Buffer {
+                header,
+                handle,
+                idx,
+            } => {
+                poll_packet_buffer_body(reader, metrics, *header, idx, handle).await?;
+                let GenericPollPacketState::Buffer { header, handle, .. } = core::mem::take(state)
+                else {
+                    unreachable!()
+                };
+                return decode_body(header, BufferResult::Pooled(handle), metrics, options);
+            }
+            GenericPollPacketState::Chunk {
+                header,
+                chunk_size,
+                acc,
+                idx,
+                ..
+            } => {
+                poll_packet_chunk_body(reader, metrics, *header, *chunk_size, idx, acc).await?;
+                let GenericPollPacketState::Chunk {
+                    header,
+                    acc,
+                    permit,
+                    ..
+                } = core::mem::take(state)
+                else {
+                    unreachable!()
+                };
+                metrics.owned_fallback(acc.len());
+                let result = decode_body(header, BufferResult::Owned(acc), metrics, options);
+                drop(permit);
+                return result;
             }
         }
     }
 }
 
+/// Decode the packet from a fully read body.
+fn decode_body<H, BH>(
+    header: H,
+    mut buffer_result: BufferResult<BH>,
+    metrics: &dyn Metrics,
+    options: &DecodeOptions,
+) -> Result<(usize, BufferResult<BH>, H::Packet), H::Error>
+where
+    H: PollHeader + Copy,
+    H::Error: From<Error>,
+    BH: BufferHandle,
+{
+    let shared = match &mut buffer_result {
+        BufferResult::Pooled(handle) => handle.freeze(),
+        BufferResult::Owned(_) => None,
+    };
+    let mut offset = 0;
+    let decoded = match &shared {
+        Some(bytes) => header.decode_bytes_with(bytes, &mut offset, options),
+        None => header.decode_buffer_with(buffer_result.as_slice(), &mut offset, options),
+    };
+    let packet = decoded.map_err(|e| {
+        if H::is_eof_error(&e) {
+            Error::InvalidRemainingLength.into()
+        } else {
+            e
+        }
+    })?;
+
+    let total_len = header.total_len();
+    metrics.packet_decoded(header.packet_type(), total_len);
+    Ok((total_len, buffer_result, packet))
+}
+
 #[cfg(feature = "tokio")]
 impl<'a, T, H, B> Future for GenericPollPacket<'a, T, H, B>
 where
@@ -337,9 +535,11 @@ where
             ref mut state,
             ref mut reader,
             ref mut buffer,
+            metrics,
+            options,
         } = self.get_mut();
 
-        let future = poll_packet(state, reader, buffer);
+        let future = poll_packet(state, reader, buffer, *metrics, options);
         futures_lite::pin!(future);
         future.as_mut().poll(cx)
     }
@@ -361,9 +561,11 @@ where
             ref mut state,
             ref mut reader,
             ref mut buffer,
+            metrics,
+            options,
         } = self.get_mut();
 
-        let future = poll_packet(state, reader, buffer);
+        let future = poll_packet(state, reader, buffer, *metrics, options);
         futures_lite::pin!(future);
         future.as_mut().poll(cx)
     }
@@ -0,0 +1,158 @@
+#[cfg(feature = "tokio")]
+use tokio::io::AsyncReadExt;
+#[cfg(all(feature = "tokio", any(feature = "v3", feature = "v5")))]
+use tokio::io::AsyncWriteExt;
+
+#[cfg(any(feature = "v3", feature = "v5"))]
+use super::AsyncWrite;
+use super::{AsyncRead, Error, IoErrorKind};
+
+/// Reader over the payload of a streamed PUBLISH packet.
+///
+/// It reads at most the payload length from the underlying reader. The
+/// payload must be read to the end (or [`skip`](Self::skip)ped) before the
+/// next packet is decoded from the same reader.
+#[derive(Debug)]
+pub struct PayloadReader<'a, T> {
+    reader: &'a mut T,
+    remaining: usize,
+}
+
+impl<'a, T> PayloadReader<'a, T> {
+    pub fn new(reader: &'a mut T, len: usize) -> Self {
+        PayloadReader {
+            reader,
+            remaining: len,
+        }
+    }
+
+    /// The payload bytes not read yet.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+}
+
+impl<T: AsyncRead + Unpin> PayloadReader<'_, T> {
+    /// Read the next chunk of the payload into `buf`, return `Ok(0)` at the
+    /// end of the payload.
+    pub async fn read_chunk(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let max = buf.len().min(self.remaining);
+        if max == 0 {
+            return Ok(0);
+        }
+        match self.reader.read(&mut buf[..max]).await {
+            Ok(0) => Err(Error::IoError(IoErrorKind::UnexpectedEof)),
+            Ok(n) => {
+                self.remaining -= n;
+                Ok(n)
+            }
+            Err(err) => Err(Error::from(err)),
+        }
+    }
+
+    /// Discard the rest of the payload.
+    pub async fn skip(&mut self) -> Result<(), Error> {
+        let mut buf = [0u8; 256];
+        while self.read_chunk(&mut buf).await? > 0 {}
+        Ok(())
+    }
+}
+
+/// Copy exactly `len` payload bytes from `reader` to `writer`, fail if
+/// `reader` ends before. Bytes after the payload are left in `reader`.
+#[cfg(any(feature = "v3", feature = "v5"))]
+pub(crate) async fn copy_payload<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    len: usize,
+) -> Result<(), Error>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = [0u8; 1024];
+    let mut copied = 0;
+    while copied < len {
+        let max = buf.len().min(len - copied);
+        let n = reader.read(&mut buf[..max]).await.map_err(Error::from)?;
+        if n == 0 {
+            return Err(Error::PayloadLengthMismatch(len));
+        }
+        writer.write_all(&buf[..n]).await.map_err(Error::from)?;
+        copied += n;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+impl<T: tokio::io::AsyncRead + Unpin> tokio::io::AsyncRead for PayloadReader<'_, T> {
+    fn poll_read(
+        self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> core::task::Poll<std::io::Result<()>> {
+        use core::task::{ready, Poll};
+
+        let this = self.get_mut();
+        let max = buf.remaining().min(this.remaining);
+        if max == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        let mut limited = buf.take(max);
+        ready!(core::pin::Pin::new(&mut *this.reader).poll_read(cx, &mut limited))?;
+        let n = limited.filled().len();
+        if n == 0 {
+            return Poll::Ready(Err(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+        // SAFETY: the bytes were filled by the inner reader.
+        unsafe { buf.assume_init(n) };
+        buf.advance(n);
+        this.remaining -= n;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Error of reading a [`PayloadReader`] through `embedded-io-async`.
+#[cfg(not(feature = "tokio"))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PayloadReadError<E> {
+    /// The underlying reader ended before the payload.
+    UnexpectedEof,
+    /// Error of the underlying reader.
+    Read(E),
+}
+
+#[cfg(not(feature = "tokio"))]
+impl<E: embedded_io::Error> embedded_io::Error for PayloadReadError<E> {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            // `embedded-io` has no kind for the end of the stream
+            PayloadReadError::UnexpectedEof => embedded_io::ErrorKind::Other,
+            PayloadReadError::Read(err) => err.kind(),
+        }
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+impl<T: embedded_io_async::Read> embedded_io::ErrorType for PayloadReader<'_, T> {
+    type Error = PayloadReadError<T::Error>;
+}
+
+#[cfg(not(feature = "tokio"))]
+impl<T: embedded_io_async::Read> embedded_io_async::Read for PayloadReader<'_, T> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let max = buf.len().min(self.remaining);
+        if max == 0 {
+            return Ok(0);
+        }
+        match self.reader.read(&mut buf[..max]).await {
+            Ok(0) => Err(PayloadReadError::UnexpectedEof),
+            Ok(n) => {
+                self.remaining -= n;
+                Ok(n)
+            }
+            Err(err) => Err(PayloadReadError::Read(err)),
+        }
+    }
+}
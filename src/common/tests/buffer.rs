@@ -6,6 +6,7 @@ use tokio::sync::Mutex;
 use crate::*;
 
 #[tokio::test]
+#[allow(deprecated)]
 async fn test_mock_buffer_basic() {
     let mut buffer = MockBuffer::new(MockBufferConfig::default());
 
@@ -16,6 +17,7 @@ async fn test_mock_buffer_basic() {
 }
 
 #[tokio::test]
+#[allow(deprecated)]
 async fn test_buffer_pool_reuse() {
     let config = MockBufferConfig {
         buffer_size: 1024,
@@ -37,6 +39,7 @@ async fn test_buffer_pool_reuse() {
 }
 
 #[tokio::test]
+#[allow(deprecated)]
 async fn test_buffer_oversized_request() {
     let config = MockBufferConfig {
         buffer_size: 1024,
@@ -52,6 +55,7 @@ async fn test_buffer_oversized_request() {
 }
 
 #[tokio::test]
+#[allow(deprecated)]
 async fn test_buffer_handle_operations() {
     let mut buffer = MockBuffer::new(MockBufferConfig::default());
     let mut handle = buffer.acquire(1024).await.unwrap();
@@ -75,6 +79,7 @@ async fn test_buffer_handle_operations() {
 }
 
 #[tokio::test]
+#[allow(deprecated)]
 async fn test_concurrent_buffer_access() {
     let buffer = Arc::new(Mutex::new(MockBuffer::new(MockBufferConfig {
         buffer_size: 1024,
@@ -104,6 +109,7 @@ async fn test_concurrent_buffer_access() {
 }
 
 #[tokio::test]
+#[allow(deprecated)]
 async fn test_read_strategy() {
     let buffer = MockBuffer::new(MockBufferConfig {
         buffer_size: 1024,
@@ -119,6 +125,7 @@ async fn test_read_strategy() {
 }
 
 #[tokio::test]
+#[allow(deprecated)]
 async fn test_buffer_config_validation() {
     let configs = [
         MockBufferConfig {
@@ -152,3 +159,424 @@ async fn test_buffer_config_validation() {
         assert_eq!(large_strategy, ReadStrategy::Chunk(config.chunk_size));
     }
 }
+
+fn small_pool(max_memory: usize) -> BufferPool {
+    BufferPool::new(BufferPoolConfig {
+        size_classes: alloc::vec![1024, 64, 256],
+        max_memory,
+        chunk_size: 512,
+    })
+}
+
+#[tokio::test]
+async fn test_buffer_pool_size_classes() {
+    let mut pool = small_pool(4096);
+    assert_eq!(pool.config().size_classes, alloc::vec![64, 256, 1024]);
+
+    let handle = pool.acquire(10).await.unwrap();
+    assert_eq!(handle.capacity(), 64);
+    let handle2 = pool.acquire(65).await.unwrap();
+    assert_eq!(handle2.capacity(), 256);
+    assert_eq!(
+        pool.stats(),
+        BufferPoolStats {
+            in_use: 2,
+            idle: 0,
+            allocated_bytes: 320,
+            waiting: 0,
+        }
+    );
+
+    pool.release(handle).await.unwrap();
+    drop(handle2);
+    let stats = pool.stats();
+    assert_eq!(
+        (stats.in_use, stats.idle, stats.allocated_bytes),
+        (0, 2, 320)
+    );
+
+    // Reuse the idle buffer instead of allocating
+    let _handle = pool.acquire(60).await.unwrap();
+    let stats = pool.stats();
+    assert_eq!(
+        (stats.in_use, stats.idle, stats.allocated_bytes),
+        (1, 1, 320)
+    );
+}
+
+#[tokio::test]
+async fn test_buffer_pool_oversized() {
+    let mut pool = small_pool(512);
+    assert_eq!(pool.read_strategy(1024), ReadStrategy::Buffer);
+    assert_eq!(pool.read_strategy(1025), ReadStrategy::Chunk(512));
+    // Larger than the biggest size class
+    assert!(matches!(
+        pool.acquire(2048).await,
+        Err(Error::IoError(IoErrorKind::OutOfMemory))
+    ));
+    // Size class larger than the memory bound
+    assert!(matches!(
+        pool.try_acquire(1024),
+        Err(Error::IoError(IoErrorKind::OutOfMemory))
+    ));
+}
+
+#[tokio::test]
+async fn test_buffer_pool_evict_idle() {
+    let mut pool = small_pool(1024);
+    let small = pool.acquire(64).await.unwrap();
+    drop(small);
+    assert_eq!(pool.stats().allocated_bytes, 64);
+
+    // The idle 64 bytes buffer is freed to fit the bound
+    let large = pool.acquire(1024).await.unwrap();
+    let stats = pool.stats();
+    assert_eq!(
+        (stats.in_use, stats.idle, stats.allocated_bytes),
+        (1, 0, 1024)
+    );
+    assert!(pool.try_acquire(64).unwrap().is_none());
+    drop(large);
+}
+
+#[tokio::test]
+async fn test_buffer_pool_wait_for_release() {
+    let pool = small_pool(512);
+    let handle1 = pool.try_acquire(256).unwrap().unwrap();
+    let handle2 = pool.try_acquire(256).unwrap().unwrap();
+    assert!(pool.try_acquire(256).unwrap().is_none());
+
+    let mut waiter_pool = pool.clone();
+    let waiter = tokio::spawn(async move {
+        let mut handle = waiter_pool.acquire(200).await.unwrap();
+        let (slice, _) = handle.as_mut_slice();
+        slice[0].write(7);
+        handle.set_len(1);
+        handle.as_slice(handle.len()).to_vec()
+    });
+    while pool.stats().waiting == 0 {
+        tokio::task::yield_now().await;
+    }
+    assert!(!waiter.is_finished());
+
+    drop(handle1);
+    assert_eq!(waiter.await.unwrap(), alloc::vec![7]);
+    drop(handle2);
+    let stats = pool.stats();
+    assert_eq!(
+        (stats.in_use, stats.idle, stats.allocated_bytes),
+        (0, 2, 512)
+    );
+    assert_eq!(stats.waiting, 0);
+}
+
+#[tokio::test]
+async fn test_buffer_pool_concurrent() {
+    let pool = small_pool(1024);
+    let mut tasks = Vec::new();
+    for i in 0..16 {
+        let mut pool = pool.clone();
+        tasks.push(tokio::spawn(async move {
+            for _ in 0..20 {
+                let mut handle = pool.acquire(100 + i * 10).await.unwrap();
+                handle.set_len(i);
+                tokio::task::yield_now().await;
+                assert_eq!(handle.len(), i);
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+    let stats = pool.stats();
+    assert_eq!(stats.in_use, 0);
+    assert!(stats.allocated_bytes <= 1024);
+}
+
+#[tokio::test]
+async fn test_bytes_buffer() {
+    let mut buffer = BytesBuffer::new(64);
+    assert_eq!(buffer.read_strategy(1 << 20), ReadStrategy::Buffer);
+
+    let mut handle = buffer.acquire(4).await.unwrap();
+    assert_eq!(handle.capacity(), 4);
+    let (slice, capacity) = handle.as_mut_slice();
+    assert_eq!(capacity, 4);
+    for (idx, byte) in slice.iter_mut().enumerate() {
+        byte.write(idx as u8);
+    }
+    handle.set_len(3);
+
+    let bytes = handle.freeze().unwrap();
+    assert_eq!(bytes.as_ref(), &[0, 1, 2]);
+    // Still readable after freeze, sharing the same memory
+    assert_eq!(handle.as_slice(handle.len()).as_ptr(), bytes.as_ptr());
+    assert_eq!(handle.into_bytes(), bytes);
+
+    let handle = buffer.acquire(100).await.unwrap();
+    assert_eq!(handle.capacity(), 100);
+    let result = BufferResult::Pooled(handle);
+    assert_eq!(result.into_bytes().len(), 0);
+    let result = BufferResult::<BytesBufferHandle>::Owned(alloc::vec![1, 2]);
+    assert_eq!(result.into_bytes().as_ref(), &[1, 2]);
+}
+
+fn budget(limit: usize, connection_limit: usize) -> MemoryBudget {
+    MemoryBudget::new(MemoryBudgetConfig {
+        limit,
+        connection_limit,
+        wait: true,
+    })
+}
+
+#[tokio::test]
+async fn test_memory_budget_accounting() {
+    let budget = budget(100, 80);
+    let conn1 = budget.connection();
+    let conn2 = budget.connection();
+
+    let permit1 = conn1.reserve(30).await.unwrap();
+    let permit2 = conn2.reserve(50).await.unwrap();
+    assert_eq!(permit1.size(), 30);
+    assert_eq!((conn1.in_use(), conn2.in_use()), (30, 50));
+    assert_eq!(
+        budget.stats(),
+        MemoryBudgetStats {
+            in_use: 80,
+            peak: 80,
+            connections: 2,
+            waiting: 0,
+            throttled: 0,
+            rejected: 0,
+        }
+    );
+
+    drop(permit1);
+    drop(permit2);
+    drop(conn2);
+    let stats = budget.stats();
+    assert_eq!((stats.in_use, stats.peak, stats.connections), (0, 80, 1));
+
+    // Larger than the limits
+    assert_eq!(
+        conn1.reserve(81).await.unwrap_err(),
+        Error::MemoryBudgetExceeded(81)
+    );
+    assert_eq!(budget.stats().rejected, 1);
+}
+
+#[tokio::test]
+async fn test_memory_budget_no_wait() {
+    let budget = MemoryBudget::new(MemoryBudgetConfig {
+        limit: 100,
+        connection_limit: 100,
+        wait: false,
+    });
+    let conn = budget.connection();
+    let _permit = conn.reserve(60).await.unwrap();
+    assert_eq!(
+        conn.reserve(60).await.unwrap_err(),
+        Error::MemoryBudgetExceeded(60)
+    );
+    assert_eq!(budget.stats().rejected, 1);
+}
+
+#[tokio::test]
+async fn test_memory_budget_fifo() {
+    let budget = budget(100, 100);
+    let holder = budget.connection();
+    let permit = holder.reserve(100).await.unwrap();
+
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let mut tasks = Vec::new();
+    for (idx, size) in [(0, 80), (1, 10), (2, 10)] {
+        let conn = budget.connection();
+        let order = Arc::clone(&order);
+        tasks.push(tokio::spawn(async move {
+            let permit = conn.reserve(size).await.unwrap();
+            order.lock().await.push(idx);
+            tokio::task::yield_now().await;
+            drop(permit);
+            conn.throttled()
+        }));
+        // Queue in a known order
+        while budget.stats().waiting <= idx {
+            tokio::task::yield_now().await;
+        }
+    }
+    assert_eq!(budget.stats().throttled, 3);
+
+    drop(permit);
+    for task in tasks {
+        assert_eq!(task.await.unwrap(), 1);
+    }
+    // Small requests do not overtake the big one queued before them
+    assert_eq!(*order.lock().await, alloc::vec![0, 1, 2]);
+    assert_eq!(budget.stats().in_use, 0);
+}
+
+#[tokio::test]
+async fn test_memory_budget_connection_limit() {
+    let budget = budget(100, 40);
+    let greedy = budget.connection();
+    let permit = greedy.reserve(40).await.unwrap();
+
+    let waiter = tokio::spawn(async move { greedy.reserve(10).await.map(|p| p.size()) });
+    while budget.stats().waiting == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    // Other connections are not blocked by the greedy one
+    let other = budget.connection();
+    let _other_permit = other.reserve(40).await.unwrap();
+    assert!(!waiter.is_finished());
+
+    drop(permit);
+    assert_eq!(waiter.await.unwrap(), Ok(10));
+}
+
+#[tokio::test]
+async fn test_memory_budget_connection_limit_queued() {
+    let budget = budget(100, 60);
+    let holder = budget.connection();
+    let permits = [
+        holder.reserve(50).await.unwrap(),
+        budget.connection().reserve(50).await.unwrap(),
+    ];
+
+    // Both fit the connection limit when queued, not together
+    let greedy = Arc::new(budget.connection());
+    let mut tasks = Vec::new();
+    for _ in 0..2 {
+        let greedy = Arc::clone(&greedy);
+        tasks.push(tokio::spawn(async move { greedy.reserve(50).await }));
+        while budget.stats().waiting < tasks.len() {
+            tokio::task::yield_now().await;
+        }
+    }
+    let other = budget.connection();
+    let other_reserve = tokio::spawn(async move { other.reserve(50).await.map(|p| p.size()) });
+    while budget.stats().waiting < 3 {
+        tokio::task::yield_now().await;
+    }
+
+    drop(permits);
+    // The second greedy reservation gives way to the other connection
+    assert_eq!(other_reserve.await.unwrap(), Ok(50));
+    assert_eq!(greedy.in_use(), 50);
+    assert_eq!(budget.stats().waiting, 1);
+    for task in tasks {
+        let permit = task.await.unwrap().unwrap();
+        assert_eq!(greedy.in_use(), 50);
+        drop(permit);
+    }
+    assert_eq!(budget.stats().in_use, 0);
+}
+
+#[tokio::test]
+async fn test_memory_budget_connection_limit_queued_last() {
+    let budget = budget(100, 60);
+    let permits = [
+        budget.connection().reserve(50).await.unwrap(),
+        budget.connection().reserve(50).await.unwrap(),
+    ];
+
+    let greedy = Arc::new(budget.connection());
+    let mut tasks = Vec::new();
+    for _ in 0..2 {
+        let greedy = Arc::clone(&greedy);
+        tasks.push(tokio::spawn(async move { greedy.reserve(50).await }));
+        while budget.stats().waiting < tasks.len() {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    drop(permits);
+    // The second reservation leaves the queue for the connection limit with
+    // nobody queued behind it, and is granted once the first is given back
+    for task in tasks {
+        let permit = task.await.unwrap().unwrap();
+        assert_eq!(greedy.in_use(), 50);
+        drop(permit);
+    }
+    let stats = budget.stats();
+    assert_eq!((stats.in_use, stats.waiting), (0, 0));
+}
+
+#[tokio::test]
+async fn test_memory_budget_cancel() {
+    let budget = budget(100, 100);
+    let conn1 = budget.connection();
+    let permit = conn1.reserve(100).await.unwrap();
+
+    let conn2 = budget.connection();
+    {
+        let reserve = conn2.reserve(50);
+        futures_lite::pin!(reserve);
+        assert!(futures_lite::future::poll_once(&mut reserve)
+            .await
+            .is_none());
+        assert_eq!(budget.stats().waiting, 1);
+    }
+    // The cancelled reservation left the queue
+    assert_eq!(budget.stats().waiting, 0);
+    drop(permit);
+    let _permit = conn2.reserve(50).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_budgeted_buffer() {
+    let budget = budget(1024, 1024);
+    let mut buffer = BudgetedBuffer::new(BytesBuffer::default(), budget.connection());
+    assert_eq!(buffer.read_strategy(4096), ReadStrategy::Buffer);
+
+    let handle = buffer.acquire(600).await.unwrap();
+    assert_eq!(handle.capacity(), 600);
+    assert_eq!(handle.permit().size(), 600);
+    assert_eq!(buffer.budget().in_use(), 600);
+    assert!(matches!(
+        buffer.reserve(2048).await,
+        Err(Error::MemoryBudgetExceeded(2048))
+    ));
+    let permit = buffer.reserve(400).await.unwrap().unwrap();
+    assert_eq!(budget.stats().in_use, 1000);
+
+    buffer.release(handle).await.unwrap();
+    drop(permit);
+    assert_eq!(budget.stats().in_use, 0);
+    // Buffers without a budget have nothing to reserve
+    assert!(BytesBuffer::default().reserve(10).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_budgeted_buffer_keeps_permit() {
+    let budget = budget(300, 300);
+    let pool = small_pool(256);
+    let held = pool.try_acquire(256).unwrap().unwrap();
+    let mut buffer = BudgetedBuffer::new(pool.clone(), budget.connection());
+    {
+        let acquire = buffer.acquire(200);
+        futures_lite::pin!(acquire);
+        assert!(futures_lite::future::poll_once(&mut acquire)
+            .await
+            .is_none());
+    }
+    // The permit is kept while the pool is exhausted
+    assert_eq!(budget.stats().in_use, 200);
+
+    let other = budget.connection();
+    let other_reserve = tokio::spawn(async move { other.reserve(200).await.map(|p| p.size()) });
+    while budget.stats().waiting == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    drop(held);
+    // The next acquire takes the kept permit instead of queueing again
+    let handle = buffer.acquire(200).await.unwrap();
+    assert_eq!(handle.permit().size(), 200);
+    assert_eq!(buffer.budget().throttled(), 0);
+    assert!(!other_reserve.is_finished());
+
+    buffer.release(handle).await.unwrap();
+    assert_eq!(other_reserve.await.unwrap(), Ok(200));
+}
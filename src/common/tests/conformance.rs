@@ -0,0 +1,207 @@
+use alloc::format;
+use alloc::string::String;
+use std::sync::Mutex;
+
+/// Environment variable regenerating `CONFORMANCE.md` from the tables.
+const UPDATE_ENV: &str = "UPDATE_CONFORMANCE";
+const REPORT_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/CONFORMANCE.md");
+
+/// Serializes the report updates of the v3 and v5 suites.
+static REPORT_LOCK: Mutex<()> = Mutex::new(());
+
+/// How the codec handles a normative statement of the specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// The decoder rejects violating packets.
+    Enforced,
+    /// Violating packets are decoded and encoded, the application checks the
+    /// statement if it cares.
+    Application,
+    /// The codec rejects packets the statement allows, only the v5.0 codec
+    /// has deviations.
+    #[cfg(feature = "v5")]
+    Deviation,
+}
+
+impl Status {
+    /// Status observed by a test: whether the codec enforced the statement.
+    pub fn observed(enforced: bool) -> Status {
+        if enforced {
+            Status::Enforced
+        } else {
+            Status::Application
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Status::Enforced => "Enforced",
+            Status::Application => "Application",
+            #[cfg(feature = "v5")]
+            Status::Deviation => "Deviation",
+        }
+    }
+}
+
+/// A normative statement relevant to the encoding of packets.
+#[derive(Debug)]
+pub struct Statement {
+    /// The `[MQTT-x.x.x-y]` identifier, or the section number for the
+    /// unnumbered statements.
+    pub id: &'static str,
+    pub summary: &'static str,
+    pub status: Status,
+}
+
+/// The statements of one version of the specification.
+#[derive(Debug)]
+pub struct Conformance {
+    pub title: &'static str,
+    pub statements: &'static [Statement],
+}
+
+impl Conformance {
+    /// Assert the table lists `observed` as the status of statement `id`.
+    pub fn check(&self, id: &str, observed: Status) {
+        let statement = self
+            .statements
+            .iter()
+            .find(|statement| statement.id == id)
+            .unwrap_or_else(|| panic!("{}: statement {id} is not in the table", self.title));
+        assert_eq!(
+            statement.status, observed,
+            "{}: status of {id} ({}) does not match the codec",
+            self.title, statement.summary
+        );
+    }
+
+    /// The markdown section of `CONFORMANCE.md` for this table.
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "## {}\n\n| Statement | Summary | Status |\n|---|---|---|\n",
+            self.title
+        );
+        for statement in self.statements {
+            out.push_str(&format!(
+                "| {} | {} | {} |\n",
+                statement.id,
+                statement.summary,
+                statement.status.as_str()
+            ));
+        }
+        out
+    }
+
+    /// Assert `CONFORMANCE.md` contains the rendered table, or replace the
+    /// section in it when `UPDATE_CONFORMANCE` is set.
+    pub fn check_report(&self) {
+        let _guard = REPORT_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let section = self.render();
+        let report = std::fs::read_to_string(REPORT_PATH).unwrap_or_default();
+        if report.contains(&section) {
+            return;
+        }
+        if std::env::var_os(UPDATE_ENV).is_none() {
+            panic!(
+                "CONFORMANCE.md is out of date, run the tests with {UPDATE_ENV}=1, expected section:\n{section}"
+            );
+        }
+        let heading = format!("## {}\n", self.title);
+        let updated = match report.find(&heading) {
+            Some(start) => {
+                let end = report[start + heading.len()..]
+                    .find("\n## ")
+                    .map(|pos| start + heading.len() + pos + 1)
+                    .unwrap_or(report.len());
+                let separator = if end == report.len() { "" } else { "\n" };
+                format!("{}{section}{separator}{}", &report[..start], &report[end..])
+            }
+            None => format!("{report}\n{section}"),
+        };
+        std::fs::write(REPORT_PATH, updated).expect("write CONFORMANCE.md");
+    }
+}
+
+/// Define the raw packet builders of the conformance tests of one version in
+/// the calling module. `$error` is the decode error of the version's
+/// `Packet`, `$level` its protocol level, packets before v5.0 have no
+/// properties.
+macro_rules! raw_packets {
+    ($error:ty, $level:expr) => {
+        fn decode(bytes: &[u8]) -> Result<Packet, $error> {
+            Packet::decode_from_slice(bytes).map(|packet| packet.expect("a complete packet").0)
+        }
+
+        fn rejected(bytes: &[u8]) -> bool {
+            decode(bytes).is_err()
+        }
+
+        fn encode(packet: Packet) -> alloc::vec::Vec<u8> {
+            packet.encode().unwrap().as_ref().to_vec()
+        }
+
+        /// A packet of one byte of remaining length.
+        fn packet(first: u8, body: &[u8]) -> alloc::vec::Vec<u8> {
+            let mut bytes = alloc::vec![first, body.len() as u8];
+            bytes.extend_from_slice(body);
+            bytes
+        }
+
+        /// A length prefixed string.
+        fn string(value: &[u8]) -> alloc::vec::Vec<u8> {
+            let mut bytes = (value.len() as u16).to_be_bytes().to_vec();
+            bytes.extend_from_slice(value);
+            bytes
+        }
+
+        /// Properties of one byte of property length, nothing before v5.0.
+        fn properties(properties: &[u8]) -> alloc::vec::Vec<u8> {
+            if $level < 5 {
+                assert!(properties.is_empty(), "no properties before v5.0");
+                return alloc::vec::Vec::new();
+            }
+            let mut bytes = alloc::vec![properties.len() as u8];
+            bytes.extend_from_slice(properties);
+            bytes
+        }
+
+        /// A CONNECT with `flags`, `props` and `payload` after them.
+        fn connect(flags: u8, props: &[u8], payload: &[u8]) -> alloc::vec::Vec<u8> {
+            let mut body = string(b"MQTT");
+            body.extend_from_slice(&[$level, flags, 0, 60]);
+            body.extend(properties(props));
+            body.extend_from_slice(payload);
+            packet(0x10, &body)
+        }
+
+        /// A QoS 0 PUBLISH.
+        fn publish(topic: &[u8], props: &[u8], payload: &[u8]) -> alloc::vec::Vec<u8> {
+            let mut body = string(topic);
+            body.extend(properties(props));
+            body.extend_from_slice(payload);
+            packet(0x30, &body)
+        }
+
+        #[allow(dead_code)]
+        fn subscribe_with(props: &[u8], filter: &[u8], options: u8) -> alloc::vec::Vec<u8> {
+            let mut body = alloc::vec![0, 1];
+            body.extend(properties(props));
+            body.extend(string(filter));
+            body.push(options);
+            packet(0x82, &body)
+        }
+
+        fn subscribe(filter: &[u8], options: u8) -> alloc::vec::Vec<u8> {
+            subscribe_with(&[], filter, options)
+        }
+
+        fn unsubscribe(filter: &[u8]) -> alloc::vec::Vec<u8> {
+            let mut body = alloc::vec![0, 1];
+            body.extend(properties(&[]));
+            body.extend(string(filter));
+            packet(0xa2, &body)
+        }
+    };
+}
+
+pub(crate) use raw_packets;
@@ -1,5 +1,16 @@
 mod buffer;
+#[cfg(all(feature = "std", any(feature = "v3", feature = "v5")))]
+mod conformance;
 mod poll;
+#[cfg(any(feature = "v3", feature = "v5"))]
+mod trickle;
+
+#[cfg(all(feature = "std", any(feature = "v3", feature = "v5")))]
+pub(crate) use conformance::raw_packets;
+#[cfg(all(feature = "std", any(feature = "v3", feature = "v5")))]
+pub use conformance::{Conformance, Statement, Status};
+#[cfg(any(feature = "v3", feature = "v5"))]
+pub use trickle::{trickle_reader, TrickleReader};
 
 #[cfg(feature = "dhat-heap")]
 #[global_allocator]
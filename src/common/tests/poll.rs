@@ -155,6 +155,10 @@ impl PollHeader for MockHeader {
         self.total_len as usize
     }
 
+    fn packet_type(&self) -> u8 {
+        self.packet_type >> 4
+    }
+
     fn is_eof_error(err: &Self::Error) -> bool {
         err.is_eof()
     }
@@ -174,7 +178,7 @@ async fn poll_stream_simulation() {
 
     println!("\n--- `common::poll` Stream Simulation ({NUM_ROUNDS} rounds) ---");
 
-    let buffer = MockBuffer::default();
+    let buffer = BufferPool::default();
 
     for i in 0..NUM_ROUNDS {
         println!("\n--- Round {} ---", i + 1);
@@ -190,7 +194,7 @@ async fn poll_stream_simulation() {
         #[cfg(not(feature = "tokio"))]
         let mut reader = embedded_io_adapters::tokio_1::FromTokio::new(reader_builder.build());
 
-        let mut state = GenericPollPacketState::<MockHeader>::default();
+        let mut state = GenericPollPacketState::<MockHeader, _>::default();
         let mut buffer = buffer.clone();
         let mut poll_packet = GenericPollPacket::new(&mut state, &mut reader, &mut buffer);
 
@@ -261,7 +265,7 @@ async fn poll_actor_model_simulation() {
     let simulation_start = std::time::Instant::now();
     let mut handles = Vec::with_capacity(NUM_TASKS);
 
-    let buffer = MockBuffer::default();
+    let buffer = BufferPool::default();
 
     for _ in 0..NUM_TASKS {
         let data = data.clone();
@@ -279,7 +283,7 @@ async fn poll_actor_model_simulation() {
             #[cfg(not(feature = "tokio"))]
             let mut reader = embedded_io_adapters::tokio_1::FromTokio::new(reader_builder.build());
 
-            let mut state = GenericPollPacketState::<MockHeader>::default();
+            let mut state = GenericPollPacketState::<MockHeader, _>::default();
             let mut buffer = buffer;
             let mut poll_packet = GenericPollPacket::new(&mut state, &mut reader, &mut buffer);
 
@@ -0,0 +1,58 @@
+use alloc::vec::Vec;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+/// Reader returning `Pending` before every byte, then a single byte.
+#[derive(Debug)]
+pub struct TrickleReader {
+    data: Vec<u8>,
+    pos: usize,
+    ready: bool,
+}
+
+impl TrickleReader {
+    pub fn new(data: &[u8]) -> Self {
+        TrickleReader {
+            data: data.to_vec(),
+            pos: 0,
+            ready: false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.data.len()
+    }
+}
+
+impl tokio::io::AsyncRead for TrickleReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<tokio::io::Result<()>> {
+        let this = self.get_mut();
+        if !this.ready {
+            this.ready = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        this.ready = false;
+        if this.pos < this.data.len() && buf.remaining() > 0 {
+            buf.put_slice(&this.data[this.pos..this.pos + 1]);
+            this.pos += 1;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// [`TrickleReader`] over `data` as the crate's [`AsyncRead`](crate::AsyncRead).
+#[cfg(feature = "tokio")]
+pub fn trickle_reader(data: &[u8]) -> TrickleReader {
+    TrickleReader::new(data)
+}
+
+/// [`TrickleReader`] over `data` as the crate's [`AsyncRead`](crate::AsyncRead).
+#[cfg(not(feature = "tokio"))]
+pub fn trickle_reader(data: &[u8]) -> embedded_io_adapters::tokio_1::FromTokio<TrickleReader> {
+    embedded_io_adapters::tokio_1::FromTokio::new(TrickleReader::new(data))
+}
@@ -0,0 +1,397 @@
+use core::cmp::{Eq, Ord, Ordering, PartialEq, PartialOrd};
+use core::convert::TryFrom;
+use core::hash::{Hash, Hasher};
+use core::ops::Deref;
+
+use alloc::sync::Arc;
+
+use super::{check_topic_filter, is_invalid_topic_name, Error, SHARED_PREFIX, SYS_PREFIX};
+
+/// Topic name.
+///
+/// See [MQTT 4.7]. The internal value is `Arc<str>`.
+///
+/// [MQTT 4.7]: http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718106
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TopicName(Arc<str>);
+
+impl TopicName {
+    /// Check if the topic name is invalid.
+    pub fn is_invalid(value: &str) -> bool {
+        is_invalid_topic_name(value)
+    }
+
+    pub fn is_shared(&self) -> bool {
+        self.0.starts_with(SHARED_PREFIX)
+    }
+    pub fn is_sys(&self) -> bool {
+        self.0.starts_with(SYS_PREFIX)
+    }
+}
+
+impl core::fmt::Display for TopicName {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<&str> for TopicName {
+    type Error = Error;
+    fn try_from(value: &str) -> Result<Self, Error> {
+        if TopicName::is_invalid(value) {
+            Err(Error::InvalidTopicName(value.into()))
+        } else {
+            Ok(TopicName(value.into()))
+        }
+    }
+}
+
+impl TryFrom<Arc<str>> for TopicName {
+    type Error = Error;
+    fn try_from(value: Arc<str>) -> Result<Self, Error> {
+        if TopicName::is_invalid(&value) {
+            Err(Error::InvalidTopicName(value))
+        } else {
+            Ok(TopicName(value))
+        }
+    }
+}
+
+impl Deref for TopicName {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for TopicName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for TopicName {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Arc::<str>::deserialize(deserializer)?;
+        TopicName::try_from(value).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Topic filter.
+///
+/// See [MQTT 4.7]. The internal value is `Arc<str>` and a cache value for
+/// where shared filter byte index started. The traits:
+/// `Hash`/`Ord`/`PartialOrd`/`Eq`/`PartialEq` are all manually implemented for
+/// only contains the string value.
+///
+/// [MQTT 4.7]: http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718106
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TopicFilter {
+    inner: Arc<str>,
+    shared_filter_sep: u16,
+}
+
+impl TopicFilter {
+    /// Check if the topic filter is invalid.
+    ///
+    ///   * The u16 returned is where the bytes index of '/' char before shared topic filter
+    pub fn is_invalid(value: &str) -> (bool, u16) {
+        check_topic_filter(value)
+    }
+
+    pub fn is_shared(&self) -> bool {
+        self.shared_filter_sep > 0
+    }
+    pub fn is_sys(&self) -> bool {
+        self.inner.starts_with(SYS_PREFIX)
+    }
+
+    pub fn shared_group_name(&self) -> Option<&str> {
+        if self.is_shared() {
+            let group_end = self.shared_filter_sep as usize;
+            Some(&self.inner[7..group_end])
+        } else {
+            None
+        }
+    }
+
+    pub fn shared_filter(&self) -> Option<&str> {
+        if self.is_shared() {
+            let filter_begin = self.shared_filter_sep as usize + 1;
+            Some(&self.inner[filter_begin..])
+        } else {
+            None
+        }
+    }
+
+    /// return (shared group name, shared filter)
+    pub fn shared_info(&self) -> Option<(&str, &str)> {
+        if self.is_shared() {
+            let group_end = self.shared_filter_sep as usize;
+            let filter_begin = self.shared_filter_sep as usize + 1;
+            Some((&self.inner[7..group_end], &self.inner[filter_begin..]))
+        } else {
+            None
+        }
+    }
+}
+
+impl Hash for TopicFilter {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state);
+    }
+}
+
+impl Ord for TopicFilter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.inner.cmp(&other.inner)
+    }
+}
+
+impl PartialOrd for TopicFilter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for TopicFilter {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner.eq(&other.inner)
+    }
+}
+
+impl Eq for TopicFilter {}
+
+impl core::fmt::Display for TopicFilter {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.inner)
+    }
+}
+
+impl TryFrom<&str> for TopicFilter {
+    type Error = Error;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let (is_invalid, shared_filter_sep) = TopicFilter::is_invalid(value);
+        if is_invalid {
+            Err(Error::InvalidTopicFilter(value.into()))
+        } else {
+            Ok(TopicFilter {
+                inner: value.into(),
+                shared_filter_sep,
+            })
+        }
+    }
+}
+
+impl TryFrom<Arc<str>> for TopicFilter {
+    type Error = Error;
+    fn try_from(value: Arc<str>) -> Result<Self, Self::Error> {
+        let (is_invalid, shared_filter_sep) = TopicFilter::is_invalid(&value);
+        if is_invalid {
+            Err(Error::InvalidTopicFilter(value))
+        } else {
+            Ok(TopicFilter {
+                inner: value,
+                shared_filter_sep,
+            })
+        }
+    }
+}
+
+impl Deref for TopicFilter {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.inner
+    }
+}
+
+/// Only the string value is serialized, the shared filter index is computed
+/// again when deserializing.
+#[cfg(feature = "serde")]
+impl serde::Serialize for TopicFilter {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.inner)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for TopicFilter {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Arc::<str>::deserialize(deserializer)?;
+        TopicFilter::try_from(value).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_topic_name() {
+        // valid topic name
+        assert!(!TopicName::is_invalid("/abc/def"));
+        assert!(!TopicName::is_invalid("abc/def"));
+        assert!(!TopicName::is_invalid("abc"));
+        assert!(!TopicName::is_invalid("/"));
+        assert!(!TopicName::is_invalid("//"));
+        // NOTE: Because v5.0 topic alias, we let up level to check empty topic name
+        assert!(!TopicName::is_invalid(""));
+        assert!(!TopicName::is_invalid(
+            "a".repeat(u16::MAX as usize).as_str()
+        ));
+
+        // invalid topic name
+        assert!(TopicName::is_invalid("#"));
+        assert!(TopicName::is_invalid("+"));
+        assert!(TopicName::is_invalid("/+"));
+        assert!(TopicName::is_invalid("/#"));
+        assert!(TopicName::is_invalid("abc/\0"));
+        assert!(TopicName::is_invalid("abc\0def"));
+        assert!(TopicName::is_invalid("abc#def"));
+        assert!(TopicName::is_invalid("abc+def"));
+        assert!(TopicName::is_invalid(
+            "a".repeat(u16::MAX as usize + 1).as_str()
+        ));
+    }
+
+    #[test]
+    fn test_valid_topic_filter() {
+        let string_65535 = "a".repeat(u16::MAX as usize);
+        let string_65536 = "a".repeat(u16::MAX as usize + 1);
+        for (is_invalid, topic) in [
+            // valid topic filter
+            (false, "abc/def"),
+            (false, "abc/+"),
+            (false, "abc/#"),
+            (false, "#"),
+            (false, "+"),
+            (false, "+/"),
+            (false, "+/+"),
+            (false, "///"),
+            (false, "//+/"),
+            (false, "//abc/"),
+            (false, "//+//#"),
+            (false, "/abc/+//#"),
+            (false, "+/abc/+"),
+            (false, string_65535.as_str()),
+            // invalid topic filter
+            (true, ""),
+            (true, "abc\0def"),
+            (true, "abc/\0def"),
+            (true, "++"),
+            (true, "++/"),
+            (true, "/++"),
+            (true, "abc/++"),
+            (true, "abc/++/"),
+            (true, "#/abc"),
+            (true, "/ab#"),
+            (true, "##"),
+            (true, "/abc/ab#"),
+            (true, "/+#"),
+            (true, "//+#"),
+            (true, "/abc/+#"),
+            (true, "xxx/abc/+#"),
+            (true, "xxx/a+bc/"),
+            (true, "x+x/abc/"),
+            (true, "x+/abc/"),
+            (true, "+x/abc/"),
+            (true, "+x"),
+            (true, "abc/+x"),
+            (true, "+/abc/++"),
+            (true, "+/a+c/+"),
+            (true, string_65536.as_str()),
+        ] {
+            assert_eq!((is_invalid, 0), TopicFilter::is_invalid(topic));
+        }
+    }
+
+    #[test]
+    fn test_valid_shared_topic_filter() {
+        for (is_invalid, topic) in [
+            // valid topic filter
+            (false, "abc/def"),
+            (false, "abc/+"),
+            (false, "abc/#"),
+            (false, "#"),
+            (false, "+"),
+            (false, "+/"),
+            (false, "+/+"),
+            (false, "///"),
+            (false, "//+/"),
+            (false, "//abc/"),
+            (false, "//+//#"),
+            (false, "/abc/+//#"),
+            (false, "+/abc/+"),
+            // invalid topic filter
+            (true, "abc\0def"),
+            (true, "abc/\0def"),
+            (true, "++"),
+            (true, "++/"),
+            (true, "/++"),
+            (true, "abc/++"),
+            (true, "abc/++/"),
+            (true, "#/abc"),
+            (true, "/ab#"),
+            (true, "##"),
+            (true, "/abc/ab#"),
+            (true, "/+#"),
+            (true, "//+#"),
+            (true, "/abc/+#"),
+            (true, "xxx/abc/+#"),
+            (true, "xxx/a+bc/"),
+            (true, "x+x/abc/"),
+            (true, "x+/abc/"),
+            (true, "+x/abc/"),
+            (true, "+x"),
+            (true, "abc/+x"),
+            (true, "+/abc/++"),
+            (true, "+/a+c/+"),
+        ] {
+            let result = if is_invalid { (true, 0) } else { (false, 10) };
+            assert_eq!(
+                result,
+                TopicFilter::is_invalid(alloc::format!("$share/xyz/{topic}").as_str()),
+            );
+        }
+
+        for (result, raw_filter) in [
+            (Some((None, None)), "$abc/a/b"),
+            (Some((None, None)), "$abc/a/b/xyz/def"),
+            (Some((None, None)), "$sys/abc"),
+            (Some((Some("abc"), Some("xyz"))), "$share/abc/xyz"),
+            (Some((Some("abc"), Some("xyz/ijk"))), "$share/abc/xyz/ijk"),
+            (Some((Some("abc"), Some("/xyz"))), "$share/abc//xyz"),
+            (Some((Some("abc"), Some("/#"))), "$share/abc//#"),
+            (Some((Some("abc"), Some("/a/x/+"))), "$share/abc//a/x/+"),
+            (Some((Some("abc"), Some("+"))), "$share/abc/+"),
+            (Some((Some("你好"), Some("+"))), "$share/你好/+"),
+            (Some((Some("你好"), Some("你好"))), "$share/你好/你好"),
+            (Some((Some("abc"), Some("#"))), "$share/abc/#"),
+            (Some((Some("abc"), Some("#"))), "$share/abc/#"),
+            (None, "$share/abc/"),
+            (None, "$share/abc"),
+            (None, "$share/+/y"),
+            (None, "$share/+/+"),
+            (None, "$share//y"),
+            (None, "$share//+"),
+        ] {
+            if let Some((shared_group, shared_filter)) = result {
+                let filter = TopicFilter::try_from(raw_filter).unwrap();
+                assert_eq!(filter.shared_group_name(), shared_group);
+                assert_eq!(filter.shared_filter(), shared_filter);
+                if let Some(group_name) = shared_group {
+                    assert_eq!(
+                        filter.shared_info(),
+                        Some((group_name, shared_filter.unwrap()))
+                    );
+                }
+            } else {
+                assert_eq!((true, 0), TopicFilter::is_invalid(raw_filter));
+            }
+        }
+    }
+}
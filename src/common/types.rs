@@ -1,17 +1,16 @@
-use core::cmp::{Eq, Ord, Ordering, PartialEq, PartialOrd};
 use core::convert::TryFrom;
-use core::hash::{Hash, Hasher};
-use core::ops::Deref;
 
+#[cfg(feature = "alloc")]
 use alloc::sync::Arc;
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
+#[cfg(feature = "alloc")]
 use simdutf8::basic::from_utf8;
 
-use super::{
-    read_bytes, read_bytes_async, read_u8, read_u8_async, write_bytes, write_u8, AsyncRead, Error,
-    SyncWrite, LEVEL_SEP, MATCH_ALL_CHAR, MATCH_ONE_CHAR, SHARED_PREFIX, SYS_PREFIX,
-};
+#[cfg(feature = "alloc")]
+use super::{read_bytes, read_bytes_async, read_u8, read_u8_async, AsyncRead};
+use super::{write_bytes, write_u8, Error, SyncWrite};
 
 pub const MQISDP: &[u8] = b"MQIsdp";
 pub const MQTT: &[u8] = b"MQTT";
@@ -27,6 +26,8 @@ pub trait Encodable {
 /// Protocol version.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Protocol {
     /// [MQTT 3.1]
     ///
@@ -45,6 +46,7 @@ pub enum Protocol {
 }
 
 impl Protocol {
+    #[cfg(feature = "alloc")]
     pub fn new(name: &[u8], level: u8) -> Result<Protocol, Error> {
         match (name, level) {
             (MQISDP, 3) => Ok(Protocol::V310),
@@ -65,12 +67,14 @@ impl Protocol {
         }
     }
 
+    #[cfg(feature = "alloc")]
     pub fn decode(buf: &[u8], offset: &mut usize) -> Result<Self, Error> {
         let name_buf = read_bytes(buf, offset)?;
         let level = read_u8(buf, offset)?;
         Protocol::new(name_buf, level)
     }
 
+    #[cfg(feature = "alloc")]
     pub async fn decode_async<T: AsyncRead + Unpin>(reader: &mut T) -> Result<Self, Error> {
         let name_buf = read_bytes_async(reader).await?;
         let level = read_u8_async(reader).await?;
@@ -109,6 +113,12 @@ impl Encodable for Protocol {
 /// Packet identifier
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u16", into = "u16")
+)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Pid(u16);
 
 impl Pid {
@@ -135,6 +145,12 @@ impl TryFrom<u16> for Pid {
     }
 }
 
+impl From<Pid> for u16 {
+    fn from(pid: Pid) -> u16 {
+        pid.0
+    }
+}
+
 impl core::ops::Add<u16> for Pid {
     type Output = Pid;
 
@@ -180,6 +196,8 @@ impl core::ops::SubAssign<u16> for Pid {
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum QoS {
     /// `QoS 0`. At most once. No ack needed.
     Level0 = 0,
@@ -209,6 +227,8 @@ impl QoS {
 /// [`Pid`]: struct.Pid.html
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum QosPid {
     Level0,
     Level1(Pid),
@@ -239,296 +259,17 @@ impl QosPid {
     }
 }
 
-/// Topic name.
-///
-/// See [MQTT 4.7]. The internal value is `Arc<str>`.
-///
-/// [MQTT 4.7]: http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718106
-#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
-#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
-pub struct TopicName(Arc<str>);
-
-impl TopicName {
-    /// Check if the topic name is invalid.
-    pub fn is_invalid(value: &str) -> bool {
-        if value.len() > u16::MAX as usize {
-            return true;
-        }
-        value.contains([MATCH_ONE_CHAR, MATCH_ALL_CHAR, '\0'])
-    }
-
-    pub fn is_shared(&self) -> bool {
-        self.0.starts_with(SHARED_PREFIX)
-    }
-    pub fn is_sys(&self) -> bool {
-        self.0.starts_with(SYS_PREFIX)
-    }
-}
-
-impl core::fmt::Display for TopicName {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{}", self.0)
-    }
-}
-
-impl TryFrom<&str> for TopicName {
-    type Error = Error;
-    fn try_from(value: &str) -> Result<Self, Error> {
-        if TopicName::is_invalid(value) {
-            Err(Error::InvalidTopicName(value.into()))
-        } else {
-            Ok(TopicName(value.into()))
-        }
-    }
-}
-
-impl TryFrom<Arc<str>> for TopicName {
-    type Error = Error;
-    fn try_from(value: Arc<str>) -> Result<Self, Error> {
-        if TopicName::is_invalid(&value) {
-            Err(Error::InvalidTopicName(value))
-        } else {
-            Ok(TopicName(value))
-        }
-    }
-}
-
-impl Deref for TopicName {
-    type Target = str;
-    fn deref(&self) -> &str {
-        &self.0
-    }
-}
-
-/// Topic filter.
-///
-/// See [MQTT 4.7]. The internal value is `Arc<str>` and a cache value for
-/// where shared filter byte index started. The traits:
-/// `Hash`/`Ord`/`PartialOrd`/`Eq`/`PartialEq` are all manually implemented for
-/// only contains the string value.
-///
-/// [MQTT 4.7]: http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718106
-#[derive(Debug, Clone)]
-#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
-pub struct TopicFilter {
-    inner: Arc<str>,
-    shared_filter_sep: u16,
-}
-
-impl TopicFilter {
-    /// Check if the topic filter is invalid.
-    ///
-    ///   * The u16 returned is where the bytes index of '/' char before shared topic filter
-    pub fn is_invalid(value: &str) -> (bool, u16) {
-        if value.len() > u16::MAX as usize {
-            return (true, 0);
-        }
-
-        const SHARED_PREFIX_CHARS: [char; 7] = ['$', 's', 'h', 'a', 'r', 'e', '/'];
-
-        // v5.0 [MQTT-4.7.3-1]
-        if value.is_empty() {
-            return (true, 0);
-        }
-
-        let mut last_sep: Option<usize> = None;
-        let mut has_all = false;
-        let mut has_one = false;
-        let mut byte_idx = 0;
-        let mut is_shared = true;
-        let mut shared_group_sep = 0;
-        let mut shared_filter_sep = 0;
-        for (char_idx, c) in value.chars().enumerate() {
-            if c == '\0' {
-                return (true, 0);
-            }
-            // "#" must be last char
-            if has_all {
-                return (true, 0);
-            }
-
-            if is_shared && char_idx < 7 && c != SHARED_PREFIX_CHARS[char_idx] {
-                is_shared = false;
-            }
-
-            if c == LEVEL_SEP {
-                if is_shared {
-                    if shared_group_sep == 0 {
-                        shared_group_sep = byte_idx as u16;
-                    } else if shared_filter_sep == 0 {
-                        shared_filter_sep = byte_idx as u16;
-                    }
-                }
-                // "+" must occupy an entire level of the filter
-                if has_one && Some(char_idx) != last_sep.map(|v| v + 2) && char_idx != 1 {
-                    return (true, 0);
-                }
-                last_sep = Some(char_idx);
-                has_one = false;
-            } else if c == MATCH_ALL_CHAR {
-                // v5.0 [MQTT-4.8.2-2]
-                if shared_group_sep > 0 && shared_filter_sep == 0 {
-                    return (true, 0);
-                }
-                if has_one {
-                    // invalid topic filter: "/+#"
-                    return (true, 0);
-                } else if Some(char_idx) == last_sep.map(|v| v + 1) || char_idx == 0 {
-                    has_all = true;
-                } else {
-                    // invalid topic filter: "/ab#"
-                    return (true, 0);
-                }
-            } else if c == MATCH_ONE_CHAR {
-                // v5.0 [MQTT-4.8.2-2]
-                if shared_group_sep > 0 && shared_filter_sep == 0 {
-                    return (true, 0);
-                }
-                if has_one {
-                    // invalid topic filter: "/++"
-                    return (true, 0);
-                } else if Some(char_idx) == last_sep.map(|v| v + 1) || char_idx == 0 {
-                    has_one = true;
-                } else {
-                    return (true, 0);
-                }
-            }
-
-            byte_idx += c.len_utf8();
-        }
-
-        // v5.0 [MQTT-4.7.3-1]
-        if shared_filter_sep > 0 && shared_filter_sep as usize == value.len() - 1 {
-            return (true, 0);
-        }
-        // v5.0 [MQTT-4.8.2-2]
-        if shared_group_sep > 0 && shared_filter_sep == 0 {
-            return (true, 0);
-        }
-        // v5.0 [MQTT-4.8.2-1]
-        if shared_group_sep + 1 == shared_filter_sep {
-            return (true, 0);
-        }
-
-        debug_assert!(shared_group_sep == 0 || shared_group_sep == 6);
-
-        (false, shared_filter_sep)
-    }
-
-    pub fn is_shared(&self) -> bool {
-        self.shared_filter_sep > 0
-    }
-    pub fn is_sys(&self) -> bool {
-        self.inner.starts_with(SYS_PREFIX)
-    }
-
-    pub fn shared_group_name(&self) -> Option<&str> {
-        if self.is_shared() {
-            let group_end = self.shared_filter_sep as usize;
-            Some(&self.inner[7..group_end])
-        } else {
-            None
-        }
-    }
-
-    pub fn shared_filter(&self) -> Option<&str> {
-        if self.is_shared() {
-            let filter_begin = self.shared_filter_sep as usize + 1;
-            Some(&self.inner[filter_begin..])
-        } else {
-            None
-        }
-    }
-
-    /// return (shared group name, shared filter)
-    pub fn shared_info(&self) -> Option<(&str, &str)> {
-        if self.is_shared() {
-            let group_end = self.shared_filter_sep as usize;
-            let filter_begin = self.shared_filter_sep as usize + 1;
-            Some((&self.inner[7..group_end], &self.inner[filter_begin..]))
-        } else {
-            None
-        }
-    }
-}
-
-impl Hash for TopicFilter {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.inner.hash(state);
-    }
-}
-
-impl Ord for TopicFilter {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.inner.cmp(&other.inner)
-    }
-}
-
-impl PartialOrd for TopicFilter {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl PartialEq for TopicFilter {
-    fn eq(&self, other: &Self) -> bool {
-        self.inner.eq(&other.inner)
-    }
-}
-
-impl Eq for TopicFilter {}
-
-impl core::fmt::Display for TopicFilter {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{}", self.inner)
-    }
-}
-
-impl TryFrom<&str> for TopicFilter {
-    type Error = Error;
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let (is_invalid, shared_filter_sep) = TopicFilter::is_invalid(value);
-        if is_invalid {
-            Err(Error::InvalidTopicFilter(value.into()))
-        } else {
-            Ok(TopicFilter {
-                inner: value.into(),
-                shared_filter_sep,
-            })
-        }
-    }
-}
-
-impl TryFrom<Arc<str>> for TopicFilter {
-    type Error = Error;
-    fn try_from(value: Arc<str>) -> Result<Self, Self::Error> {
-        let (is_invalid, shared_filter_sep) = TopicFilter::is_invalid(&value);
-        if is_invalid {
-            Err(Error::InvalidTopicFilter(value))
-        } else {
-            Ok(TopicFilter {
-                inner: value,
-                shared_filter_sep,
-            })
-        }
-    }
-}
-
-impl Deref for TopicFilter {
-    type Target = str;
-    fn deref(&self) -> &str {
-        &self.inner
-    }
-}
-
 /// A bytes data structure represent a dynamic vector or fixed array.
+#[cfg(feature = "alloc")]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VarBytes {
     Dynamic(Vec<u8>),
     Fixed2([u8; 2]),
     Fixed4([u8; 4]),
 }
 
+#[cfg(feature = "alloc")]
 impl AsRef<[u8]> for VarBytes {
     /// Return the slice of the internal bytes.
     fn as_ref(&self) -> &[u8] {
@@ -541,9 +282,11 @@ impl AsRef<[u8]> for VarBytes {
 }
 
 /// The [client identifier](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901059).
+#[cfg(feature = "alloc")]
 pub type ClientId = Arc<str>;
 
 /// The [user name](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901071).
+#[cfg(feature = "alloc")]
 pub type Username = Arc<str>;
 
 #[cfg(test)]
@@ -552,7 +295,7 @@ mod tests {
 
     #[test]
     fn pid_add_sub() {
-        let t: Vec<(u16, u16, u16, u16)> = alloc::vec![
+        let t: [(u16, u16, u16, u16); 11] = [
             (2, 1, 1, 3),
             (100, 1, 99, 101),
             (1, 1, u16::MAX, 2),
@@ -573,165 +316,4 @@ mod tests {
             assert_eq!(next, add.value(), "{cur:?} + {d} should be {next}");
         }
     }
-
-    #[test]
-    fn test_valid_topic_name() {
-        // valid topic name
-        assert!(!TopicName::is_invalid("/abc/def"));
-        assert!(!TopicName::is_invalid("abc/def"));
-        assert!(!TopicName::is_invalid("abc"));
-        assert!(!TopicName::is_invalid("/"));
-        assert!(!TopicName::is_invalid("//"));
-        // NOTE: Because v5.0 topic alias, we let up level to check empty topic name
-        assert!(!TopicName::is_invalid(""));
-        assert!(!TopicName::is_invalid(
-            "a".repeat(u16::MAX as usize).as_str()
-        ));
-
-        // invalid topic name
-        assert!(TopicName::is_invalid("#"));
-        assert!(TopicName::is_invalid("+"));
-        assert!(TopicName::is_invalid("/+"));
-        assert!(TopicName::is_invalid("/#"));
-        assert!(TopicName::is_invalid("abc/\0"));
-        assert!(TopicName::is_invalid("abc\0def"));
-        assert!(TopicName::is_invalid("abc#def"));
-        assert!(TopicName::is_invalid("abc+def"));
-        assert!(TopicName::is_invalid(
-            "a".repeat(u16::MAX as usize + 1).as_str()
-        ));
-    }
-
-    #[test]
-    fn test_valid_topic_filter() {
-        let string_65535 = "a".repeat(u16::MAX as usize);
-        let string_65536 = "a".repeat(u16::MAX as usize + 1);
-        for (is_invalid, topic) in [
-            // valid topic filter
-            (false, "abc/def"),
-            (false, "abc/+"),
-            (false, "abc/#"),
-            (false, "#"),
-            (false, "+"),
-            (false, "+/"),
-            (false, "+/+"),
-            (false, "///"),
-            (false, "//+/"),
-            (false, "//abc/"),
-            (false, "//+//#"),
-            (false, "/abc/+//#"),
-            (false, "+/abc/+"),
-            (false, string_65535.as_str()),
-            // invalid topic filter
-            (true, ""),
-            (true, "abc\0def"),
-            (true, "abc/\0def"),
-            (true, "++"),
-            (true, "++/"),
-            (true, "/++"),
-            (true, "abc/++"),
-            (true, "abc/++/"),
-            (true, "#/abc"),
-            (true, "/ab#"),
-            (true, "##"),
-            (true, "/abc/ab#"),
-            (true, "/+#"),
-            (true, "//+#"),
-            (true, "/abc/+#"),
-            (true, "xxx/abc/+#"),
-            (true, "xxx/a+bc/"),
-            (true, "x+x/abc/"),
-            (true, "x+/abc/"),
-            (true, "+x/abc/"),
-            (true, "+/abc/++"),
-            (true, "+/a+c/+"),
-            (true, string_65536.as_str()),
-        ] {
-            assert_eq!((is_invalid, 0), TopicFilter::is_invalid(topic));
-        }
-    }
-
-    #[test]
-    fn test_valid_shared_topic_filter() {
-        for (is_invalid, topic) in [
-            // valid topic filter
-            (false, "abc/def"),
-            (false, "abc/+"),
-            (false, "abc/#"),
-            (false, "#"),
-            (false, "+"),
-            (false, "+/"),
-            (false, "+/+"),
-            (false, "///"),
-            (false, "//+/"),
-            (false, "//abc/"),
-            (false, "//+//#"),
-            (false, "/abc/+//#"),
-            (false, "+/abc/+"),
-            // invalid topic filter
-            (true, "abc\0def"),
-            (true, "abc/\0def"),
-            (true, "++"),
-            (true, "++/"),
-            (true, "/++"),
-            (true, "abc/++"),
-            (true, "abc/++/"),
-            (true, "#/abc"),
-            (true, "/ab#"),
-            (true, "##"),
-            (true, "/abc/ab#"),
-            (true, "/+#"),
-            (true, "//+#"),
-            (true, "/abc/+#"),
-            (true, "xxx/abc/+#"),
-            (true, "xxx/a+bc/"),
-            (true, "x+x/abc/"),
-            (true, "x+/abc/"),
-            (true, "+x/abc/"),
-            (true, "+/abc/++"),
-            (true, "+/a+c/+"),
-        ] {
-            let result = if is_invalid { (true, 0) } else { (false, 10) };
-            assert_eq!(
-                result,
-                TopicFilter::is_invalid(alloc::format!("$share/xyz/{topic}").as_str()),
-            );
-        }
-
-        for (result, raw_filter) in [
-            (Some((None, None)), "$abc/a/b"),
-            (Some((None, None)), "$abc/a/b/xyz/def"),
-            (Some((None, None)), "$sys/abc"),
-            (Some((Some("abc"), Some("xyz"))), "$share/abc/xyz"),
-            (Some((Some("abc"), Some("xyz/ijk"))), "$share/abc/xyz/ijk"),
-            (Some((Some("abc"), Some("/xyz"))), "$share/abc//xyz"),
-            (Some((Some("abc"), Some("/#"))), "$share/abc//#"),
-            (Some((Some("abc"), Some("/a/x/+"))), "$share/abc//a/x/+"),
-            (Some((Some("abc"), Some("+"))), "$share/abc/+"),
-            (Some((Some("你好"), Some("+"))), "$share/你好/+"),
-            (Some((Some("你好"), Some("你好"))), "$share/你好/你好"),
-            (Some((Some("abc"), Some("#"))), "$share/abc/#"),
-            (Some((Some("abc"), Some("#"))), "$share/abc/#"),
-            (None, "$share/abc/"),
-            (None, "$share/abc"),
-            (None, "$share/+/y"),
-            (None, "$share/+/+"),
-            (None, "$share//y"),
-            (None, "$share//+"),
-        ] {
-            if let Some((shared_group, shared_filter)) = result {
-                let filter = TopicFilter::try_from(raw_filter).unwrap();
-                assert_eq!(filter.shared_group_name(), shared_group);
-                assert_eq!(filter.shared_filter(), shared_filter);
-                if let Some(group_name) = shared_group {
-                    assert_eq!(
-                        filter.shared_info(),
-                        Some((group_name, shared_filter.unwrap()))
-                    );
-                }
-            } else {
-                assert_eq!((true, 0), TopicFilter::is_invalid(raw_filter));
-            }
-        }
-    }
 }
@@ -1,13 +1,60 @@
 use core::slice;
 
+#[cfg(any(feature = "v3", feature = "v5"))]
 use alloc::sync::Arc;
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
+#[cfg(any(feature = "v3", feature = "v5", feature = "heapless"))]
 use simdutf8::basic::from_utf8;
 #[cfg(feature = "tokio")]
 use tokio::io::AsyncReadExt;
 
-use super::{AsyncRead, Encodable, Error, SyncWrite, ToError};
+use super::{AsyncRead, Error, SyncWrite, ToError};
+#[cfg(any(feature = "v3", feature = "v5"))]
+use super::{Encodable, SyncRead};
+#[cfg(any(feature = "alloc", feature = "heapless"))]
+use super::{LEVEL_SEP, MATCH_ALL_CHAR, MATCH_ONE_CHAR};
+
+/// Decode the fixed header from a slice, return `Ok(None)` if the slice ends
+/// before the header does.
+#[inline]
+pub fn decode_raw_header(buf: &[u8]) -> Result<Option<(u8, u32, usize)>, Error> {
+    let Some(&typ) = buf.first() else {
+        return Ok(None);
+    };
+    let mut offset = 1;
+    match decode_var_int(buf, &mut offset) {
+        Ok((remaining_len, bytes)) => Ok(Some((typ, remaining_len, bytes))),
+        Err(err) if err.is_eof() => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Blocking read of the first byte and the remaining length, the same way as
+/// the poll decoder does.
+#[cfg(any(feature = "v3", feature = "v5"))]
+pub(crate) fn read_raw_header<R: SyncRead>(reader: &mut R) -> Result<(u8, u32, usize), Error> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf).map_err(ToError::to_error)?;
+    let typ = buf[0];
+
+    let mut var_int: u32 = 0;
+    let mut var_idx: u32 = 0;
+    loop {
+        reader.read_exact(&mut buf).map_err(ToError::to_error)?;
+        let byte = buf[0];
+        var_int |= (u32::from(byte) & 0x7F) << (7 * var_idx);
+        if byte & 0x80 == 0 {
+            break;
+        } else if var_idx < 3 {
+            var_idx += 1;
+        } else {
+            return Err(Error::InvalidVarByteInt);
+        }
+    }
+    Ok((typ, var_int, var_idx as usize + 1))
+}
 
 /// Read first byte(packet type and flags) and decode remaining length
 #[inline]
@@ -20,12 +67,14 @@ pub async fn decode_raw_header_async<T: AsyncRead + Unpin>(
 }
 
 #[inline]
+#[cfg(any(feature = "v3", feature = "v5", feature = "heapless"))]
 pub fn read_string<'a>(data: &'a [u8], offset: &mut usize) -> Result<&'a str, Error> {
     let data_slice = read_bytes(data, offset)?;
     from_utf8(data_slice).map_err(|_| Error::InvalidString)
 }
 
 #[inline]
+#[cfg(any(feature = "v3", feature = "v5"))]
 pub(crate) async fn read_string_async<T: AsyncRead + Unpin>(
     reader: &mut T,
 ) -> Result<Arc<str>, Error> {
@@ -35,12 +84,14 @@ pub(crate) async fn read_string_async<T: AsyncRead + Unpin>(
 }
 
 #[inline]
+#[cfg(any(feature = "alloc", feature = "heapless"))]
 pub fn read_bytes<'a>(data: &'a [u8], offset: &mut usize) -> Result<&'a [u8], Error> {
     let data_len = read_u16(data, offset)? as usize;
     read_raw_bytes(data, offset, data_len)
 }
 
 #[inline]
+#[cfg(any(feature = "alloc", feature = "heapless"))]
 pub fn read_raw_bytes<'a>(
     data: &'a [u8],
     offset: &mut usize,
@@ -55,6 +106,7 @@ pub fn read_raw_bytes<'a>(
 }
 
 #[inline]
+#[cfg(feature = "alloc")]
 pub(crate) async fn read_bytes_async<T: AsyncRead + Unpin>(
     reader: &mut T,
 ) -> Result<Vec<u8>, Error> {
@@ -69,6 +121,7 @@ pub(crate) async fn read_bytes_async<T: AsyncRead + Unpin>(
 
 // Only for v5.0
 #[inline]
+#[cfg(feature = "v5")]
 pub(crate) fn read_u32(data: &[u8], offset: &mut usize) -> Result<u32, Error> {
     if *offset + 4 > data.len() {
         return Err(Error::IoError(crate::IoErrorKind::UnexpectedEof));
@@ -84,6 +137,7 @@ pub(crate) fn read_u32(data: &[u8], offset: &mut usize) -> Result<u32, Error> {
 }
 
 #[inline]
+#[cfg(feature = "v5")]
 pub(crate) async fn read_u32_async<T: AsyncRead + Unpin>(reader: &mut T) -> Result<u32, Error> {
     let mut len4_bytes = [0u8; 4];
     reader
@@ -94,6 +148,7 @@ pub(crate) async fn read_u32_async<T: AsyncRead + Unpin>(reader: &mut T) -> Resu
 }
 
 #[inline]
+#[cfg(any(feature = "alloc", feature = "heapless"))]
 pub(crate) fn read_u16(data: &[u8], offset: &mut usize) -> Result<u16, Error> {
     if *offset + 2 > data.len() {
         return Err(Error::IoError(crate::IoErrorKind::UnexpectedEof));
@@ -104,6 +159,7 @@ pub(crate) fn read_u16(data: &[u8], offset: &mut usize) -> Result<u16, Error> {
 }
 
 #[inline]
+#[cfg(feature = "alloc")]
 pub(crate) async fn read_u16_async<T: AsyncRead + Unpin>(reader: &mut T) -> Result<u16, Error> {
     let mut len2_bytes = [0u8; 2];
     reader
@@ -114,6 +170,7 @@ pub(crate) async fn read_u16_async<T: AsyncRead + Unpin>(reader: &mut T) -> Resu
 }
 
 #[inline]
+#[cfg(any(feature = "alloc", feature = "heapless"))]
 pub(crate) fn read_u8(data: &[u8], offset: &mut usize) -> Result<u8, Error> {
     if *offset >= data.len() {
         return Err(Error::IoError(crate::IoErrorKind::UnexpectedEof));
@@ -134,6 +191,7 @@ pub(crate) async fn read_u8_async<T: AsyncRead + Unpin>(reader: &mut T) -> Resul
 }
 
 #[inline]
+#[cfg(any(feature = "v3", feature = "heapless"))]
 pub(crate) fn write_string<W: SyncWrite>(writer: &mut W, value: &str) -> Result<(), Error> {
     write_bytes(writer, value.as_bytes())?;
     Ok(())
@@ -147,6 +205,7 @@ pub(crate) fn write_bytes<W: SyncWrite>(writer: &mut W, data: &[u8]) -> Result<(
 }
 
 #[inline]
+#[cfg(feature = "v5")]
 pub(crate) fn write_u32<W: SyncWrite>(writer: &mut W, value: u32) -> Result<(), Error> {
     writer.write_all(&value.to_be_bytes())?;
     Ok(())
@@ -165,6 +224,7 @@ pub(crate) fn write_u8<W: SyncWrite>(writer: &mut W, value: u8) -> Result<(), Er
 }
 
 #[inline]
+#[cfg(any(feature = "v3", feature = "v5", feature = "heapless"))]
 pub(crate) fn write_var_int<W: SyncWrite>(writer: &mut W, mut len: usize) -> Result<(), Error> {
     loop {
         let mut byte = (len % 128) as u8;
@@ -229,6 +289,18 @@ pub(crate) async fn decode_var_int_async<T: AsyncRead + Unpin>(
     Ok((var_int, i + 1))
 }
 
+/// Check the variable byte integer `value` decoded from `bytes` bytes used the
+/// minimum number of bytes.
+#[inline]
+#[cfg(feature = "alloc")]
+pub(crate) fn check_var_int(value: u32, bytes: usize) -> Result<(), Error> {
+    if var_int_len(value as usize)? == bytes {
+        Ok(())
+    } else {
+        Err(Error::NonMinimalVarByteInt(value, bytes))
+    }
+}
+
 /// Return the encoded size of the variable byte integer.
 #[inline]
 pub fn var_int_len(value: usize) -> Result<usize, Error> {
@@ -287,6 +359,7 @@ pub fn header_len(total_len: usize) -> usize {
 
 /// Encode packet use control byte and body type
 #[inline]
+#[cfg(any(feature = "v3", feature = "v5"))]
 pub(crate) fn encode_packet<E: Encodable>(control_byte: u8, body: &E) -> Result<Vec<u8>, Error> {
     let remaining_len = body.encode_len();
     let total = total_len(remaining_len)?;
@@ -301,6 +374,121 @@ pub(crate) fn encode_packet<E: Encodable>(control_byte: u8, body: &E) -> Result<
     Ok(buf)
 }
 
+/// Check if the topic name is invalid.
+#[cfg(any(feature = "alloc", feature = "heapless"))]
+pub(crate) fn is_invalid_topic_name(value: &str) -> bool {
+    if value.len() > u16::MAX as usize {
+        return true;
+    }
+    value.contains([MATCH_ONE_CHAR, MATCH_ALL_CHAR, '\0'])
+}
+
+/// Check if the topic filter is invalid.
+///
+///   * The u16 returned is where the bytes index of '/' char before shared topic filter
+#[cfg(any(feature = "alloc", feature = "heapless"))]
+pub(crate) fn check_topic_filter(value: &str) -> (bool, u16) {
+    if value.len() > u16::MAX as usize {
+        return (true, 0);
+    }
+
+    const SHARED_PREFIX_CHARS: [char; 7] = ['$', 's', 'h', 'a', 'r', 'e', '/'];
+
+    // v5.0 [MQTT-4.7.3-1]
+    if value.is_empty() {
+        return (true, 0);
+    }
+
+    let mut last_sep: Option<usize> = None;
+    let mut has_all = false;
+    let mut has_one = false;
+    let mut byte_idx = 0;
+    let mut is_shared = true;
+    let mut shared_group_sep = 0;
+    let mut shared_filter_sep = 0;
+    for (char_idx, c) in value.chars().enumerate() {
+        if c == '\0' {
+            return (true, 0);
+        }
+        // "#" must be last char
+        if has_all {
+            return (true, 0);
+        }
+
+        if is_shared && char_idx < 7 && c != SHARED_PREFIX_CHARS[char_idx] {
+            is_shared = false;
+        }
+
+        if c == LEVEL_SEP {
+            if is_shared {
+                if shared_group_sep == 0 {
+                    shared_group_sep = byte_idx as u16;
+                } else if shared_filter_sep == 0 {
+                    shared_filter_sep = byte_idx as u16;
+                }
+            }
+            // "+" must occupy an entire level of the filter
+            if has_one && Some(char_idx) != last_sep.map(|v| v + 2) && char_idx != 1 {
+                return (true, 0);
+            }
+            last_sep = Some(char_idx);
+            has_one = false;
+        } else if c == MATCH_ALL_CHAR {
+            // v5.0 [MQTT-4.8.2-2]
+            if shared_group_sep > 0 && shared_filter_sep == 0 {
+                return (true, 0);
+            }
+            if has_one {
+                // invalid topic filter: "/+#"
+                return (true, 0);
+            } else if Some(char_idx) == last_sep.map(|v| v + 1) || char_idx == 0 {
+                has_all = true;
+            } else {
+                // invalid topic filter: "/ab#"
+                return (true, 0);
+            }
+        } else if c == MATCH_ONE_CHAR {
+            // v5.0 [MQTT-4.8.2-2]
+            if shared_group_sep > 0 && shared_filter_sep == 0 {
+                return (true, 0);
+            }
+            if has_one {
+                // invalid topic filter: "/++"
+                return (true, 0);
+            } else if Some(char_idx) == last_sep.map(|v| v + 1) || char_idx == 0 {
+                has_one = true;
+            } else {
+                return (true, 0);
+            }
+        }
+
+        byte_idx += c.len_utf8();
+    }
+
+    // "+" must occupy the entire last level too: "abc/+x"
+    if has_one && !value.ends_with(MATCH_ONE_CHAR) {
+        return (true, 0);
+    }
+
+    // v5.0 [MQTT-4.7.3-1]
+    if shared_filter_sep > 0 && shared_filter_sep as usize == value.len() - 1 {
+        return (true, 0);
+    }
+    // v5.0 [MQTT-4.8.2-2]
+    if shared_group_sep > 0 && shared_filter_sep == 0 {
+        return (true, 0);
+    }
+    // v5.0 [MQTT-4.8.2-1]
+    if shared_group_sep + 1 == shared_filter_sep {
+        return (true, 0);
+    }
+
+    debug_assert!(shared_group_sep == 0 || shared_group_sep == 6);
+
+    (false, shared_filter_sep)
+}
+
+#[cfg(any(feature = "v3", feature = "v5", feature = "sn"))]
 macro_rules! packet_from {
     ($($t:ident),+) => {
         $(
@@ -313,6 +501,7 @@ macro_rules! packet_from {
     }
 }
 
+#[cfg(any(feature = "v3", feature = "v5", feature = "sn"))]
 pub(crate) use packet_from;
 
 #[cfg(test)]
@@ -344,4 +533,32 @@ mod tests {
             .unwrap_err()
             .is_eof());
     }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_check_var_int() {
+        for (data, minimal) in [
+            (&[0x00][..], true),
+            (&[0x80, 0x01][..], true),
+            (&[0xff, 0xff, 0xff, 0x7f][..], true),
+            (&[0x80, 0x00][..], false),
+            (&[0x81, 0x00][..], false),
+            (&[0xff, 0x80, 0x00][..], false),
+            (&[0x80, 0x80, 0x80, 0x01][..], true),
+            (&[0x80, 0x80, 0x80, 0x00][..], false),
+        ] {
+            let (value, bytes) = decode_var_int(data, &mut 0).unwrap();
+            assert_eq!(bytes, data.len());
+            let res = check_var_int(value, bytes);
+            if minimal {
+                assert_eq!(res, Ok(()), "{data:?}");
+            } else {
+                assert_eq!(
+                    res,
+                    Err(Error::NonMinimalVarByteInt(value, bytes)),
+                    "{data:?}"
+                );
+            }
+        }
+    }
 }
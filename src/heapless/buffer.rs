@@ -0,0 +1,141 @@
+use core::mem::MaybeUninit;
+
+#[cfg(feature = "tokio")]
+use tokio::io::AsyncReadExt;
+
+use crate::{decode_raw_header_async, AsyncRead, Buffer, BufferHandle, ReadStrategy, ToError};
+
+use super::Error;
+
+/// [`Buffer`] handing out fixed-capacity buffers of `N` bytes.
+///
+/// The bytes live inside the handle itself, so they are placed wherever the
+/// handle is stored (a static task arena, the stack) and no allocator is
+/// involved. There is no chunked path without `alloc`, so packets larger than
+/// `N` are refused with [`Error::BufferTooSmall`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StaticBuffer<const N: usize>;
+
+impl<const N: usize> StaticBuffer<N> {
+    pub const fn new() -> Self {
+        StaticBuffer
+    }
+}
+
+impl<const N: usize> Buffer for StaticBuffer<N> {
+    type Handle = StaticBufferHandle<N>;
+    type Error = Error;
+
+    async fn acquire(&mut self, size: usize) -> Result<Self::Handle, Self::Error> {
+        if size > N {
+            return Err(Error::BufferTooSmall(size));
+        }
+        Ok(StaticBufferHandle::new())
+    }
+
+    async fn release(&mut self, _handle: Self::Handle) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn read_strategy(&self, _packet_size: usize) -> ReadStrategy {
+        ReadStrategy::Buffer
+    }
+}
+
+/// Buffer handle of [`StaticBuffer`], cloning it copies the bytes.
+#[derive(Debug, Clone)]
+pub struct StaticBufferHandle<const N: usize> {
+    data: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> StaticBufferHandle<N> {
+    pub const fn new() -> Self {
+        StaticBufferHandle {
+            data: [0; N],
+            len: 0,
+        }
+    }
+}
+
+impl<const N: usize> Default for StaticBufferHandle<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> BufferHandle for StaticBufferHandle<N> {
+    type Error = Error;
+
+    fn as_mut_slice(&mut self) -> (&mut [MaybeUninit<u8>], usize) {
+        let ptr = self.data.as_mut_ptr() as *mut MaybeUninit<u8>;
+        // SAFETY: `u8` and `MaybeUninit<u8>` have the same layout and the
+        // bytes are always initialized.
+        let slice = unsafe { core::slice::from_raw_parts_mut(ptr, N) };
+        (slice, N)
+    }
+
+    fn as_slice(&self, len: usize) -> &[u8] {
+        &self.data[..len.min(N)]
+    }
+
+    /// The length is capped at the capacity `N`.
+    fn set_len(&mut self, len: usize) {
+        self.len = len.min(N);
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn capacity(&self) -> usize {
+        N
+    }
+}
+
+/// Read the next packet from `reader` into a handle acquired from `buffer`.
+///
+/// The whole packet, fixed header included, is copied into the handle, decode
+/// it with [`Packet::decode`](super::Packet::decode) from
+/// `handle.as_slice(handle.len())`. The handle is sized from the remaining
+/// length before the body is read, a packet which does not fit fails with
+/// [`Error::BufferTooSmall`] without consuming its body.
+pub async fn read_packet<T, B>(reader: &mut T, buffer: &mut B) -> Result<B::Handle, Error>
+where
+    T: AsyncRead + Unpin,
+    B: Buffer,
+    Error: From<B::Error>,
+{
+    let (hd, remaining_len, var_bytes) = decode_raw_header_async(reader).await?;
+    let header_len = 1 + var_bytes;
+    let total = header_len + remaining_len as usize;
+
+    let mut handle = buffer.acquire(total).await?;
+    let (slice, capacity) = handle.as_mut_slice();
+    if capacity < total || slice.len() < total {
+        buffer.release(handle).await?;
+        return Err(Error::BufferTooSmall(total));
+    }
+
+    // Copy the header as it was received, a non-minimal remaining length
+    // keeps its length so the offsets of `Packet::decode` match.
+    slice[0].write(hd);
+    let mut len = remaining_len;
+    for (idx, byte) in slice[1..header_len].iter_mut().enumerate() {
+        let more = if idx + 1 < var_bytes { 0x80 } else { 0 };
+        byte.write((len & 0x7f) as u8 | more);
+        len >>= 7;
+    }
+
+    let body = &mut slice[header_len..total];
+    // SAFETY: `read_exact` only writes into the slice, every byte is
+    // initialized once it returns `Ok`.
+    let body: &mut [u8] =
+        unsafe { core::slice::from_raw_parts_mut(body.as_mut_ptr() as *mut u8, body.len()) };
+    if let Err(err) = reader.read_exact(body).await {
+        buffer.release(handle).await?;
+        return Err(err.to_error().into());
+    }
+    handle.set_len(total);
+    Ok(handle)
+}
@@ -0,0 +1,232 @@
+use crate::{
+    read_bytes, read_string, read_u16, read_u8, write_bytes, write_string, write_u16, write_u8,
+    Encodable, Protocol, QoS, SyncWrite,
+};
+
+use super::{check_topic_name, Error};
+
+/// Connect packet body type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connect<'a> {
+    pub protocol: Protocol,
+    pub clean_session: bool,
+    pub keep_alive: u16,
+    pub client_id: &'a str,
+    pub last_will: Option<LastWill<'a>>,
+    pub username: Option<&'a str>,
+    pub password: Option<&'a [u8]>,
+}
+
+impl<'a> Connect<'a> {
+    pub fn new(client_id: &'a str, keep_alive: u16) -> Self {
+        Connect {
+            protocol: Protocol::V311,
+            clean_session: true,
+            keep_alive,
+            client_id,
+            last_will: None,
+            username: None,
+            password: None,
+        }
+    }
+
+    pub fn decode(buf: &'a [u8], offset: &mut usize) -> Result<Self, Error> {
+        let name = read_bytes(buf, offset)?;
+        let level = read_u8(buf, offset)?;
+        let protocol = match [Protocol::V310, Protocol::V311, Protocol::V500]
+            .into_iter()
+            .find(|protocol| protocol.to_pair() == (name, level))
+        {
+            Some(Protocol::V500) => {
+                return Err(crate::Error::UnexpectedProtocol(Protocol::V500).into())
+            }
+            Some(protocol) => protocol,
+            None => return Err(Error::InvalidProtocol(level)),
+        };
+
+        let connect_flags: u8 = read_u8(buf, offset)?;
+        if connect_flags & 1 != 0 {
+            return Err(crate::Error::InvalidConnectFlags(connect_flags).into());
+        }
+        let keep_alive = read_u16(buf, offset)?;
+        let client_id = read_string(buf, offset)?;
+        let last_will = if connect_flags & 0b100 != 0 {
+            let topic_name = check_topic_name(read_string(buf, offset)?)?;
+            let message = read_bytes(buf, offset)?;
+            let qos = QoS::from_u8((connect_flags & 0b11000) >> 3)?;
+            let retain = (connect_flags & 0b00100000) != 0;
+            Some(LastWill {
+                qos,
+                retain,
+                topic_name,
+                message,
+            })
+        } else if connect_flags & 0b11000 != 0 {
+            return Err(crate::Error::InvalidConnectFlags(connect_flags).into());
+        } else {
+            None
+        };
+        let username = if connect_flags & 0b10000000 != 0 {
+            Some(read_string(buf, offset)?)
+        } else {
+            None
+        };
+        let password = if connect_flags & 0b01000000 != 0 {
+            Some(read_bytes(buf, offset)?)
+        } else {
+            None
+        };
+        let clean_session = (connect_flags & 0b10) != 0;
+        Ok(Connect {
+            protocol,
+            clean_session,
+            keep_alive,
+            client_id,
+            last_will,
+            username,
+            password,
+        })
+    }
+}
+
+impl Encodable for Connect<'_> {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), crate::Error> {
+        let mut connect_flags: u8 = 0b00000000;
+        if self.clean_session {
+            connect_flags |= 0b10;
+        }
+        if self.username.is_some() {
+            connect_flags |= 0b10000000;
+        }
+        if self.password.is_some() {
+            connect_flags |= 0b01000000;
+        }
+        if let Some(last_will) = self.last_will.as_ref() {
+            connect_flags |= 0b00000100;
+            connect_flags |= (last_will.qos as u8) << 3;
+            if last_will.retain {
+                connect_flags |= 0b00100000;
+            }
+        }
+
+        self.protocol.encode(writer)?;
+        write_u8(writer, connect_flags)?;
+        write_u16(writer, self.keep_alive)?;
+        write_string(writer, self.client_id)?;
+        if let Some(last_will) = self.last_will.as_ref() {
+            write_string(writer, last_will.topic_name)?;
+            write_bytes(writer, last_will.message)?;
+        }
+        if let Some(username) = self.username {
+            write_string(writer, username)?;
+        }
+        if let Some(password) = self.password {
+            write_bytes(writer, password)?;
+        }
+        Ok(())
+    }
+
+    fn encode_len(&self) -> usize {
+        let mut length = self.protocol.encode_len();
+        // flags + keep-alive
+        length += 1 + 2;
+        // client identifier
+        length += 2 + self.client_id.len();
+        if let Some(last_will) = self.last_will.as_ref() {
+            length += 4 + last_will.topic_name.len() + last_will.message.len();
+        }
+        if let Some(username) = self.username {
+            length += 2 + username.len();
+        }
+        if let Some(password) = self.password {
+            length += 2 + password.len();
+        }
+        length
+    }
+}
+
+/// Connack packet body type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Connack {
+    pub session_present: bool,
+    pub code: ConnectReturnCode,
+}
+
+impl Connack {
+    pub fn new(session_present: bool, code: ConnectReturnCode) -> Self {
+        Connack {
+            session_present,
+            code,
+        }
+    }
+
+    pub fn decode(buf: &[u8], offset: &mut usize) -> Result<Self, Error> {
+        let session_present = match read_u8(buf, offset)? {
+            0 => false,
+            1 => true,
+            flag => return Err(crate::Error::InvalidConnackFlags(flag).into()),
+        };
+        let code = ConnectReturnCode::from_u8(read_u8(buf, offset)?)?;
+        Ok(Connack {
+            session_present,
+            code,
+        })
+    }
+}
+
+/// Message that the server should publish when the client disconnects.
+///
+/// Sent by the client in the [Connect] packet. [MQTT 3.1.3.3].
+///
+/// [Connect]: struct.Connect.html
+/// [MQTT 3.1.3.3]: http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718031
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LastWill<'a> {
+    pub qos: QoS,
+    pub retain: bool,
+    pub topic_name: &'a str,
+    pub message: &'a [u8],
+}
+
+impl<'a> LastWill<'a> {
+    pub fn new(qos: QoS, topic_name: &'a str, message: &'a [u8]) -> Self {
+        LastWill {
+            qos,
+            retain: false,
+            topic_name,
+            message,
+        }
+    }
+}
+
+/// Return code of a [Connack] packet.
+///
+/// See [MQTT 3.2.2.3] for interpretations.
+///
+/// [Connack]: struct.Connack.html
+/// [MQTT 3.2.2.3]: http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718035
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConnectReturnCode {
+    Accepted = 0,
+    UnacceptableProtocolVersion = 1,
+    IdentifierRejected = 2,
+    ServerUnavailable = 3,
+    BadUserNameOrPassword = 4,
+    NotAuthorized = 5,
+}
+
+impl ConnectReturnCode {
+    pub fn from_u8(byte: u8) -> Result<ConnectReturnCode, crate::Error> {
+        match byte {
+            0 => Ok(ConnectReturnCode::Accepted),
+            1 => Ok(ConnectReturnCode::UnacceptableProtocolVersion),
+            2 => Ok(ConnectReturnCode::IdentifierRejected),
+            3 => Ok(ConnectReturnCode::ServerUnavailable),
+            4 => Ok(ConnectReturnCode::BadUserNameOrPassword),
+            5 => Ok(ConnectReturnCode::NotAuthorized),
+            n => Err(crate::Error::InvalidConnectReturnCode(n)),
+        }
+    }
+}
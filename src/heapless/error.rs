@@ -0,0 +1,40 @@
+use thiserror::Error;
+
+/// Errors returned by the allocation free codec.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// Common error of MQTT v3 and v5.
+    #[error("common error of v3/v5: {0}")]
+    Common(#[from] crate::Error),
+
+    /// Invalid protocol name or level.
+    #[error("invalid protocol level: `{0}`")]
+    InvalidProtocol(u8),
+
+    /// Invalid topic name.
+    #[error("invalid topic name")]
+    InvalidTopicName,
+
+    /// Invalid topic filter.
+    #[error("invalid topic filter")]
+    InvalidTopicFilter,
+
+    /// More topics than the capacity of the `heapless::Vec`.
+    #[error("too many topics, capacity is `{0}`")]
+    CapacityExceeded(usize),
+
+    /// The output buffer can not hold the encoded packet.
+    #[error("buffer too small, `{0}` bytes required")]
+    BufferTooSmall(usize),
+}
+
+impl Error {
+    pub fn is_eof(&self) -> bool {
+        if let Error::Common(e) = self {
+            e.is_eof()
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! Allocation free codec for MQTT [v3.1.1] and [v3.1]
+//!
+//! This module works without `alloc`, for targets which have no global
+//! allocator. Strings and payloads are borrowed from the input slice, lists
+//! of topics are stored in [`heapless::Vec`] with a const-generic capacity
+//! `N`, and packets are encoded into a caller provided `&mut [u8]`.
+//!
+//! [v3.1.1]: http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html
+//! [v3.1]: https://public.dhe.ibm.com/software/dw/webservices/ws-mqtt/mqtt-v3r1.html
+//! [`heapless::Vec`]: https://docs.rs/heapless/0.8/heapless/struct.Vec.html
+
+mod buffer;
+mod connect;
+mod error;
+mod packet;
+mod publish;
+mod subscribe;
+
+#[cfg(test)]
+mod tests;
+
+pub use buffer::{read_packet, StaticBuffer, StaticBufferHandle};
+pub use connect::{Connack, Connect, ConnectReturnCode, LastWill};
+pub use error::Error;
+pub use packet::Packet;
+pub use publish::Publish;
+pub use subscribe::{Suback, Subscribe, SubscribeReturnCode, Unsubscribe};
+
+use crate::{check_topic_filter, is_invalid_topic_name};
+
+#[inline]
+fn check_topic_name(value: &str) -> Result<&str, Error> {
+    if is_invalid_topic_name(value) {
+        Err(Error::InvalidTopicName)
+    } else {
+        Ok(value)
+    }
+}
+
+#[inline]
+fn check_filter(value: &str) -> Result<&str, Error> {
+    if check_topic_filter(value).0 {
+        Err(Error::InvalidTopicFilter)
+    } else {
+        Ok(value)
+    }
+}
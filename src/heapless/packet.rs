@@ -0,0 +1,260 @@
+use crate::{
+    decode_raw_header, read_u16, total_len, write_u16, write_u8, write_var_int, Encodable, Pid,
+    QosPid,
+};
+
+use super::{
+    check_filter, check_topic_name, Connack, Connect, Error, Publish, Suback, Subscribe,
+    Unsubscribe,
+};
+
+/// MQTT v3.x packet types borrowing from the buffer they are decoded from.
+///
+/// `N` is the maximum number of topics in a subscribe, suback or unsubscribe
+/// packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packet<'a, const N: usize> {
+    /// [MQTT 3.1](http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718028)
+    Connect(Connect<'a>),
+    /// [MQTT 3.2](http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718033)
+    Connack(Connack),
+    /// [MQTT 3.3](http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718037)
+    Publish(Publish<'a>),
+    /// [MQTT 3.4](http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718043)
+    Puback(Pid),
+    /// [MQTT 3.5](http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718048)
+    Pubrec(Pid),
+    /// [MQTT 3.6](http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718053)
+    Pubrel(Pid),
+    /// [MQTT 3.7](http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718058)
+    Pubcomp(Pid),
+    /// [MQTT 3.8](http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718063)
+    Subscribe(Subscribe<'a, N>),
+    /// [MQTT 3.9](http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718068)
+    Suback(Suback<N>),
+    /// [MQTT 3.10](http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718072)
+    Unsubscribe(Unsubscribe<'a, N>),
+    /// [MQTT 3.11](http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718077)
+    Unsuback(Pid),
+    /// [MQTT 3.12](http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718081)
+    Pingreq,
+    /// [MQTT 3.13](http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718086)
+    Pingresp,
+    /// [MQTT 3.14](http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718090)
+    Disconnect,
+}
+
+impl<'a, const N: usize> Packet<'a, N> {
+    /// Decode a packet from the start of `buf`, return the packet and the
+    /// number of bytes it occupies. If not enough bytes to decode a packet, it
+    /// will return `Ok(None)`.
+    pub fn decode(buf: &'a [u8]) -> Result<Option<(Self, usize)>, Error> {
+        let Some((hd, remaining_len, var_bytes)) = decode_raw_header(buf)? else {
+            return Ok(None);
+        };
+        let offset = 1 + var_bytes;
+        let total = offset + remaining_len as usize;
+        if buf.len() < total {
+            return Ok(None);
+        }
+
+        let body = &buf[offset..total];
+        let mut offset = 0;
+        let packet = match Self::decode_body(hd, body, &mut offset) {
+            Ok(packet) => packet,
+            // The whole packet is available, the remaining length is wrong.
+            Err(err) if err.is_eof() => return Err(crate::Error::InvalidRemainingLength.into()),
+            Err(err) => return Err(err),
+        };
+        if offset != body.len() {
+            return Err(crate::Error::InvalidRemainingLength.into());
+        }
+        Ok(Some((packet, total)))
+    }
+
+    fn decode_body(hd: u8, body: &'a [u8], offset: &mut usize) -> Result<Self, Error> {
+        const FLAGS_MASK: u8 = 0b1111;
+        let (packet_type, flags) = (hd >> 4, hd & FLAGS_MASK);
+        let read_pid = |offset: &mut usize| -> Result<Pid, Error> {
+            Ok(Pid::try_from(read_u16(body, offset)?)?)
+        };
+        let packet = match (packet_type, flags) {
+            (1, 0) => Packet::Connect(Connect::decode(body, offset)?),
+            (2, 0) => Packet::Connack(Connack::decode(body, offset)?),
+            (3, _) => Packet::Publish(Publish::decode(body, offset, flags)?),
+            (4, 0) => Packet::Puback(read_pid(offset)?),
+            (5, 0) => Packet::Pubrec(read_pid(offset)?),
+            (6, 0b0010) => Packet::Pubrel(read_pid(offset)?),
+            (7, 0) => Packet::Pubcomp(read_pid(offset)?),
+            (8, 0b0010) => Packet::Subscribe(Subscribe::decode(body, offset)?),
+            (9, 0) => Packet::Suback(Suback::decode(body, offset)?),
+            (10, 0b0010) => Packet::Unsubscribe(Unsubscribe::decode(body, offset)?),
+            (11, 0) => Packet::Unsuback(read_pid(offset)?),
+            (12, 0) => Packet::Pingreq,
+            (13, 0) => Packet::Pingresp,
+            (14, 0) => Packet::Disconnect,
+            _ => return Err(crate::Error::InvalidHeader.into()),
+        };
+        Ok(packet)
+    }
+
+    /// Encode the packet into `buf`, return the number of bytes written.
+    pub fn encode(&self, mut buf: &mut [u8]) -> Result<usize, Error> {
+        self.check_topics()?;
+        let total = self.encode_len()?;
+        if buf.len() < total {
+            return Err(Error::BufferTooSmall(total));
+        }
+        let writer = &mut buf;
+        match self {
+            Packet::Pingreq => encode_empty(writer, 0b11000000)?,
+            Packet::Pingresp => encode_empty(writer, 0b11010000)?,
+            Packet::Disconnect => encode_empty(writer, 0b11100000)?,
+            Packet::Connect(connect) => encode_body(writer, 0b00010000, connect)?,
+            Packet::Connack(connack) => {
+                const CONTROL_BYTE: u8 = 0b00100000;
+                write_u8(writer, CONTROL_BYTE)?;
+                write_u8(writer, 2)?;
+                write_u8(writer, connack.session_present.into())?;
+                write_u8(writer, connack.code as u8)?;
+            }
+            Packet::Publish(publish) => {
+                let mut control_byte: u8 = match publish.qos_pid {
+                    QosPid::Level0 => 0b00110000,
+                    QosPid::Level1(_) => 0b00110010,
+                    QosPid::Level2(_) => 0b00110100,
+                };
+                if publish.dup {
+                    control_byte |= 0b00001000;
+                }
+                if publish.retain {
+                    control_byte |= 0b00000001;
+                }
+                encode_body(writer, control_byte, publish)?
+            }
+            Packet::Puback(pid) => encode_with_pid(writer, 0b01000000, *pid)?,
+            Packet::Pubrec(pid) => encode_with_pid(writer, 0b01010000, *pid)?,
+            Packet::Pubrel(pid) => encode_with_pid(writer, 0b01100010, *pid)?,
+            Packet::Pubcomp(pid) => encode_with_pid(writer, 0b01110000, *pid)?,
+            Packet::Subscribe(subscribe) => encode_body(writer, 0b10000010, subscribe)?,
+            Packet::Suback(suback) => encode_body(writer, 0b10010000, suback)?,
+            Packet::Unsubscribe(unsubscribe) => encode_body(writer, 0b10100010, unsubscribe)?,
+            Packet::Unsuback(pid) => encode_with_pid(writer, 0b10110000, *pid)?,
+        }
+        Ok(total)
+    }
+
+    /// Return the total length of bytes the packet encoded into.
+    pub fn encode_len(&self) -> Result<usize, Error> {
+        let remaining_len = match self {
+            Packet::Pingreq => return Ok(2),
+            Packet::Pingresp => return Ok(2),
+            Packet::Disconnect => return Ok(2),
+            Packet::Connack(_) => return Ok(4),
+            Packet::Puback(_) => return Ok(4),
+            Packet::Pubrec(_) => return Ok(4),
+            Packet::Pubrel(_) => return Ok(4),
+            Packet::Pubcomp(_) => return Ok(4),
+            Packet::Unsuback(_) => return Ok(4),
+            Packet::Connect(inner) => inner.encode_len(),
+            Packet::Publish(inner) => inner.encode_len(),
+            Packet::Subscribe(inner) => inner.encode_len(),
+            Packet::Suback(inner) => inner.encode_len(),
+            Packet::Unsubscribe(inner) => inner.encode_len(),
+        };
+        Ok(total_len(remaining_len)?)
+    }
+
+    // Borrowed strings are not validated when the packet is built.
+    fn check_topics(&self) -> Result<(), Error> {
+        match self {
+            Packet::Connect(Connect {
+                last_will: Some(last_will),
+                ..
+            }) => {
+                check_topic_name(last_will.topic_name)?;
+            }
+            Packet::Publish(publish) => {
+                check_topic_name(publish.topic_name)?;
+            }
+            Packet::Subscribe(subscribe) => {
+                if subscribe.topics.is_empty() {
+                    return Err(crate::Error::EmptySubscription.into());
+                }
+                for (topic_filter, _) in &subscribe.topics {
+                    check_filter(topic_filter)?;
+                }
+            }
+            Packet::Unsubscribe(unsubscribe) => {
+                if unsubscribe.topics.is_empty() {
+                    return Err(crate::Error::EmptySubscription.into());
+                }
+                for topic_filter in &unsubscribe.topics {
+                    check_filter(topic_filter)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[inline]
+fn encode_empty(writer: &mut &mut [u8], control_byte: u8) -> Result<(), crate::Error> {
+    write_u8(writer, control_byte)?;
+    write_u8(writer, 0)
+}
+
+#[inline]
+fn encode_with_pid(writer: &mut &mut [u8], control_byte: u8, pid: Pid) -> Result<(), crate::Error> {
+    write_u8(writer, control_byte)?;
+    write_u8(writer, 2)?;
+    write_u16(writer, pid.value())
+}
+
+#[inline]
+fn encode_body<E: Encodable>(
+    writer: &mut &mut [u8],
+    control_byte: u8,
+    body: &E,
+) -> Result<(), crate::Error> {
+    write_u8(writer, control_byte)?;
+    write_var_int(writer, body.encode_len())?;
+    body.encode(writer)
+}
+
+impl<'a, const N: usize> From<Connect<'a>> for Packet<'a, N> {
+    fn from(p: Connect<'a>) -> Self {
+        Packet::Connect(p)
+    }
+}
+
+impl<'a, const N: usize> From<Publish<'a>> for Packet<'a, N> {
+    fn from(p: Publish<'a>) -> Self {
+        Packet::Publish(p)
+    }
+}
+
+impl<const N: usize> From<Connack> for Packet<'_, N> {
+    fn from(p: Connack) -> Self {
+        Packet::Connack(p)
+    }
+}
+
+impl<'a, const N: usize> From<Subscribe<'a, N>> for Packet<'a, N> {
+    fn from(p: Subscribe<'a, N>) -> Self {
+        Packet::Subscribe(p)
+    }
+}
+
+impl<const N: usize> From<Suback<N>> for Packet<'_, N> {
+    fn from(p: Suback<N>) -> Self {
+        Packet::Suback(p)
+    }
+}
+
+impl<'a, const N: usize> From<Unsubscribe<'a, N>> for Packet<'a, N> {
+    fn from(p: Unsubscribe<'a, N>) -> Self {
+        Packet::Unsubscribe(p)
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{
+    read_string, read_u16, write_string, write_u16, Encodable, Pid, QoS, QosPid, SyncWrite,
+};
+
+use super::{check_topic_name, Error};
+
+/// Publish packet body type, the payload is the rest of the packet.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Publish<'a> {
+    pub dup: bool,
+    pub retain: bool,
+    pub qos_pid: QosPid,
+    pub topic_name: &'a str,
+    pub payload: &'a [u8],
+}
+
+impl<'a> Publish<'a> {
+    pub fn new(qos_pid: QosPid, topic_name: &'a str, payload: &'a [u8]) -> Self {
+        Publish {
+            dup: false,
+            retain: false,
+            qos_pid,
+            topic_name,
+            payload,
+        }
+    }
+
+    /// Decode the packet body, `buf` must end where the packet ends.
+    pub fn decode(buf: &'a [u8], offset: &mut usize, flags: u8) -> Result<Self, Error> {
+        let topic_name = check_topic_name(read_string(buf, offset)?)?;
+        let qos_pid = match QoS::from_u8((flags & 0b110) >> 1)? {
+            QoS::Level0 => QosPid::Level0,
+            QoS::Level1 => QosPid::Level1(Pid::try_from(read_u16(buf, offset)?)?),
+            QoS::Level2 => QosPid::Level2(Pid::try_from(read_u16(buf, offset)?)?),
+        };
+        let payload = &buf[*offset..];
+        *offset = buf.len();
+        Ok(Publish {
+            dup: flags & 0b1000 != 0,
+            retain: flags & 1 == 1,
+            qos_pid,
+            topic_name,
+            payload,
+        })
+    }
+}
+
+impl Encodable for Publish<'_> {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), crate::Error> {
+        write_string(writer, self.topic_name)?;
+        match self.qos_pid {
+            QosPid::Level0 => {}
+            QosPid::Level1(pid) | QosPid::Level2(pid) => {
+                write_u16(writer, pid.value())?;
+            }
+        }
+        writer.write_all(self.payload)?;
+        Ok(())
+    }
+
+    fn encode_len(&self) -> usize {
+        let mut length = 2 + self.topic_name.len();
+        match self.qos_pid {
+            QosPid::Level0 => {}
+            QosPid::Level1(_) | QosPid::Level2(_) => {
+                length += 2;
+            }
+        }
+        length += self.payload.len();
+        length
+    }
+}
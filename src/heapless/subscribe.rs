@@ -0,0 +1,175 @@
+use heapless::Vec;
+
+use crate::{
+    read_string, read_u16, read_u8, write_string, write_u16, write_u8, Encodable, Pid, QoS,
+    SyncWrite,
+};
+
+use super::{check_filter, Error};
+
+/// Subscribe packet body type, holds at most `N` topic filters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subscribe<'a, const N: usize> {
+    pub pid: Pid,
+    pub topics: Vec<(&'a str, QoS), N>,
+}
+
+/// Suback packet body type, holds at most `N` return codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suback<const N: usize> {
+    pub pid: Pid,
+    pub topics: Vec<SubscribeReturnCode, N>,
+}
+
+/// Unsubscribe packet body type, holds at most `N` topic filters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsubscribe<'a, const N: usize> {
+    pub pid: Pid,
+    pub topics: Vec<&'a str, N>,
+}
+
+impl<'a, const N: usize> Subscribe<'a, N> {
+    pub fn new(pid: Pid, topics: Vec<(&'a str, QoS), N>) -> Self {
+        Self { pid, topics }
+    }
+
+    /// Decode the packet body, `buf` must end where the packet ends.
+    pub fn decode(buf: &'a [u8], offset: &mut usize) -> Result<Self, Error> {
+        let pid = Pid::try_from(read_u16(buf, offset)?)?;
+        if *offset == buf.len() {
+            return Err(crate::Error::EmptySubscription.into());
+        }
+        let mut topics = Vec::new();
+        while *offset < buf.len() {
+            let topic_filter = check_filter(read_string(buf, offset)?)?;
+            let max_qos = QoS::from_u8(read_u8(buf, offset)?)?;
+            topics
+                .push((topic_filter, max_qos))
+                .map_err(|_| Error::CapacityExceeded(N))?;
+        }
+        Ok(Subscribe { pid, topics })
+    }
+}
+
+impl<const N: usize> Encodable for Subscribe<'_, N> {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), crate::Error> {
+        write_u16(writer, self.pid.value())?;
+        for (topic_filter, max_qos) in &self.topics {
+            write_string(writer, topic_filter)?;
+            write_u8(writer, *max_qos as u8)?;
+        }
+        Ok(())
+    }
+
+    fn encode_len(&self) -> usize {
+        2 + self
+            .topics
+            .iter()
+            .map(|(filter, _)| 3 + filter.len())
+            .sum::<usize>()
+    }
+}
+
+impl<const N: usize> Suback<N> {
+    pub fn new(pid: Pid, topics: Vec<SubscribeReturnCode, N>) -> Self {
+        Self { pid, topics }
+    }
+
+    /// Decode the packet body, `buf` must end where the packet ends.
+    pub fn decode(buf: &[u8], offset: &mut usize) -> Result<Self, Error> {
+        let pid = Pid::try_from(read_u16(buf, offset)?)?;
+        let mut topics = Vec::new();
+        while *offset < buf.len() {
+            let code = SubscribeReturnCode::from_u8(read_u8(buf, offset)?)?;
+            topics.push(code).map_err(|_| Error::CapacityExceeded(N))?;
+        }
+        Ok(Suback { pid, topics })
+    }
+}
+
+impl<const N: usize> Encodable for Suback<N> {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), crate::Error> {
+        write_u16(writer, self.pid.value())?;
+        for code in &self.topics {
+            write_u8(writer, *code as u8)?;
+        }
+        Ok(())
+    }
+
+    fn encode_len(&self) -> usize {
+        2 + self.topics.len()
+    }
+}
+
+impl<'a, const N: usize> Unsubscribe<'a, N> {
+    pub fn new(pid: Pid, topics: Vec<&'a str, N>) -> Self {
+        Self { pid, topics }
+    }
+
+    /// Decode the packet body, `buf` must end where the packet ends.
+    pub fn decode(buf: &'a [u8], offset: &mut usize) -> Result<Self, Error> {
+        let pid = Pid::try_from(read_u16(buf, offset)?)?;
+        if *offset == buf.len() {
+            return Err(crate::Error::EmptySubscription.into());
+        }
+        let mut topics = Vec::new();
+        while *offset < buf.len() {
+            let topic_filter = check_filter(read_string(buf, offset)?)?;
+            topics
+                .push(topic_filter)
+                .map_err(|_| Error::CapacityExceeded(N))?;
+        }
+        Ok(Unsubscribe { pid, topics })
+    }
+}
+
+impl<const N: usize> Encodable for Unsubscribe<'_, N> {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), crate::Error> {
+        write_u16(writer, self.pid.value())?;
+        for topic_filter in &self.topics {
+            write_string(writer, topic_filter)?;
+        }
+        Ok(())
+    }
+
+    fn encode_len(&self) -> usize {
+        2 + self
+            .topics
+            .iter()
+            .map(|filter| 2 + filter.len())
+            .sum::<usize>()
+    }
+}
+
+/// Subscribe return code type.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SubscribeReturnCode {
+    MaxLevel0 = 0,
+    MaxLevel1 = 1,
+    MaxLevel2 = 2,
+    Failure = 0x80,
+}
+
+impl SubscribeReturnCode {
+    pub fn from_u8(value: u8) -> Result<SubscribeReturnCode, crate::Error> {
+        match value {
+            0x80 => Ok(SubscribeReturnCode::Failure),
+            0 => Ok(SubscribeReturnCode::MaxLevel0),
+            1 => Ok(SubscribeReturnCode::MaxLevel1),
+            2 => Ok(SubscribeReturnCode::MaxLevel2),
+            _ => Err(crate::Error::InvalidQos(value)),
+        }
+    }
+}
+
+impl From<QoS> for SubscribeReturnCode {
+    fn from(qos: QoS) -> SubscribeReturnCode {
+        match qos {
+            QoS::Level0 => SubscribeReturnCode::MaxLevel0,
+            QoS::Level1 => SubscribeReturnCode::MaxLevel1,
+            QoS::Level2 => SubscribeReturnCode::MaxLevel2,
+        }
+    }
+}
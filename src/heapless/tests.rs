@@ -0,0 +1,228 @@
+use heapless::Vec;
+
+use crate::{block_on, Buffer, BufferHandle, Pid, Protocol, QoS, QosPid, ReadStrategy};
+
+use super::*;
+
+type Pkt<'a> = Packet<'a, 4>;
+
+fn assert_round_trip(packet: Pkt<'_>, expected: &[u8]) {
+    let mut buf = [0u8; 128];
+    let n = packet.encode(&mut buf).unwrap();
+    assert_eq!(&buf[..n], expected, "{packet:?}");
+    assert_eq!(packet.encode_len().unwrap(), n);
+    let (decoded, used) = Pkt::decode(&buf[..n]).unwrap().unwrap();
+    assert_eq!(decoded, packet);
+    assert_eq!(used, n);
+}
+
+#[test]
+fn test_heapless_round_trip() {
+    let pid = Pid::try_from(10).unwrap();
+    assert_round_trip(Packet::Pingreq, &[0xc0, 0]);
+    assert_round_trip(Packet::Disconnect, &[0xe0, 0]);
+    assert_round_trip(Packet::Puback(pid), &[0x40, 2, 0, 10]);
+    assert_round_trip(Packet::Pubrel(pid), &[0x62, 2, 0, 10]);
+    assert_round_trip(
+        Connack::new(true, ConnectReturnCode::Accepted).into(),
+        &[0x20, 2, 1, 0],
+    );
+
+    let mut publish = Publish::new(QosPid::Level1(pid), "a/b", b"hi");
+    publish.retain = true;
+    assert_round_trip(
+        publish.into(),
+        &[0x33, 9, 0, 3, b'a', b'/', b'b', 0, 10, b'h', b'i'],
+    );
+
+    let topics = Vec::from_slice(&[("a/+", QoS::Level1), ("#", QoS::Level0)]).unwrap();
+    assert_round_trip(
+        Subscribe::new(pid, topics).into(),
+        &[0x82, 12, 0, 10, 0, 3, b'a', b'/', b'+', 1, 0, 1, b'#', 0],
+    );
+    let codes =
+        Vec::from_slice(&[SubscribeReturnCode::MaxLevel1, SubscribeReturnCode::Failure]).unwrap();
+    assert_round_trip(Suback::new(pid, codes).into(), &[0x90, 4, 0, 10, 1, 0x80]);
+    let topics = Vec::from_slice(&["a/b"]).unwrap();
+    assert_round_trip(
+        Unsubscribe::new(pid, topics).into(),
+        &[0xa2, 7, 0, 10, 0, 3, b'a', b'/', b'b'],
+    );
+
+    let mut connect = Connect::new("c1", 30);
+    connect.last_will = Some(LastWill::new(QoS::Level1, "will", b"bye"));
+    connect.username = Some("user");
+    connect.password = Some(b"pass");
+    let mut buf = [0u8; 64];
+    let n = Pkt::from(connect.clone()).encode(&mut buf).unwrap();
+    let (decoded, used) = Pkt::decode(&buf[..n]).unwrap().unwrap();
+    assert_eq!(decoded, Packet::Connect(connect));
+    assert_eq!(used, n);
+}
+
+#[test]
+fn test_heapless_decode_partial() {
+    let data = [0x30, 6, 0, 1, b'a', b'x', b'y', b'z', 0xc0, 0];
+    for end in 0..8 {
+        assert_eq!(Pkt::decode(&data[..end]), Ok(None));
+    }
+    let (packet, used) = Pkt::decode(&data).unwrap().unwrap();
+    assert_eq!(used, 8);
+    assert_eq!(
+        packet,
+        Packet::Publish(Publish::new(QosPid::Level0, "a", b"xyz"))
+    );
+    let (packet, used) = Pkt::decode(&data[used..]).unwrap().unwrap();
+    assert_eq!((packet, used), (Packet::Pingreq, 2));
+}
+
+#[test]
+fn test_heapless_decode_errors() {
+    // Remaining length shorter than the topic name
+    assert_eq!(
+        Pkt::decode(&[0x30, 2, 0, 3, b'a']),
+        Err(crate::Error::InvalidRemainingLength.into())
+    );
+    // Wildcard in topic name
+    assert_eq!(
+        Pkt::decode(&[0x30, 3, 0, 1, b'+']),
+        Err(Error::InvalidTopicName)
+    );
+    // Invalid flags
+    assert_eq!(
+        Pkt::decode(&[0x80, 6, 0, 1, 0, 1, b'a', 0]),
+        Err(crate::Error::InvalidHeader.into())
+    );
+    // Too many topics for the capacity
+    assert_eq!(
+        Packet::<1>::decode(&[0x82, 10, 0, 1, 0, 1, b'a', 0, 0, 1, b'b', 0]),
+        Err(Error::CapacityExceeded(1))
+    );
+    // MQTT v5.0 is not supported
+    assert_eq!(
+        Pkt::decode(&[0x10, 12, 0, 4, b'M', b'Q', b'T', b'T', 5, 2, 0, 0, 0, 0]),
+        Err(crate::Error::UnexpectedProtocol(Protocol::V500).into())
+    );
+}
+
+#[test]
+fn test_heapless_encode_errors() {
+    let publish = Pkt::from(Publish::new(QosPid::Level0, "a/b", b"payload"));
+    let mut buf = [0u8; 8];
+    assert_eq!(publish.encode(&mut buf), Err(Error::BufferTooSmall(14)));
+
+    let publish = Pkt::from(Publish::new(QosPid::Level0, "a/#", b""));
+    assert_eq!(publish.encode(&mut buf), Err(Error::InvalidTopicName));
+
+    let subscribe = Pkt::from(Subscribe::new(Pid::default(), Vec::new()));
+    assert_eq!(
+        subscribe.encode(&mut buf),
+        Err(crate::Error::EmptySubscription.into())
+    );
+}
+
+#[cfg(feature = "v3")]
+#[test]
+fn test_heapless_same_as_v3() {
+    use core::convert::TryFrom;
+
+    use bytes::Bytes;
+
+    use crate::{v3, TopicFilter, TopicName};
+
+    let pid = Pid::try_from(7).unwrap();
+    let mut connect = Connect::new("client", 60);
+    connect.last_will = Some(LastWill::new(QoS::Level2, "will/t", b"gone"));
+    connect.password = Some(b"secret");
+    let mut v3_connect = v3::Connect::new("client".into(), 60);
+    v3_connect.last_will = Some(v3::LastWill::new(
+        QoS::Level2,
+        TopicName::try_from("will/t").unwrap(),
+        Bytes::from_static(b"gone"),
+    ));
+    v3_connect.password = Some(Bytes::from_static(b"secret"));
+
+    let topics = Vec::from_slice(&[("x/#", QoS::Level2)]).unwrap();
+    let v3_topics = vec![(TopicFilter::try_from("x/#").unwrap(), QoS::Level2)];
+
+    for (packet, v3_packet) in [
+        (Pkt::from(connect), v3::Packet::from(v3_connect)),
+        (
+            Publish::new(QosPid::Level2(pid), "t", b"data").into(),
+            v3::Publish::new(
+                QosPid::Level2(pid),
+                TopicName::try_from("t").unwrap(),
+                Bytes::from_static(b"data"),
+            )
+            .into(),
+        ),
+        (
+            Subscribe::new(pid, topics).into(),
+            v3::Subscribe::new(pid, v3_topics).into(),
+        ),
+        (Packet::Pubcomp(pid), v3::Packet::Pubcomp(pid)),
+    ] {
+        let mut buf = [0u8; 64];
+        let n = packet.encode(&mut buf).unwrap();
+        assert_eq!(&buf[..n], v3_packet.encode().unwrap().as_ref());
+    }
+}
+
+#[test]
+fn test_static_buffer() {
+    let mut buffer = StaticBuffer::<16>::new();
+    assert_eq!(buffer.read_strategy(16), ReadStrategy::Buffer);
+    assert_eq!(buffer.read_strategy(17), ReadStrategy::Buffer);
+    assert_eq!(
+        block_on(buffer.acquire(17)).unwrap_err(),
+        Error::BufferTooSmall(17)
+    );
+
+    let mut handle = block_on(buffer.acquire(4)).unwrap();
+    assert_eq!(handle.capacity(), 16);
+    let (slice, capacity) = handle.as_mut_slice();
+    assert_eq!(capacity, 16);
+    slice[0].write(0xAB);
+    handle.set_len(1);
+    let cloned = handle.clone();
+    assert_eq!(cloned.as_slice(cloned.len()), &[0xAB]);
+    block_on(buffer.release(handle)).unwrap();
+}
+
+#[test]
+fn test_heapless_read_packet() {
+    let mut buffer = StaticBuffer::<16>::new();
+
+    let mut data: &[u8] = &[0x30, 0x06, 0x00, 0x01, b't', b'd', b'a', b't', 0xc0, 0x00];
+    let handle = block_on(read_packet(&mut data, &mut buffer)).unwrap();
+    assert_eq!(handle.len(), 8);
+    let (packet, used) = Pkt::decode(handle.as_slice(handle.len())).unwrap().unwrap();
+    assert_eq!(packet, Publish::new(QosPid::Level0, "t", b"dat").into());
+    assert_eq!(used, 8);
+    let handle = block_on(read_packet(&mut data, &mut buffer)).unwrap();
+    assert_eq!(
+        Pkt::decode(handle.as_slice(handle.len())).unwrap(),
+        Some((Packet::Pingreq, 2))
+    );
+    assert!(block_on(read_packet(&mut data, &mut buffer))
+        .unwrap_err()
+        .is_eof());
+
+    // A non-minimal remaining length is copied as received.
+    let mut data: &[u8] = &[0xc0, 0x80, 0x00];
+    let handle = block_on(read_packet(&mut data, &mut buffer)).unwrap();
+    assert_eq!(handle.as_slice(handle.len()), &[0xc0, 0x80, 0x00]);
+
+    // The body of a packet larger than the buffer is not read.
+    let mut data: &[u8] = &[0x30, 0x20, 0x00];
+    assert_eq!(
+        block_on(read_packet(&mut data, &mut buffer)).unwrap_err(),
+        Error::BufferTooSmall(34)
+    );
+    assert_eq!(data, &[0x00]);
+
+    let mut data: &[u8] = &[0x30, 0x06, 0x00, 0x01];
+    assert!(block_on(read_packet(&mut data, &mut buffer))
+        .unwrap_err()
+        .is_eof());
+}
@@ -3,24 +3,84 @@
 #[cfg(feature = "std")]
 extern crate std;
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
 mod common;
+#[cfg(feature = "heapless")]
+pub mod heapless;
+#[cfg(feature = "pcap")]
+pub mod pcap;
+#[cfg(feature = "proxy")]
+pub mod proxy;
+#[cfg(feature = "sn")]
+pub mod sn;
+#[cfg(feature = "v3")]
 pub mod v3;
+#[cfg(feature = "v5")]
 pub mod v5;
+#[cfg(feature = "websocket")]
+pub mod websocket;
 
+#[cfg(any(feature = "v3", feature = "v5", feature = "sn"))]
+#[allow(unused_imports)]
+pub(crate) use common::packet_from;
+#[cfg(any(feature = "v3", feature = "heapless"))]
+#[allow(unused_imports)]
+pub(crate) use common::write_string;
+#[allow(unused_imports)]
+pub(crate) use common::{
+    block_on, decode_var_int, decode_var_int_async, read_u8_async, write_bytes, write_u16,
+    write_u8, AsyncRead, AsyncWrite, SyncRead, SyncWrite, ToError,
+};
+#[cfg(any(feature = "alloc", feature = "heapless"))]
 #[allow(unused_imports)]
 pub(crate) use common::{
-    block_on, decode_var_int, decode_var_int_async, encode_packet, packet_from, read_bytes,
-    read_bytes_async, read_raw_bytes, read_string, read_string_async, read_u16, read_u16_async,
-    read_u32, read_u32_async, read_u8, read_u8_async, write_bytes, write_string, write_u16,
-    write_u32, write_u8, write_var_int, AsyncRead, AsyncWrite, SyncRead, SyncWrite, ToError,
+    check_topic_filter, is_invalid_topic_name, read_bytes, read_raw_bytes, read_u16, read_u8,
 };
+#[cfg(feature = "alloc")]
+#[allow(unused_imports)]
+pub(crate) use common::{
+    check_var_int, decode_slice, new_header, read_bytes_async, read_u16_async,
+};
+#[cfg(any(feature = "v3", feature = "v5"))]
+#[allow(unused_imports)]
+pub(crate) use common::{
+    copy_payload, dissect_connect_flags, dissect_packets, dissect_protocol, encode_packet,
+    read_raw_header, read_string_async, DResult, Dissector,
+};
+#[cfg(all(test, feature = "std", any(feature = "v3", feature = "v5")))]
+#[allow(unused_imports)]
+pub(crate) use common::{raw_packets, Conformance, Statement, Status};
+#[cfg(any(feature = "v3", feature = "v5", feature = "heapless"))]
+#[allow(unused_imports)]
+pub(crate) use common::{read_string, write_var_int};
+#[cfg(feature = "v5")]
+#[allow(unused_imports)]
+pub(crate) use common::{read_u32, read_u32_async, write_u32};
+#[cfg(all(test, any(feature = "v3", feature = "v5")))]
+#[allow(unused_imports)]
+pub(crate) use common::{trickle_reader, TrickleReader};
 
+#[cfg(not(feature = "tokio"))]
+pub use common::PayloadReadError;
+pub use common::{
+    decode_raw_header, decode_raw_header_async, header_len, remaining_len, total_len, var_int_len,
+    Buffer, BufferHandle, DecodeOptions, Encodable, Error, IoErrorKind, Leniencies, Leniency,
+    LenientReport, Metrics, NoopMetrics, PayloadReader, Pid, Protocol, QoS, QosPid, ReadStrategy,
+    StringPolicy, LEVEL_SEP, MATCH_ALL_CHAR, MATCH_ALL_STR, MATCH_ONE_CHAR, MATCH_ONE_STR,
+    SHARED_PREFIX, SYS_PREFIX,
+};
+#[cfg(feature = "alloc")]
 pub use common::{
-    decode_raw_header_async, header_len, remaining_len, total_len, var_int_len, Buffer,
-    BufferHandle, ClientId, Encodable, Error, GenericPollPacket, GenericPollPacketState,
-    IoErrorKind, MockBuffer, MockBufferConfig, MockBufferHandle, Pid, PollHeader, Protocol, QoS,
-    QosPid, ReadStrategy, TopicFilter, TopicName, Username, VarBytes, LEVEL_SEP, MATCH_ALL_CHAR,
-    MATCH_ALL_STR, MATCH_ONE_CHAR, MATCH_ONE_STR, SHARED_PREFIX, SYS_PREFIX,
+    BudgetedBuffer, BudgetedHandle, BufferPool, BufferPoolConfig, BufferPoolStats, BufferResult,
+    BytesBuffer, BytesBufferHandle, ClientId, ConnectionBudget, GenericDecodeBatch,
+    GenericPollPacket, GenericPollPacketState, MemoryBudget, MemoryBudgetConfig, MemoryBudgetStats,
+    MemoryPermit, MemoryReserve, PollHeader, PooledBuffer, TopicFilter, TopicName, Username,
+    VarBytes,
 };
+#[cfg(any(feature = "v3", feature = "v5"))]
+pub use common::{DissectError, Dissection, Field};
+#[cfg(feature = "alloc")]
+#[allow(deprecated)]
+pub use common::{MockBuffer, MockBufferConfig, MockBufferHandle};
@@ -0,0 +1,322 @@
+use std::io::Read;
+use std::time::Duration;
+use std::vec::Vec;
+
+use super::Error;
+
+/// The pcapng Section Header Block type, also the first 4 bytes of a file.
+const SHB_TYPE: [u8; 4] = [0x0a, 0x0d, 0x0d, 0x0a];
+/// Block types of pcapng.
+const IDB_TYPE: u32 = 1;
+const PB_TYPE: u32 = 2;
+const SPB_TYPE: u32 = 3;
+const EPB_TYPE: u32 = 6;
+/// Interface option holding the timestamp resolution.
+const IF_TSRESOL: u16 = 9;
+/// Upper bound of a record or block, larger ones are treated as corruption.
+const MAX_RECORD_LEN: usize = 16 * 1024 * 1024;
+
+/// A frame read from a capture file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// Capture time since the UNIX epoch.
+    pub timestamp: Duration,
+    /// Link-layer header type of the interface.
+    pub link_type: u16,
+    /// The captured bytes, may be shorter than the frame on the wire.
+    pub data: Vec<u8>,
+}
+
+/// Byte order of the capture file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Endian {
+    big: bool,
+}
+
+impl Endian {
+    fn u16(self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.big {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+
+    fn u32(self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.big {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Interface {
+    link_type: u16,
+    /// Timestamp units per second.
+    ts_units: u64,
+}
+
+#[derive(Debug)]
+enum Format {
+    Pcap {
+        endian: Endian,
+        ts_units: u64,
+        link_type: u16,
+    },
+    PcapNg {
+        endian: Endian,
+        interfaces: Vec<Interface>,
+    },
+}
+
+/// Reader of the frames in a pcap or pcapng file, the format is detected
+/// from the magic number.
+#[derive(Debug)]
+pub struct CaptureReader<R> {
+    reader: R,
+    format: Format,
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Read the file header.
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        let (big, ts_units) = match magic {
+            [0xd4, 0xc3, 0xb2, 0xa1] => (false, 1_000_000),
+            [0xa1, 0xb2, 0xc3, 0xd4] => (true, 1_000_000),
+            [0x4d, 0x3c, 0xb2, 0xa1] => (false, 1_000_000_000),
+            [0xa1, 0xb2, 0x3c, 0x4d] => (true, 1_000_000_000),
+            SHB_TYPE => {
+                let endian = read_section_header(&mut reader)?;
+                return Ok(CaptureReader {
+                    reader,
+                    format: Format::PcapNg {
+                        endian,
+                        interfaces: Vec::new(),
+                    },
+                });
+            }
+            _ => return Err(Error::InvalidMagic(u32::from_be_bytes(magic))),
+        };
+        let endian = Endian { big };
+        let mut header = [0u8; 20];
+        reader.read_exact(&mut header)?;
+        // The upper bits of the link type field hold the FCS length.
+        let link_type = endian.u32(&header[16..20]) as u16;
+        Ok(CaptureReader {
+            reader,
+            format: Format::Pcap {
+                endian,
+                ts_units,
+                link_type,
+            },
+        })
+    }
+
+    /// Link-layer header type of a pcap file, pcapng files have one per
+    /// interface.
+    pub fn link_type(&self) -> Option<u16> {
+        match self.format {
+            Format::Pcap { link_type, .. } => Some(link_type),
+            Format::PcapNg { .. } => None,
+        }
+    }
+
+    /// Read the next frame, return `Ok(None)` at the end of the file.
+    pub fn next_frame(&mut self) -> Result<Option<Frame>, Error> {
+        match self.format {
+            Format::Pcap {
+                endian,
+                ts_units,
+                link_type,
+            } => {
+                let mut header = [0u8; 16];
+                if !read_or_eof(&mut self.reader, &mut header)? {
+                    return Ok(None);
+                }
+                let seconds = endian.u32(&header[0..4]);
+                let fraction = endian.u32(&header[4..8]);
+                let len = endian.u32(&header[8..12]) as usize;
+                if len > MAX_RECORD_LEN {
+                    return Err(Error::InvalidFile("record too large"));
+                }
+                let mut data = std::vec![0u8; len];
+                self.reader.read_exact(&mut data)?;
+                let timestamp =
+                    Duration::from_secs(seconds.into()) + timestamp(fraction.into(), ts_units);
+                Ok(Some(Frame {
+                    timestamp,
+                    link_type,
+                    data,
+                }))
+            }
+            Format::PcapNg { .. } => self.next_block_frame(),
+        }
+    }
+
+    fn next_block_frame(&mut self) -> Result<Option<Frame>, Error> {
+        loop {
+            let mut header = [0u8; 8];
+            if !read_or_eof(&mut self.reader, &mut header)? {
+                return Ok(None);
+            }
+            if header[..4] == SHB_TYPE {
+                // A new section, it may change the byte order.
+                let mut rest = [0u8; 4];
+                rest.copy_from_slice(&header[4..8]);
+                let endian = read_section_header(&mut Read::chain(&rest[..], &mut self.reader))?;
+                self.format = Format::PcapNg {
+                    endian,
+                    interfaces: Vec::new(),
+                };
+                continue;
+            }
+            let Format::PcapNg {
+                endian,
+                ref mut interfaces,
+            } = self.format
+            else {
+                unreachable!("pcapng block in a pcap file");
+            };
+            let typ = endian.u32(&header[0..4]);
+            let body = read_block_body(&mut self.reader, endian.u32(&header[4..8]))?;
+            match typ {
+                IDB_TYPE => {
+                    if body.len() < 8 {
+                        return Err(Error::InvalidFile("interface description too short"));
+                    }
+                    let ts_units = interface_ts_units(endian, &body[8..])?;
+                    interfaces.push(Interface {
+                        link_type: endian.u16(&body[0..2]),
+                        ts_units,
+                    });
+                }
+                EPB_TYPE | PB_TYPE => {
+                    if body.len() < 20 {
+                        return Err(Error::InvalidFile("packet block too short"));
+                    }
+                    let interface_id = if typ == EPB_TYPE {
+                        endian.u32(&body[0..4])
+                    } else {
+                        endian.u16(&body[0..2]).into()
+                    };
+                    let interface = interfaces
+                        .get(interface_id as usize)
+                        .ok_or(Error::InvalidFile("packet of an unknown interface"))?;
+                    let ts = (u64::from(endian.u32(&body[4..8])) << 32)
+                        | u64::from(endian.u32(&body[8..12]));
+                    let len = endian.u32(&body[12..16]) as usize;
+                    let data = body
+                        .get(20..20 + len)
+                        .ok_or(Error::InvalidFile("packet data exceeds the block"))?;
+                    return Ok(Some(Frame {
+                        timestamp: timestamp(ts, interface.ts_units),
+                        link_type: interface.link_type,
+                        data: data.to_vec(),
+                    }));
+                }
+                SPB_TYPE => {
+                    if body.len() < 4 {
+                        return Err(Error::InvalidFile("packet block too short"));
+                    }
+                    let interface = interfaces
+                        .first()
+                        .ok_or(Error::InvalidFile("packet of an unknown interface"))?;
+                    // The captured length is only bounded by the block size.
+                    let len = (endian.u32(&body[0..4]) as usize).min(body.len() - 4);
+                    return Ok(Some(Frame {
+                        timestamp: Duration::ZERO,
+                        link_type: interface.link_type,
+                        data: body[4..4 + len].to_vec(),
+                    }));
+                }
+                // Name resolution, statistics and custom blocks
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Read the Section Header Block after its type, return the byte order.
+fn read_section_header<R: Read>(reader: &mut R) -> Result<Endian, Error> {
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header)?;
+    let endian = match header[4..8] {
+        [0x1a, 0x2b, 0x3c, 0x4d] => Endian { big: true },
+        [0x4d, 0x3c, 0x2b, 0x1a] => Endian { big: false },
+        _ => return Err(Error::InvalidFile("invalid byte-order magic")),
+    };
+    let total_len = endian.u32(&header[0..4]) as usize;
+    if total_len < 28 || !total_len.is_multiple_of(4) || total_len > MAX_RECORD_LEN {
+        return Err(Error::InvalidFile("invalid block length"));
+    }
+    // The rest of the body after the byte-order magic, and the trailing length
+    let mut body = std::vec![0u8; total_len - 12];
+    reader.read_exact(&mut body)?;
+    if endian.u16(&body[0..2]) != 1 {
+        return Err(Error::InvalidFile("unsupported pcapng version"));
+    }
+    Ok(endian)
+}
+
+/// Read the block body after the type and length, and the trailing length.
+fn read_block_body<R: Read>(reader: &mut R, total_len: u32) -> Result<Vec<u8>, Error> {
+    let total_len = total_len as usize;
+    if total_len < 12 || !total_len.is_multiple_of(4) || total_len > MAX_RECORD_LEN {
+        return Err(Error::InvalidFile("invalid block length"));
+    }
+    let mut body = std::vec![0u8; total_len - 8];
+    reader.read_exact(&mut body)?;
+    body.truncate(total_len - 12);
+    Ok(body)
+}
+
+/// Timestamp units per second from the `if_tsresol` option, microseconds by
+/// default.
+fn interface_ts_units(endian: Endian, mut options: &[u8]) -> Result<u64, Error> {
+    while options.len() >= 4 {
+        let code = endian.u16(&options[0..2]);
+        let len = endian.u16(&options[2..4]) as usize;
+        let value = options
+            .get(4..4 + len)
+            .ok_or(Error::InvalidFile("option exceeds the block"))?;
+        match code {
+            0 => break,
+            IF_TSRESOL if len == 1 => {
+                let exponent = u32::from(value[0] & 0x7f);
+                let base: u64 = if value[0] & 0x80 == 0 { 10 } else { 2 };
+                return base
+                    .checked_pow(exponent)
+                    .ok_or(Error::InvalidFile("invalid timestamp resolution"));
+            }
+            _ => {}
+        }
+        options = options.get(4 + len.next_multiple_of(4)..).unwrap_or(&[]);
+    }
+    Ok(1_000_000)
+}
+
+fn timestamp(ts: u64, units: u64) -> Duration {
+    let nanos = u128::from(ts % units) * 1_000_000_000 / u128::from(units);
+    Duration::new(ts / units, nanos as u32)
+}
+
+/// Fill `buf`, return `Ok(false)` if the reader is at its end.
+fn read_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool, Error> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(Error::InvalidFile("truncated record header")),
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,54 @@
+use thiserror::Error;
+
+use crate::v5::ErrorV5;
+
+/// Errors of the capture reader, also reported for the MQTT streams found
+/// in the capture.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Common error of MQTT v3 and v5, also used for io errors.
+    #[error("common error of v3/v5: {0}")]
+    Common(#[from] crate::Error),
+
+    /// Error of the MQTT v5.0 decoder.
+    #[error("v5 error: {0}")]
+    V5(#[from] ErrorV5),
+
+    /// The file is neither a pcap nor a pcapng file.
+    #[error("invalid capture file magic: `{0:#010x}`")]
+    InvalidMagic(u32),
+
+    /// The capture file is malformed.
+    #[error("invalid capture file: {0}")]
+    InvalidFile(&'static str),
+
+    /// Link-layer header type of a pcap file other than Ethernet, Linux
+    /// cooked capture, raw IP or BSD loopback.
+    #[error("unsupported link type: `{0}`")]
+    UnsupportedLinkType(u16),
+
+    /// The bytes following a lost TCP segment exceed
+    /// [`PcapConfig::max_pending`](super::PcapConfig::max_pending).
+    #[error("missing TCP segment, {0} bytes pending")]
+    MissingSegment(usize),
+
+    /// A TCP segment was cut by the snapshot length of the capture.
+    #[error("TCP segment truncated by the capture")]
+    TruncatedSegment,
+}
+
+impl Error {
+    pub fn is_eof(&self) -> bool {
+        if let Error::Common(e) = self {
+            e.is_eof()
+        } else {
+            false
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Common(err.into())
+    }
+}
@@ -0,0 +1,35 @@
+//! Offline reader of MQTT traffic in [pcap] and [pcapng] capture files
+//!
+//! [`PcapReader`] reads the frames of Ethernet, Linux cooked, raw IP or
+//! loopback captures, reassembles the TCP streams of the MQTT server ports
+//! and decodes both directions with the v3.x or v5.0 codec, picked from the
+//! CONNECT packet of each connection.
+//!
+//! ```no_run
+//! use mqtt_proto::pcap::PcapReader;
+//!
+//! for event in PcapReader::open("capture.pcapng").unwrap() {
+//!     let event = event.unwrap();
+//!     println!("{:?} {:?} {:?}", event.timestamp, event.direction, event.packet);
+//! }
+//! ```
+//!
+//! [pcap]: https://www.ietf.org/archive/id/draft-ietf-opsawg-pcap-04.html
+//! [pcapng]: https://www.ietf.org/archive/id/draft-ietf-opsawg-pcapng-02.html
+
+mod capture;
+mod error;
+mod net;
+mod reader;
+mod stream;
+
+#[cfg(test)]
+mod tests;
+
+pub use capture::{CaptureReader, Frame};
+pub use error::Error;
+pub use net::{
+    is_supported_link_type, LINKTYPE_ETHERNET, LINKTYPE_IPV4, LINKTYPE_IPV6, LINKTYPE_LINUX_SLL,
+    LINKTYPE_LINUX_SLL2, LINKTYPE_LOOP, LINKTYPE_NULL, LINKTYPE_RAW,
+};
+pub use reader::{Direction, Event, Packet, PcapConfig, PcapReader, PcapStats};
@@ -0,0 +1,173 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// Link-layer header types, see <https://www.tcpdump.org/linktypes.html>.
+pub const LINKTYPE_NULL: u16 = 0;
+pub const LINKTYPE_ETHERNET: u16 = 1;
+pub const LINKTYPE_RAW: u16 = 101;
+pub const LINKTYPE_LOOP: u16 = 108;
+pub const LINKTYPE_LINUX_SLL: u16 = 113;
+pub const LINKTYPE_IPV4: u16 = 228;
+pub const LINKTYPE_IPV6: u16 = 229;
+pub const LINKTYPE_LINUX_SLL2: u16 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: [u16; 3] = [0x8100, 0x88a8, 0x9100];
+const IPPROTO_TCP: u8 = 6;
+
+pub(crate) const TCP_FIN: u8 = 0x01;
+pub(crate) const TCP_SYN: u8 = 0x02;
+pub(crate) const TCP_RST: u8 = 0x04;
+pub(crate) const TCP_ACK: u8 = 0x10;
+
+/// Whether frames of the link type are understood.
+pub fn is_supported_link_type(link_type: u16) -> bool {
+    matches!(
+        link_type,
+        LINKTYPE_NULL
+            | LINKTYPE_ETHERNET
+            | LINKTYPE_RAW
+            | LINKTYPE_LOOP
+            | LINKTYPE_LINUX_SLL
+            | LINKTYPE_IPV4
+            | LINKTYPE_IPV6
+            | LINKTYPE_LINUX_SLL2
+    )
+}
+
+/// A TCP segment of a captured frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Segment<'a> {
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    pub seq: u32,
+    pub flags: u8,
+    pub payload: &'a [u8],
+    /// The capture holds less than the IP packet length.
+    pub truncated: bool,
+}
+
+/// Parse the TCP segment in a frame, return `None` for other protocols,
+/// IP fragments and malformed frames.
+pub(crate) fn parse_frame(link_type: u16, data: &[u8]) -> Option<Segment<'_>> {
+    let (ethertype, ip) = match link_type {
+        LINKTYPE_ETHERNET => {
+            let mut ethertype = read_u16(data, 12)?;
+            let mut offset = 14;
+            while ETHERTYPE_VLAN.contains(&ethertype) {
+                ethertype = read_u16(data, offset + 2)?;
+                offset += 4;
+            }
+            (ethertype, data.get(offset..)?)
+        }
+        LINKTYPE_LINUX_SLL => (read_u16(data, 14)?, data.get(16..)?),
+        LINKTYPE_LINUX_SLL2 => (read_u16(data, 0)?, data.get(20..)?),
+        LINKTYPE_NULL | LINKTYPE_LOOP => {
+            // The address family is in the byte order of the capturing host
+            // for NULL, and big-endian for LOOP.
+            let family = data.get(..4)?;
+            let family = if family[0] == 0 { family[3] } else { family[0] };
+            let ethertype = match family {
+                2 => ETHERTYPE_IPV4,
+                10 | 24 | 28 | 30 => ETHERTYPE_IPV6,
+                _ => return None,
+            };
+            (ethertype, data.get(4..)?)
+        }
+        LINKTYPE_RAW => match data.first()? >> 4 {
+            4 => (ETHERTYPE_IPV4, data),
+            6 => (ETHERTYPE_IPV6, data),
+            _ => return None,
+        },
+        LINKTYPE_IPV4 => (ETHERTYPE_IPV4, data),
+        LINKTYPE_IPV6 => (ETHERTYPE_IPV6, data),
+        _ => return None,
+    };
+    match ethertype {
+        ETHERTYPE_IPV4 => parse_ipv4(ip),
+        ETHERTYPE_IPV6 => parse_ipv6(ip),
+        _ => None,
+    }
+}
+
+fn parse_ipv4(data: &[u8]) -> Option<Segment<'_>> {
+    let header_len = usize::from(data.first()? & 0x0f) * 4;
+    if data[0] >> 4 != 4 || header_len < 20 || data.len() < header_len {
+        return None;
+    }
+    // More fragments flag or a fragment offset
+    if read_u16(data, 6)? & 0x3fff != 0 || data[9] != IPPROTO_TCP {
+        return None;
+    }
+    let src = Ipv4Addr::new(data[12], data[13], data[14], data[15]);
+    let dst = Ipv4Addr::new(data[16], data[17], data[18], data[19]);
+    // The total length is 0 with TCP segmentation offload.
+    let total_len = match usize::from(read_u16(data, 2)?) {
+        0 => data.len(),
+        len => len.max(header_len),
+    };
+    let truncated = total_len > data.len();
+    let end = total_len.min(data.len());
+    parse_tcp(src.into(), dst.into(), &data[header_len..end], truncated)
+}
+
+fn parse_ipv6(data: &[u8]) -> Option<Segment<'_>> {
+    if data.len() < 40 || data[0] >> 4 != 6 {
+        return None;
+    }
+    let src: [u8; 16] = data[8..24].try_into().ok()?;
+    let dst: [u8; 16] = data[24..40].try_into().ok()?;
+    // The payload length is 0 for jumbograms.
+    let total_len = match usize::from(read_u16(data, 4)?) {
+        0 => data.len(),
+        len => 40 + len,
+    };
+    let truncated = total_len > data.len();
+    let mut payload = &data[40..total_len.min(data.len())];
+    let mut next_header = data[6];
+    loop {
+        match next_header {
+            IPPROTO_TCP => break,
+            // Hop-by-hop, routing and destination options
+            0 | 43 | 60 => {
+                let len = (usize::from(*payload.get(1)?) + 1) * 8;
+                next_header = payload[0];
+                payload = payload.get(len..)?;
+            }
+            // Authentication header
+            51 => {
+                let len = (usize::from(*payload.get(1)?) + 2) * 4;
+                next_header = payload[0];
+                payload = payload.get(len..)?;
+            }
+            // Fragments and other protocols
+            _ => return None,
+        }
+    }
+    parse_tcp(
+        Ipv6Addr::from(src).into(),
+        Ipv6Addr::from(dst).into(),
+        payload,
+        truncated,
+    )
+}
+
+fn parse_tcp(src: IpAddr, dst: IpAddr, data: &[u8], truncated: bool) -> Option<Segment<'_>> {
+    let header_len = usize::from(data.get(12)? >> 4) * 4;
+    if header_len < 20 {
+        return None;
+    }
+    Some(Segment {
+        src: SocketAddr::new(src, read_u16(data, 0)?),
+        dst: SocketAddr::new(dst, read_u16(data, 2)?),
+        seq: u32::from_be_bytes(data.get(4..8)?.try_into().ok()?),
+        flags: data[13],
+        payload: data.get(header_len..)?,
+        truncated,
+    })
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
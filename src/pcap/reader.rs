@@ -0,0 +1,391 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+use std::vec::Vec;
+
+use crate::{decode_raw_header, v3, v5, Protocol};
+
+use super::capture::{CaptureReader, Frame};
+use super::net::{
+    is_supported_link_type, parse_frame, Segment, TCP_ACK, TCP_FIN, TCP_RST, TCP_SYN,
+};
+use super::stream::TcpStream;
+use super::Error;
+
+/// Configuration of [`PcapReader`].
+#[derive(Debug, Clone)]
+pub struct PcapConfig {
+    /// Server ports of the MQTT connections, the other side is the client.
+    pub ports: Vec<u16>,
+    /// Protocol of the connections captured without their CONNECT packet.
+    pub fallback_protocol: Protocol,
+    /// Upper bound of the bytes kept after a lost TCP segment, per direction.
+    pub max_pending: usize,
+}
+
+impl Default for PcapConfig {
+    fn default() -> Self {
+        Self {
+            ports: std::vec![1883],
+            fallback_protocol: Protocol::V311,
+            max_pending: 1024 * 1024,
+        }
+    }
+}
+
+/// Direction of a packet within its connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    ClientToServer,
+    ServerToClient,
+}
+
+/// A packet decoded by the MQTT v3.x or v5.0 codec.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packet {
+    V3(v3::Packet),
+    V5(Box<v5::Packet>),
+}
+
+/// A packet, or the error that stopped decoding one direction of a
+/// connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// Capture time of the frame completing the packet.
+    pub timestamp: Duration,
+    pub client: SocketAddr,
+    pub server: SocketAddr,
+    pub direction: Direction,
+    /// Protocol of the connection, detected from the CONNECT packet or
+    /// [`PcapConfig::fallback_protocol`].
+    pub protocol: Protocol,
+    pub packet: Result<Packet, Error>,
+}
+
+/// Counters of a [`PcapReader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PcapStats {
+    /// Frames read from the file.
+    pub frames: usize,
+    /// Frames of a link type the reader does not understand.
+    pub unsupported_frames: usize,
+    /// TCP segments to or from the server ports.
+    pub segments: usize,
+    /// Connections seen, a new SYN starts a new connection.
+    pub connections: usize,
+}
+
+/// Read a pcap or pcapng capture, reassemble the TCP streams of the server
+/// ports and decode the MQTT packets of both directions.
+///
+/// The iterator yields the packets in capture order. Stream errors, like an
+/// invalid packet or a lost segment, are yielded as events and stop the
+/// decoding of that direction only. Errors of the file end the iteration,
+/// after the packets still buffered.
+#[derive(Debug)]
+pub struct PcapReader<R> {
+    capture: CaptureReader<R>,
+    config: PcapConfig,
+    connections: BTreeMap<(SocketAddr, SocketAddr), Connection>,
+    events: VecDeque<Event>,
+    stats: PcapStats,
+    last_timestamp: Duration,
+    finished: bool,
+}
+
+impl PcapReader<BufReader<File>> {
+    /// Open a capture file with the default configuration.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read> PcapReader<R> {
+    pub fn new(reader: R) -> Result<Self, Error> {
+        Self::with_config(reader, PcapConfig::default())
+    }
+
+    pub fn with_config(reader: R, config: PcapConfig) -> Result<Self, Error> {
+        let capture = CaptureReader::new(reader)?;
+        if let Some(link_type) = capture.link_type() {
+            if !is_supported_link_type(link_type) {
+                return Err(Error::UnsupportedLinkType(link_type));
+            }
+        }
+        Ok(PcapReader {
+            capture,
+            config,
+            connections: BTreeMap::new(),
+            events: VecDeque::new(),
+            stats: PcapStats::default(),
+            last_timestamp: Duration::ZERO,
+            finished: false,
+        })
+    }
+
+    pub fn stats(&self) -> PcapStats {
+        self.stats
+    }
+
+    fn handle_frame(&mut self, frame: Frame) {
+        self.stats.frames += 1;
+        self.last_timestamp = frame.timestamp;
+        if !is_supported_link_type(frame.link_type) {
+            self.stats.unsupported_frames += 1;
+            return;
+        }
+        let Some(segment) = parse_frame(frame.link_type, &frame.data) else {
+            return;
+        };
+        let (client, server, direction) = if self.config.ports.contains(&segment.dst.port()) {
+            (segment.src, segment.dst, Direction::ClientToServer)
+        } else if self.config.ports.contains(&segment.src.port()) {
+            (segment.dst, segment.src, Direction::ServerToClient)
+        } else {
+            return;
+        };
+        self.stats.segments += 1;
+        let key = (client, server);
+        let new_connection = segment.flags & (TCP_SYN | TCP_ACK) == TCP_SYN;
+        let known = self.connections.contains_key(&key);
+        if !new_connection && !known && segment.payload.is_empty() {
+            // A late ACK or FIN of a closed connection
+            return;
+        }
+        if new_connection || !known {
+            self.stats.connections += 1;
+            if let Some(mut conn) = self.connections.remove(&key) {
+                conn.finish(&self.config, &mut self.events);
+            }
+            self.connections
+                .insert(key, Connection::new(client, server));
+        }
+        let conn = self.connections.get_mut(&key).expect("connection inserted");
+        conn.push(
+            frame.timestamp,
+            direction,
+            &segment,
+            &self.config,
+            &mut self.events,
+        );
+        if segment.flags & TCP_RST != 0 || conn.closed() {
+            if let Some(mut conn) = self.connections.remove(&key) {
+                conn.finish(&self.config, &mut self.events);
+            }
+        }
+    }
+
+    /// Decode the data of the connections still waiting for their protocol.
+    fn finish(&mut self) {
+        self.finished = true;
+        for conn in self.connections.values_mut() {
+            conn.timestamp = self.last_timestamp;
+            conn.finish(&self.config, &mut self.events);
+        }
+        self.connections.clear();
+    }
+}
+
+impl<R: Read> Iterator for PcapReader<R> {
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Some(Ok(event));
+            }
+            if self.finished {
+                return None;
+            }
+            match self.capture.next_frame() {
+                Ok(Some(frame)) => self.handle_frame(frame),
+                Ok(None) => self.finish(),
+                Err(err) => {
+                    self.finish();
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Connection {
+    client: SocketAddr,
+    server: SocketAddr,
+    protocol: Option<Protocol>,
+    /// Timestamp of the last segment.
+    timestamp: Duration,
+    /// Client to server and server to client.
+    halves: [Half; 2],
+}
+
+#[derive(Debug, Default)]
+struct Half {
+    tcp: TcpStream,
+    /// Reassembled bytes not decoded yet.
+    buf: Vec<u8>,
+    fin: bool,
+    /// Decoding stopped after an error.
+    failed: bool,
+}
+
+impl Connection {
+    fn new(client: SocketAddr, server: SocketAddr) -> Self {
+        Connection {
+            client,
+            server,
+            protocol: None,
+            timestamp: Duration::ZERO,
+            halves: Default::default(),
+        }
+    }
+
+    fn closed(&self) -> bool {
+        self.halves.iter().all(|half| half.fin)
+    }
+
+    fn push(
+        &mut self,
+        timestamp: Duration,
+        direction: Direction,
+        segment: &Segment<'_>,
+        config: &PcapConfig,
+        events: &mut VecDeque<Event>,
+    ) {
+        self.timestamp = timestamp;
+        let half = &mut self.halves[direction as usize];
+        half.fin |= segment.flags & TCP_FIN != 0;
+        if half.failed {
+            return;
+        }
+        let result = if segment.truncated {
+            Err(Error::TruncatedSegment)
+        } else {
+            half.tcp.push(
+                segment.seq,
+                segment.flags & TCP_SYN != 0,
+                segment.payload,
+                config.max_pending,
+                &mut half.buf,
+            )
+        };
+        if let Err(err) = result {
+            let protocol = self.protocol.unwrap_or(config.fallback_protocol);
+            self.fail(direction, protocol, err, events);
+            return;
+        }
+        if self.protocol.is_none() && direction == Direction::ClientToServer {
+            self.protocol = sniff(&half.buf, config.fallback_protocol);
+        }
+        if let Some(protocol) = self.protocol {
+            self.decode(Direction::ClientToServer, protocol, events);
+            self.decode(Direction::ServerToClient, protocol, events);
+        }
+    }
+
+    /// Decode the buffered data at the end of the connection or the capture.
+    fn finish(&mut self, config: &PcapConfig, events: &mut VecDeque<Event>) {
+        let protocol = *self.protocol.get_or_insert(config.fallback_protocol);
+        for direction in [Direction::ClientToServer, Direction::ServerToClient] {
+            self.decode(direction, protocol, events);
+            let half = &self.halves[direction as usize];
+            if !half.failed && half.tcp.pending_len() > 0 {
+                let err = Error::MissingSegment(half.tcp.pending_len());
+                self.fail(direction, protocol, err, events);
+            }
+        }
+    }
+
+    fn decode(&mut self, direction: Direction, protocol: Protocol, events: &mut VecDeque<Event>) {
+        if self.halves[direction as usize].failed {
+            return;
+        }
+        let buf = core::mem::take(&mut self.halves[direction as usize].buf);
+        let mut offset = 0;
+        let mut error = None;
+        while offset < buf.len() {
+            let bytes = &buf[offset..];
+            let result = if protocol == Protocol::V500 {
+                v5::Packet::decode_from_slice(bytes)
+                    .map(|packet| packet.map(|(packet, len)| (Packet::V5(Box::new(packet)), len)))
+                    .map_err(Error::from)
+            } else {
+                v3::Packet::decode_from_slice(bytes)
+                    .map(|packet| packet.map(|(packet, len)| (Packet::V3(packet), len)))
+                    .map_err(Error::from)
+            };
+            match result {
+                Ok(Some((packet, len))) => {
+                    offset += len;
+                    events.push_back(self.event(direction, protocol, Ok(packet)));
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    error = Some(err);
+                    break;
+                }
+            }
+        }
+        let half = &mut self.halves[direction as usize];
+        half.buf = buf;
+        half.buf.drain(..offset);
+        if let Some(err) = error {
+            self.fail(direction, protocol, err, events);
+        }
+    }
+
+    fn fail(
+        &mut self,
+        direction: Direction,
+        protocol: Protocol,
+        err: Error,
+        events: &mut VecDeque<Event>,
+    ) {
+        let half = &mut self.halves[direction as usize];
+        half.failed = true;
+        half.buf = Vec::new();
+        half.tcp = TcpStream::default();
+        events.push_back(self.event(direction, protocol, Err(err)));
+    }
+
+    fn event(
+        &self,
+        direction: Direction,
+        protocol: Protocol,
+        packet: Result<Packet, Error>,
+    ) -> Event {
+        Event {
+            timestamp: self.timestamp,
+            client: self.client,
+            server: self.server,
+            direction,
+            protocol,
+            packet,
+        }
+    }
+}
+
+/// Protocol of the CONNECT packet at the start of the client data, the
+/// fallback protocol if the data starts with another packet, `None` if more
+/// data is needed.
+fn sniff(buf: &[u8], fallback: Protocol) -> Option<Protocol> {
+    let (typ, _, var_bytes) = match decode_raw_header(buf) {
+        Ok(Some(header)) => header,
+        Ok(None) => return None,
+        Err(_) => return Some(fallback),
+    };
+    if typ >> 4 != 1 {
+        return Some(fallback);
+    }
+    let mut offset = 1 + var_bytes;
+    match Protocol::decode(buf, &mut offset) {
+        Ok(protocol) => Some(protocol),
+        Err(err) if err.is_eof() => None,
+        Err(_) => Some(fallback),
+    }
+}
@@ -0,0 +1,78 @@
+use std::vec::Vec;
+
+use super::Error;
+
+/// Reassembler of one direction of a TCP connection.
+///
+/// Retransmitted bytes are dropped, segments after a lost or reordered one
+/// are kept until the gap is filled. Sequence numbers are compared with
+/// wrapping arithmetic.
+#[derive(Debug, Default)]
+pub(crate) struct TcpStream {
+    /// Sequence number of the next byte in order, unknown until the SYN or
+    /// the first segment when the capture starts in the middle.
+    next_seq: Option<u32>,
+    /// Segments ahead of `next_seq`.
+    pending: Vec<(u32, Vec<u8>)>,
+    pending_len: usize,
+}
+
+impl TcpStream {
+    /// Add a segment, append the bytes now in order to `out`.
+    pub fn push(
+        &mut self,
+        seq: u32,
+        syn: bool,
+        payload: &[u8],
+        max_pending: usize,
+        out: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let seq = if syn {
+            // The SYN takes one sequence number.
+            let seq = seq.wrapping_add(1);
+            self.next_seq = Some(seq);
+            seq
+        } else {
+            seq
+        };
+        let next_seq = *self.next_seq.get_or_insert(seq);
+        if payload.is_empty() {
+            return Ok(());
+        }
+        if (seq.wrapping_sub(next_seq) as i32) > 0 {
+            self.pending_len += payload.len();
+            if self.pending_len > max_pending {
+                return Err(Error::MissingSegment(self.pending_len));
+            }
+            self.pending.push((seq, payload.to_vec()));
+            return Ok(());
+        }
+        self.append(seq, payload, out);
+        // Pending segments the new bytes reached
+        while let Some(idx) = self
+            .pending
+            .iter()
+            .position(|(seq, _)| (seq.wrapping_sub(self.next_seq.unwrap_or(*seq)) as i32) <= 0)
+        {
+            let (seq, payload) = self.pending.swap_remove(idx);
+            self.pending_len -= payload.len();
+            self.append(seq, &payload, out);
+        }
+        Ok(())
+    }
+
+    /// Append the bytes of a segment starting at or before `next_seq`.
+    fn append(&mut self, seq: u32, payload: &[u8], out: &mut Vec<u8>) {
+        let next_seq = self.next_seq.unwrap_or(seq);
+        let skip = next_seq.wrapping_sub(seq) as usize;
+        if let Some(data) = payload.get(skip..) {
+            out.extend_from_slice(data);
+            self.next_seq = Some(next_seq.wrapping_add(data.len() as u32));
+        }
+    }
+
+    /// Bytes waiting for a lost segment.
+    pub fn pending_len(&self) -> usize {
+        self.pending_len
+    }
+}
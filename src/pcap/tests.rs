@@ -0,0 +1,379 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+use std::vec::Vec;
+
+use bytes::Bytes;
+
+use super::*;
+use crate::{v3, v5, Pid, Protocol, QosPid, TopicName};
+
+const CLIENT: &str = "10.0.0.2:50000";
+const SERVER: &str = "10.0.0.1:1883";
+const SYN: u8 = 0x02;
+const ACK: u8 = 0x10;
+const PSH_ACK: u8 = 0x18;
+const FIN_ACK: u8 = 0x11;
+
+/// A TCP segment in an Ethernet frame with a VLAN tag.
+fn frame(src: &str, dst: &str, seq: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+    let src: SocketAddr = src.parse().unwrap();
+    let dst: SocketAddr = dst.parse().unwrap();
+    let mut tcp = Vec::new();
+    tcp.extend(src.port().to_be_bytes());
+    tcp.extend(dst.port().to_be_bytes());
+    tcp.extend(seq.to_be_bytes());
+    tcp.extend([0, 0, 0, 0, 0x50, flags, 0xff, 0xff, 0, 0, 0, 0]);
+    tcp.extend_from_slice(payload);
+    let mut data = std::vec![0xaa; 12];
+    data.extend([0x81, 0x00, 0x00, 0x01]);
+    match (src.ip(), dst.ip()) {
+        (std::net::IpAddr::V4(src), std::net::IpAddr::V4(dst)) => {
+            data.extend([0x08, 0x00, 0x45, 0]);
+            data.extend((20 + tcp.len() as u16).to_be_bytes());
+            data.extend([0, 0, 0x40, 0, 64, 6, 0, 0]);
+            data.extend(src.octets());
+            data.extend(dst.octets());
+        }
+        (std::net::IpAddr::V6(src), std::net::IpAddr::V6(dst)) => {
+            data.extend([0x86, 0xdd, 0x60, 0, 0, 0]);
+            data.extend((tcp.len() as u16).to_be_bytes());
+            data.extend([6, 64]);
+            data.extend(src.octets());
+            data.extend(dst.octets());
+        }
+        _ => unreachable!(),
+    }
+    data.extend(tcp);
+    data
+}
+
+/// A little-endian pcap file with microsecond timestamps.
+fn pcap(link_type: u32, frames: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let mut file = Vec::new();
+    file.extend([0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0]);
+    file.extend([0; 8]);
+    file.extend(65535u32.to_le_bytes());
+    file.extend(link_type.to_le_bytes());
+    for (millis, data) in frames {
+        file.extend((millis / 1000).to_le_bytes());
+        file.extend((millis % 1000 * 1000).to_le_bytes());
+        file.extend((data.len() as u32).to_le_bytes());
+        file.extend((data.len() as u32).to_le_bytes());
+        file.extend(data);
+    }
+    file
+}
+
+fn block(typ: u32, body: &[u8]) -> Vec<u8> {
+    let len = 12 + body.len().next_multiple_of(4) as u32;
+    let mut block = Vec::new();
+    block.extend(typ.to_be_bytes());
+    block.extend(len.to_be_bytes());
+    block.extend(body);
+    block.resize(len as usize - 4, 0);
+    block.extend(len.to_be_bytes());
+    block
+}
+
+/// A big-endian pcapng file with nanosecond timestamps.
+fn pcapng(frames: &[(u64, Vec<u8>)]) -> Vec<u8> {
+    let mut shb = std::vec![0x1a, 0x2b, 0x3c, 0x4d, 0, 1, 0, 0];
+    shb.extend([0xff; 8]);
+    let mut idb = std::vec![0, 1, 0, 0, 0, 0, 0xff, 0xff];
+    // if_tsresol = 9, then the end of options
+    idb.extend([0, 9, 0, 1, 9, 0, 0, 0, 0, 0, 0, 0]);
+    let mut file = block(0x0a0d0d0a, &shb);
+    file.extend(block(1, &idb));
+    // Name resolution block, skipped
+    file.extend(block(4, &[0, 0, 0, 0]));
+    for (nanos, data) in frames {
+        let mut epb = Vec::new();
+        epb.extend(0u32.to_be_bytes());
+        epb.extend(((nanos >> 32) as u32).to_be_bytes());
+        epb.extend((*nanos as u32).to_be_bytes());
+        epb.extend((data.len() as u32).to_be_bytes());
+        epb.extend((data.len() as u32).to_be_bytes());
+        epb.extend(data);
+        file.extend(block(6, &epb));
+    }
+    file
+}
+
+fn v3_bytes(packet: v3::Packet) -> Vec<u8> {
+    packet.encode().unwrap().as_ref().to_vec()
+}
+
+fn v5_bytes(packet: v5::Packet) -> Vec<u8> {
+    packet.encode().unwrap().as_ref().to_vec()
+}
+
+fn read_all(file: &[u8], config: PcapConfig) -> Vec<Event> {
+    PcapReader::with_config(file, config)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn test_pcap_v3_session() {
+    let connect = v3_bytes(v3::Connect::new("client".into(), 30).into());
+    let connack = v3_bytes(v3::Packet::Connack(v3::Connack::new(
+        false,
+        v3::ConnectReturnCode::Accepted,
+    )));
+    let publish = v3::Publish::new(
+        QosPid::Level1(Pid::try_from(1).unwrap()),
+        TopicName::try_from("a/b").unwrap(),
+        Bytes::from_static(b"hello"),
+    );
+    let publish_bytes = v3_bytes(publish.clone().into());
+    let (head, tail) = publish_bytes.split_at(4);
+    let c_seq = 1000u32;
+    let s_seq = u32::MAX - 2;
+    let c_data = c_seq + 1 + connect.len() as u32;
+    let file = pcap(
+        1,
+        &[
+            (0, frame(CLIENT, SERVER, c_seq, SYN, &[])),
+            (1, frame(SERVER, CLIENT, s_seq, SYN | ACK, &[])),
+            // CONNECT split in two segments
+            (2, frame(CLIENT, SERVER, c_seq + 1, PSH_ACK, &connect[..3])),
+            (3, frame(CLIENT, SERVER, c_seq + 4, PSH_ACK, &connect[3..])),
+            // The server sequence number wraps around
+            (
+                4,
+                frame(SERVER, CLIENT, s_seq.wrapping_add(1), PSH_ACK, &connack),
+            ),
+            // Reordered and retransmitted segments of the PUBLISH
+            (5, frame(CLIENT, SERVER, c_data + 4, PSH_ACK, tail)),
+            (6, frame(CLIENT, SERVER, c_data, PSH_ACK, head)),
+            (7, frame(CLIENT, SERVER, c_data, PSH_ACK, &publish_bytes)),
+            (
+                8,
+                frame(
+                    CLIENT,
+                    SERVER,
+                    c_data + publish_bytes.len() as u32,
+                    FIN_ACK,
+                    &[],
+                ),
+            ),
+            (
+                9,
+                frame(SERVER, CLIENT, s_seq.wrapping_add(5), FIN_ACK, &[]),
+            ),
+            (10, frame(CLIENT, SERVER, 0, ACK, &[])),
+        ],
+    );
+    let mut reader = PcapReader::new(&file[..]).unwrap();
+    let events: Vec<Event> = reader.by_ref().collect::<Result<_, _>>().unwrap();
+    let client: SocketAddr = CLIENT.parse().unwrap();
+    let server: SocketAddr = SERVER.parse().unwrap();
+    let summary: Vec<_> = events
+        .iter()
+        .map(|event| {
+            assert_eq!((event.client, event.server), (client, server));
+            assert_eq!(event.protocol, Protocol::V311);
+            (
+                event.timestamp,
+                event.direction,
+                event.packet.clone().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (
+                Duration::from_millis(3),
+                Direction::ClientToServer,
+                Packet::V3(v3::Connect::new("client".into(), 30).into())
+            ),
+            (
+                Duration::from_millis(4),
+                Direction::ServerToClient,
+                Packet::V3(v3::Packet::Connack(v3::Connack::new(
+                    false,
+                    v3::ConnectReturnCode::Accepted
+                )))
+            ),
+            (
+                Duration::from_millis(6),
+                Direction::ClientToServer,
+                Packet::V3(publish.into())
+            ),
+        ]
+    );
+    assert_eq!(
+        reader.stats(),
+        PcapStats {
+            frames: 11,
+            unsupported_frames: 0,
+            segments: 11,
+            connections: 1,
+        }
+    );
+}
+
+#[test]
+fn test_pcapng_v5_ipv6() {
+    let client = "[fd00::2]:50000";
+    let server = "[fd00::1]:8883";
+    let mut connect = v5::Connect::new("c".into(), 10);
+    connect.properties.session_expiry_interval = Some(60);
+    let connect = v5_bytes(connect.into());
+    let connack = v5_bytes(v5::Connack::new(false, v5::ConnectReasonCode::Success).into());
+    let file = pcapng(&[
+        // The CONNACK is captured before the CONNECT is complete
+        (
+            1_000_000_001,
+            frame(client, server, 1, PSH_ACK, &connect[..2]),
+        ),
+        (1_000_000_002, frame(server, client, 7, PSH_ACK, &connack)),
+        (
+            1_000_000_003,
+            frame(client, server, 3, PSH_ACK, &connect[2..]),
+        ),
+        (
+            1_000_000_004,
+            frame(server, client, 7 + connack.len() as u32, PSH_ACK, &[0xc0]),
+        ),
+    ]);
+    let config = PcapConfig {
+        ports: std::vec![1883, 8883],
+        ..Default::default()
+    };
+    let events = read_all(&file, config.clone());
+    // The incomplete PINGREQ is dropped at the end of the capture
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].timestamp, Duration::new(1, 3));
+    assert_eq!(events[0].direction, Direction::ClientToServer);
+    assert_eq!(events[0].protocol, Protocol::V500);
+    assert!(matches!(
+        &events[0].packet,
+        Ok(Packet::V5(packet)) if packet.get_type() == v5::PacketType::Connect
+    ));
+    // Decoded once the protocol is known
+    assert_eq!(events[1].timestamp, Duration::new(1, 3));
+    assert_eq!(events[1].direction, Direction::ServerToClient);
+    assert!(matches!(
+        &events[1].packet,
+        Ok(Packet::V5(packet)) if packet.get_type() == v5::PacketType::Connack
+    ));
+
+    // Not a server port
+    let events = read_all(&file, PcapConfig::default());
+    assert!(events.is_empty());
+}
+
+#[test]
+fn test_stream_errors() {
+    let pingreq = v3_bytes(v3::Packet::Pingreq);
+    let file = pcap(
+        1,
+        &[
+            // No CONNECT, the fallback protocol is used
+            (0, frame(CLIENT, SERVER, 100, PSH_ACK, &pingreq)),
+            (1, frame(SERVER, CLIENT, 500, PSH_ACK, &[0xd0, 0x00])),
+            (2, frame(SERVER, CLIENT, 502, PSH_ACK, &pingreq)),
+            // A segment is lost
+            (3, frame(CLIENT, SERVER, 106, PSH_ACK, &pingreq)),
+        ],
+    );
+    let config = PcapConfig {
+        fallback_protocol: Protocol::V500,
+        ..Default::default()
+    };
+    let events = read_all(&file, config);
+    let summary: Vec<_> = events
+        .iter()
+        .map(|event| (event.direction, event.protocol, event.packet.clone()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (
+                Direction::ClientToServer,
+                Protocol::V500,
+                Ok(Packet::V5(Box::new(v5::Packet::Pingreq)))
+            ),
+            (
+                Direction::ServerToClient,
+                Protocol::V500,
+                Ok(Packet::V5(Box::new(v5::Packet::Pingresp)))
+            ),
+            (
+                Direction::ServerToClient,
+                Protocol::V500,
+                Ok(Packet::V5(Box::new(v5::Packet::Pingreq)))
+            ),
+            (
+                Direction::ClientToServer,
+                Protocol::V500,
+                Err(Error::MissingSegment(2))
+            ),
+        ]
+    );
+
+    let file = pcap(
+        1,
+        &[
+            (0, frame(CLIENT, SERVER, 100, PSH_ACK, &[0x00, 0x00])),
+            (1, frame(CLIENT, SERVER, 102, PSH_ACK, &pingreq)),
+        ],
+    );
+    let events = read_all(&file, PcapConfig::default());
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        events[0].packet,
+        Err(Error::Common(crate::Error::InvalidHeader))
+    );
+
+    let config = PcapConfig {
+        max_pending: 3,
+        ..Default::default()
+    };
+    let file = pcap(
+        1,
+        &[
+            (0, frame(CLIENT, SERVER, 100, PSH_ACK, &pingreq)),
+            (1, frame(CLIENT, SERVER, 104, PSH_ACK, &pingreq)),
+            (2, frame(CLIENT, SERVER, 106, PSH_ACK, &pingreq)),
+        ],
+    );
+    let events = read_all(&file, config);
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].packet, Err(Error::MissingSegment(4)));
+}
+
+#[test]
+fn test_file_errors() {
+    assert_eq!(
+        PcapReader::new(&b"GET / HTTP/1.1"[..]).unwrap_err(),
+        Error::InvalidMagic(0x47455420)
+    );
+    assert_eq!(
+        PcapReader::new(&pcap(147, &[])[..]).unwrap_err(),
+        Error::UnsupportedLinkType(147)
+    );
+
+    // A truncated last record ends the iteration after the buffered data
+    let connect = v3_bytes(v3::Connect::new("c".into(), 10).into());
+    let mut file = pcap(
+        LINKTYPE_ETHERNET.into(),
+        &[(0, frame(CLIENT, SERVER, 1, PSH_ACK, &connect[..4]))],
+    );
+    file.extend([0; 10]);
+    let results: Vec<_> = PcapReader::new(&file[..]).unwrap().collect();
+    assert_eq!(results.len(), 1);
+    assert_eq!(
+        results[0],
+        Err(Error::InvalidFile("truncated record header"))
+    );
+
+    // Truncated by the snapshot length
+    let mut data = frame(CLIENT, SERVER, 1, PSH_ACK, &connect);
+    data.truncate(data.len() - 1);
+    let events = read_all(&pcap(1, &[(0, data)]), PcapConfig::default());
+    assert_eq!(events[0].packet, Err(Error::TruncatedSegment));
+}
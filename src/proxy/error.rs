@@ -0,0 +1,45 @@
+use thiserror::Error;
+
+/// Errors of the PROXY protocol header decoder.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Common error of MQTT v3 and v5, also used for io errors.
+    #[error("common error of v3/v5: {0}")]
+    Common(#[from] crate::Error),
+
+    /// The data does not start with a PROXY protocol signature.
+    #[error("invalid PROXY protocol signature")]
+    InvalidSignature,
+
+    /// The header is malformed.
+    #[error("invalid PROXY protocol header: {0}")]
+    InvalidHeader(&'static str),
+
+    /// Version of a binary header other than 2.
+    #[error("unsupported PROXY protocol version: `{0}`")]
+    UnsupportedVersion(u8),
+
+    /// Command of a binary header other than LOCAL and PROXY.
+    #[error("unsupported PROXY protocol command: `{0}`")]
+    UnsupportedCommand(u8),
+
+    /// Unknown address family or transport protocol of a binary header.
+    #[error("unsupported address family: `{0:#04x}`")]
+    UnsupportedFamily(u8),
+}
+
+impl Error {
+    pub fn is_eof(&self) -> bool {
+        if let Error::Common(e) = self {
+            e.is_eof()
+        } else {
+            false
+        }
+    }
+}
+
+impl<E: embedded_io::Error> From<E> for Error {
+    fn from(err: E) -> Error {
+        Error::Common(err.into())
+    }
+}
@@ -0,0 +1,277 @@
+use alloc::vec::Vec;
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+
+use bytes::Bytes;
+
+use super::Error;
+
+/// Signature of a version 2 (binary) header.
+pub const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Upper bound of a version 1 (text) header, CRLF included.
+pub const V1_MAX_LEN: usize = 107;
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+
+/// Length of the fixed part of a version 2 header.
+pub(crate) const V2_HEADER_LEN: usize = 16;
+
+const UNIX_ADDR_LEN: usize = 108;
+
+/// Addresses of the original connection.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ProxyAddresses {
+    /// Unknown or unsupported protocol, or a LOCAL command.
+    Unspec,
+    Inet {
+        source: SocketAddr,
+        destination: SocketAddr,
+    },
+    /// Unix socket paths, trailing zeros removed.
+    Unix { source: Bytes, destination: Bytes },
+}
+
+/// Type-length-value field of a version 2 header.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Tlv {
+    pub typ: u8,
+    pub value: Bytes,
+}
+
+impl Tlv {
+    pub const ALPN: u8 = 0x01;
+    pub const AUTHORITY: u8 = 0x02;
+    pub const CRC32C: u8 = 0x03;
+    pub const NOOP: u8 = 0x04;
+    pub const UNIQUE_ID: u8 = 0x05;
+    pub const SSL: u8 = 0x20;
+    pub const NETNS: u8 = 0x30;
+}
+
+/// PROXY protocol header.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProxyHeader {
+    /// 1 for the text format, 2 for the binary format.
+    pub version: u8,
+    /// A LOCAL command, sent by the proxy itself (e.g. health checks). The
+    /// connection endpoints are the real ones.
+    pub local: bool,
+    pub addresses: ProxyAddresses,
+    /// Only present in version 2 headers.
+    pub tlvs: Vec<Tlv>,
+}
+
+impl ProxyHeader {
+    /// Address of the original client.
+    pub fn source(&self) -> Option<SocketAddr> {
+        match self.addresses {
+            ProxyAddresses::Inet { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+
+    /// Address the original client connected to.
+    pub fn destination(&self) -> Option<SocketAddr> {
+        match self.addresses {
+            ProxyAddresses::Inet { destination, .. } => Some(destination),
+            _ => None,
+        }
+    }
+
+    /// Value of the first TLV of the type.
+    pub fn tlv(&self, typ: u8) -> Option<&Bytes> {
+        self.tlvs
+            .iter()
+            .find(|tlv| tlv.typ == typ)
+            .map(|tlv| &tlv.value)
+    }
+
+    /// Decode a version 1 or 2 header from the start of `buf`, return the
+    /// header and its length. If not enough bytes to decode the header, it
+    /// will return `Ok(None)`.
+    pub fn decode(buf: &[u8]) -> Result<Option<(Self, usize)>, Error> {
+        match buf.first() {
+            None => Ok(None),
+            Some(b'P') => Self::decode_v1(buf),
+            Some(b'\r') => Self::decode_v2(buf),
+            Some(_) => Err(Error::InvalidSignature),
+        }
+    }
+
+    fn decode_v1(buf: &[u8]) -> Result<Option<(Self, usize)>, Error> {
+        let prefix_len = buf.len().min(V1_PREFIX.len());
+        if buf[..prefix_len] != V1_PREFIX[..prefix_len] {
+            return Err(Error::InvalidSignature);
+        }
+        let searched = &buf[..buf.len().min(V1_MAX_LEN)];
+        let Some(end) = searched.windows(2).position(|w| w == b"\r\n") else {
+            if buf.len() >= V1_MAX_LEN {
+                return Err(Error::InvalidHeader("line too long"));
+            }
+            return Ok(None);
+        };
+        let line = core::str::from_utf8(&buf[V1_PREFIX.len()..end])
+            .map_err(|_| Error::InvalidHeader("line is not ascii"))?;
+        let mut parts = line.split(' ');
+        let addresses = match parts.next() {
+            Some("UNKNOWN") => ProxyAddresses::Unspec,
+            Some("TCP4") => {
+                let (source, destination) = parse_v1_addrs::<Ipv4Addr>(&mut parts)?;
+                ProxyAddresses::Inet {
+                    source,
+                    destination,
+                }
+            }
+            Some("TCP6") => {
+                let (source, destination) = parse_v1_addrs::<Ipv6Addr>(&mut parts)?;
+                ProxyAddresses::Inet {
+                    source,
+                    destination,
+                }
+            }
+            _ => return Err(Error::InvalidHeader("unknown protocol")),
+        };
+        let header = ProxyHeader {
+            version: 1,
+            local: false,
+            addresses,
+            tlvs: Vec::new(),
+        };
+        Ok(Some((header, end + 2)))
+    }
+
+    fn decode_v2(buf: &[u8]) -> Result<Option<(Self, usize)>, Error> {
+        let signature_len = buf.len().min(V2_SIGNATURE.len());
+        if buf[..signature_len] != V2_SIGNATURE[..signature_len] {
+            return Err(Error::InvalidSignature);
+        }
+        if buf.len() < V2_HEADER_LEN {
+            return Ok(None);
+        }
+        let ver_cmd = buf[12];
+        let family = buf[13];
+        let len = u16::from_be_bytes([buf[14], buf[15]]) as usize;
+        let total = V2_HEADER_LEN + len;
+        let Some(body) = buf.get(V2_HEADER_LEN..total) else {
+            return Ok(None);
+        };
+
+        if ver_cmd >> 4 != 2 {
+            return Err(Error::UnsupportedVersion(ver_cmd >> 4));
+        }
+        let local = match ver_cmd & 0x0F {
+            0 => true,
+            1 => false,
+            cmd => return Err(Error::UnsupportedCommand(cmd)),
+        };
+        if local || family >> 4 == 0 {
+            // The receiver must discard the address block and the TLVs of
+            // LOCAL and of AF_UNSPEC
+            let header = ProxyHeader {
+                version: 2,
+                local,
+                addresses: ProxyAddresses::Unspec,
+                tlvs: Vec::new(),
+            };
+            return Ok(Some((header, total)));
+        }
+
+        if family & 0x0F > 2 {
+            return Err(Error::UnsupportedFamily(family));
+        }
+        let addr_len = match family >> 4 {
+            1 => 12,
+            2 => 36,
+            3 => UNIX_ADDR_LEN * 2,
+            _ => return Err(Error::UnsupportedFamily(family)),
+        };
+        if body.len() < addr_len {
+            return Err(Error::InvalidHeader("address block too short"));
+        }
+        let (addr, mut rest) = body.split_at(addr_len);
+        let addresses = match family >> 4 {
+            1 => {
+                let ip = |b: &[u8]| Ipv4Addr::new(b[0], b[1], b[2], b[3]);
+                ProxyAddresses::Inet {
+                    source: SocketAddrV4::new(ip(&addr[0..4]), port(&addr[8..10])).into(),
+                    destination: SocketAddrV4::new(ip(&addr[4..8]), port(&addr[10..12])).into(),
+                }
+            }
+            2 => {
+                let ip = |b: &[u8]| Ipv6Addr::from(<[u8; 16]>::try_from(b).unwrap());
+                ProxyAddresses::Inet {
+                    source: SocketAddrV6::new(ip(&addr[0..16]), port(&addr[32..34]), 0, 0).into(),
+                    destination: SocketAddrV6::new(ip(&addr[16..32]), port(&addr[34..36]), 0, 0)
+                        .into(),
+                }
+            }
+            3 => {
+                let path = |b: &[u8]| {
+                    let end = b.iter().rposition(|c| *c != 0).map_or(0, |i| i + 1);
+                    Bytes::copy_from_slice(&b[..end])
+                };
+                ProxyAddresses::Unix {
+                    source: path(&addr[..UNIX_ADDR_LEN]),
+                    destination: path(&addr[UNIX_ADDR_LEN..]),
+                }
+            }
+            _ => ProxyAddresses::Unspec,
+        };
+
+        let mut tlvs = Vec::new();
+        while !rest.is_empty() {
+            if rest.len() < 3 {
+                return Err(Error::InvalidHeader("truncated TLV"));
+            }
+            let value_len = u16::from_be_bytes([rest[1], rest[2]]) as usize;
+            let Some(value) = rest.get(3..3 + value_len) else {
+                return Err(Error::InvalidHeader("truncated TLV"));
+            };
+            tlvs.push(Tlv {
+                typ: rest[0],
+                value: Bytes::copy_from_slice(value),
+            });
+            rest = &rest[3 + value_len..];
+        }
+
+        let header = ProxyHeader {
+            version: 2,
+            local,
+            addresses,
+            tlvs,
+        };
+        Ok(Some((header, total)))
+    }
+}
+
+fn port(buf: &[u8]) -> u16 {
+    u16::from_be_bytes([buf[0], buf[1]])
+}
+
+fn parse_v1_addrs<'a, A>(
+    parts: &mut impl Iterator<Item = &'a str>,
+) -> Result<(SocketAddr, SocketAddr), Error>
+where
+    A: core::str::FromStr + Into<IpAddr>,
+{
+    let mut next = || parts.next().ok_or(Error::InvalidHeader("missing address"));
+    let source_ip: A = next()?
+        .parse()
+        .map_err(|_| Error::InvalidHeader("invalid source address"))?;
+    let destination_ip: A = next()?
+        .parse()
+        .map_err(|_| Error::InvalidHeader("invalid destination address"))?;
+    let source_port: u16 = next()?
+        .parse()
+        .map_err(|_| Error::InvalidHeader("invalid source port"))?;
+    let destination_port: u16 = next()?
+        .parse()
+        .map_err(|_| Error::InvalidHeader("invalid destination port"))?;
+    if parts.next().is_some() {
+        return Err(Error::InvalidHeader("trailing fields"));
+    }
+    Ok((
+        SocketAddr::new(source_ip.into(), source_port),
+        SocketAddr::new(destination_ip.into(), destination_port),
+    ))
+}
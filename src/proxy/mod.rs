@@ -0,0 +1,20 @@
+//! [PROXY protocol] header decoder
+//!
+//! Load balancers like HAProxy or AWS NLB prepend a PROXY protocol header
+//! carrying the address of the original client before the first MQTT byte.
+//! [`ProxyStream::accept`] decodes a version 1 (text) or version 2 (binary)
+//! header if there is one, and the returned stream is read by
+//! `Packet::decode_async` or `PollPacket` like the raw connection.
+//!
+//! [PROXY protocol]: https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt
+
+mod error;
+mod header;
+mod stream;
+
+#[cfg(test)]
+mod tests;
+
+pub use error::Error;
+pub use header::{ProxyAddresses, ProxyHeader, Tlv, V1_MAX_LEN, V2_SIGNATURE};
+pub use stream::ProxyStream;
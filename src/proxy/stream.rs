@@ -0,0 +1,170 @@
+use alloc::vec::Vec;
+use core::net::SocketAddr;
+#[cfg(feature = "tokio")]
+use core::pin::Pin;
+#[cfg(feature = "tokio")]
+use core::task::{Context, Poll};
+
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncReadExt, ReadBuf};
+
+use crate::{read_u8_async, AsyncRead, ToError};
+
+use super::header::{V1_MAX_LEN, V2_HEADER_LEN};
+use super::{Error, ProxyHeader};
+
+/// Stream after an optional PROXY protocol header.
+///
+/// [`accept`](Self::accept) decodes the header, and reading returns the bytes
+/// after it, so the stream can be handed to `Packet::decode_async` or
+/// `PollPacket` as is. Writing goes to the inner stream.
+#[derive(Debug)]
+pub struct ProxyStream<S> {
+    inner: S,
+    header: Option<ProxyHeader>,
+    /// First byte read from a stream without a header.
+    pending: Option<u8>,
+}
+
+impl<S: AsyncRead + Unpin> ProxyStream<S> {
+    /// Decode the PROXY protocol header if the stream starts with one.
+    ///
+    /// The header is detected by its first byte, neither `P` nor `\r` starts
+    /// a CONNECT packet. Nothing is read after the header.
+    pub async fn accept(mut inner: S) -> Result<Self, Error> {
+        let first = read_u8_async(&mut inner).await?;
+        let mut buf = Vec::with_capacity(V1_MAX_LEN);
+        buf.push(first);
+        match first {
+            b'P' => {
+                // Byte by byte to stop right after the CRLF
+                while !buf.ends_with(b"\r\n") {
+                    if buf.len() >= V1_MAX_LEN {
+                        return Err(Error::InvalidHeader("line too long"));
+                    }
+                    buf.push(read_u8_async(&mut inner).await?);
+                    // Fail early on another protocol
+                    ProxyHeader::decode(&buf)?;
+                }
+            }
+            b'\r' => {
+                buf.resize(V2_HEADER_LEN, 0);
+                read_exact(&mut inner, &mut buf[1..]).await?;
+                ProxyHeader::decode(&buf)?;
+                let len = u16::from_be_bytes([buf[14], buf[15]]) as usize;
+                buf.resize(V2_HEADER_LEN + len, 0);
+                read_exact(&mut inner, &mut buf[V2_HEADER_LEN..]).await?;
+            }
+            _ => {
+                return Ok(ProxyStream {
+                    inner,
+                    header: None,
+                    pending: Some(first),
+                })
+            }
+        }
+        let (header, _) = ProxyHeader::decode(&buf)?.expect("whole header read");
+        Ok(ProxyStream {
+            inner,
+            header: Some(header),
+            pending: None,
+        })
+    }
+}
+
+async fn read_exact<S: AsyncRead + Unpin>(inner: &mut S, buf: &mut [u8]) -> Result<(), Error> {
+    inner.read_exact(buf).await.map_err(ToError::to_error)?;
+    Ok(())
+}
+
+impl<S> ProxyStream<S> {
+    pub fn header(&self) -> Option<&ProxyHeader> {
+        self.header.as_ref()
+    }
+
+    /// Address of the original client, `None` without a header or for a
+    /// LOCAL command, use the address of the connection then.
+    pub fn source(&self) -> Option<SocketAddr> {
+        self.header.as_ref().and_then(ProxyHeader::source)
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Return the inner stream and the byte already read from it when there
+    /// is no header.
+    pub fn into_parts(self) -> (S, Option<u8>) {
+        (self.inner, self.pending)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<S: tokio::io::AsyncRead + Unpin> tokio::io::AsyncRead for ProxyStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        if buf.remaining() > 0 {
+            if let Some(byte) = this.pending.take() {
+                buf.put_slice(&[byte]);
+                return Poll::Ready(Ok(()));
+            }
+        }
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<S: tokio::io::AsyncWrite + Unpin> tokio::io::AsyncWrite for ProxyStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+impl<S: embedded_io_async::ErrorType> embedded_io_async::ErrorType for ProxyStream<S> {
+    type Error = S::Error;
+}
+
+#[cfg(not(feature = "tokio"))]
+impl<S: embedded_io_async::Read> embedded_io_async::Read for ProxyStream<S> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if !buf.is_empty() {
+            if let Some(byte) = self.pending.take() {
+                buf[0] = byte;
+                return Ok(1);
+            }
+        }
+        self.inner.read(buf).await
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+impl<S: embedded_io_async::Write> embedded_io_async::Write for ProxyStream<S> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.inner.write(buf).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush().await
+    }
+}
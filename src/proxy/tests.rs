@@ -0,0 +1,254 @@
+use alloc::vec::Vec;
+use core::net::SocketAddr;
+
+use bytes::Bytes;
+
+use super::*;
+use crate::block_on;
+
+fn addr(value: &str) -> SocketAddr {
+    value.parse().unwrap()
+}
+
+fn v2_header(ver_cmd: u8, family: u8, body: &[u8]) -> Vec<u8> {
+    let mut data = V2_SIGNATURE.to_vec();
+    data.extend_from_slice(&[ver_cmd, family]);
+    data.extend_from_slice(&(body.len() as u16).to_be_bytes());
+    data.extend_from_slice(body);
+    data
+}
+
+#[test]
+fn test_decode_v1() {
+    let data = b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\n\x10";
+    let (header, len) = ProxyHeader::decode(data).unwrap().unwrap();
+    assert_eq!(len, data.len() - 1);
+    assert_eq!(header.version, 1);
+    assert_eq!(header.source(), Some(addr("192.168.0.1:56324")));
+    assert_eq!(header.destination(), Some(addr("192.168.0.11:443")));
+
+    let data = b"PROXY TCP6 2001:db8::1 ::1 4000 1883\r\n";
+    let (header, _) = ProxyHeader::decode(data).unwrap().unwrap();
+    assert_eq!(header.source(), Some(addr("[2001:db8::1]:4000")));
+    assert_eq!(header.destination(), Some(addr("[::1]:1883")));
+
+    for data in [&b"PROXY UNKNOWN\r\n"[..], b"PROXY UNKNOWN ::1 ::1 1 2\r\n"] {
+        let (header, len) = ProxyHeader::decode(data).unwrap().unwrap();
+        assert_eq!(len, data.len());
+        assert_eq!(header.addresses, ProxyAddresses::Unspec);
+    }
+
+    // Not enough bytes
+    let data = b"PROXY TCP4 1.2.3.4 5.6.7.8 1 2\r\n";
+    for end in 0..data.len() {
+        assert_eq!(ProxyHeader::decode(&data[..end]), Ok(None));
+    }
+
+    let cases: &[(&[u8], Error)] = &[
+        (b"PRAXY", Error::InvalidSignature),
+        (b"\x10\x0c", Error::InvalidSignature),
+        (
+            b"PROXY UDP4 1.2.3.4 5.6.7.8 1 2\r\n",
+            Error::InvalidHeader("unknown protocol"),
+        ),
+        (
+            b"PROXY TCP4 ::1 5.6.7.8 1 2\r\n",
+            Error::InvalidHeader("invalid source address"),
+        ),
+        (
+            b"PROXY TCP4 1.2.3.4 5.6.7.8 1 65536\r\n",
+            Error::InvalidHeader("invalid destination port"),
+        ),
+        (
+            b"PROXY TCP4 1.2.3.4 5.6.7.8 1\r\n",
+            Error::InvalidHeader("missing address"),
+        ),
+    ];
+    for (data, err) in cases {
+        assert_eq!(ProxyHeader::decode(data).as_ref(), Err(err));
+    }
+    let mut long = b"PROXY UNKNOWN ".to_vec();
+    long.resize(V1_MAX_LEN, b'x');
+    assert_eq!(
+        ProxyHeader::decode(&long),
+        Err(Error::InvalidHeader("line too long"))
+    );
+}
+
+#[test]
+fn test_decode_v2() {
+    let mut body = alloc::vec![127, 0, 0, 1, 10, 0, 0, 2, 0x1F, 0x90, 0x07, 0x5B];
+    body.extend_from_slice(&[Tlv::ALPN, 0, 4]);
+    body.extend_from_slice(b"mqtt");
+    body.extend_from_slice(&[Tlv::NOOP, 0, 0]);
+    let data = v2_header(0x21, 0x11, &body);
+    let (header, len) = ProxyHeader::decode(&data).unwrap().unwrap();
+    assert_eq!(len, data.len());
+    assert_eq!(header.version, 2);
+    assert!(!header.local);
+    assert_eq!(header.source(), Some(addr("127.0.0.1:8080")));
+    assert_eq!(header.destination(), Some(addr("10.0.0.2:1883")));
+    assert_eq!(header.tlv(Tlv::ALPN), Some(&Bytes::from_static(b"mqtt")));
+    assert_eq!(header.tlv(Tlv::NOOP), Some(&Bytes::new()));
+    assert_eq!(header.tlv(Tlv::AUTHORITY), None);
+    for end in 0..data.len() {
+        assert_eq!(ProxyHeader::decode(&data[..end]), Ok(None));
+    }
+
+    let mut body = [0u8; 36];
+    body[15] = 1;
+    body[31] = 2;
+    body[32..].copy_from_slice(&[0, 1, 0, 2]);
+    let (header, _) = ProxyHeader::decode(&v2_header(0x21, 0x21, &body))
+        .unwrap()
+        .unwrap();
+    assert_eq!(header.source(), Some(addr("[::1]:1")));
+    assert_eq!(header.destination(), Some(addr("[::2]:2")));
+
+    let mut body = [0u8; 216];
+    body[..4].copy_from_slice(b"/a/b");
+    body[108..110].copy_from_slice(b"/c");
+    let (header, _) = ProxyHeader::decode(&v2_header(0x21, 0x31, &body))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        header.addresses,
+        ProxyAddresses::Unix {
+            source: Bytes::from_static(b"/a/b"),
+            destination: Bytes::from_static(b"/c"),
+        }
+    );
+
+    // LOCAL discards the address block
+    let (header, _) = ProxyHeader::decode(&v2_header(0x20, 0x11, &[1, 2, 3]))
+        .unwrap()
+        .unwrap();
+    assert!(header.local);
+    assert_eq!(header.addresses, ProxyAddresses::Unspec);
+    assert!(header.tlvs.is_empty());
+
+    // So does AF_UNSPEC, whatever the block holds
+    for family in [0x00, 0x01, 0x02] {
+        let data = v2_header(0x21, family, &[Tlv::ALPN, 0, 2, 0]);
+        let (header, len) = ProxyHeader::decode(&data).unwrap().unwrap();
+        assert_eq!(len, data.len());
+        assert!(!header.local);
+        assert_eq!(header.addresses, ProxyAddresses::Unspec);
+        assert!(header.tlvs.is_empty());
+    }
+
+    let cases = [
+        (
+            v2_header(0x11, 0x11, &[0; 12]),
+            Error::UnsupportedVersion(1),
+        ),
+        (
+            v2_header(0x22, 0x11, &[0; 12]),
+            Error::UnsupportedCommand(2),
+        ),
+        (
+            v2_header(0x21, 0x41, &[0; 12]),
+            Error::UnsupportedFamily(0x41),
+        ),
+        (
+            v2_header(0x21, 0x13, &[0; 12]),
+            Error::UnsupportedFamily(0x13),
+        ),
+        (
+            v2_header(0x21, 0x11, &[0; 8]),
+            Error::InvalidHeader("address block too short"),
+        ),
+        (
+            v2_header(0x21, 0x11, &[0; 14]),
+            Error::InvalidHeader("truncated TLV"),
+        ),
+    ];
+    for (data, err) in cases {
+        assert_eq!(ProxyHeader::decode(&data), Err(err));
+    }
+    assert_eq!(
+        ProxyHeader::decode(b"\r\n\r\n\0\r\nQUIX"),
+        Err(Error::InvalidSignature)
+    );
+}
+
+#[test]
+fn test_accept() {
+    let mut v1 = b"PROXY TCP4 1.2.3.4 5.6.7.8 1000 1883\r\n".to_vec();
+    let mut v2 = v2_header(
+        0x21,
+        0x11,
+        &[1, 2, 3, 4, 5, 6, 7, 8, 0x03, 0xE8, 0x07, 0x5B],
+    );
+    let mut none = Vec::new();
+    for data in [&mut v1, &mut v2, &mut none] {
+        data.extend_from_slice(&[0xC0, 0x00]);
+    }
+    for data in [v1, v2] {
+        let mut stream = block_on(ProxyStream::accept(&data[..])).unwrap();
+        assert_eq!(stream.source(), Some(addr("1.2.3.4:1000")));
+        assert_eq!(
+            stream.header().unwrap().destination(),
+            Some(addr("5.6.7.8:1883"))
+        );
+        // Nothing read after the header
+        assert_eq!(stream.get_ref(), &[0xC0, 0x00]);
+        let mut rest = [0u8; 2];
+        block_on(read_exact(&mut stream, &mut rest));
+        assert_eq!(rest, [0xC0, 0x00]);
+    }
+
+    let mut stream = block_on(ProxyStream::accept(&none[..])).unwrap();
+    assert_eq!(stream.header(), None);
+    assert_eq!(stream.source(), None);
+    let mut rest = [0u8; 2];
+    block_on(read_exact(&mut stream, &mut rest));
+    assert_eq!(rest, [0xC0, 0x00]);
+
+    let (inner, pending) = block_on(ProxyStream::accept(&none[..]))
+        .unwrap()
+        .into_parts();
+    assert_eq!((inner, pending), (&none[1..], Some(0xC0)));
+
+    let err = block_on(ProxyStream::accept(&b"PROXY TCP4 1.2"[..])).unwrap_err();
+    assert!(err.is_eof());
+    let err = block_on(ProxyStream::accept(&b"PROXY TCP5 \r\n"[..])).unwrap_err();
+    assert_eq!(err, Error::InvalidHeader("unknown protocol"));
+    // Another protocol fails before the end of the line is read
+    let err = block_on(ProxyStream::accept(&b"POST / HTTP/1.1"[..])).unwrap_err();
+    assert_eq!(err, Error::InvalidSignature);
+}
+
+#[cfg(feature = "v3")]
+#[test]
+fn test_accept_then_decode() {
+    use crate::v3::{Connect, Packet, PollPacket};
+    use crate::BufferPool;
+
+    let packet = Packet::from(Connect::new("client".into(), 30));
+    let mut data = b"PROXY TCP6 ::1 ::2 1 2\r\n".to_vec();
+    data.extend_from_slice(packet.encode().unwrap().as_ref());
+    let mut stream = block_on(ProxyStream::accept(&data[..])).unwrap();
+    assert_eq!(stream.source(), Some(addr("[::1]:1")));
+    assert_eq!(
+        block_on(Packet::decode_async(&mut stream)),
+        Ok(packet.clone())
+    );
+
+    let data = packet.encode().unwrap();
+    let mut stream = block_on(ProxyStream::accept(data.as_ref())).unwrap();
+    let mut buffer = BufferPool::default();
+    let (_, _, decoded) = block_on(PollPacket::new(
+        &mut Default::default(),
+        &mut stream,
+        &mut buffer,
+    ))
+    .unwrap();
+    assert_eq!(decoded, packet);
+}
+
+async fn read_exact<T: crate::AsyncRead + Unpin>(reader: &mut T, buf: &mut [u8]) {
+    #[cfg(feature = "tokio")]
+    use tokio::io::AsyncReadExt;
+    reader.read_exact(buf).await.unwrap();
+}
@@ -0,0 +1,130 @@
+use core::convert::TryFrom;
+
+use crate::{
+    read_u16, read_u8, write_u16, write_u8, ClientId, Encodable, Error, QoS, SyncWrite, TopicName,
+};
+
+use super::types::{
+    qos_from_flags, qos_to_flags, read_rest_str, FLAG_CLEAN_SESSION, FLAG_RETAIN, FLAG_WILL,
+};
+use super::{ErrorSn, MsgType, SnQoS};
+
+/// Protocol id of MQTT-SN 1.2.
+const PROTOCOL_ID: u8 = 0x01;
+
+/// Connect message body type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Connect {
+    /// The gateway asks for the will topic and message when set.
+    pub will: bool,
+    pub clean_session: bool,
+    /// Keep alive duration in seconds.
+    pub duration: u16,
+    pub client_id: ClientId,
+}
+
+impl Connect {
+    pub fn new(client_id: ClientId, duration: u16) -> Self {
+        Connect {
+            will: false,
+            clean_session: true,
+            duration,
+            client_id,
+        }
+    }
+
+    pub fn decode(buf: &[u8], offset: &mut usize) -> Result<Self, ErrorSn> {
+        let flags = read_u8(buf, offset)?;
+        let protocol_id = read_u8(buf, offset)?;
+        if protocol_id != PROTOCOL_ID {
+            return Err(ErrorSn::InvalidProtocolId(protocol_id));
+        }
+        let duration = read_u16(buf, offset)?;
+        let client_id = read_rest_str(buf, offset)?.into();
+        Ok(Connect {
+            will: flags & FLAG_WILL != 0,
+            clean_session: flags & FLAG_CLEAN_SESSION != 0,
+            duration,
+            client_id,
+        })
+    }
+}
+
+impl Encodable for Connect {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+        let mut flags = 0;
+        if self.will {
+            flags |= FLAG_WILL;
+        }
+        if self.clean_session {
+            flags |= FLAG_CLEAN_SESSION;
+        }
+        write_u8(writer, flags)?;
+        write_u8(writer, PROTOCOL_ID)?;
+        write_u16(writer, self.duration)?;
+        writer.write_all(self.client_id.as_bytes())?;
+        Ok(())
+    }
+
+    fn encode_len(&self) -> usize {
+        4 + self.client_id.len()
+    }
+}
+
+/// Will topic message body type, also used by the will topic update message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WillTopic {
+    pub qos: QoS,
+    pub retain: bool,
+    pub topic_name: TopicName,
+}
+
+impl WillTopic {
+    pub fn new(qos: QoS, topic_name: TopicName) -> Self {
+        WillTopic {
+            qos,
+            retain: false,
+            topic_name,
+        }
+    }
+
+    /// Decode the body, an empty body deletes the will and returns `None`.
+    pub fn decode(
+        msg_type: MsgType,
+        buf: &[u8],
+        offset: &mut usize,
+    ) -> Result<Option<Self>, ErrorSn> {
+        if *offset == buf.len() {
+            return Ok(None);
+        }
+        let flags = read_u8(buf, offset)?;
+        let qos = match qos_from_flags(msg_type, flags)? {
+            SnQoS::LevelMinus1 => return Err(ErrorSn::InvalidFlags(msg_type, flags)),
+            qos => qos.to_qos(),
+        };
+        let topic_name = TopicName::try_from(read_rest_str(buf, offset)?)?;
+        Ok(Some(WillTopic {
+            qos,
+            retain: flags & FLAG_RETAIN != 0,
+            topic_name,
+        }))
+    }
+}
+
+impl Encodable for WillTopic {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+        let mut flags = qos_to_flags(self.qos.into());
+        if self.retain {
+            flags |= FLAG_RETAIN;
+        }
+        write_u8(writer, flags)?;
+        writer.write_all(self.topic_name.as_bytes())?;
+        Ok(())
+    }
+
+    fn encode_len(&self) -> usize {
+        1 + self.topic_name.len()
+    }
+}
@@ -0,0 +1,59 @@
+use thiserror::Error;
+
+use super::MsgType;
+
+/// MQTT-SN errors returned by encoding and decoding process.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ErrorSn {
+    /// Common error of MQTT v3 and v5.
+    #[error("common error of v3/v5: {0}")]
+    Common(#[from] crate::Error),
+
+    /// Invalid length field, or a length not matching the message.
+    #[error("invalid length: `{0}`")]
+    InvalidLength(usize),
+
+    /// The encoded message is longer than 65535 bytes.
+    #[error("message too large: `{0}` bytes")]
+    MessageTooLarge(usize),
+
+    /// Unknown message type.
+    #[error("invalid message type: `{0}`")]
+    InvalidMsgType(u8),
+
+    /// Protocol id of the connect message is not 0x01.
+    #[error("invalid protocol id: `{0}`")]
+    InvalidProtocolId(u8),
+
+    /// Invalid flags for the message type.
+    #[error("invalid flags `{1}` for message `{0:?}`")]
+    InvalidFlags(MsgType, u8),
+
+    /// Invalid return code.
+    #[error("invalid return code: `{0}`")]
+    InvalidReturnCode(u8),
+
+    /// Topic id not in the [`TopicRegistry`](super::TopicRegistry).
+    #[error("unknown topic id: `{0}`")]
+    UnknownTopicId(u16),
+
+    /// Topic name neither registered nor a short topic name.
+    #[error("unregistered topic name: `{0}`")]
+    UnregisteredTopic(crate::TopicName),
+}
+
+impl ErrorSn {
+    pub fn is_eof(&self) -> bool {
+        if let ErrorSn::Common(e) = self {
+            e.is_eof()
+        } else {
+            false
+        }
+    }
+}
+
+impl<E: embedded_io::Error> From<E> for ErrorSn {
+    fn from(err: E) -> ErrorSn {
+        ErrorSn::Common(err.into())
+    }
+}
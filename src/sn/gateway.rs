@@ -0,0 +1,65 @@
+use bytes::Bytes;
+
+use crate::{read_u16, read_u8, write_u16, write_u8, Encodable, Error, SyncWrite};
+
+use super::ErrorSn;
+
+/// Advertise message body type, broadcast periodically by a gateway.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Advertise {
+    pub gw_id: u8,
+    /// Seconds until the next advertise message.
+    pub duration: u16,
+}
+
+impl Advertise {
+    pub fn decode(buf: &[u8], offset: &mut usize) -> Result<Self, ErrorSn> {
+        let gw_id = read_u8(buf, offset)?;
+        let duration = read_u16(buf, offset)?;
+        Ok(Advertise { gw_id, duration })
+    }
+}
+
+impl Encodable for Advertise {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+        write_u8(writer, self.gw_id)?;
+        write_u16(writer, self.duration)?;
+        Ok(())
+    }
+
+    fn encode_len(&self) -> usize {
+        3
+    }
+}
+
+/// Gateway info message body type, the answer to a search gateway message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GwInfo {
+    pub gw_id: u8,
+    /// Address of the gateway, only present when sent by a client.
+    pub gw_addr: Bytes,
+}
+
+impl GwInfo {
+    pub fn decode(buf: &[u8], offset: &mut usize) -> Result<Self, ErrorSn> {
+        let gw_id = read_u8(buf, offset)?;
+        let gw_addr = Bytes::copy_from_slice(&buf[*offset..]);
+        *offset = buf.len();
+        Ok(GwInfo { gw_id, gw_addr })
+    }
+}
+
+impl Encodable for GwInfo {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+        write_u8(writer, self.gw_id)?;
+        writer.write_all(&self.gw_addr)?;
+        Ok(())
+    }
+
+    fn encode_len(&self) -> usize {
+        1 + self.gw_addr.len()
+    }
+}
@@ -0,0 +1,27 @@
+//! Codec for [MQTT-SN v1.2], MQTT for sensor networks over datagram
+//! transports
+//!
+//! Every message is one datagram, so there is no poll decoder. Topic names
+//! are replaced by topic ids, use [`TopicRegistry`] to convert [`Publish`]
+//! messages to and from MQTT v3/v5 publish packets.
+//!
+//! [MQTT-SN v1.2]: https://www.oasis-open.org/committees/download.php/66091/MQTT-SN_spec_v1.2.pdf
+
+mod connect;
+mod error;
+mod gateway;
+mod packet;
+mod publish;
+mod subscribe;
+mod types;
+
+#[cfg(test)]
+mod tests;
+
+pub use connect::{Connect, WillTopic};
+pub use error::ErrorSn;
+pub use gateway::{Advertise, GwInfo};
+pub use packet::{Encapsulated, Packet};
+pub use publish::{Publish, Register, TopicAck, TopicRegistry};
+pub use subscribe::{Suback, Subscribe, Unsubscribe};
+pub use types::{MsgType, ReturnCode, SnQoS, SubscribeTopic, TopicId};
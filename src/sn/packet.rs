@@ -0,0 +1,444 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::convert::AsRef;
+
+use bytes::Bytes;
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{
+    read_u16, read_u16_async, read_u8, read_u8_async, write_u16, write_u8, AsyncRead, AsyncWrite,
+    ClientId, Encodable, Error, SyncRead, SyncWrite, ToError, VarBytes,
+};
+
+use super::{
+    Advertise, Connect, ErrorSn, GwInfo, MsgType, Publish, Register, ReturnCode, Suback, Subscribe,
+    TopicAck, Unsubscribe, WillTopic,
+};
+
+/// First byte of a three bytes length field.
+const LONG_LEN_FLAG: u8 = 0x01;
+
+/// MQTT-SN message types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Packet {
+    Advertise(Advertise),
+    /// Broadcast radius of the search.
+    SearchGw(u8),
+    GwInfo(GwInfo),
+    Connect(Connect),
+    Connack(ReturnCode),
+    WillTopicReq,
+    /// `None` for an empty message, which deletes the will.
+    WillTopic(Option<WillTopic>),
+    WillMsgReq,
+    WillMsg(Bytes),
+    Register(Register),
+    Regack(TopicAck),
+    Publish(Publish),
+    Puback(TopicAck),
+    Pubcomp(u16),
+    Pubrec(u16),
+    Pubrel(u16),
+    Subscribe(Subscribe),
+    Suback(Suback),
+    Unsubscribe(Unsubscribe),
+    Unsuback(u16),
+    /// Client id of a sleeping client checking for buffered messages.
+    Pingreq(Option<ClientId>),
+    Pingresp,
+    /// Sleep duration in seconds of a client going asleep.
+    Disconnect(Option<u16>),
+    /// `None` for an empty message, which deletes the will.
+    WillTopicUpd(Option<WillTopic>),
+    WillTopicResp(ReturnCode),
+    WillMsgUpd(Bytes),
+    WillMsgResp(ReturnCode),
+    Encapsulated(Encapsulated),
+}
+
+/// Message forwarded between a gateway and a wireless node by a forwarder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Encapsulated {
+    /// Broadcast radius, only the two lowest bits are used.
+    pub radius: u8,
+    pub wireless_node_id: Bytes,
+    pub packet: Box<Packet>,
+}
+
+impl Packet {
+    /// Return the message type.
+    pub fn get_type(&self) -> MsgType {
+        match self {
+            Packet::Advertise(_) => MsgType::Advertise,
+            Packet::SearchGw(_) => MsgType::SearchGw,
+            Packet::GwInfo(_) => MsgType::GwInfo,
+            Packet::Connect(_) => MsgType::Connect,
+            Packet::Connack(_) => MsgType::Connack,
+            Packet::WillTopicReq => MsgType::WillTopicReq,
+            Packet::WillTopic(_) => MsgType::WillTopic,
+            Packet::WillMsgReq => MsgType::WillMsgReq,
+            Packet::WillMsg(_) => MsgType::WillMsg,
+            Packet::Register(_) => MsgType::Register,
+            Packet::Regack(_) => MsgType::Regack,
+            Packet::Publish(_) => MsgType::Publish,
+            Packet::Puback(_) => MsgType::Puback,
+            Packet::Pubcomp(_) => MsgType::Pubcomp,
+            Packet::Pubrec(_) => MsgType::Pubrec,
+            Packet::Pubrel(_) => MsgType::Pubrel,
+            Packet::Subscribe(_) => MsgType::Subscribe,
+            Packet::Suback(_) => MsgType::Suback,
+            Packet::Unsubscribe(_) => MsgType::Unsubscribe,
+            Packet::Unsuback(_) => MsgType::Unsuback,
+            Packet::Pingreq(_) => MsgType::Pingreq,
+            Packet::Pingresp => MsgType::Pingresp,
+            Packet::Disconnect(_) => MsgType::Disconnect,
+            Packet::WillTopicUpd(_) => MsgType::WillTopicUpd,
+            Packet::WillTopicResp(_) => MsgType::WillTopicResp,
+            Packet::WillMsgUpd(_) => MsgType::WillMsgUpd,
+            Packet::WillMsgResp(_) => MsgType::WillMsgResp,
+            Packet::Encapsulated(_) => MsgType::Encapsulated,
+        }
+    }
+
+    /// Asynchronously decode a message from an async reader.
+    pub async fn decode_async<T: AsyncRead + Unpin>(reader: &mut T) -> Result<Self, ErrorSn> {
+        let mut encapsulations = Vec::new();
+        loop {
+            let mut len = read_u8_async(reader).await? as usize;
+            let mut field_len = 1;
+            if len == LONG_LEN_FLAG as usize {
+                len = read_u16_async(reader).await? as usize;
+                field_len = 3;
+            }
+            check_len(len, field_len)?;
+            let mut frame = alloc::vec![0u8; len - field_len];
+            reader
+                .read_exact(&mut frame)
+                .await
+                .map_err(ToError::to_error)?;
+            if let Some(packet) = Self::decode_frame(len, &frame, &mut encapsulations)? {
+                return Ok(packet);
+            }
+        }
+    }
+
+    /// Asynchronously encode the message to an async writer.
+    pub async fn encode_async<T: AsyncWrite + Unpin>(&self, writer: &mut T) -> Result<(), ErrorSn> {
+        let data = self.encode()?;
+        writer.write_all(data.as_ref()).await?;
+        Ok(())
+    }
+
+    /// Decode a message from some bytes. If not enough bytes to decode a
+    /// message, it will return `Ok(None)`.
+    pub fn decode(bytes: &[u8]) -> Result<Option<Self>, ErrorSn> {
+        Ok(Self::decode_from_slice(bytes)?.map(|(packet, _)| packet))
+    }
+
+    /// Decode a message from a slice, return the message and the consumed
+    /// length. If the slice does not hold a whole message yet, it will return
+    /// `Ok(None)`.
+    pub fn decode_from_slice(bytes: &[u8]) -> Result<Option<(Self, usize)>, ErrorSn> {
+        let mut offset = 0;
+        let mut encapsulations = Vec::new();
+        loop {
+            let rest = &bytes[offset..];
+            let (len, field_len) = match rest {
+                [] | [LONG_LEN_FLAG] | [LONG_LEN_FLAG, _] => return Ok(None),
+                [LONG_LEN_FLAG, a, b, ..] => (u16::from_be_bytes([*a, *b]) as usize, 3),
+                [len, ..] => (*len as usize, 1),
+            };
+            check_len(len, field_len)?;
+            let Some(frame) = rest.get(field_len..len) else {
+                return Ok(None);
+            };
+            offset += len;
+            if let Some(packet) = Self::decode_frame(len, frame, &mut encapsulations)? {
+                return Ok(Some((packet, offset)));
+            }
+        }
+    }
+
+    /// Blocking read of one message from a `std::io` or `embedded_io` reader.
+    pub fn read_from<R: SyncRead>(reader: &mut R) -> Result<Self, ErrorSn> {
+        let mut encapsulations = Vec::new();
+        let mut buf = [0u8; 2];
+        loop {
+            reader
+                .read_exact(&mut buf[..1])
+                .map_err(ToError::to_error)?;
+            let mut len = buf[0] as usize;
+            let mut field_len = 1;
+            if buf[0] == LONG_LEN_FLAG {
+                reader.read_exact(&mut buf).map_err(ToError::to_error)?;
+                len = u16::from_be_bytes(buf) as usize;
+                field_len = 3;
+            }
+            check_len(len, field_len)?;
+            let mut frame = alloc::vec![0u8; len - field_len];
+            reader.read_exact(&mut frame).map_err(ToError::to_error)?;
+            if let Some(packet) = Self::decode_frame(len, &frame, &mut encapsulations)? {
+                return Ok(packet);
+            }
+        }
+    }
+
+    /// Blocking write of the message to a `std::io` or `embedded_io` writer.
+    pub fn write_to<W: SyncWrite>(&self, writer: &mut W) -> Result<(), ErrorSn> {
+        let data = self.encode()?;
+        writer.write_all(data.as_ref()).map_err(Error::from)?;
+        Ok(())
+    }
+
+    /// Decode the message type and body of one frame, an encapsulation header
+    /// is pushed to `encapsulations` and the inner message follows.
+    fn decode_frame(
+        len: usize,
+        frame: &[u8],
+        encapsulations: &mut Vec<(u8, Bytes)>,
+    ) -> Result<Option<Self>, ErrorSn> {
+        let msg_type = MsgType::from_u8(frame[0])?;
+        let body = &frame[1..];
+        let result = if msg_type == MsgType::Encapsulated {
+            Self::decode_encapsulation(body).map(|header| {
+                encapsulations.push(header);
+                None
+            })
+        } else {
+            let mut offset = 0;
+            Self::decode_body(msg_type, body, &mut offset).and_then(|packet| {
+                if offset == body.len() {
+                    Ok(Some(packet))
+                } else {
+                    Err(ErrorSn::InvalidLength(len))
+                }
+            })
+        };
+        let packet = match result {
+            Ok(Some(packet)) => packet,
+            Ok(None) => return Ok(None),
+            // The whole message was read, the length is wrong.
+            Err(err) if err.is_eof() => return Err(ErrorSn::InvalidLength(len)),
+            Err(err) => return Err(err),
+        };
+        let packet =
+            encapsulations
+                .drain(..)
+                .rev()
+                .fold(packet, |packet, (ctrl, wireless_node_id)| {
+                    Packet::Encapsulated(Encapsulated {
+                        radius: ctrl & 0b11,
+                        wireless_node_id,
+                        packet: Box::new(packet),
+                    })
+                });
+        Ok(Some(packet))
+    }
+
+    fn decode_encapsulation(body: &[u8]) -> Result<(u8, Bytes), ErrorSn> {
+        let mut offset = 0;
+        let ctrl = read_u8(body, &mut offset)?;
+        Ok((ctrl, Bytes::copy_from_slice(&body[offset..])))
+    }
+
+    fn decode_body(msg_type: MsgType, buf: &[u8], offset: &mut usize) -> Result<Self, ErrorSn> {
+        Ok(match msg_type {
+            MsgType::Advertise => Packet::Advertise(Advertise::decode(buf, offset)?),
+            MsgType::SearchGw => Packet::SearchGw(read_u8(buf, offset)?),
+            MsgType::GwInfo => Packet::GwInfo(GwInfo::decode(buf, offset)?),
+            MsgType::Connect => Packet::Connect(Connect::decode(buf, offset)?),
+            MsgType::Connack => Packet::Connack(ReturnCode::from_u8(read_u8(buf, offset)?)?),
+            MsgType::WillTopicReq => Packet::WillTopicReq,
+            MsgType::WillTopic => Packet::WillTopic(WillTopic::decode(msg_type, buf, offset)?),
+            MsgType::WillMsgReq => Packet::WillMsgReq,
+            MsgType::WillMsg => Packet::WillMsg(read_rest_bytes(buf, offset)),
+            MsgType::Register => Packet::Register(Register::decode(buf, offset)?),
+            MsgType::Regack => Packet::Regack(TopicAck::decode(buf, offset)?),
+            MsgType::Publish => Packet::Publish(Publish::decode(buf, offset)?),
+            MsgType::Puback => Packet::Puback(TopicAck::decode(buf, offset)?),
+            MsgType::Pubcomp => Packet::Pubcomp(read_u16(buf, offset)?),
+            MsgType::Pubrec => Packet::Pubrec(read_u16(buf, offset)?),
+            MsgType::Pubrel => Packet::Pubrel(read_u16(buf, offset)?),
+            MsgType::Subscribe => Packet::Subscribe(Subscribe::decode(buf, offset)?),
+            MsgType::Suback => Packet::Suback(Suback::decode(buf, offset)?),
+            MsgType::Unsubscribe => Packet::Unsubscribe(Unsubscribe::decode(buf, offset)?),
+            MsgType::Unsuback => Packet::Unsuback(read_u16(buf, offset)?),
+            MsgType::Pingreq => {
+                let client_id = super::types::read_rest_str(buf, offset)?;
+                Packet::Pingreq((!client_id.is_empty()).then(|| client_id.into()))
+            }
+            MsgType::Pingresp => Packet::Pingresp,
+            MsgType::Disconnect => {
+                let duration = if buf.is_empty() {
+                    None
+                } else {
+                    Some(read_u16(buf, offset)?)
+                };
+                Packet::Disconnect(duration)
+            }
+            MsgType::WillTopicUpd => {
+                Packet::WillTopicUpd(WillTopic::decode(msg_type, buf, offset)?)
+            }
+            MsgType::WillTopicResp => {
+                Packet::WillTopicResp(ReturnCode::from_u8(read_u8(buf, offset)?)?)
+            }
+            MsgType::WillMsgUpd => Packet::WillMsgUpd(read_rest_bytes(buf, offset)),
+            MsgType::WillMsgResp => {
+                Packet::WillMsgResp(ReturnCode::from_u8(read_u8(buf, offset)?)?)
+            }
+            MsgType::Encapsulated => unreachable!("decoded by decode_frame"),
+        })
+    }
+
+    /// Encode the message to a dynamic vector.
+    pub fn encode(&self) -> Result<VarBytes, ErrorSn> {
+        let mut buf = Vec::with_capacity(self.encode_len()?);
+        self.encode_frame(&mut buf)?;
+        Ok(VarBytes::Dynamic(buf))
+    }
+
+    fn encode_frame(&self, buf: &mut Vec<u8>) -> Result<(), ErrorSn> {
+        let len = frame_len(self.body_len())?;
+        if len > u8::MAX as usize {
+            write_u8(buf, LONG_LEN_FLAG)?;
+            write_u16(buf, len as u16)?;
+        } else {
+            write_u8(buf, len as u8)?;
+        }
+        write_u8(buf, self.get_type().as_u8())?;
+        self.encode_body(buf)?;
+        if let Packet::Encapsulated(inner) = self {
+            inner.packet.encode_frame(buf)?;
+        }
+        Ok(())
+    }
+
+    fn encode_body<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+        match self {
+            Packet::Advertise(inner) => inner.encode(writer),
+            Packet::GwInfo(inner) => inner.encode(writer),
+            Packet::Connect(inner) => inner.encode(writer),
+            Packet::WillTopic(Some(inner)) | Packet::WillTopicUpd(Some(inner)) => {
+                inner.encode(writer)
+            }
+            Packet::Register(inner) => inner.encode(writer),
+            Packet::Regack(inner) | Packet::Puback(inner) => inner.encode(writer),
+            Packet::Publish(inner) => inner.encode(writer),
+            Packet::Subscribe(inner) => inner.encode(writer),
+            Packet::Suback(inner) => inner.encode(writer),
+            Packet::Unsubscribe(inner) => inner.encode(writer),
+
+            Packet::SearchGw(value) => write_u8(writer, *value),
+            Packet::Connack(code) | Packet::WillTopicResp(code) | Packet::WillMsgResp(code) => {
+                write_u8(writer, code.as_u8())
+            }
+            Packet::Pubcomp(value)
+            | Packet::Pubrec(value)
+            | Packet::Pubrel(value)
+            | Packet::Unsuback(value)
+            | Packet::Disconnect(Some(value)) => write_u16(writer, *value),
+            Packet::WillMsg(data) | Packet::WillMsgUpd(data) => Ok(writer.write_all(data)?),
+            Packet::Pingreq(Some(client_id)) => Ok(writer.write_all(client_id.as_bytes())?),
+            Packet::Encapsulated(inner) => {
+                write_u8(writer, inner.radius & 0b11)?;
+                Ok(writer.write_all(&inner.wireless_node_id)?)
+            }
+
+            Packet::WillTopicReq
+            | Packet::WillTopic(None)
+            | Packet::WillMsgReq
+            | Packet::Pingreq(None)
+            | Packet::Pingresp
+            | Packet::Disconnect(None)
+            | Packet::WillTopicUpd(None) => Ok(()),
+        }
+    }
+
+    fn body_len(&self) -> usize {
+        match self {
+            Packet::Advertise(inner) => inner.encode_len(),
+            Packet::GwInfo(inner) => inner.encode_len(),
+            Packet::Connect(inner) => inner.encode_len(),
+            Packet::WillTopic(Some(inner)) | Packet::WillTopicUpd(Some(inner)) => {
+                inner.encode_len()
+            }
+            Packet::Register(inner) => inner.encode_len(),
+            Packet::Regack(inner) | Packet::Puback(inner) => inner.encode_len(),
+            Packet::Publish(inner) => inner.encode_len(),
+            Packet::Subscribe(inner) => inner.encode_len(),
+            Packet::Suback(inner) => inner.encode_len(),
+            Packet::Unsubscribe(inner) => inner.encode_len(),
+
+            Packet::SearchGw(_)
+            | Packet::Connack(_)
+            | Packet::WillTopicResp(_)
+            | Packet::WillMsgResp(_) => 1,
+            Packet::Pubcomp(_)
+            | Packet::Pubrec(_)
+            | Packet::Pubrel(_)
+            | Packet::Unsuback(_)
+            | Packet::Disconnect(Some(_)) => 2,
+            Packet::WillMsg(data) | Packet::WillMsgUpd(data) => data.len(),
+            Packet::Pingreq(Some(client_id)) => client_id.len(),
+            Packet::Encapsulated(inner) => 1 + inner.wireless_node_id.len(),
+
+            Packet::WillTopicReq
+            | Packet::WillTopic(None)
+            | Packet::WillMsgReq
+            | Packet::Pingreq(None)
+            | Packet::Pingresp
+            | Packet::Disconnect(None)
+            | Packet::WillTopicUpd(None) => 0,
+        }
+    }
+
+    /// Return the length of the encoded message.
+    pub fn encode_len(&self) -> Result<usize, ErrorSn> {
+        let len = frame_len(self.body_len())?;
+        match self {
+            Packet::Encapsulated(inner) => Ok(len + inner.packet.encode_len()?),
+            _ => Ok(len),
+        }
+    }
+}
+
+/// Length of a message with the length field and the message type.
+fn frame_len(body_len: usize) -> Result<usize, ErrorSn> {
+    let len = body_len + 2;
+    if len <= u8::MAX as usize {
+        Ok(len)
+    } else if len + 2 <= u16::MAX as usize {
+        Ok(len + 2)
+    } else {
+        Err(ErrorSn::MessageTooLarge(len + 2))
+    }
+}
+
+fn check_len(len: usize, field_len: usize) -> Result<(), ErrorSn> {
+    // The length field and the message type at least
+    if len <= field_len {
+        return Err(ErrorSn::InvalidLength(len));
+    }
+    Ok(())
+}
+
+fn read_rest_bytes(buf: &[u8], offset: &mut usize) -> Bytes {
+    let data = Bytes::copy_from_slice(&buf[*offset..]);
+    *offset = buf.len();
+    data
+}
+
+crate::packet_from!(
+    Advertise,
+    GwInfo,
+    Connect,
+    Register,
+    Publish,
+    Subscribe,
+    Suback,
+    Unsubscribe,
+    Encapsulated
+);
@@ -0,0 +1,377 @@
+use alloc::collections::BTreeMap;
+use core::convert::TryFrom;
+
+use bytes::Bytes;
+
+use crate::{read_u16, read_u8, write_u16, write_u8, Encodable, Error, SyncWrite, TopicName};
+#[cfg(any(feature = "v3", feature = "v5"))]
+use crate::{Pid, QosPid};
+
+use super::types::{qos_from_flags, qos_to_flags, read_rest_str, FLAG_DUP, FLAG_RETAIN};
+use super::{ErrorSn, MsgType, ReturnCode, SnQoS, TopicId};
+
+/// Register message body type, sent in both directions to map a topic name
+/// to a topic id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Register {
+    /// Topic id assigned by the gateway, 0 when sent by a client.
+    pub topic_id: u16,
+    pub msg_id: u16,
+    pub topic_name: TopicName,
+}
+
+impl Register {
+    pub fn decode(buf: &[u8], offset: &mut usize) -> Result<Self, ErrorSn> {
+        let topic_id = read_u16(buf, offset)?;
+        let msg_id = read_u16(buf, offset)?;
+        let topic_name = TopicName::try_from(read_rest_str(buf, offset)?)?;
+        Ok(Register {
+            topic_id,
+            msg_id,
+            topic_name,
+        })
+    }
+}
+
+impl Encodable for Register {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+        write_u16(writer, self.topic_id)?;
+        write_u16(writer, self.msg_id)?;
+        writer.write_all(self.topic_name.as_bytes())?;
+        Ok(())
+    }
+
+    fn encode_len(&self) -> usize {
+        4 + self.topic_name.len()
+    }
+}
+
+/// Acknowledge message body type of register and publish messages, used by
+/// both REGACK and PUBACK.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TopicAck {
+    pub topic_id: u16,
+    pub msg_id: u16,
+    pub return_code: ReturnCode,
+}
+
+impl TopicAck {
+    pub fn new(topic_id: u16, msg_id: u16, return_code: ReturnCode) -> Self {
+        TopicAck {
+            topic_id,
+            msg_id,
+            return_code,
+        }
+    }
+
+    pub fn decode(buf: &[u8], offset: &mut usize) -> Result<Self, ErrorSn> {
+        let topic_id = read_u16(buf, offset)?;
+        let msg_id = read_u16(buf, offset)?;
+        let return_code = ReturnCode::from_u8(read_u8(buf, offset)?)?;
+        Ok(TopicAck {
+            topic_id,
+            msg_id,
+            return_code,
+        })
+    }
+}
+
+impl Encodable for TopicAck {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+        write_u16(writer, self.topic_id)?;
+        write_u16(writer, self.msg_id)?;
+        write_u8(writer, self.return_code.as_u8())?;
+        Ok(())
+    }
+
+    fn encode_len(&self) -> usize {
+        5
+    }
+}
+
+/// Publish message body type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Publish {
+    pub dup: bool,
+    pub qos: SnQoS,
+    pub retain: bool,
+    pub topic_id: TopicId,
+    /// Message id, 0 for QoS 0 and QoS -1.
+    pub msg_id: u16,
+    pub data: Bytes,
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Publish {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Publish {
+            dup: u.arbitrary()?,
+            qos: u.arbitrary()?,
+            retain: u.arbitrary()?,
+            topic_id: u.arbitrary()?,
+            msg_id: u.arbitrary()?,
+            data: Bytes::from(alloc::vec::Vec::<u8>::arbitrary(u)?),
+        })
+    }
+}
+
+impl Publish {
+    pub fn new(qos: SnQoS, topic_id: TopicId, msg_id: u16, data: Bytes) -> Self {
+        Publish {
+            dup: false,
+            qos,
+            retain: false,
+            topic_id,
+            msg_id,
+            data,
+        }
+    }
+
+    pub fn decode(buf: &[u8], offset: &mut usize) -> Result<Self, ErrorSn> {
+        let flags = read_u8(buf, offset)?;
+        let qos = qos_from_flags(MsgType::Publish, flags)?;
+        let topic_id = TopicId::from_parts(MsgType::Publish, flags, read_u16(buf, offset)?)?;
+        let msg_id = read_u16(buf, offset)?;
+        let data = Bytes::copy_from_slice(&buf[*offset..]);
+        *offset = buf.len();
+        Ok(Publish {
+            dup: flags & FLAG_DUP != 0,
+            qos,
+            retain: flags & FLAG_RETAIN != 0,
+            topic_id,
+            msg_id,
+            data,
+        })
+    }
+
+    /// Convert to a MQTT v3 publish packet, the topic id is resolved by the
+    /// registry.
+    #[cfg(feature = "v3")]
+    pub fn to_v3(&self, registry: &TopicRegistry) -> Result<crate::v3::Publish, ErrorSn> {
+        Ok(crate::v3::Publish {
+            dup: self.dup,
+            retain: self.retain,
+            qos_pid: self.qos_pid()?,
+            topic_name: registry.resolve(&self.topic_id)?,
+            payload: self.data.clone(),
+        })
+    }
+
+    /// Convert from a MQTT v3 publish packet, the topic name must be
+    /// registered or a short topic name.
+    #[cfg(feature = "v3")]
+    pub fn from_v3(
+        publish: &crate::v3::Publish,
+        registry: &TopicRegistry,
+    ) -> Result<Self, ErrorSn> {
+        Self::from_parts(
+            publish.dup,
+            publish.retain,
+            publish.qos_pid,
+            &publish.topic_name,
+            &publish.payload,
+            registry,
+        )
+    }
+
+    /// Convert to a MQTT v5 publish packet with empty properties, the topic
+    /// id is resolved by the registry.
+    #[cfg(feature = "v5")]
+    pub fn to_v5(&self, registry: &TopicRegistry) -> Result<crate::v5::Publish, ErrorSn> {
+        Ok(crate::v5::Publish {
+            dup: self.dup,
+            retain: self.retain,
+            qos_pid: self.qos_pid()?,
+            topic_name: registry.resolve(&self.topic_id)?,
+            payload: self.data.clone(),
+            properties: Default::default(),
+        })
+    }
+
+    /// Convert from a MQTT v5 publish packet, the topic name must be
+    /// registered or a short topic name. Properties are dropped.
+    #[cfg(feature = "v5")]
+    pub fn from_v5(
+        publish: &crate::v5::Publish,
+        registry: &TopicRegistry,
+    ) -> Result<Self, ErrorSn> {
+        Self::from_parts(
+            publish.dup,
+            publish.retain,
+            publish.qos_pid,
+            &publish.topic_name,
+            &publish.payload,
+            registry,
+        )
+    }
+
+    #[cfg(any(feature = "v3", feature = "v5"))]
+    fn qos_pid(&self) -> Result<QosPid, ErrorSn> {
+        Ok(match self.qos {
+            SnQoS::Level0 | SnQoS::LevelMinus1 => QosPid::Level0,
+            SnQoS::Level1 => QosPid::Level1(Pid::try_from(self.msg_id)?),
+            SnQoS::Level2 => QosPid::Level2(Pid::try_from(self.msg_id)?),
+        })
+    }
+
+    #[cfg(any(feature = "v3", feature = "v5"))]
+    fn from_parts(
+        dup: bool,
+        retain: bool,
+        qos_pid: QosPid,
+        topic_name: &TopicName,
+        payload: &Bytes,
+        registry: &TopicRegistry,
+    ) -> Result<Self, ErrorSn> {
+        Ok(Publish {
+            dup,
+            qos: qos_pid.qos().into(),
+            retain,
+            topic_id: registry.topic_id(topic_name)?,
+            msg_id: qos_pid.pid().map(u16::from).unwrap_or(0),
+            data: payload.clone(),
+        })
+    }
+}
+
+impl Encodable for Publish {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+        let mut flags = qos_to_flags(self.qos) | self.topic_id.id_type();
+        if self.dup {
+            flags |= FLAG_DUP;
+        }
+        if self.retain {
+            flags |= FLAG_RETAIN;
+        }
+        write_u8(writer, flags)?;
+        write_u16(writer, self.topic_id.to_u16())?;
+        write_u16(writer, self.msg_id)?;
+        writer.write_all(&self.data)?;
+        Ok(())
+    }
+
+    fn encode_len(&self) -> usize {
+        5 + self.data.len()
+    }
+}
+
+/// Topic ids known by one side of a MQTT-SN connection.
+///
+/// Normal topic ids are assigned by REGISTER messages, predefined topic ids
+/// are agreed on in advance. Short topic names need no entry.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TopicRegistry {
+    normal: BTreeMap<u16, TopicName>,
+    predefined: BTreeMap<u16, TopicName>,
+    // Reverse lookups of `normal` and `predefined`
+    normal_ids: BTreeMap<TopicName, u16>,
+    predefined_ids: BTreeMap<TopicName, u16>,
+    next_id: u16,
+}
+
+impl TopicRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a topic id assigned by a REGISTER or REGACK message.
+    pub fn insert(&mut self, topic_id: u16, topic_name: TopicName) -> Option<TopicName> {
+        insert_topic(&mut self.normal, &mut self.normal_ids, topic_id, topic_name)
+    }
+
+    pub fn insert_predefined(&mut self, topic_id: u16, topic_name: TopicName) -> Option<TopicName> {
+        insert_topic(
+            &mut self.predefined,
+            &mut self.predefined_ids,
+            topic_id,
+            topic_name,
+        )
+    }
+
+    /// Return the topic id of a registered topic name, or assign the next
+    /// free one as a gateway would. Returns `None` if all ids are in use.
+    pub fn register(&mut self, topic_name: &TopicName) -> Option<u16> {
+        if let Some(id) = self.get_id(topic_name) {
+            return Some(id);
+        }
+        for _ in 0..=u16::MAX {
+            // 0x0000 and 0xFFFF are reserved
+            self.next_id = match self.next_id {
+                0xFFFE => 1,
+                id => id + 1,
+            };
+            if !self.normal.contains_key(&self.next_id) {
+                self.insert(self.next_id, topic_name.clone());
+                return Some(self.next_id);
+            }
+        }
+        None
+    }
+
+    pub fn get(&self, topic_id: u16) -> Option<&TopicName> {
+        self.normal.get(&topic_id)
+    }
+
+    pub fn get_predefined(&self, topic_id: u16) -> Option<&TopicName> {
+        self.predefined.get(&topic_id)
+    }
+
+    /// The normal topic id of a topic name.
+    pub fn get_id(&self, topic_name: &TopicName) -> Option<u16> {
+        self.normal_ids.get(topic_name).copied()
+    }
+
+    /// The topic name of a topic id.
+    pub fn resolve(&self, topic_id: &TopicId) -> Result<TopicName, ErrorSn> {
+        match *topic_id {
+            TopicId::Normal(id) => self.get(id).cloned().ok_or(ErrorSn::UnknownTopicId(id)),
+            TopicId::Predefined(id) => self
+                .get_predefined(id)
+                .cloned()
+                .ok_or(ErrorSn::UnknownTopicId(id)),
+            TopicId::Short(name) => {
+                let name = simdutf8::basic::from_utf8(&name).map_err(|_| Error::InvalidString)?;
+                Ok(TopicName::try_from(name)?)
+            }
+        }
+    }
+
+    /// The topic id of a topic name, registered ids take precedence over
+    /// predefined ids and short topic names.
+    pub fn topic_id(&self, topic_name: &TopicName) -> Result<TopicId, ErrorSn> {
+        if let Some(id) = self.get_id(topic_name) {
+            return Ok(TopicId::Normal(id));
+        }
+        if let Some(id) = self.predefined_ids.get(topic_name) {
+            return Ok(TopicId::Predefined(*id));
+        }
+        match topic_name.as_bytes() {
+            [a, b] => Ok(TopicId::Short([*a, *b])),
+            _ => Err(ErrorSn::UnregisteredTopic(topic_name.clone())),
+        }
+    }
+}
+
+/// Insert into `ids` and keep the reverse lookup `names` in sync, a name
+/// given to several ids resolves to the last inserted one.
+fn insert_topic(
+    ids: &mut BTreeMap<u16, TopicName>,
+    names: &mut BTreeMap<TopicName, u16>,
+    topic_id: u16,
+    topic_name: TopicName,
+) -> Option<TopicName> {
+    names.insert(topic_name.clone(), topic_id);
+    let old = ids.insert(topic_id, topic_name)?;
+    if names.get(&old) == Some(&topic_id) {
+        // Rare, fall back to another id with the replaced name
+        match ids.iter().find(|(_, name)| **name == old) {
+            Some((id, _)) => names.insert(old.clone(), *id),
+            None => names.remove(&old),
+        };
+    }
+    Some(old)
+}
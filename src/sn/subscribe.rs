@@ -0,0 +1,130 @@
+use crate::{read_u16, read_u8, write_u16, write_u8, Encodable, Error, QoS, SyncWrite};
+
+use super::types::{qos_from_flags, qos_to_flags, FLAG_DUP};
+use super::{ErrorSn, MsgType, ReturnCode, SubscribeTopic};
+
+/// Subscribe message body type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Subscribe {
+    pub dup: bool,
+    pub qos: QoS,
+    pub msg_id: u16,
+    pub topic: SubscribeTopic,
+}
+
+impl Subscribe {
+    pub fn new(qos: QoS, msg_id: u16, topic: SubscribeTopic) -> Self {
+        Subscribe {
+            dup: false,
+            qos,
+            msg_id,
+            topic,
+        }
+    }
+
+    pub fn decode(buf: &[u8], offset: &mut usize) -> Result<Self, ErrorSn> {
+        let flags = read_u8(buf, offset)?;
+        let qos = qos_from_flags(MsgType::Subscribe, flags)?.to_qos();
+        let msg_id = read_u16(buf, offset)?;
+        let topic = SubscribeTopic::decode(MsgType::Subscribe, flags, buf, offset)?;
+        Ok(Subscribe {
+            dup: flags & FLAG_DUP != 0,
+            qos,
+            msg_id,
+            topic,
+        })
+    }
+}
+
+impl Encodable for Subscribe {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+        let mut flags = qos_to_flags(self.qos.into()) | self.topic.id_type();
+        if self.dup {
+            flags |= FLAG_DUP;
+        }
+        write_u8(writer, flags)?;
+        write_u16(writer, self.msg_id)?;
+        self.topic.encode(writer)
+    }
+
+    fn encode_len(&self) -> usize {
+        3 + self.topic.encode_len()
+    }
+}
+
+/// Suback message body type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Suback {
+    /// Granted QoS.
+    pub qos: QoS,
+    /// Topic id of a topic name without wildcards, 0 otherwise.
+    pub topic_id: u16,
+    pub msg_id: u16,
+    pub return_code: ReturnCode,
+}
+
+impl Suback {
+    pub fn decode(buf: &[u8], offset: &mut usize) -> Result<Self, ErrorSn> {
+        let flags = read_u8(buf, offset)?;
+        let qos = qos_from_flags(MsgType::Suback, flags)?.to_qos();
+        let topic_id = read_u16(buf, offset)?;
+        let msg_id = read_u16(buf, offset)?;
+        let return_code = ReturnCode::from_u8(read_u8(buf, offset)?)?;
+        Ok(Suback {
+            qos,
+            topic_id,
+            msg_id,
+            return_code,
+        })
+    }
+}
+
+impl Encodable for Suback {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+        write_u8(writer, qos_to_flags(self.qos.into()))?;
+        write_u16(writer, self.topic_id)?;
+        write_u16(writer, self.msg_id)?;
+        write_u8(writer, self.return_code.as_u8())?;
+        Ok(())
+    }
+
+    fn encode_len(&self) -> usize {
+        6
+    }
+}
+
+/// Unsubscribe message body type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Unsubscribe {
+    pub msg_id: u16,
+    pub topic: SubscribeTopic,
+}
+
+impl Unsubscribe {
+    pub fn new(msg_id: u16, topic: SubscribeTopic) -> Self {
+        Unsubscribe { msg_id, topic }
+    }
+
+    pub fn decode(buf: &[u8], offset: &mut usize) -> Result<Self, ErrorSn> {
+        let flags = read_u8(buf, offset)?;
+        let msg_id = read_u16(buf, offset)?;
+        let topic = SubscribeTopic::decode(MsgType::Unsubscribe, flags, buf, offset)?;
+        Ok(Unsubscribe { msg_id, topic })
+    }
+}
+
+impl Encodable for Unsubscribe {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+        write_u8(writer, self.topic.id_type())?;
+        write_u16(writer, self.msg_id)?;
+        self.topic.encode(writer)
+    }
+
+    fn encode_len(&self) -> usize {
+        3 + self.topic.encode_len()
+    }
+}
@@ -0,0 +1,232 @@
+use bytes::Bytes;
+
+use crate::sn::*;
+use crate::*;
+
+#[test]
+fn test_decode_spec_examples() {
+    let cases: &[(&[u8], Packet)] = &[
+        (
+            &[5, 0x00, 7, 0, 60],
+            Advertise {
+                gw_id: 7,
+                duration: 60,
+            }
+            .into(),
+        ),
+        (&[3, 0x01, 1], Packet::SearchGw(1)),
+        (
+            &[5, 0x02, 3, 10, 1],
+            GwInfo {
+                gw_id: 3,
+                gw_addr: Bytes::from_static(&[10, 1]),
+            }
+            .into(),
+        ),
+        (
+            &[8, 0x04, 0x0C, 0x01, 0, 30, b'c', b'1'],
+            Connect {
+                will: true,
+                clean_session: true,
+                duration: 30,
+                client_id: "c1".into(),
+            }
+            .into(),
+        ),
+        (
+            &[3, 0x05, 0x03],
+            Packet::Connack(ReturnCode::RejectedNotSupported),
+        ),
+        (&[2, 0x06], Packet::WillTopicReq),
+        (&[2, 0x07], Packet::WillTopic(None)),
+        (
+            &[6, 0x07, 0x30, b'a', b'/', b'b'],
+            Packet::WillTopic(Some(WillTopic {
+                qos: QoS::Level1,
+                retain: true,
+                topic_name: TopicName::try_from("a/b").unwrap(),
+            })),
+        ),
+        (
+            &[4, 0x09, 1, 2],
+            Packet::WillMsg(Bytes::from_static(&[1, 2])),
+        ),
+        (
+            &[9, 0x0A, 0, 0, 0, 5, b'a', b'/', b'b'],
+            Register {
+                topic_id: 0,
+                msg_id: 5,
+                topic_name: TopicName::try_from("a/b").unwrap(),
+            }
+            .into(),
+        ),
+        (
+            &[7, 0x0B, 0, 1, 0, 5, 0x00],
+            Packet::Regack(TopicAck::new(1, 5, ReturnCode::Accepted)),
+        ),
+        (
+            &[9, 0x0C, 0xB1, 0x12, 0x34, 0, 0, 0xAA, 0xBB],
+            Publish {
+                dup: true,
+                qos: SnQoS::Level1,
+                retain: true,
+                topic_id: TopicId::Predefined(0x1234),
+                msg_id: 0,
+                data: Bytes::from_static(&[0xAA, 0xBB]),
+            }
+            .into(),
+        ),
+        (
+            &[7, 0x0C, 0x62, b'a', b'b', 0, 0],
+            Publish::new(SnQoS::LevelMinus1, TopicId::Short(*b"ab"), 0, Bytes::new()).into(),
+        ),
+        (
+            &[7, 0x0D, 0, 1, 0, 9, 0x02],
+            Packet::Puback(TopicAck::new(1, 9, ReturnCode::RejectedInvalidTopicId)),
+        ),
+        (&[4, 0x0E, 0, 9], Packet::Pubcomp(9)),
+        (&[4, 0x0F, 0, 9], Packet::Pubrec(9)),
+        (&[4, 0x10, 0, 9], Packet::Pubrel(9)),
+        (
+            &[8, 0x12, 0x40, 0, 2, b'a', b'/', b'#'],
+            Subscribe::new(
+                QoS::Level2,
+                2,
+                SubscribeTopic::Filter(TopicFilter::try_from("a/#").unwrap()),
+            )
+            .into(),
+        ),
+        (
+            &[7, 0x12, 0x01, 0, 2, 0, 7],
+            Subscribe::new(QoS::Level0, 2, SubscribeTopic::Predefined(7)).into(),
+        ),
+        (
+            &[8, 0x13, 0x20, 0, 1, 0, 2, 0],
+            Suback {
+                qos: QoS::Level1,
+                topic_id: 1,
+                msg_id: 2,
+                return_code: ReturnCode::Accepted,
+            }
+            .into(),
+        ),
+        (
+            &[7, 0x14, 0x02, 0, 3, b'x', b'y'],
+            Unsubscribe::new(3, SubscribeTopic::Short(*b"xy")).into(),
+        ),
+        (&[4, 0x15, 0, 3], Packet::Unsuback(3)),
+        (&[2, 0x16], Packet::Pingreq(None)),
+        (&[4, 0x16, b'c', b'1'], Packet::Pingreq(Some("c1".into()))),
+        (&[2, 0x17], Packet::Pingresp),
+        (&[2, 0x18], Packet::Disconnect(None)),
+        (&[4, 0x18, 0, 60], Packet::Disconnect(Some(60))),
+        (&[2, 0x1A], Packet::WillTopicUpd(None)),
+        (&[3, 0x1B, 0], Packet::WillTopicResp(ReturnCode::Accepted)),
+        (&[3, 0x1C, 1], Packet::WillMsgUpd(Bytes::from_static(&[1]))),
+        (
+            &[3, 0x1D, 1],
+            Packet::WillMsgResp(ReturnCode::RejectedCongestion),
+        ),
+    ];
+    for (data, packet) in cases {
+        assert_eq!(
+            Packet::decode_from_slice(data),
+            Ok(Some((packet.clone(), data.len()))),
+            "{data:02x?}"
+        );
+        assert_eq!(
+            &block_on(Packet::decode_async(&mut &data[..])).unwrap(),
+            packet
+        );
+        assert_eq!(&Packet::read_from(&mut &data[..]).unwrap(), packet);
+    }
+}
+
+#[test]
+fn test_decode_long_length() {
+    let mut data = alloc::vec![0x01, 0x01, 0x06, 0x0C, 0x00, 0, 1, 0, 0];
+    data.extend_from_slice(&[7; 253]);
+    let (packet, len) = Packet::decode_from_slice(&data).unwrap().unwrap();
+    assert_eq!(len, 262);
+    assert_eq!(
+        packet,
+        Publish::new(
+            SnQoS::Level0,
+            TopicId::Normal(1),
+            0,
+            Bytes::from(alloc::vec![7; 253])
+        )
+        .into()
+    );
+
+    // Not enough bytes
+    for end in 0..data.len() {
+        assert_eq!(Packet::decode(&data[..end]), Ok(None));
+        assert!(block_on(Packet::decode_async(&mut &data[..end]))
+            .unwrap_err()
+            .is_eof());
+    }
+}
+
+#[test]
+fn test_decode_encapsulated() {
+    let data = [5, 0xFE, 0x01, 0xAB, 0xCD, 4, 0x0E, 0, 9];
+    let packet = Packet::Encapsulated(Encapsulated {
+        radius: 1,
+        wireless_node_id: Bytes::from_static(&[0xAB, 0xCD]),
+        packet: alloc::boxed::Box::new(Packet::Pubcomp(9)),
+    });
+    assert_eq!(
+        Packet::decode_from_slice(&data),
+        Ok(Some((packet.clone(), 9)))
+    );
+    assert_eq!(block_on(Packet::decode_async(&mut &data[..])), Ok(packet));
+    assert_eq!(Packet::decode(&data[..8]), Ok(None));
+}
+
+#[test]
+fn test_decode_invalid() {
+    let cases: &[(&[u8], ErrorSn)] = &[
+        (&[0], ErrorSn::InvalidLength(0)),
+        (&[1, 0, 2], ErrorSn::InvalidLength(2)),
+        (&[2, 0x03], ErrorSn::InvalidMsgType(0x03)),
+        (&[3, 0x05, 4], ErrorSn::InvalidReturnCode(4)),
+        (&[6, 0x04, 0, 0x02, 0, 1], ErrorSn::InvalidProtocolId(2)),
+        (
+            &[7, 0x0C, 0x03, 0, 1, 0, 0],
+            ErrorSn::InvalidFlags(MsgType::Publish, 0x03),
+        ),
+        (
+            &[7, 0x12, 0x60, 0, 1, 0, 1],
+            ErrorSn::InvalidFlags(MsgType::Subscribe, 0x60),
+        ),
+        (
+            &[6, 0x07, 0x60, b'a', b'b', b'c'],
+            ErrorSn::InvalidFlags(MsgType::WillTopic, 0x60),
+        ),
+        // Too short or too long for the message type
+        (&[3, 0x0E, 0], ErrorSn::InvalidLength(3)),
+        (&[5, 0x0E, 0, 1, 2], ErrorSn::InvalidLength(5)),
+        (&[3, 0x18, 0], ErrorSn::InvalidLength(3)),
+        (
+            &[5, 0x0A, 0, 0, 0, 5, b'a', b'/', b'+'],
+            ErrorSn::InvalidLength(5),
+        ),
+        (
+            &[7, 0x0A, 0, 0, 0, 5, b'+'],
+            ErrorSn::Common(Error::InvalidTopicName("+".into())),
+        ),
+        (
+            &[4, 0x16, 0xFF, 0xFE],
+            ErrorSn::Common(Error::InvalidString),
+        ),
+    ];
+    for (data, err) in cases {
+        assert_eq!(Packet::decode(data).as_ref(), Err(err), "{data:02x?}");
+        assert_eq!(
+            Packet::read_from(&mut &data[..]).as_ref(),
+            Err(err),
+            "{data:02x?}"
+        );
+    }
+}
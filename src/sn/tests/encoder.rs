@@ -0,0 +1,275 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use bytes::Bytes;
+
+use crate::sn::*;
+use crate::*;
+
+fn assert_encode(pkt: Packet, len: usize) {
+    let mut data_async = Vec::new();
+    block_on(pkt.encode_async(&mut data_async)).unwrap();
+    let var_bytes = pkt.encode().unwrap();
+    assert_eq!(var_bytes.as_ref(), &data_async);
+    assert_eq!(pkt.encode_len().unwrap(), len);
+    assert_eq!(data_async.len(), len);
+
+    let mut data_sync = Vec::new();
+    pkt.write_to(&mut data_sync).unwrap();
+    assert_eq!(data_sync, data_async);
+
+    assert_eq!(Packet::decode(&data_async).unwrap().unwrap(), pkt);
+    assert_eq!(Packet::read_from(&mut &data_async[..]).unwrap(), pkt);
+}
+
+fn topic_name(value: &str) -> TopicName {
+    TopicName::try_from(value).unwrap()
+}
+
+#[test]
+fn test_encode_packets() {
+    assert_encode(
+        Advertise {
+            gw_id: 1,
+            duration: 900,
+        }
+        .into(),
+        5,
+    );
+    assert_encode(Packet::SearchGw(2), 3);
+    assert_encode(
+        GwInfo {
+            gw_id: 1,
+            gw_addr: Bytes::new(),
+        }
+        .into(),
+        3,
+    );
+    assert_encode(Connect::new("client".into(), 60).into(), 12);
+    assert_encode(Packet::Connack(ReturnCode::Accepted), 3);
+    assert_encode(Packet::WillTopicReq, 2);
+    assert_encode(Packet::WillTopic(None), 2);
+    assert_encode(
+        Packet::WillTopic(Some(WillTopic::new(QoS::Level2, topic_name("will")))),
+        7,
+    );
+    assert_encode(Packet::WillMsgReq, 2);
+    assert_encode(Packet::WillMsg(Bytes::from_static(b"bye")), 5);
+    assert_encode(
+        Register {
+            topic_id: 3,
+            msg_id: 4,
+            topic_name: topic_name("a/b"),
+        }
+        .into(),
+        9,
+    );
+    assert_encode(Packet::Regack(TopicAck::new(3, 4, ReturnCode::Accepted)), 7);
+    for qos in [
+        SnQoS::Level0,
+        SnQoS::Level1,
+        SnQoS::Level2,
+        SnQoS::LevelMinus1,
+    ] {
+        for topic_id in [
+            TopicId::Normal(1),
+            TopicId::Predefined(2),
+            TopicId::Short(*b"ab"),
+        ] {
+            let mut publish = Publish::new(qos, topic_id, 7, Bytes::from_static(b"data"));
+            publish.dup = true;
+            publish.retain = true;
+            assert_encode(publish.into(), 11);
+        }
+    }
+    assert_encode(
+        Packet::Puback(TopicAck::new(1, 7, ReturnCode::RejectedCongestion)),
+        7,
+    );
+    assert_encode(Packet::Pubrec(7), 4);
+    assert_encode(Packet::Pubrel(7), 4);
+    assert_encode(Packet::Pubcomp(7), 4);
+    assert_encode(
+        Subscribe::new(
+            QoS::Level1,
+            8,
+            SubscribeTopic::Filter(TopicFilter::try_from("a/+").unwrap()),
+        )
+        .into(),
+        8,
+    );
+    assert_encode(
+        Suback {
+            qos: QoS::Level1,
+            topic_id: 0,
+            msg_id: 8,
+            return_code: ReturnCode::Accepted,
+        }
+        .into(),
+        8,
+    );
+    assert_encode(Unsubscribe::new(9, SubscribeTopic::Predefined(5)).into(), 7);
+    assert_encode(Packet::Unsuback(9), 4);
+    assert_encode(Packet::Pingreq(None), 2);
+    assert_encode(Packet::Pingreq(Some("client".into())), 8);
+    assert_encode(Packet::Pingresp, 2);
+    assert_encode(Packet::Disconnect(None), 2);
+    assert_encode(Packet::Disconnect(Some(300)), 4);
+    assert_encode(Packet::WillTopicUpd(None), 2);
+    assert_encode(Packet::WillTopicResp(ReturnCode::Accepted), 3);
+    assert_encode(Packet::WillMsgUpd(Bytes::new()), 2);
+    assert_encode(Packet::WillMsgResp(ReturnCode::Accepted), 3);
+    assert_encode(
+        Encapsulated {
+            radius: 3,
+            wireless_node_id: Bytes::from_static(&[1, 2, 3]),
+            packet: Box::new(Packet::Pingresp),
+        }
+        .into(),
+        8,
+    );
+}
+
+#[test]
+fn test_encode_long_length() {
+    let publish = |len: usize| {
+        Packet::from(Publish::new(
+            SnQoS::Level0,
+            TopicId::Normal(1),
+            0,
+            Bytes::from(alloc::vec![0; len]),
+        ))
+    };
+    // The longest message with a one byte length field
+    assert_encode(publish(248), 255);
+    let data = publish(249).encode().unwrap();
+    assert_eq!(&data.as_ref()[..4], &[0x01, 0x01, 0x02, 0x0C]);
+    assert_encode(publish(249), 258);
+    assert_encode(publish(65526), 65535);
+    assert_eq!(
+        publish(65527).encode().unwrap_err(),
+        ErrorSn::MessageTooLarge(65536)
+    );
+}
+
+#[test]
+fn test_topic_registry() {
+    let mut registry = TopicRegistry::new();
+    assert_eq!(registry.register(&topic_name("a/b")), Some(1));
+    assert_eq!(registry.register(&topic_name("c")), Some(2));
+    assert_eq!(registry.register(&topic_name("a/b")), Some(1));
+    assert_eq!(registry.insert(9, topic_name("x/y")), None);
+    assert_eq!(registry.insert_predefined(1, topic_name("pre")), None);
+
+    assert_eq!(registry.resolve(&TopicId::Normal(9)), Ok(topic_name("x/y")));
+    assert_eq!(
+        registry.resolve(&TopicId::Predefined(1)),
+        Ok(topic_name("pre"))
+    );
+    assert_eq!(
+        registry.resolve(&TopicId::Short(*b"ab")),
+        Ok(topic_name("ab"))
+    );
+    assert_eq!(
+        registry.resolve(&TopicId::Normal(3)),
+        Err(ErrorSn::UnknownTopicId(3))
+    );
+    assert_eq!(
+        registry.resolve(&TopicId::Short(*b"a+")),
+        Err(ErrorSn::Common(Error::InvalidTopicName("a+".into())))
+    );
+
+    assert_eq!(registry.topic_id(&topic_name("c")), Ok(TopicId::Normal(2)));
+    assert_eq!(
+        registry.topic_id(&topic_name("pre")),
+        Ok(TopicId::Predefined(1))
+    );
+    assert_eq!(
+        registry.topic_id(&topic_name("xy")),
+        Ok(TopicId::Short(*b"xy"))
+    );
+    assert_eq!(
+        registry.topic_id(&topic_name("d/e")),
+        Err(ErrorSn::UnregisteredTopic(topic_name("d/e")))
+    );
+
+    // Replaced names are no longer found by id lookups
+    assert_eq!(registry.insert(2, topic_name("d/e")), Some(topic_name("c")));
+    assert_eq!(registry.get_id(&topic_name("c")), None);
+    assert_eq!(registry.get_id(&topic_name("d/e")), Some(2));
+    assert_eq!(registry.insert(10, topic_name("x/y")), None);
+    assert_eq!(registry.get_id(&topic_name("x/y")), Some(10));
+    assert_eq!(
+        registry.insert(10, topic_name("z")),
+        Some(topic_name("x/y"))
+    );
+    assert_eq!(registry.get_id(&topic_name("x/y")), Some(9));
+    assert_eq!(registry.register(&topic_name("c")), Some(3));
+}
+
+#[cfg(feature = "v3")]
+#[test]
+fn test_publish_v3() {
+    let mut registry = TopicRegistry::new();
+    registry.insert(5, topic_name("a/b"));
+
+    let mut publish = Publish::new(
+        SnQoS::Level1,
+        TopicId::Normal(5),
+        10,
+        Bytes::from_static(b"data"),
+    );
+    publish.retain = true;
+    let mut v3_publish = v3::Publish::new(
+        QosPid::Level1(Pid::try_from(10).unwrap()),
+        topic_name("a/b"),
+        Bytes::from_static(b"data"),
+    );
+    v3_publish.retain = true;
+    assert_eq!(publish.to_v3(&registry), Ok(v3_publish.clone()));
+    assert_eq!(Publish::from_v3(&v3_publish, &registry), Ok(publish));
+
+    // QoS -1 is delivered as QoS 0
+    let publish = Publish::new(SnQoS::LevelMinus1, TopicId::Short(*b"ab"), 0, Bytes::new());
+    let v3_publish = v3::Publish::new(QosPid::Level0, topic_name("ab"), Bytes::new());
+    assert_eq!(publish.to_v3(&registry), Ok(v3_publish.clone()));
+    assert_eq!(
+        Publish::from_v3(&v3_publish, &registry),
+        Ok(Publish::new(
+            SnQoS::Level0,
+            TopicId::Short(*b"ab"),
+            0,
+            Bytes::new()
+        ))
+    );
+
+    let publish = Publish::new(SnQoS::Level2, TopicId::Normal(5), 0, Bytes::new());
+    assert_eq!(publish.to_v3(&registry), Err(Error::ZeroPid.into()));
+}
+
+#[cfg(feature = "v5")]
+#[test]
+fn test_publish_v5() {
+    let mut registry = TopicRegistry::new();
+    registry.insert_predefined(1, topic_name("sensors/temp"));
+
+    let publish = Publish::new(
+        SnQoS::Level2,
+        TopicId::Predefined(1),
+        3,
+        Bytes::from_static(b"21.5"),
+    );
+    let v5_publish = v5::Publish::new(
+        QosPid::Level2(Pid::try_from(3).unwrap()),
+        topic_name("sensors/temp"),
+        Bytes::from_static(b"21.5"),
+    );
+    assert_eq!(publish.to_v5(&registry), Ok(v5_publish.clone()));
+    assert_eq!(Publish::from_v5(&v5_publish, &registry), Ok(publish));
+
+    let v5_publish = v5::Publish::new(QosPid::Level0, topic_name("other"), Bytes::new());
+    assert_eq!(
+        Publish::from_v5(&v5_publish, &registry),
+        Err(ErrorSn::UnregisteredTopic(topic_name("other")))
+    );
+}
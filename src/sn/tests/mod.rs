@@ -0,0 +1,2 @@
+mod decoder;
+mod encoder;
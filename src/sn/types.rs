@@ -0,0 +1,296 @@
+use core::convert::TryFrom;
+
+use crate::{read_u16, write_u16, Encodable, Error, QoS, SyncWrite, TopicFilter};
+
+use super::ErrorSn;
+
+/// MQTT-SN message type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MsgType {
+    Advertise,
+    SearchGw,
+    GwInfo,
+    Connect,
+    Connack,
+    WillTopicReq,
+    WillTopic,
+    WillMsgReq,
+    WillMsg,
+    Register,
+    Regack,
+    Publish,
+    Puback,
+    Pubcomp,
+    Pubrec,
+    Pubrel,
+    Subscribe,
+    Suback,
+    Unsubscribe,
+    Unsuback,
+    Pingreq,
+    Pingresp,
+    Disconnect,
+    WillTopicUpd,
+    WillTopicResp,
+    WillMsgUpd,
+    WillMsgResp,
+    Encapsulated,
+}
+
+impl MsgType {
+    pub fn from_u8(value: u8) -> Result<Self, ErrorSn> {
+        Ok(match value {
+            0x00 => MsgType::Advertise,
+            0x01 => MsgType::SearchGw,
+            0x02 => MsgType::GwInfo,
+            0x04 => MsgType::Connect,
+            0x05 => MsgType::Connack,
+            0x06 => MsgType::WillTopicReq,
+            0x07 => MsgType::WillTopic,
+            0x08 => MsgType::WillMsgReq,
+            0x09 => MsgType::WillMsg,
+            0x0A => MsgType::Register,
+            0x0B => MsgType::Regack,
+            0x0C => MsgType::Publish,
+            0x0D => MsgType::Puback,
+            0x0E => MsgType::Pubcomp,
+            0x0F => MsgType::Pubrec,
+            0x10 => MsgType::Pubrel,
+            0x12 => MsgType::Subscribe,
+            0x13 => MsgType::Suback,
+            0x14 => MsgType::Unsubscribe,
+            0x15 => MsgType::Unsuback,
+            0x16 => MsgType::Pingreq,
+            0x17 => MsgType::Pingresp,
+            0x18 => MsgType::Disconnect,
+            0x1A => MsgType::WillTopicUpd,
+            0x1B => MsgType::WillTopicResp,
+            0x1C => MsgType::WillMsgUpd,
+            0x1D => MsgType::WillMsgResp,
+            0xFE => MsgType::Encapsulated,
+            _ => return Err(ErrorSn::InvalidMsgType(value)),
+        })
+    }
+
+    pub fn as_u8(self) -> u8 {
+        match self {
+            MsgType::Advertise => 0x00,
+            MsgType::SearchGw => 0x01,
+            MsgType::GwInfo => 0x02,
+            MsgType::Connect => 0x04,
+            MsgType::Connack => 0x05,
+            MsgType::WillTopicReq => 0x06,
+            MsgType::WillTopic => 0x07,
+            MsgType::WillMsgReq => 0x08,
+            MsgType::WillMsg => 0x09,
+            MsgType::Register => 0x0A,
+            MsgType::Regack => 0x0B,
+            MsgType::Publish => 0x0C,
+            MsgType::Puback => 0x0D,
+            MsgType::Pubcomp => 0x0E,
+            MsgType::Pubrec => 0x0F,
+            MsgType::Pubrel => 0x10,
+            MsgType::Subscribe => 0x12,
+            MsgType::Suback => 0x13,
+            MsgType::Unsubscribe => 0x14,
+            MsgType::Unsuback => 0x15,
+            MsgType::Pingreq => 0x16,
+            MsgType::Pingresp => 0x17,
+            MsgType::Disconnect => 0x18,
+            MsgType::WillTopicUpd => 0x1A,
+            MsgType::WillTopicResp => 0x1B,
+            MsgType::WillMsgUpd => 0x1C,
+            MsgType::WillMsgResp => 0x1D,
+            MsgType::Encapsulated => 0xFE,
+        }
+    }
+}
+
+/// QoS of a publish message, level -1 publishes without a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SnQoS {
+    Level0,
+    Level1,
+    Level2,
+    LevelMinus1,
+}
+
+impl SnQoS {
+    /// The QoS of MQTT, level -1 is delivered as level 0.
+    pub fn to_qos(self) -> QoS {
+        match self {
+            SnQoS::Level0 | SnQoS::LevelMinus1 => QoS::Level0,
+            SnQoS::Level1 => QoS::Level1,
+            SnQoS::Level2 => QoS::Level2,
+        }
+    }
+}
+
+impl From<QoS> for SnQoS {
+    fn from(qos: QoS) -> Self {
+        match qos {
+            QoS::Level0 => SnQoS::Level0,
+            QoS::Level1 => SnQoS::Level1,
+            QoS::Level2 => SnQoS::Level2,
+        }
+    }
+}
+
+/// Topic of a publish message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TopicId {
+    /// Topic id registered by a REGISTER message.
+    Normal(u16),
+    /// Topic id agreed on in advance by the client and the gateway.
+    Predefined(u16),
+    /// Topic name of two characters.
+    Short([u8; 2]),
+}
+
+impl TopicId {
+    pub(crate) fn id_type(&self) -> u8 {
+        match self {
+            TopicId::Normal(_) => 0b00,
+            TopicId::Predefined(_) => 0b01,
+            TopicId::Short(_) => 0b10,
+        }
+    }
+
+    pub(crate) fn to_u16(self) -> u16 {
+        match self {
+            TopicId::Normal(id) | TopicId::Predefined(id) => id,
+            TopicId::Short(name) => u16::from_be_bytes(name),
+        }
+    }
+
+    pub(crate) fn from_parts(msg_type: MsgType, flags: u8, value: u16) -> Result<Self, ErrorSn> {
+        match flags & 0b11 {
+            0b00 => Ok(TopicId::Normal(value)),
+            0b01 => Ok(TopicId::Predefined(value)),
+            0b10 => Ok(TopicId::Short(value.to_be_bytes())),
+            _ => Err(ErrorSn::InvalidFlags(msg_type, flags)),
+        }
+    }
+}
+
+/// Topic of a subscribe or unsubscribe message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SubscribeTopic {
+    /// Topic filter, wildcards are allowed.
+    Filter(TopicFilter),
+    /// Topic id agreed on in advance by the client and the gateway.
+    Predefined(u16),
+    /// Topic name of two characters.
+    Short([u8; 2]),
+}
+
+impl SubscribeTopic {
+    pub(crate) fn id_type(&self) -> u8 {
+        match self {
+            SubscribeTopic::Filter(_) => 0b00,
+            SubscribeTopic::Predefined(_) => 0b01,
+            SubscribeTopic::Short(_) => 0b10,
+        }
+    }
+
+    pub(crate) fn decode(
+        msg_type: MsgType,
+        flags: u8,
+        buf: &[u8],
+        offset: &mut usize,
+    ) -> Result<Self, ErrorSn> {
+        match flags & 0b11 {
+            0b00 => {
+                let filter = read_rest_str(buf, offset)?;
+                Ok(SubscribeTopic::Filter(TopicFilter::try_from(filter)?))
+            }
+            0b01 => Ok(SubscribeTopic::Predefined(read_u16(buf, offset)?)),
+            0b10 => Ok(SubscribeTopic::Short(read_u16(buf, offset)?.to_be_bytes())),
+            _ => Err(ErrorSn::InvalidFlags(msg_type, flags)),
+        }
+    }
+}
+
+impl Encodable for SubscribeTopic {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+        match self {
+            SubscribeTopic::Filter(filter) => writer.write_all(filter.as_bytes())?,
+            SubscribeTopic::Predefined(id) => write_u16(writer, *id)?,
+            SubscribeTopic::Short(name) => writer.write_all(name)?,
+        }
+        Ok(())
+    }
+
+    fn encode_len(&self) -> usize {
+        match self {
+            SubscribeTopic::Filter(filter) => filter.len(),
+            _ => 2,
+        }
+    }
+}
+
+/// Return code of the acknowledge messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReturnCode {
+    Accepted,
+    RejectedCongestion,
+    RejectedInvalidTopicId,
+    RejectedNotSupported,
+}
+
+impl ReturnCode {
+    pub fn from_u8(value: u8) -> Result<Self, ErrorSn> {
+        Ok(match value {
+            0x00 => ReturnCode::Accepted,
+            0x01 => ReturnCode::RejectedCongestion,
+            0x02 => ReturnCode::RejectedInvalidTopicId,
+            0x03 => ReturnCode::RejectedNotSupported,
+            _ => return Err(ErrorSn::InvalidReturnCode(value)),
+        })
+    }
+
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+}
+
+pub(crate) const FLAG_DUP: u8 = 0x80;
+pub(crate) const FLAG_RETAIN: u8 = 0x10;
+pub(crate) const FLAG_WILL: u8 = 0x08;
+pub(crate) const FLAG_CLEAN_SESSION: u8 = 0x04;
+
+pub(crate) fn qos_from_flags(msg_type: MsgType, flags: u8) -> Result<SnQoS, ErrorSn> {
+    Ok(match (flags >> 5) & 0b11 {
+        0 => SnQoS::Level0,
+        1 => SnQoS::Level1,
+        2 => SnQoS::Level2,
+        _ if msg_type == MsgType::Publish => SnQoS::LevelMinus1,
+        _ => return Err(ErrorSn::InvalidFlags(msg_type, flags)),
+    })
+}
+
+pub(crate) fn qos_to_flags(qos: SnQoS) -> u8 {
+    let value = match qos {
+        SnQoS::Level0 => 0,
+        SnQoS::Level1 => 1,
+        SnQoS::Level2 => 2,
+        SnQoS::LevelMinus1 => 3,
+    };
+    value << 5
+}
+
+/// The rest of the message as a string, strings are not length prefixed in
+/// MQTT-SN.
+pub(crate) fn read_rest_str<'a>(buf: &'a [u8], offset: &mut usize) -> Result<&'a str, ErrorSn> {
+    let data = &buf[*offset..];
+    *offset = buf.len();
+    simdutf8::basic::from_utf8(data).map_err(|_| crate::Error::InvalidString.into())
+}
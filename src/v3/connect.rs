@@ -7,11 +7,13 @@ use tokio::io::AsyncReadExt;
 use crate::{
     read_bytes, read_bytes_async, read_string, read_string_async, read_u16, read_u16_async,
     read_u8, read_u8_async, write_bytes, write_string, write_u16, write_u8, AsyncRead, ClientId,
-    Encodable, Error, Protocol, QoS, SyncWrite, ToError, TopicName, Username,
+    DecodeOptions, Encodable, Error, Leniency, Protocol, QoS, SyncWrite, ToError, TopicName,
+    Username,
 };
 
 /// Connect packet body type.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Connect {
     pub protocol: Protocol,
     pub clean_session: bool,
@@ -51,13 +53,28 @@ impl Connect {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize) -> Result<Self, Error> {
+        Self::decode_with(buf, offset, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        options: &DecodeOptions,
+    ) -> Result<Self, Error> {
         let protocol = Protocol::decode(buf, offset)?;
-        Self::decode_buffer_with_protocol(buf, offset, protocol)
+        Self::decode_buffer_with_options(buf, offset, protocol, options)
     }
 
     pub async fn decode_async<T: AsyncRead + Unpin>(reader: &mut T) -> Result<Self, Error> {
+        Self::decode_async_with(reader, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        options: &DecodeOptions,
+    ) -> Result<Self, Error> {
         let protocol = Protocol::decode_async(reader).await?;
-        Self::decode_stream_with_protocol(reader, protocol).await
+        Self::decode_stream_with_options(reader, protocol, options).await
     }
 
     #[inline]
@@ -65,18 +82,30 @@ impl Connect {
         buf: &[u8],
         offset: &mut usize,
         protocol: Protocol,
+    ) -> Result<Self, Error> {
+        Self::decode_buffer_with_options(buf, offset, protocol, &DecodeOptions::default())
+    }
+
+    /// [`Connect::decode_buffer_with_protocol`] with the decode `options`.
+    pub fn decode_buffer_with_options(
+        buf: &[u8],
+        offset: &mut usize,
+        protocol: Protocol,
+        options: &DecodeOptions,
     ) -> Result<Self, Error> {
         if protocol as u8 > 4 {
             return Err(Error::UnexpectedProtocol(protocol));
         }
         let connect_flags: u8 = read_u8(buf, offset)?;
-        if connect_flags & 1 != 0 {
+        if connect_flags & 1 != 0 && !options.tolerate(Leniency::ConnectReservedFlag) {
             return Err(Error::InvalidConnectFlags(connect_flags));
         }
         let keep_alive = read_u16(buf, offset)?;
-        let client_id = read_string(buf, offset)?.into();
+        let client_id = read_string(buf, offset)?;
+        options.check_string(client_id)?;
         let last_will = if connect_flags & 0b100 != 0 {
             let topic_name_slice = read_string(buf, offset)?;
+            options.check_string(topic_name_slice)?;
             let message_slice = read_bytes(buf, offset)?;
             let qos = QoS::from_u8((connect_flags & 0b11000) >> 3)?;
             let retain = (connect_flags & 0b00100000) != 0;
@@ -92,7 +121,9 @@ impl Connect {
             None
         };
         let username = if connect_flags & 0b10000000 != 0 {
-            Some(read_string(buf, offset)?.into())
+            let username = read_string(buf, offset)?;
+            options.check_string(username)?;
+            Some(username.into())
         } else {
             None
         };
@@ -106,7 +137,7 @@ impl Connect {
             protocol,
             clean_session,
             keep_alive,
-            client_id,
+            client_id: client_id.into(),
             last_will,
             username,
             password,
@@ -117,18 +148,29 @@ impl Connect {
     pub async fn decode_stream_with_protocol<T: AsyncRead + Unpin>(
         reader: &mut T,
         protocol: Protocol,
+    ) -> Result<Self, Error> {
+        Self::decode_stream_with_options(reader, protocol, &DecodeOptions::default()).await
+    }
+
+    /// [`Connect::decode_stream_with_protocol`] with the decode `options`.
+    pub async fn decode_stream_with_options<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        protocol: Protocol,
+        options: &DecodeOptions,
     ) -> Result<Self, Error> {
         if protocol as u8 > 4 {
             return Err(Error::UnexpectedProtocol(protocol));
         }
         let connect_flags: u8 = read_u8_async(reader).await?;
-        if connect_flags & 1 != 0 {
+        if connect_flags & 1 != 0 && !options.tolerate(Leniency::ConnectReservedFlag) {
             return Err(Error::InvalidConnectFlags(connect_flags));
         }
         let keep_alive = read_u16_async(reader).await?;
         let client_id = read_string_async(reader).await?;
+        options.check_string(&client_id)?;
         let last_will = if connect_flags & 0b100 != 0 {
             let topic_name = read_string_async(reader).await?;
+            options.check_string(&topic_name)?;
             let message = read_bytes_async(reader).await?;
             let qos = QoS::from_u8((connect_flags & 0b11000) >> 3)?;
             let retain = (connect_flags & 0b00100000) != 0;
@@ -144,7 +186,9 @@ impl Connect {
             None
         };
         let username = if connect_flags & 0b10000000 != 0 {
-            Some(read_string_async(reader).await?)
+            let username = read_string_async(reader).await?;
+            options.check_string(&username)?;
+            Some(username)
         } else {
             None
         };
@@ -224,6 +268,7 @@ impl Encodable for Connect {
 /// Connack packet body type.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Connack {
     pub session_present: bool,
     pub code: ConnectReturnCode,
@@ -276,6 +321,7 @@ impl Connack {
 /// [Connect]: struct.Connect.html
 /// [MQTT 3.1.3.3]: http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718031
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LastWill {
     pub qos: QoS,
     pub retain: bool,
@@ -327,6 +373,8 @@ impl Encodable for LastWill {
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ConnectReturnCode {
     Accepted = 0,
     UnacceptableProtocolVersion = 1,
@@ -0,0 +1,113 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+
+use crate::{
+    dissect_connect_flags, dissect_packets, dissect_protocol, DResult, Dissection, Dissector,
+};
+
+use super::{ConnectReturnCode, SubscribeReturnCode};
+
+/// Dissect the MQTT v3.x packets in `bytes` into an annotated field tree,
+/// for debugging. Dissecting stops at the first malformed field.
+///
+/// ```
+/// let dissection = mqtt_proto::v3::dissect(&[0xc0, 0x00]);
+/// assert_eq!(dissection.packets[0].value, "PINGREQ");
+/// assert!(dissection.error.is_none());
+/// ```
+pub fn dissect(bytes: &[u8]) -> Dissection {
+    dissect_packets(bytes, 14, |d, first| match first >> 4 {
+        1 => connect(d),
+        2 => connack(d),
+        3 => publish(d, (first >> 1) & 0b11),
+        4 | 5 | 6 | 7 | 11 => d.u16("Packet identifier").map(drop),
+        8 => subscribe(d),
+        9 => suback(d),
+        10 => unsubscribe(d),
+        _ => Ok(()),
+    })
+}
+
+fn connect(d: &mut Dissector<'_>) -> DResult<()> {
+    dissect_protocol(d)?;
+    let flags = dissect_connect_flags(d)?;
+    d.u16("Keep alive")?;
+    d.string("Client identifier")?;
+    if flags & 0x04 != 0 {
+        d.string("Will topic")?;
+        d.binary("Will message")?;
+    }
+    if flags & 0x80 != 0 {
+        d.string("Username")?;
+    }
+    if flags & 0x40 != 0 {
+        d.binary("Password")?;
+    }
+    Ok(())
+}
+
+fn connack(d: &mut Dissector<'_>) -> DResult<()> {
+    d.flags("Acknowledge flags", &[("Session present", 0x01)], 0xFE)?;
+    d.u8_with("Return code", |value| {
+        ConnectReturnCode::from_u8(value)
+            .map(|code| Some(format!("{code:?}")))
+            .map_err(|err| err.to_string())
+    })?;
+    Ok(())
+}
+
+fn publish(d: &mut Dissector<'_>, qos: u8) -> DResult<()> {
+    d.string("Topic name")?;
+    if qos > 0 {
+        d.u16("Packet identifier")?;
+    }
+    d.payload("Payload")?;
+    Ok(())
+}
+
+fn subscribe(d: &mut Dissector<'_>) -> DResult<()> {
+    d.u16("Packet identifier")?;
+    if !d.has_more() {
+        return no_topics(d);
+    }
+    while d.has_more() {
+        d.group("Subscription", String::new(), |d| {
+            let filter = d.string("Topic filter")?;
+            d.set_value(format!("{filter:?}"));
+            let options = d.flags("Options", &[("QoS", 0x03)], 0xFC)?;
+            if options & 0x03 == 0x03 {
+                return d.fail(d.offset() - 1, "invalid QoS 3".into());
+            }
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+fn suback(d: &mut Dissector<'_>) -> DResult<()> {
+    d.u16("Packet identifier")?;
+    while d.has_more() {
+        d.u8_with("Return code", |value| {
+            SubscribeReturnCode::from_u8(value)
+                .map(|code| Some(format!("{code:?}")))
+                .map_err(|err| err.to_string())
+        })?;
+    }
+    Ok(())
+}
+
+fn unsubscribe(d: &mut Dissector<'_>) -> DResult<()> {
+    d.u16("Packet identifier")?;
+    if !d.has_more() {
+        return no_topics(d);
+    }
+    while d.has_more() {
+        d.string("Topic filter")?;
+    }
+    Ok(())
+}
+
+fn no_topics(d: &mut Dissector<'_>) -> DResult<()> {
+    let offset = d.offset();
+    d.fail(offset, crate::Error::EmptySubscription.to_string())
+}
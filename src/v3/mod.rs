@@ -4,6 +4,7 @@
 //! [v3.1]: https://public.dhe.ibm.com/software/dw/webservices/ws-mqtt/mqtt-v3r1.html
 
 mod connect;
+mod dissect;
 mod packet;
 mod poll;
 mod publish;
@@ -13,7 +14,8 @@ mod subscribe;
 mod tests;
 
 pub use connect::{Connack, Connect, ConnectReturnCode, LastWill};
-pub use packet::{Header, Packet, PacketType};
-pub use poll::{PollPacket, PollPacketState};
+pub use dissect::dissect;
+pub use packet::{Header, Packet, PacketType, StreamPacket};
+pub use poll::{DecodeBatch, PollPacket, PollPacketState};
 pub use publish::Publish;
 pub use subscribe::{Suback, Subscribe, SubscribeReturnCode, Unsubscribe};
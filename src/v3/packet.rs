@@ -1,18 +1,22 @@
 use core::convert::AsRef;
 
+use bytes::Bytes;
 #[cfg(feature = "tokio")]
 use tokio::io::AsyncWriteExt;
 
 use crate::{
-    block_on, decode_raw_header_async, encode_packet, packet_from, read_u16_async, total_len,
-    AsyncRead, AsyncWrite, Encodable, Error, Pid, QoS, QosPid, VarBytes,
+    block_on, decode_raw_header, decode_raw_header_async, decode_slice, encode_packet, new_header,
+    packet_from, read_raw_header, read_u16_async, total_len, AsyncRead, AsyncWrite, DecodeOptions,
+    Encodable, Error, IoErrorKind, Leniency, PayloadReader, Pid, PollHeader, QoS, SyncRead,
+    SyncWrite, ToError, VarBytes,
 };
 
-use super::{Connack, Connect, Publish, Suback, Subscribe, Unsubscribe};
+use super::{Connack, Connect, DecodeBatch, Publish, Suback, Subscribe, Unsubscribe};
 
 /// MQTT v3.x packet types.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Packet {
     /// [MQTT 3.1](http://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718028)
     Connect(Connect),
@@ -70,22 +74,71 @@ impl Packet {
 
     /// Asynchronously decode a packet from an async reader.
     pub async fn decode_async<T: AsyncRead + Unpin>(reader: &mut T) -> Result<Self, Error> {
-        let header = Header::decode_async(reader).await?;
+        Self::decode_async_with(reader, &DecodeOptions::default()).await
+    }
+
+    /// Asynchronously decode a packet from an async reader with `options`.
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        options: &DecodeOptions,
+    ) -> Result<Self, Error> {
+        let header = Header::decode_async_with(reader, options).await?;
+        Self::decode_body_async(reader, header, options).await
+    }
+
+    /// Asynchronously decode a packet, the payload of a publish packet is left
+    /// in the reader and read through the returned [`PayloadReader`].
+    pub async fn decode_streaming<T: AsyncRead + Unpin>(
+        reader: &mut T,
+    ) -> Result<StreamPacket<'_, T>, Error> {
+        Self::decode_streaming_with(reader, &DecodeOptions::default()).await
+    }
+
+    /// Asynchronously decode a packet with `options`, the payload of a publish
+    /// packet is left in the reader and read through the returned
+    /// [`PayloadReader`].
+    pub async fn decode_streaming_with<'a, T: AsyncRead + Unpin>(
+        reader: &'a mut T,
+        options: &DecodeOptions,
+    ) -> Result<StreamPacket<'a, T>, Error> {
+        let header = Header::decode_async_with(reader, options).await?;
+        if header.typ == PacketType::Publish {
+            let (publish, payload_len) =
+                Publish::decode_head_async_with(reader, header, options).await?;
+            let payload = PayloadReader::new(reader, payload_len);
+            return Ok(StreamPacket::Publish(publish, payload));
+        }
+        Self::decode_body_async(reader, header, options)
+            .await
+            .map(StreamPacket::Other)
+    }
+
+    async fn decode_body_async<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, Error> {
         Ok(match header.typ {
             PacketType::Pingreq => Packet::Pingreq,
             PacketType::Pingresp => Packet::Pingresp,
             PacketType::Disconnect => Packet::Disconnect,
 
-            PacketType::Connect => Connect::decode_async(reader).await?.into(),
+            PacketType::Connect => Connect::decode_async_with(reader, options).await?.into(),
             PacketType::Connack => Connack::decode_async(reader).await?.into(),
-            PacketType::Publish => Publish::decode_async(reader, header).await?.into(),
+            PacketType::Publish => Publish::decode_async_with(reader, header, options)
+                .await?
+                .into(),
             PacketType::Puback => Packet::Puback(Pid::try_from(read_u16_async(reader).await?)?),
             PacketType::Pubrec => Packet::Pubrec(Pid::try_from(read_u16_async(reader).await?)?),
             PacketType::Pubrel => Packet::Pubrel(Pid::try_from(read_u16_async(reader).await?)?),
             PacketType::Pubcomp => Packet::Pubcomp(Pid::try_from(read_u16_async(reader).await?)?),
-            PacketType::Subscribe => Subscribe::decode_async(reader, header).await?.into(),
+            PacketType::Subscribe => Subscribe::decode_async_with(reader, header, options)
+                .await?
+                .into(),
             PacketType::Suback => Suback::decode_async(reader, header).await?.into(),
-            PacketType::Unsubscribe => Unsubscribe::decode_async(reader, header).await?.into(),
+            PacketType::Unsubscribe => Unsubscribe::decode_async_with(reader, header, options)
+                .await?
+                .into(),
             PacketType::Unsuback => Packet::Unsuback(Pid::try_from(read_u16_async(reader).await?)?),
         })
     }
@@ -99,8 +152,13 @@ impl Packet {
 
     /// Decode a packet from some bytes. If not enough bytes to decode a packet,
     /// it will return `Ok(None)`.
-    pub fn decode(mut bytes: &[u8]) -> Result<Option<Self>, Error> {
-        match block_on(Self::decode_async(&mut bytes)) {
+    pub fn decode(bytes: &[u8]) -> Result<Option<Self>, Error> {
+        Self::decode_with(bytes, &DecodeOptions::default())
+    }
+
+    /// Decode a packet from some bytes with `options`.
+    pub fn decode_with(mut bytes: &[u8], options: &DecodeOptions) -> Result<Option<Self>, Error> {
+        match block_on(Self::decode_async_with(&mut bytes, options)) {
             Ok(pkt) => Ok(Some(pkt)),
             Err(err) => {
                 if err.is_eof() {
@@ -112,6 +170,68 @@ impl Packet {
         }
     }
 
+    /// Decode a packet from a slice without the async machinery, return the
+    /// packet and the consumed length. If the slice does not hold a whole
+    /// packet yet, it will return `Ok(None)`.
+    pub fn decode_from_slice(bytes: &[u8]) -> Result<Option<(Self, usize)>, Error> {
+        decode_slice::<Header>(bytes, &DecodeOptions::default())
+    }
+
+    /// Decode a packet from a slice with `options`, see
+    /// [`Packet::decode_from_slice`].
+    pub fn decode_from_slice_with(
+        bytes: &[u8],
+        options: &DecodeOptions,
+    ) -> Result<Option<(Self, usize)>, Error> {
+        decode_slice::<Header>(bytes, options)
+    }
+
+    /// Decode all the complete packets in `bytes`, the bytes of a trailing
+    /// incomplete packet are left in [`DecodeBatch::leftover`].
+    pub fn decode_batch(bytes: &[u8]) -> DecodeBatch<'_> {
+        DecodeBatch::new(bytes)
+    }
+
+    /// Blocking read of one packet from a `std::io` or `embedded_io` reader.
+    /// The fixed header is read byte by byte like the poll decoder does, then
+    /// the body is read in one go.
+    pub fn read_from<R: SyncRead>(reader: &mut R) -> Result<Self, Error> {
+        Self::read_from_with(reader, &DecodeOptions::default())
+    }
+
+    /// Blocking read of one packet with `options`, see [`Packet::read_from`].
+    pub fn read_from_with<R: SyncRead>(
+        reader: &mut R,
+        options: &DecodeOptions,
+    ) -> Result<Self, Error> {
+        let (typ, remaining_len, var_bytes) = read_raw_header(reader)?;
+        let total = 1 + var_bytes + remaining_len as usize;
+        let header: Header = new_header(typ, remaining_len, var_bytes, total as u32, options)?;
+        if let Some(packet) = header.build_empty_packet() {
+            return Ok(packet);
+        }
+        if remaining_len == 0 {
+            return Err(Error::InvalidRemainingLength);
+        }
+        let mut body = alloc::vec![0u8; remaining_len as usize];
+        reader.read_exact(&mut body).map_err(ToError::to_error)?;
+        let body = Bytes::from(body);
+        let mut offset = 0;
+        match header.decode_bytes_with(&body, &mut offset, options) {
+            Ok(packet) => Ok(packet),
+            // The whole body was read, the remaining length is wrong.
+            Err(err) if Header::is_eof_error(&err) => Err(Error::InvalidRemainingLength),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Blocking write of the packet to a `std::io` or `embedded_io` writer.
+    pub fn write_to<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+        let data = self.encode()?;
+        writer.write_all(data.as_ref()).map_err(Error::from)?;
+        Ok(())
+    }
+
     /// Encode the packet to a dynamic vector or fixed array.
     pub fn encode(&self) -> Result<VarBytes, Error> {
         const VOID_PACKET_REMAINING_LEN: u8 = 0;
@@ -135,20 +255,7 @@ impl Packet {
                 let rc: u8 = connack.code as u8;
                 return Ok(VarBytes::Fixed4([CONTROL_BYTE, REMAINING_LEN, flags, rc]));
             }
-            Packet::Publish(publish) => {
-                let mut control_byte: u8 = match publish.qos_pid {
-                    QosPid::Level0 => 0b00110000,
-                    QosPid::Level1(_) => 0b00110010,
-                    QosPid::Level2(_) => 0b00110100,
-                };
-                if publish.dup {
-                    control_byte |= 0b00001000;
-                }
-                if publish.retain {
-                    control_byte |= 0b00000001;
-                }
-                encode_packet(control_byte, publish)?
-            }
+            Packet::Publish(publish) => encode_packet(publish.control_byte(), publish)?,
             Packet::Puback(pid) => {
                 const CONTROL_BYTE: u8 = 0b01000000;
                 return Ok(VarBytes::Fixed4(encode_with_pid(CONTROL_BYTE, *pid)));
@@ -211,8 +318,76 @@ impl Packet {
     }
 }
 
+/// Compact summary of the packet, payloads and strings other than topic name
+/// and client identifier are only reported by length.
+#[cfg(feature = "defmt")]
+impl defmt::Format for Packet {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Packet::Connect(p) => defmt::write!(
+                f,
+                "Connect {{ protocol: {}, client_id: {=str}, keep_alive: {=u16}, clean_session: {=bool}, will: {=bool} }}",
+                p.protocol,
+                &p.client_id,
+                p.keep_alive,
+                p.clean_session,
+                p.last_will.is_some(),
+            ),
+            Packet::Connack(p) => defmt::write!(
+                f,
+                "Connack {{ session_present: {=bool}, code: {} }}",
+                p.session_present,
+                p.code,
+            ),
+            Packet::Publish(p) => defmt::write!(
+                f,
+                "Publish {{ topic_name: {=str}, qos_pid: {}, dup: {=bool}, retain: {=bool}, payload_len: {=usize} }}",
+                &p.topic_name,
+                p.qos_pid,
+                p.dup,
+                p.retain,
+                p.payload.len(),
+            ),
+            Packet::Puback(pid) => defmt::write!(f, "Puback({})", pid),
+            Packet::Pubrec(pid) => defmt::write!(f, "Pubrec({})", pid),
+            Packet::Pubrel(pid) => defmt::write!(f, "Pubrel({})", pid),
+            Packet::Pubcomp(pid) => defmt::write!(f, "Pubcomp({})", pid),
+            Packet::Subscribe(p) => defmt::write!(
+                f,
+                "Subscribe {{ pid: {}, topics: {=usize} }}",
+                p.pid,
+                p.topics.len(),
+            ),
+            Packet::Suback(p) => {
+                defmt::write!(f, "Suback {{ pid: {}, topics: {} }}", p.pid, &p.topics[..])
+            }
+            Packet::Unsubscribe(p) => defmt::write!(
+                f,
+                "Unsubscribe {{ pid: {}, topics: {=usize} }}",
+                p.pid,
+                p.topics.len(),
+            ),
+            Packet::Unsuback(pid) => defmt::write!(f, "Unsuback({})", pid),
+            Packet::Pingreq => defmt::write!(f, "Pingreq"),
+            Packet::Pingresp => defmt::write!(f, "Pingresp"),
+            Packet::Disconnect => defmt::write!(f, "Disconnect"),
+        }
+    }
+}
+
+/// Packet decoded by [`Packet::decode_streaming`].
+#[derive(Debug)]
+pub enum StreamPacket<'a, T> {
+    /// Publish packet with an empty `payload`, the payload is read from the
+    /// [`PayloadReader`].
+    Publish(Publish, PayloadReader<'a, T>),
+    Other(Packet),
+}
+
 /// MQTT v3.x packet type variant, without the associated data.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum PacketType {
     Connect,
     Connack,
@@ -232,6 +407,8 @@ pub enum PacketType {
 
 /// Fixed header type.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Header {
     pub typ: PacketType,    // 1 B
     pub dup: bool,          // 1 B
@@ -261,6 +438,15 @@ impl Header {
     }
 
     pub fn new_with(hd: u8, remaining_len: u32, total_len: u32) -> Result<Header, Error> {
+        Self::new_with_options(hd, remaining_len, total_len, &DecodeOptions::default())
+    }
+
+    pub fn new_with_options(
+        hd: u8,
+        remaining_len: u32,
+        total_len: u32,
+        options: &DecodeOptions,
+    ) -> Result<Header, Error> {
         const FLAGS_MASK: u8 = 0b1111;
         let (typ, flags_ok) = match hd >> 4 {
             1 => (PacketType::Connect, hd & FLAGS_MASK == 0),
@@ -288,7 +474,7 @@ impl Header {
             14 => (PacketType::Disconnect, hd & FLAGS_MASK == 0),
             _ => return Err(Error::InvalidHeader),
         };
-        if !flags_ok {
+        if !flags_ok && !options.tolerate(Leniency::ReservedHeaderFlags) {
             return Err(Error::InvalidHeader);
         }
         Ok(Header {
@@ -301,13 +487,29 @@ impl Header {
         })
     }
 
-    pub fn decode(mut reader: &[u8]) -> Result<Self, Error> {
-        block_on(Self::decode_async(&mut reader))
+    pub fn decode(reader: &[u8]) -> Result<Self, Error> {
+        match decode_raw_header(reader)? {
+            Some((typ, remaining_len, var_bytes)) => new_header(
+                typ,
+                remaining_len,
+                var_bytes,
+                var_bytes as u32,
+                &DecodeOptions::default(),
+            ),
+            None => Err(Error::IoError(IoErrorKind::UnexpectedEof)),
+        }
     }
 
     pub async fn decode_async<T: AsyncRead + Unpin>(reader: &mut T) -> Result<Self, Error> {
-        let (typ, remaining_len, total_len) = decode_raw_header_async(reader).await?;
-        Header::new_with(typ, remaining_len, total_len as u32)
+        Self::decode_async_with(reader, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        options: &DecodeOptions,
+    ) -> Result<Self, Error> {
+        let (typ, remaining_len, var_bytes) = decode_raw_header_async(reader).await?;
+        new_header(typ, remaining_len, var_bytes, var_bytes as u32, options)
     }
 }
 
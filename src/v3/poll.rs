@@ -1,6 +1,8 @@
+use bytes::Bytes;
+
 use crate::{
-    read_u16, read_u16_async, AsyncRead, Error, GenericPollPacket, GenericPollPacketState, Pid,
-    PollHeader,
+    read_u16, read_u16_async, AsyncRead, DecodeOptions, Error, GenericDecodeBatch,
+    GenericPollPacket, GenericPollPacketState, Pid, PollHeader,
 };
 
 use super::{
@@ -18,6 +20,20 @@ impl PollHeader for Header {
         Header::new_with(hd, remaining_len, total_len)
     }
 
+    fn new_with_options(
+        hd: u8,
+        remaining_len: u32,
+        total_len: u32,
+        options: &DecodeOptions,
+    ) -> Result<Self, Self::Error> {
+        Header::new_with_options(hd, remaining_len, total_len, options)
+    }
+
+    /// v3.1.1 does not require the minimal encoding, lenient by default.
+    fn strict_var_int(&self, options: &DecodeOptions) -> bool {
+        options.strict_var_int(false)
+    }
+
     fn build_empty_packet(&self) -> Option<Self::Packet> {
         let packet = match self.typ {
             PacketType::Pingreq => Packet::Pingreq,
@@ -29,38 +45,75 @@ impl PollHeader for Header {
     }
 
     fn decode_buffer(self, buf: &[u8], offset: &mut usize) -> Result<Self::Packet, Self::Error> {
+        self.decode_buffer_with(buf, offset, &DecodeOptions::default())
+    }
+
+    fn decode_bytes(self, buf: &Bytes, offset: &mut usize) -> Result<Self::Packet, Self::Error> {
+        self.decode_bytes_with(buf, offset, &DecodeOptions::default())
+    }
+
+    async fn decode_stream<T: AsyncRead + Unpin>(
+        self,
+        reader: &mut T,
+    ) -> Result<Self::Packet, Self::Error> {
+        self.decode_stream_with(reader, &DecodeOptions::default())
+            .await
+    }
+
+    #[rustfmt::skip]
+    fn decode_buffer_with(
+        self,
+        buf: &[u8],
+        offset: &mut usize,
+        options: &DecodeOptions,
+    ) -> Result<Self::Packet, Self::Error> {
         match self.typ {
-            PacketType::Connect => Connect::decode(buf, offset).map(Into::into),
+            PacketType::Connect => Connect::decode_with(buf, offset, options).map(Into::into),
             PacketType::Connack => Connack::decode(buf, offset).map(Into::into),
-            PacketType::Publish => Publish::decode(buf, offset, self).map(Into::into),
+            PacketType::Publish => Publish::decode_with(buf, offset, self, options).map(Into::into),
             PacketType::Puback => Ok(Packet::Puback(Pid::try_from(read_u16(buf, offset)?)?)),
             PacketType::Pubrec => Ok(Packet::Pubrec(Pid::try_from(read_u16(buf, offset)?)?)),
             PacketType::Pubrel => Ok(Packet::Pubrel(Pid::try_from(read_u16(buf, offset)?)?)),
             PacketType::Pubcomp => Ok(Packet::Pubcomp(Pid::try_from(read_u16(buf, offset)?)?)),
-            PacketType::Subscribe => Subscribe::decode(buf, offset, self).map(Into::into),
+            PacketType::Subscribe => Subscribe::decode_with(buf, offset, self, options).map(Into::into),
             PacketType::Suback => Suback::decode(buf, offset, self).map(Into::into),
-            PacketType::Unsubscribe => Unsubscribe::decode(buf, offset, self).map(Into::into),
+            PacketType::Unsubscribe => Unsubscribe::decode_with(buf, offset, self, options).map(Into::into),
             PacketType::Unsuback => Ok(Packet::Unsuback(Pid::try_from(read_u16(buf, offset)?)?)),
             PacketType::Pingreq | PacketType::Pingresp | PacketType::Disconnect => unreachable!(),
         }
     }
 
+    fn decode_bytes_with(
+        self,
+        buf: &Bytes,
+        offset: &mut usize,
+        options: &DecodeOptions,
+    ) -> Result<Self::Packet, Self::Error> {
+        match self.typ {
+            PacketType::Publish => {
+                Publish::decode_bytes_with(buf, offset, self, options).map(Into::into)
+            }
+            _ => self.decode_buffer_with(buf, offset, options),
+        }
+    }
+
     #[rustfmt::skip]
-    async fn decode_stream<T: AsyncRead + Unpin>(
+    async fn decode_stream_with<T: AsyncRead + Unpin>(
         self,
         reader: &mut T,
+        options: &DecodeOptions,
     ) -> Result<Self::Packet, Self::Error> {
         match self.typ {
-            PacketType::Connect => Connect::decode_async(reader).await.map(Into::into),
+            PacketType::Connect => Connect::decode_async_with(reader, options).await.map(Into::into),
             PacketType::Connack => Connack::decode_async(reader).await.map(Into::into),
-            PacketType::Publish => Publish::decode_async(reader, self).await.map(Into::into),
+            PacketType::Publish => Publish::decode_async_with(reader, self, options).await.map(Into::into),
             PacketType::Puback => Ok(Packet::Puback(Pid::try_from(read_u16_async(reader).await?)?)),
             PacketType::Pubrec => Ok(Packet::Pubrec(Pid::try_from(read_u16_async(reader).await?)?)),
             PacketType::Pubrel => Ok(Packet::Pubrel(Pid::try_from(read_u16_async(reader).await?)?)),
             PacketType::Pubcomp => Ok(Packet::Pubcomp(Pid::try_from(read_u16_async(reader).await?)?)),
-            PacketType::Subscribe => Subscribe::decode_async(reader, self).await.map(Into::into),
+            PacketType::Subscribe => Subscribe::decode_async_with(reader, self, options).await.map(Into::into),
             PacketType::Suback => Suback::decode_async(reader, self).await.map(Into::into),
-            PacketType::Unsubscribe => Unsubscribe::decode_async(reader, self).await.map(Into::into),
+            PacketType::Unsubscribe => Unsubscribe::decode_async_with(reader, self, options).await.map(Into::into),
             PacketType::Unsuback => Ok(Packet::Unsuback(Pid::try_from(read_u16_async(reader).await?)?)),
             PacketType::Pingreq | PacketType::Pingresp | PacketType::Disconnect => unreachable!(),
         }
@@ -74,10 +127,15 @@ impl PollHeader for Header {
         self.total_len as usize
     }
 
+    fn packet_type(&self) -> u8 {
+        self.typ as u8 + 1
+    }
+
     fn is_eof_error(err: &Self::Error) -> bool {
         err.is_eof()
     }
 }
 
 pub type PollPacket<'a, T, B> = GenericPollPacket<'a, T, Header, B>;
-pub type PollPacketState = GenericPollPacketState<Header>;
+pub type DecodeBatch<'a> = GenericDecodeBatch<'a, Header>;
+pub type PollPacketState<BH> = GenericPollPacketState<Header, BH>;
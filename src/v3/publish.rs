@@ -2,17 +2,19 @@ use alloc::vec::Vec;
 
 use bytes::Bytes;
 #[cfg(feature = "tokio")]
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 use crate::{
-    read_raw_bytes, read_string, read_string_async, read_u16, read_u16_async, write_string,
-    write_u16, AsyncRead, Encodable, Error, Pid, QoS, QosPid, SyncWrite, ToError, TopicName,
+    copy_payload, read_raw_bytes, read_string, read_string_async, read_u16, read_u16_async,
+    total_len, write_string, write_u16, write_var_int, AsyncRead, AsyncWrite, DecodeOptions,
+    Encodable, Error, Pid, QoS, QosPid, SyncWrite, ToError, TopicName,
 };
 
 use super::Header;
 
 /// Publish packet body type.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Publish {
     pub dup: bool,
     pub retain: bool,
@@ -46,8 +48,42 @@ impl Publish {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, header: Header) -> Result<Self, Error> {
+        Self::decode_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, Error> {
+        Self::decode_payload_with(buf, offset, header, options, Bytes::copy_from_slice)
+    }
+
+    /// Decode the packet body, the payload is sliced from `buf` without copy.
+    pub fn decode_bytes(buf: &Bytes, offset: &mut usize, header: Header) -> Result<Self, Error> {
+        Self::decode_bytes_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_bytes_with(
+        buf: &Bytes,
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, Error> {
+        Self::decode_payload_with(buf, offset, header, options, |data| buf.slice_ref(data))
+    }
+
+    fn decode_payload_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+        payload: impl FnOnce(&[u8]) -> Bytes,
+    ) -> Result<Self, Error> {
         let mut remaining_len = header.remaining_len as usize;
         let topic_name = read_string(buf, offset)?;
+        options.check_string(topic_name)?;
         remaining_len = remaining_len
             .checked_sub(2 + topic_name.len())
             .ok_or(Error::InvalidRemainingLength)?;
@@ -67,7 +103,7 @@ impl Publish {
             }
         };
         let payload = if remaining_len > 0 {
-            Bytes::copy_from_slice(read_raw_bytes(buf, offset, remaining_len)?)
+            payload(read_raw_bytes(buf, offset, remaining_len)?)
         } else {
             Bytes::new()
         };
@@ -84,8 +120,44 @@ impl Publish {
         reader: &mut T,
         header: Header,
     ) -> Result<Self, Error> {
+        Self::decode_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, Error> {
+        let (mut publish, payload_len) =
+            Self::decode_head_async_with(reader, header, options).await?;
+        if payload_len > 0 {
+            let mut data = alloc::vec![0u8; payload_len];
+            reader
+                .read_exact(&mut data)
+                .await
+                .map_err(ToError::to_error)?;
+            publish.payload = Bytes::from(data);
+        }
+        Ok(publish)
+    }
+
+    /// Decode everything before the payload, return the packet with an empty
+    /// payload and the payload length.
+    pub async fn decode_head_async<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+    ) -> Result<(Self, usize), Error> {
+        Self::decode_head_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_head_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<(Self, usize), Error> {
         let mut remaining_len = header.remaining_len as usize;
         let topic_name = read_string_async(reader).await?;
+        options.check_string(&topic_name)?;
         remaining_len = remaining_len
             .checked_sub(2 + topic_name.len())
             .ok_or(Error::InvalidRemainingLength)?;
@@ -104,28 +176,69 @@ impl Publish {
                 QosPid::Level2(Pid::try_from(read_u16_async(reader).await?)?)
             }
         };
-        let payload = if remaining_len > 0 {
-            let mut data = alloc::vec![0u8; remaining_len];
-            reader
-                .read_exact(&mut data)
-                .await
-                .map_err(ToError::to_error)?;
-            data
-        } else {
-            Vec::new()
-        };
-        Ok(Publish {
+        let publish = Publish {
             dup: header.dup,
             qos_pid,
             retain: header.retain,
             topic_name: TopicName::try_from(topic_name)?,
-            payload: Bytes::from(payload),
-        })
+            payload: Bytes::new(),
+        };
+        Ok((publish, remaining_len))
     }
 }
 
-impl Encodable for Publish {
-    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+impl Publish {
+    /// The first byte of the fixed header.
+    pub(crate) fn control_byte(&self) -> u8 {
+        let mut control_byte: u8 = match self.qos_pid {
+            QosPid::Level0 => 0b00110000,
+            QosPid::Level1(_) => 0b00110010,
+            QosPid::Level2(_) => 0b00110100,
+        };
+        if self.dup {
+            control_byte |= 0b00001000;
+        }
+        if self.retain {
+            control_byte |= 0b00000001;
+        }
+        control_byte
+    }
+
+    /// Encode the fixed header and the variable header for a payload of
+    /// `payload_len` bytes written afterwards, `self.payload` is ignored.
+    pub fn encode_head(&self, payload_len: usize) -> Result<Vec<u8>, Error> {
+        let head_len = self.encode_len() - self.payload.len();
+        let remaining_len = head_len + payload_len;
+        let total = total_len(remaining_len)?;
+        let mut buf = Vec::with_capacity(total - payload_len);
+        buf.push(self.control_byte());
+        write_var_int(&mut buf, remaining_len)?;
+        self.encode_variable_header(&mut buf)?;
+        debug_assert_eq!(buf.len(), total - payload_len);
+        Ok(buf)
+    }
+
+    /// Write the packet with `payload_len` bytes copied from `payload`
+    /// instead of `self.payload`. `payload` must supply exactly `payload_len`
+    /// bytes, e.g. a reader wrapped in `take(payload_len)`: bytes after the
+    /// payload are left unread, and a `payload` ending early fails after the
+    /// head was written.
+    pub async fn encode_streaming_async<R, W>(
+        &self,
+        payload_len: usize,
+        payload: &mut R,
+        writer: &mut W,
+    ) -> Result<(), Error>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        writer.write_all(&self.encode_head(payload_len)?).await?;
+        copy_payload(payload, writer, payload_len).await?;
+        Ok(())
+    }
+
+    fn encode_variable_header<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
         write_string(writer, &self.topic_name)?;
         match self.qos_pid {
             QosPid::Level0 => {}
@@ -133,6 +246,13 @@ impl Encodable for Publish {
                 write_u16(writer, pid.value())?;
             }
         }
+        Ok(())
+    }
+}
+
+impl Encodable for Publish {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+        self.encode_variable_header(writer)?;
         writer.write_all(self.payload.as_ref())?;
         Ok(())
     }
@@ -2,7 +2,8 @@ use alloc::vec::Vec;
 
 use crate::{
     read_string, read_string_async, read_u16, read_u16_async, read_u8, read_u8_async, write_string,
-    write_u16, write_u8, AsyncRead, Encodable, Error, Pid, QoS, SyncWrite, TopicFilter,
+    write_u16, write_u8, AsyncRead, DecodeOptions, Encodable, Error, Pid, QoS, SyncWrite,
+    TopicFilter,
 };
 
 use super::Header;
@@ -10,6 +11,7 @@ use super::Header;
 /// Subscribe packet body type.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Subscribe {
     pub pid: Pid,
     pub topics: Vec<(TopicFilter, QoS)>,
@@ -18,6 +20,7 @@ pub struct Subscribe {
 /// Suback packet body type.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Suback {
     pub pid: Pid,
     pub topics: Vec<SubscribeReturnCode>,
@@ -26,6 +29,7 @@ pub struct Suback {
 /// Unsubscribe packet body type.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Unsubscribe {
     pub pid: Pid,
     pub topics: Vec<TopicFilter>,
@@ -37,6 +41,15 @@ impl Subscribe {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, header: Header) -> Result<Self, Error> {
+        Self::decode_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, Error> {
         let mut remaining_len = header.remaining_len as usize;
         let pid = Pid::try_from(read_u16(buf, offset)?)?;
         remaining_len = remaining_len
@@ -48,6 +61,7 @@ impl Subscribe {
         let mut topics = Vec::new();
         while remaining_len > 0 {
             let topic_filter = TopicFilter::try_from(read_string(buf, offset)?)?;
+            options.check_string(&topic_filter)?;
             let max_qos = QoS::from_u8(read_u8(buf, offset)?)?;
             remaining_len = remaining_len
                 .checked_sub(3 + topic_filter.len())
@@ -60,6 +74,14 @@ impl Subscribe {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         header: Header,
+    ) -> Result<Self, Error> {
+        Self::decode_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
     ) -> Result<Self, Error> {
         let mut remaining_len = header.remaining_len as usize;
         let pid = Pid::try_from(read_u16_async(reader).await?)?;
@@ -72,6 +94,7 @@ impl Subscribe {
         let mut topics = Vec::new();
         while remaining_len > 0 {
             let topic_filter = TopicFilter::try_from(read_string_async(reader).await?)?;
+            options.check_string(&topic_filter)?;
             let max_qos = QoS::from_u8(read_u8_async(reader).await?)?;
             remaining_len = remaining_len
                 .checked_sub(3 + topic_filter.len())
@@ -161,6 +184,15 @@ impl Unsubscribe {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, header: Header) -> Result<Self, Error> {
+        Self::decode_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, Error> {
         let mut remaining_len = header.remaining_len as usize;
         let pid = Pid::try_from(read_u16(buf, offset)?)?;
         remaining_len = remaining_len
@@ -172,6 +204,7 @@ impl Unsubscribe {
         let mut topics = Vec::new();
         while remaining_len > 0 {
             let topic_filter = TopicFilter::try_from(read_string(buf, offset)?)?;
+            options.check_string(&topic_filter)?;
             remaining_len = remaining_len
                 .checked_sub(2 + topic_filter.len())
                 .ok_or(Error::InvalidRemainingLength)?;
@@ -183,6 +216,14 @@ impl Unsubscribe {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         header: Header,
+    ) -> Result<Self, Error> {
+        Self::decode_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
     ) -> Result<Self, Error> {
         let mut remaining_len = header.remaining_len as usize;
         let pid = Pid::try_from(read_u16_async(reader).await?)?;
@@ -195,6 +236,7 @@ impl Unsubscribe {
         let mut topics = Vec::new();
         while remaining_len > 0 {
             let topic_filter = TopicFilter::try_from(read_string_async(reader).await?)?;
+            options.check_string(&topic_filter)?;
             remaining_len = remaining_len
                 .checked_sub(2 + topic_filter.len())
                 .ok_or(Error::InvalidRemainingLength)?;
@@ -223,13 +265,16 @@ impl Encodable for Unsubscribe {
 }
 
 /// Subscribe return code type.
+#[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum SubscribeReturnCode {
-    MaxLevel0,
-    MaxLevel1,
-    MaxLevel2,
-    Failure,
+    MaxLevel0 = 0,
+    MaxLevel1 = 1,
+    MaxLevel2 = 2,
+    Failure = 0x80,
 }
 
 impl SubscribeReturnCode {
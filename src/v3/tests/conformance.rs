@@ -0,0 +1,357 @@
+//! The normative statements of MQTT v3.1.1 about the encoding of packets, one
+//! test per statement checking the status listed in `CONFORMANCE.md`.
+
+use crate::v3::*;
+use crate::*;
+
+use Status::{Application, Enforced};
+
+#[rustfmt::skip]
+static V3: Conformance = Conformance {
+    title: "MQTT v3.1.1",
+    statements: &[
+        Statement { id: "MQTT-1.5.3-1", summary: "UTF-8 encoded strings are well-formed and exclude the surrogates U+D800 to U+DFFF", status: Enforced },
+        Statement { id: "MQTT-1.5.3-2", summary: "UTF-8 encoded strings do not include the null character U+0000", status: Application },
+        Statement { id: "MQTT-1.5.3-3", summary: "A leading 0xEF 0xBB 0xBF is decoded as U+FEFF, not stripped", status: Enforced },
+        Statement { id: "2.2.3", summary: "The Remaining Length is at most four bytes", status: Enforced },
+        Statement { id: "MQTT-2.2.2-2", summary: "Invalid fixed header flags are a protocol violation", status: Enforced },
+        Statement { id: "MQTT-2.3.1-1", summary: "SUBSCRIBE, UNSUBSCRIBE and PUBLISH (QoS > 0) carry a non-zero Packet Identifier", status: Enforced },
+        Statement { id: "MQTT-3.1.2-1", summary: "The protocol name is \"MQTT\" (\"MQIsdp\" for v3.1)", status: Enforced },
+        Statement { id: "MQTT-3.1.2-2", summary: "An unsupported protocol level is answered with CONNACK return code 0x01", status: Application },
+        Statement { id: "MQTT-3.1.2-3", summary: "The reserved CONNECT flag is zero", status: Enforced },
+        Statement { id: "MQTT-3.1.2-13", summary: "Will QoS is 0 when the Will Flag is 0", status: Enforced },
+        Statement { id: "MQTT-3.1.2-14", summary: "Will QoS is not 3", status: Enforced },
+        Statement { id: "MQTT-3.1.2-15", summary: "Will Retain is 0 when the Will Flag is 0", status: Application },
+        Statement { id: "MQTT-3.1.2-22", summary: "The Password Flag is 0 when the User Name Flag is 0", status: Application },
+        Statement { id: "3.2.2.1", summary: "The reserved CONNACK acknowledge flags are zero", status: Enforced },
+        Statement { id: "MQTT-3.2.2-4", summary: "Session Present is 0 with a non-zero CONNACK return code", status: Application },
+        Statement { id: "3.2.2.3", summary: "CONNACK return codes 6 to 255 are reserved", status: Enforced },
+        Statement { id: "MQTT-3.3.1-2", summary: "DUP is 0 for QoS 0 messages", status: Application },
+        Statement { id: "MQTT-3.3.1-4", summary: "A PUBLISH does not have both QoS bits set", status: Enforced },
+        Statement { id: "MQTT-3.3.2-2", summary: "The PUBLISH Topic Name has no wildcard characters", status: Enforced },
+        Statement { id: "MQTT-3.6.1-1", summary: "PUBREL fixed header flags are 0010", status: Enforced },
+        Statement { id: "MQTT-3.8.1-1", summary: "SUBSCRIBE fixed header flags are 0010", status: Enforced },
+        Statement { id: "MQTT-3.8.3-1", summary: "SUBSCRIBE Topic Filters are UTF-8 encoded strings", status: Enforced },
+        Statement { id: "MQTT-3.8.3-3", summary: "A SUBSCRIBE has at least one Topic Filter", status: Enforced },
+        Statement { id: "MQTT-3.8.3-4", summary: "The reserved bits of the requested QoS are zero and the QoS is not 3", status: Enforced },
+        Statement { id: "MQTT-3.9.3-2", summary: "SUBACK return codes other than 0x00, 0x01, 0x02 and 0x80 are reserved", status: Enforced },
+        Statement { id: "MQTT-3.10.1-1", summary: "UNSUBSCRIBE fixed header flags are 0010", status: Enforced },
+        Statement { id: "MQTT-3.10.3-2", summary: "An UNSUBSCRIBE has at least one Topic Filter", status: Enforced },
+        Statement { id: "MQTT-4.7.1-1", summary: "Wildcards are only used in Topic Filters", status: Enforced },
+        Statement { id: "MQTT-4.7.1-2", summary: "'#' is alone or after a '/', and is the last character", status: Enforced },
+        Statement { id: "MQTT-4.7.1-3", summary: "'+' occupies an entire level of the filter", status: Enforced },
+        Statement { id: "MQTT-4.7.3-1", summary: "Topic Names are at least one character long (empty Topic Filters are rejected)", status: Application },
+        Statement { id: "MQTT-4.7.3-2", summary: "Topic Names and Topic Filters do not include U+0000", status: Enforced },
+        Statement { id: "MQTT-4.7.3-3", summary: "Topic Names and Topic Filters are at most 65535 bytes", status: Enforced },
+    ],
+};
+
+raw_packets!(Error, 4);
+
+#[test]
+fn mqtt_1_5_3_1() {
+    let surrogate = publish(&[0xed, 0xa0, 0x80], &[], b"x");
+    let invalid = connect(0x02, &[], &string(&[0xff]));
+    assert!(decode(&publish("é".as_bytes(), &[], b"x")).is_ok());
+    V3.check(
+        "MQTT-1.5.3-1",
+        Status::observed(rejected(&surrogate) && rejected(&invalid)),
+    );
+}
+
+#[test]
+fn mqtt_1_5_3_2() {
+    let packet = connect(0x02, &[], &string(b"a\0b"));
+    V3.check("MQTT-1.5.3-2", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_1_5_3_3() {
+    let packet = decode(&publish("\u{feff}a".as_bytes(), &[], b"x")).unwrap();
+    let Packet::Publish(publish) = packet else {
+        panic!("not a PUBLISH: {packet:?}");
+    };
+    V3.check(
+        "MQTT-1.5.3-3",
+        Status::observed(&*publish.topic_name == "\u{feff}a"),
+    );
+}
+
+#[test]
+fn mqtt_2_2_3() {
+    assert!(decode(&[0xc0, 0x80, 0x80, 0x80, 0x00]).is_ok());
+    let packet = [0xc0, 0x80, 0x80, 0x80, 0x80, 0x00];
+    V3.check("2.2.3", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_2_2_2_2() {
+    let pid = Pid::try_from(1).unwrap();
+    let packets: [Packet; 12] = [
+        Connect::new("c".into(), 60).into(),
+        Connack::new(false, ConnectReturnCode::Accepted).into(),
+        Packet::Puback(pid),
+        Packet::Pubrec(pid),
+        Packet::Pubrel(pid),
+        Packet::Pubcomp(pid),
+        Subscribe::new(
+            pid,
+            alloc::vec![(TopicFilter::try_from("a").unwrap(), QoS::Level0)],
+        )
+        .into(),
+        Suback::new(pid, alloc::vec![SubscribeReturnCode::MaxLevel0]).into(),
+        Unsubscribe::new(pid, alloc::vec![TopicFilter::try_from("a").unwrap()]).into(),
+        Packet::Unsuback(pid),
+        Packet::Pingreq,
+        Packet::Disconnect,
+    ];
+    let enforced = packets.into_iter().all(|packet| {
+        let mut bytes = encode(packet);
+        assert!(decode(&bytes).is_ok());
+        bytes[0] ^= 0x01;
+        rejected(&bytes)
+    });
+    V3.check("MQTT-2.2.2-2", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_2_3_1_1() {
+    let mut qos1 = string(b"a");
+    qos1.extend_from_slice(&[0, 0, b'x']);
+    let enforced = rejected(&packet(0x32, &qos1))
+        && rejected(&packet(0x82, &[0, 0, 0, 1, b'a', 0]))
+        && rejected(&packet(0xa2, &[0, 0, 0, 1, b'a']))
+        && Pid::try_from(0).is_err();
+    V3.check("MQTT-2.3.1-1", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_3_1_2_1() {
+    let mut body = string(b"MQTX");
+    body.extend_from_slice(&[4, 0x02, 0, 60]);
+    body.extend(string(b"c"));
+    V3.check(
+        "MQTT-3.1.2-1",
+        Status::observed(rejected(&packet(0x10, &body))),
+    );
+}
+
+#[test]
+fn mqtt_3_1_2_2() {
+    let connect = |level| {
+        let mut body = string(b"MQTT");
+        body.extend_from_slice(&[level, 0x02, 0, 60]);
+        body.extend(string(b"c"));
+        decode(&packet(0x10, &body))
+    };
+    // The level is reported for the application to send CONNACK 0x01.
+    assert_eq!(connect(5), Err(Error::UnexpectedProtocol(Protocol::V500)));
+    assert_eq!(connect(6), Err(Error::InvalidProtocol("MQTT".into(), 6)));
+    V3.check("MQTT-3.1.2-2", Application);
+}
+
+#[test]
+fn mqtt_3_1_2_3() {
+    assert!(decode(&connect(0x02, &[], &string(b"c"))).is_ok());
+    let packet = connect(0x03, &[], &string(b"c"));
+    V3.check("MQTT-3.1.2-3", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_1_2_13() {
+    let packet = connect(0x02 | 0x08, &[], &string(b"c"));
+    V3.check("MQTT-3.1.2-13", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_1_2_14() {
+    let mut payload = string(b"c");
+    payload.extend(string(b"t"));
+    payload.extend(string(b"m"));
+    assert!(decode(&connect(0x02 | 0x04 | 0x10, &[], &payload)).is_ok());
+    let packet = connect(0x02 | 0x04 | 0x18, &[], &payload);
+    V3.check("MQTT-3.1.2-14", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_1_2_15() {
+    let packet = connect(0x02 | 0x20, &[], &string(b"c"));
+    V3.check("MQTT-3.1.2-15", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_1_2_22() {
+    let mut payload = string(b"c");
+    payload.extend(string(b"p"));
+    let packet = connect(0x02 | 0x40, &[], &payload);
+    V3.check("MQTT-3.1.2-22", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_2_2_1() {
+    assert!(decode(&[0x20, 2, 0x01, 0]).is_ok());
+    V3.check("3.2.2.1", Status::observed(rejected(&[0x20, 2, 0x02, 0])));
+}
+
+#[test]
+fn mqtt_3_2_2_4() {
+    let connack = Connack::new(true, ConnectReturnCode::ServerUnavailable);
+    let bytes = encode(connack.into());
+    V3.check(
+        "MQTT-3.2.2-4",
+        Status::observed(rejected(&[0x20, 2, 0x01, 3]) && bytes[2] == 0),
+    );
+}
+
+#[test]
+fn mqtt_3_2_2_3() {
+    assert!(decode(&[0x20, 2, 0, 5]).is_ok());
+    V3.check("3.2.2.3", Status::observed(rejected(&[0x20, 2, 0, 6])));
+}
+
+#[test]
+fn mqtt_3_3_1_2() {
+    let mut bytes = publish(b"a", &[], b"x");
+    bytes[0] |= 0x08;
+    V3.check("MQTT-3.3.1-2", Status::observed(rejected(&bytes)));
+}
+
+#[test]
+fn mqtt_3_3_1_4() {
+    let mut body = string(b"a");
+    body.extend_from_slice(&[0, 1, b'x']);
+    V3.check(
+        "MQTT-3.3.1-4",
+        Status::observed(rejected(&packet(0x36, &body))),
+    );
+}
+
+#[test]
+fn mqtt_3_3_2_2() {
+    let enforced = rejected(&publish(b"a/+", &[], b"x")) && rejected(&publish(b"a/#", &[], b"x"));
+    V3.check("MQTT-3.3.2-2", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_3_6_1_1() {
+    assert!(decode(&[0x62, 2, 0, 1]).is_ok());
+    let enforced = [0x60, 0x61, 0x63, 0x66, 0x6a]
+        .into_iter()
+        .all(|first| rejected(&[first, 2, 0, 1]));
+    V3.check("MQTT-3.6.1-1", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_3_8_1_1() {
+    let mut bytes = subscribe(b"a", 0);
+    assert!(decode(&bytes).is_ok());
+    bytes[0] = 0x80;
+    V3.check("MQTT-3.8.1-1", Status::observed(rejected(&bytes)));
+}
+
+#[test]
+fn mqtt_3_8_3_1() {
+    let packet = subscribe(&[0xc3, 0x28], 0);
+    V3.check("MQTT-3.8.3-1", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_8_3_3() {
+    let packet = [0x82, 2, 0, 1];
+    V3.check("MQTT-3.8.3-3", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_8_3_4() {
+    assert!(decode(&subscribe(b"a", 2)).is_ok());
+    let enforced = rejected(&subscribe(b"a", 3)) && rejected(&subscribe(b"a", 0x04));
+    V3.check("MQTT-3.8.3-4", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_3_9_3_2() {
+    use SubscribeReturnCode::*;
+
+    let pid = Pid::try_from(1).unwrap();
+    let codes = alloc::vec![MaxLevel0, MaxLevel1, MaxLevel2, Failure];
+    let bytes = encode(Suback::new(pid, codes).into());
+    assert_eq!(bytes, [0x90, 6, 0, 1, 0, 1, 2, 0x80]);
+    V3.check(
+        "MQTT-3.9.3-2",
+        Status::observed(rejected(&[0x90, 3, 0, 1, 3])),
+    );
+}
+
+#[test]
+fn mqtt_3_10_1_1() {
+    let mut bytes = unsubscribe(b"a");
+    assert!(decode(&bytes).is_ok());
+    bytes[0] = 0xa0;
+    V3.check("MQTT-3.10.1-1", Status::observed(rejected(&bytes)));
+}
+
+#[test]
+fn mqtt_3_10_3_2() {
+    let packet = [0xa2, 2, 0, 1];
+    V3.check("MQTT-3.10.3-2", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_4_7_1_1() {
+    let enforced = TopicName::try_from("a/+").is_err()
+        && TopicName::try_from("#").is_err()
+        && rejected(&publish(b"+", &[], b"x"));
+    V3.check("MQTT-4.7.1-1", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_4_7_1_2() {
+    assert!(decode(&subscribe(b"a/#", 0)).is_ok());
+    let enforced = ["a#", "a/#/b", "#/"].into_iter().all(|filter| {
+        TopicFilter::try_from(filter).is_err() && rejected(&subscribe(filter.as_bytes(), 0))
+    });
+    V3.check("MQTT-4.7.1-2", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_4_7_1_3() {
+    assert!(decode(&subscribe(b"+/a/+", 0)).is_ok());
+    let enforced = ["a+", "a/+b", "+a/b"].into_iter().all(|filter| {
+        TopicFilter::try_from(filter).is_err() && rejected(&subscribe(filter.as_bytes(), 0))
+    });
+    V3.check("MQTT-4.7.1-3", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_4_7_3_1() {
+    assert!(rejected(&subscribe(b"", 0)));
+    assert!(rejected(&unsubscribe(b"")));
+    V3.check(
+        "MQTT-4.7.3-1",
+        Status::observed(rejected(&publish(b"", &[], b"x"))),
+    );
+}
+
+#[test]
+fn mqtt_4_7_3_2() {
+    let enforced = rejected(&publish(b"a\0b", &[], b"x"))
+        && rejected(&subscribe(b"a\0b", 0))
+        && rejected(&unsubscribe(b"a\0b"))
+        && TopicName::try_from("a\0b").is_err()
+        && TopicFilter::try_from("a\0b").is_err();
+    V3.check("MQTT-4.7.3-2", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_4_7_3_3() {
+    let long = "a".repeat(u16::MAX as usize + 1);
+    assert!(TopicName::try_from(&long[1..]).is_ok());
+    let enforced = TopicName::try_from(long.as_str()).is_err()
+        && TopicFilter::try_from(long.as_str()).is_err();
+    V3.check("MQTT-4.7.3-3", Status::observed(enforced));
+}
+
+#[test]
+fn report() {
+    V3.check_report();
+}
@@ -1,3 +1,5 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
 use core::ops::Deref;
 
 use bytes::Bytes;
@@ -101,7 +103,7 @@ fn test_non_utf8_string() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -121,7 +123,7 @@ fn test_inner_length_too_long() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err(),
         Error::InvalidRemainingLength
@@ -147,7 +149,7 @@ fn test_decode_half_connect() {
     assert!(block_on(PollPacket::new(
         &mut Default::default(),
         &mut data,
-        &mut MockBuffer::default()
+        &mut BufferPool::default()
     ))
     .unwrap_err()
     .is_eof());
@@ -174,7 +176,7 @@ fn test_decode_connect_wrong_version() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -197,7 +199,7 @@ fn test_decode_reserved_connect_flags() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -247,7 +249,7 @@ fn test_decode_packet_n() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data1,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -261,7 +263,7 @@ fn test_decode_packet_n() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data2,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -275,7 +277,7 @@ fn test_decode_packet_n() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data3,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -301,7 +303,7 @@ fn test_decode_connack() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -317,7 +319,7 @@ fn test_decode_ping_req() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -333,7 +335,7 @@ fn test_decode_ping_resp() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -349,7 +351,7 @@ fn test_decode_disconnect() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -386,7 +388,7 @@ fn test_decode_publish() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data1,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -408,7 +410,7 @@ fn test_decode_publish() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data2,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -430,7 +432,7 @@ fn test_decode_publish() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data3,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -449,7 +451,7 @@ fn test_decode_pub_ack() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -468,7 +470,7 @@ fn test_decode_pub_rec() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -487,7 +489,7 @@ fn test_decode_pub_rel() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -506,7 +508,7 @@ fn test_decode_pub_comp() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -528,7 +530,7 @@ fn test_decode_subscribe() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -550,7 +552,7 @@ fn test_decode_suback() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -572,7 +574,7 @@ fn test_decode_unsubscribe() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -591,7 +593,7 @@ fn test_decode_unsub_ack() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2
@@ -622,7 +624,7 @@ async fn poll_actor_model_simulation_v3() {
     }
 
     for size in (0..15).map(|i| 1 << i) {
-        let payload = vec![b'x'; size];
+        let payload = alloc::vec![b'x'; size];
         let pkt = Packet::Publish(Publish {
             dup: false,
             qos_pid: QosPid::Level1(Pid::try_from(1).unwrap()),
@@ -636,14 +638,14 @@ async fn poll_actor_model_simulation_v3() {
     for qos in [QoS::Level0, QoS::Level1, QoS::Level2] {
         let pkt = Packet::Subscribe(Subscribe::new(
             Pid::try_from(10).unwrap(),
-            vec![(TopicFilter::try_from("a/+").unwrap(), qos)],
+            alloc::vec![(TopicFilter::try_from("a/+").unwrap(), qos)],
         ));
         packets.push(pkt.encode().unwrap());
     }
     for _ in 0..3 {
         let pkt = Packet::Unsubscribe(Unsubscribe::new(
             Pid::try_from(20).unwrap(),
-            vec![TopicFilter::try_from("b/#").unwrap()],
+            alloc::vec![TopicFilter::try_from("b/#").unwrap()],
         ));
         packets.push(pkt.encode().unwrap());
     }
@@ -715,3 +717,716 @@ async fn poll_actor_model_simulation_v3() {
 
     println!("--- End Report ---");
 }
+
+#[test]
+fn test_decode_publish_shared_payload() {
+    let mut data: &[u8] = &[
+        0b00110010, 11, // type=Publish, qos=1, remaining_len=11
+        0x00, 0x03, b'a', b'/', b'b', // topic
+        0x00, 0x0a, // pid
+        b'h', b'e', b'l', b'l', // payload
+    ];
+    let mut buffer = BytesBuffer::default();
+    let (_, body, packet) = block_on(PollPacket::new(
+        &mut Default::default(),
+        &mut data,
+        &mut buffer,
+    ))
+    .unwrap();
+    let body = body.into_bytes();
+    assert_eq!(body.len(), 11);
+    let Packet::Publish(publish) = packet else {
+        panic!("unexpected packet");
+    };
+    assert_eq!(publish.payload.as_ref(), b"hell");
+    // The payload is a slice of the packet body
+    assert_eq!(publish.payload.as_ptr(), body[7..].as_ptr());
+}
+
+#[test]
+fn test_poll_metrics() {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct Counters {
+        packets: [AtomicUsize; 16],
+        bytes: AtomicUsize,
+        buffer: AtomicUsize,
+        chunk: AtomicUsize,
+        hits: AtomicUsize,
+        misses: AtomicUsize,
+        owned: AtomicUsize,
+    }
+
+    impl Metrics for Counters {
+        fn packet_decoded(&self, packet_type: u8, _total_len: usize) {
+            self.packets[packet_type as usize].fetch_add(1, Ordering::Relaxed);
+        }
+        fn bytes_read(&self, len: usize) {
+            self.bytes.fetch_add(len, Ordering::Relaxed);
+        }
+        fn read_strategy(&self, strategy: ReadStrategy, _packet_size: usize) {
+            match strategy {
+                ReadStrategy::Buffer => self.buffer.fetch_add(1, Ordering::Relaxed),
+                ReadStrategy::Chunk(_) => self.chunk.fetch_add(1, Ordering::Relaxed),
+            };
+        }
+        fn buffer_acquired(&self, _size: usize, hit: bool) {
+            let counter = if hit { &self.hits } else { &self.misses };
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+        fn owned_fallback(&self, len: usize) {
+            self.owned.fetch_add(len, Ordering::Relaxed);
+        }
+    }
+
+    let mut data = Vec::new();
+    for payload in [&b"hi"[..], b"ho", &[7; 40]] {
+        let publish = Publish::new(
+            QosPid::Level0,
+            TopicName::try_from("t").unwrap(),
+            Bytes::copy_from_slice(payload),
+        );
+        data.extend_from_slice(Packet::from(publish).encode().unwrap().as_ref());
+    }
+    data.extend_from_slice(&[0xc0, 0]);
+
+    let counters = Arc::new(Counters::default());
+    let mut buffer = BufferPool::with_metrics(
+        BufferPoolConfig {
+            size_classes: alloc::vec![16],
+            max_memory: 64,
+            chunk_size: 8,
+        },
+        counters.clone(),
+    );
+    let mut reader = data.as_slice();
+    let mut state = Default::default();
+    for _ in 0..4 {
+        block_on(PollPacket::new(&mut state, &mut reader, &mut buffer).with_metrics(&*counters))
+            .unwrap();
+    }
+
+    let load = |counter: &AtomicUsize| counter.load(Ordering::Relaxed);
+    assert_eq!(load(&counters.packets[3]), 3);
+    assert_eq!(load(&counters.packets[12]), 1);
+    assert_eq!(load(&counters.bytes), data.len());
+    assert_eq!((load(&counters.buffer), load(&counters.chunk)), (2, 1));
+    assert_eq!((load(&counters.hits), load(&counters.misses)), (1, 1));
+    assert_eq!(load(&counters.owned), 43);
+}
+
+#[cfg(feature = "std")]
+#[tokio::test]
+async fn test_poll_metrics_acquire_waited() {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct Waited(Mutex<Vec<Duration>>);
+
+    impl Metrics for Waited {
+        fn acquire_waited(&self, waited: Duration) {
+            self.0.lock().unwrap().push(waited);
+        }
+    }
+
+    let budget = MemoryBudget::new(MemoryBudgetConfig {
+        limit: 100,
+        connection_limit: 100,
+        wait: true,
+    });
+    let holder = budget.connection();
+    let permit = holder.reserve(100).await.unwrap();
+
+    let publish = Packet::from(Publish::new(
+        QosPid::Level0,
+        TopicName::try_from("t").unwrap(),
+        Bytes::from_static(b"hello"),
+    ));
+    let data = publish.encode().unwrap().as_ref().to_vec();
+    let mut buffer = BudgetedBuffer::new(BufferPool::default(), budget.connection());
+    let waited = Arc::new(Waited::default());
+    let metrics = Arc::clone(&waited);
+    let decoder = tokio::spawn(async move {
+        let mut reader: &[u8] = &data;
+        let mut state = PollPacketState::default();
+        PollPacket::new(&mut state, &mut reader, &mut buffer)
+            .with_metrics(&*metrics)
+            .await
+            .map(|(_, _, packet)| packet)
+    });
+    while budget.stats().throttled == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    // The buffer is blocked for at least this long
+    let blocked = Duration::from_millis(50);
+    tokio::time::sleep(blocked).await;
+    drop(permit);
+    assert_eq!(decoder.await.unwrap(), Ok(publish));
+    let waited = waited.0.lock().unwrap();
+    assert_eq!(waited.len(), 1);
+    assert!(waited[0] >= blocked, "{:?}", waited[0]);
+}
+
+#[test]
+fn test_decode_streaming_publish() {
+    let payload: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    let publish = Publish::new(
+        QosPid::Level1(Pid::try_from(3).unwrap()),
+        TopicName::try_from("fw/update").unwrap(),
+        Bytes::from(payload.clone()),
+    );
+    let mut data = Packet::from(publish.clone())
+        .encode()
+        .unwrap()
+        .as_ref()
+        .to_vec();
+    data.extend_from_slice(&[0xc0, 0]);
+    let mut reader = data.as_slice();
+
+    let StreamPacket::Publish(head, mut payload_reader) =
+        block_on(Packet::decode_streaming(&mut reader)).unwrap()
+    else {
+        panic!("expected a streamed publish");
+    };
+    assert_eq!(head.topic_name, publish.topic_name);
+    assert_eq!(head.qos_pid, publish.qos_pid);
+    assert!(head.payload.is_empty());
+    assert_eq!(payload_reader.remaining(), 1000);
+
+    let mut received = Vec::new();
+    let mut chunk = [0u8; 64];
+    loop {
+        let n = block_on(payload_reader.read_chunk(&mut chunk)).unwrap();
+        if n == 0 {
+            break;
+        }
+        received.extend_from_slice(&chunk[..n]);
+    }
+    assert_eq!(received, payload);
+
+    // The next packet follows the payload
+    assert!(matches!(
+        block_on(Packet::decode_streaming(&mut reader)).unwrap(),
+        StreamPacket::Other(Packet::Pingreq)
+    ));
+}
+
+#[test]
+fn test_decode_streaming_truncated() {
+    let mut data: &[u8] = &[
+        0b00110000, 10, // type=Publish, remaining_len=10
+        0x00, 0x03, b'a', b'/', b'b', // topic
+        b'h', b'e', // truncated payload
+    ];
+    let StreamPacket::Publish(_, mut payload) =
+        block_on(Packet::decode_streaming(&mut data)).unwrap()
+    else {
+        panic!("expected a streamed publish");
+    };
+    assert_eq!(
+        block_on(payload.skip()),
+        Err(Error::IoError(IoErrorKind::UnexpectedEof))
+    );
+    assert_eq!(payload.remaining(), 3);
+
+    // Read through the reader traits the early end is an error as well
+    let mut buf = [0u8; 8];
+    #[cfg(feature = "tokio")]
+    {
+        use tokio::io::AsyncReadExt;
+        let err = block_on(payload.read(&mut buf)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+    #[cfg(not(feature = "tokio"))]
+    {
+        use embedded_io_async::Read;
+        assert_eq!(
+            block_on(payload.read(&mut buf)),
+            Err(PayloadReadError::UnexpectedEof)
+        );
+    }
+}
+
+#[test]
+fn test_decode_streaming_with() {
+    // Publish with the remaining length 10 in two bytes
+    let data: &[u8] = &[
+        0b00110000, 0x8a, 0x00, // type=Publish, remaining_len=10
+        0x00, 0x03, b'a', b'/', b'b', // topic
+        b'h', b'e', b'l', b'l', b'o', // payload
+    ];
+    let mut reader = data;
+    assert!(matches!(
+        block_on(Packet::decode_streaming(&mut reader)),
+        Ok(StreamPacket::Publish(_, _))
+    ));
+
+    let strict = DecodeOptions::default().with_strict_var_int(true);
+    let mut reader = data;
+    assert!(matches!(
+        block_on(Packet::decode_streaming_with(&mut reader, &strict)),
+        Err(Error::NonMinimalVarByteInt(10, 2))
+    ));
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_decode_streaming_tokio_read() {
+    use tokio::io::AsyncReadExt;
+
+    let publish = Publish::new(
+        QosPid::Level0,
+        TopicName::try_from("t").unwrap(),
+        Bytes::from(alloc::vec![9u8; 300]),
+    );
+    let mut data = Packet::from(publish).encode().unwrap().as_ref().to_vec();
+    data.extend_from_slice(&[0xe0, 0]);
+    let mut reader = data.as_slice();
+    let StreamPacket::Publish(_, mut payload) =
+        Packet::decode_streaming(&mut reader).await.unwrap()
+    else {
+        panic!("expected a streamed publish");
+    };
+    let mut received = Vec::new();
+    payload.read_to_end(&mut received).await.unwrap();
+    assert_eq!(received, alloc::vec![9u8; 300]);
+    assert_eq!(reader, &[0xe0, 0]);
+}
+
+#[test]
+fn test_decode_from_slice() {
+    let pid = Pid::try_from(5).unwrap();
+    let packets = [
+        Packet::from(Connect::new("client".into(), 30)),
+        Publish::new(
+            QosPid::Level1(pid),
+            TopicName::try_from("a/b").unwrap(),
+            Bytes::from_static(b"hello"),
+        )
+        .into(),
+        Subscribe::new(
+            pid,
+            alloc::vec![(TopicFilter::try_from("a/+").unwrap(), QoS::Level2)],
+        )
+        .into(),
+        Packet::Pubrel(pid),
+        Packet::Pingreq,
+        Packet::Disconnect,
+    ];
+    let mut data = Vec::new();
+    for packet in &packets {
+        data.extend_from_slice(packet.encode().unwrap().as_ref());
+    }
+
+    let mut offset = 0;
+    for packet in &packets {
+        let (decoded, used) = Packet::decode_from_slice(&data[offset..]).unwrap().unwrap();
+        assert_eq!(&decoded, packet);
+        assert_eq!(
+            Packet::decode(&data[offset..]).unwrap().as_ref(),
+            Some(packet)
+        );
+        assert_eq!(used, packet.encode_len().unwrap());
+        offset += used;
+    }
+    assert_eq!(offset, data.len());
+
+    // Partial packets
+    for end in 0..packets[1].encode_len().unwrap() {
+        let start = packets[0].encode_len().unwrap();
+        assert_eq!(
+            Packet::decode_from_slice(&data[start..start + end]),
+            Ok(None)
+        );
+    }
+}
+
+#[test]
+fn test_decode_from_slice_errors() {
+    // Password length goes beyond the remaining length
+    let data: &[u8] = &[
+        0b00010000, 20, // Connect packet, remaining_len=20
+        0x00, 0x04, b'M', b'Q', b'T', b'T', 0x04, 0b01000000, // +password
+        0x00, 0x0a, // keepalive 10 sec
+        0x00, 0x04, b't', b'e', b's', b't', // client_id
+        0x00, 0x03, b'm', b'q', // password with invalid length
+    ];
+    assert_eq!(
+        Packet::decode_from_slice(data),
+        Err(Error::InvalidRemainingLength)
+    );
+    assert_eq!(
+        Packet::decode_from_slice(&[0x30, 0]),
+        Err(Error::InvalidRemainingLength)
+    );
+    assert_eq!(
+        Packet::decode_from_slice(&[0x10, 0x80, 0x80, 0x80, 0x80]),
+        Err(Error::InvalidVarByteInt)
+    );
+    // A malformed header fails before the claimed body is available
+    assert_eq!(
+        Packet::decode_from_slice(&[0xf0, 0xff, 0xff, 0xff, 0x7f]),
+        Err(Error::InvalidHeader)
+    );
+    assert_eq!(
+        Packet::decode_from_slice(&[0x80, 0x10, 0x00]),
+        Err(Error::InvalidHeader)
+    );
+    let strict = DecodeOptions::default().with_strict_var_int(true);
+    assert_eq!(
+        Packet::decode_from_slice_with(&[0x30, 0x85, 0x00], &strict),
+        Err(Error::NonMinimalVarByteInt(5, 2))
+    );
+    assert_eq!(
+        DecodeBatch::new(&[0xf0, 0x10, 0x00]).next(),
+        Some(Err(Error::InvalidHeader))
+    );
+}
+
+#[test]
+fn test_read_from_write_to() {
+    let pid = Pid::try_from(7).unwrap();
+    let packets = [
+        Packet::from(Connect::new("client".into(), 30)),
+        Publish::new(
+            QosPid::Level2(pid),
+            TopicName::try_from("a/b").unwrap(),
+            Bytes::from(alloc::vec![3u8; 300]),
+        )
+        .into(),
+        Packet::Puback(pid),
+        Packet::Pingresp,
+    ];
+    let mut data = Vec::new();
+    for packet in &packets {
+        packet.write_to(&mut data).unwrap();
+    }
+
+    let mut reader = &data[..];
+    for packet in &packets {
+        assert_eq!(&Packet::read_from(&mut reader).unwrap(), packet);
+    }
+    assert!(reader.is_empty());
+    assert!(Packet::read_from(&mut reader).unwrap_err().is_eof());
+
+    // Body ends early
+    let mut reader = &data[..data.len() - packets[3].encode_len().unwrap() - 1];
+    for _ in 0..2 {
+        Packet::read_from(&mut reader).unwrap();
+    }
+    assert!(Packet::read_from(&mut reader).unwrap_err().is_eof());
+
+    let mut reader: &[u8] = &[0x10, 0x80, 0x80, 0x80, 0x80];
+    assert_eq!(
+        Packet::read_from(&mut reader),
+        Err(Error::InvalidVarByteInt)
+    );
+    let mut reader: &[u8] = &[0x30, 0];
+    assert_eq!(
+        Packet::read_from(&mut reader),
+        Err(Error::InvalidRemainingLength)
+    );
+}
+
+#[test]
+fn test_poll_packet_resumable() {
+    use core::future::{poll_fn, Future};
+    use core::pin::Pin;
+
+    let pid = Pid::try_from(9).unwrap();
+    let packets = [
+        Packet::from(Connect::new("client".into(), 30)),
+        Publish::new(
+            QosPid::Level1(pid),
+            TopicName::try_from("a/b").unwrap(),
+            Bytes::from((0..200u8).collect::<Vec<_>>()),
+        )
+        .into(),
+        Packet::Pingreq,
+        Packet::Puback(pid),
+    ];
+    let mut data = Vec::new();
+    for packet in &packets {
+        data.extend_from_slice(packet.encode().unwrap().as_ref());
+    }
+
+    fn read_all<B: Buffer<Error = Error>>(data: &[u8], packets: &[Packet], mut buffer: B) {
+        let mut reader = trickle_reader(data);
+        let mut state = PollPacketState::default();
+        for packet in packets {
+            let mut polls = 0;
+            let (total_len, _buf, decoded) = block_on(poll_fn(|cx| {
+                polls += 1;
+                // A new future on every poll, like a `select!` loop does
+                Pin::new(&mut PollPacket::new(&mut state, &mut reader, &mut buffer)).poll(cx)
+            }))
+            .unwrap();
+            assert_eq!(&decoded, packet);
+            assert_eq!(total_len, packet.encode_len().unwrap());
+            assert!(polls > total_len);
+        }
+        #[cfg(feature = "tokio")]
+        assert!(reader.is_empty());
+        #[cfg(not(feature = "tokio"))]
+        assert!(reader.inner().is_empty());
+    }
+
+    read_all(&data, &packets, BufferPool::default());
+    read_all(
+        &data,
+        &packets,
+        BufferPool::new(BufferPoolConfig {
+            size_classes: alloc::vec![16],
+            max_memory: 64,
+            chunk_size: 7,
+        }),
+    );
+    read_all(&data, &packets, BytesBuffer::default());
+}
+
+#[test]
+fn test_decode_batch() {
+    let mut data = Vec::new();
+    for pid in 1..=30 {
+        let packet = Packet::Puback(Pid::try_from(pid).unwrap());
+        data.extend_from_slice(packet.encode().unwrap().as_ref());
+    }
+    data.extend_from_slice(Packet::Pingresp.encode().unwrap().as_ref());
+    // Incomplete publish
+    let publish = Packet::from(Publish::new(
+        QosPid::Level0,
+        TopicName::try_from("a/b").unwrap(),
+        Bytes::from_static(b"hello"),
+    ));
+    data.extend_from_slice(&publish.encode().unwrap().as_ref()[..6]);
+
+    let mut batch = Packet::decode_batch(&data);
+    let packets: Vec<_> = batch.by_ref().collect::<Result<_, _>>().unwrap();
+    assert_eq!(packets.len(), 31);
+    assert_eq!(packets[29], Packet::Puback(Pid::try_from(30).unwrap()));
+    assert_eq!(packets[30], Packet::Pingresp);
+    assert_eq!(batch.consumed(), 30 * 4 + 2);
+    assert_eq!(batch.leftover(), 6);
+    assert!(batch.next().is_none());
+
+    let mut batch = Packet::decode_batch(&[]);
+    assert!(batch.next().is_none());
+    assert_eq!(batch.leftover(), 0);
+
+    // Stop at the first invalid packet
+    let data = [0x40, 0x02, 0x00, 0x01, 0x30, 0x00, 0xc0, 0x00];
+    let mut batch = Packet::decode_batch(&data);
+    assert_eq!(
+        batch.next(),
+        Some(Ok(Packet::Puback(Pid::try_from(1).unwrap())))
+    );
+    assert_eq!(batch.next(), Some(Err(Error::InvalidRemainingLength)));
+    assert!(batch.next().is_none());
+    assert_eq!(batch.consumed(), 4);
+    assert_eq!(batch.leftover(), 4);
+}
+
+#[test]
+fn test_strict_var_int() {
+    // Pingreq with the remaining length 0 in two bytes
+    let data: &[u8] = &[0xc0, 0x80, 0x00];
+    assert_eq!(Packet::decode(data), Ok(Some(Packet::Pingreq)));
+    assert_eq!(
+        Packet::decode_from_slice(data),
+        Ok(Some((Packet::Pingreq, 3)))
+    );
+    assert_eq!(Packet::read_from(&mut &data[..]), Ok(Packet::Pingreq));
+
+    let strict = DecodeOptions::default().with_strict_var_int(true);
+    let err = Error::NonMinimalVarByteInt(0, 2);
+    assert_eq!(Packet::decode_with(data, &strict), Err(err.clone()));
+    assert_eq!(
+        Packet::decode_from_slice_with(data, &strict),
+        Err(err.clone())
+    );
+    assert_eq!(
+        Packet::read_from_with(&mut &data[..], &strict),
+        Err(err.clone())
+    );
+    assert_eq!(
+        DecodeBatch::new(data).with_options(strict.clone()).next(),
+        Some(Err(err.clone()))
+    );
+    assert_eq!(
+        block_on(
+            PollPacket::new(
+                &mut Default::default(),
+                &mut &data[..],
+                &mut BufferPool::default(),
+            )
+            .with_options(strict.clone())
+        )
+        .unwrap_err(),
+        err
+    );
+}
+
+#[test]
+fn test_string_policy() {
+    let strict = DecodeOptions::default().with_string_policy(StringPolicy::Strict);
+    let client_id = Connect::new("c\u{1b}".into(), 60);
+    let mut username = Connect::new("c".into(), 60);
+    username.username = Some("\u{fdef}".into());
+    let mut will_topic = Connect::new("c".into(), 60);
+    will_topic.last_will = Some(LastWill {
+        topic_name: TopicName::try_from("will\u{7f}").unwrap(),
+        message: Bytes::new(),
+        qos: QoS::Level0,
+        retain: false,
+    });
+    let topic_name = Publish::new(
+        QosPid::Level0,
+        TopicName::try_from("a/\u{1}").unwrap(),
+        Bytes::new(),
+    );
+    let pid = Pid::try_from(1).unwrap();
+    let topic_filter =
+        Unsubscribe::new(pid, alloc::vec![TopicFilter::try_from("\u{ffff}").unwrap()]);
+
+    for (packet, code) in [
+        (Packet::from(client_id), 0x1b),
+        (username.into(), 0xfdef),
+        (will_topic.into(), 0x7f),
+        (topic_name.into(), 0x01),
+        (topic_filter.into(), 0xffff),
+    ] {
+        let data = packet.encode().unwrap();
+        let data = data.as_ref();
+        assert_eq!(Packet::decode(data), Ok(Some(packet.clone())));
+        let err = Error::DisallowedChar(code);
+        assert_eq!(Packet::decode_with(data, &strict), Err(err.clone()));
+        assert_eq!(
+            Packet::decode_from_slice_with(data, &strict),
+            Err(err.clone())
+        );
+        assert_eq!(Packet::read_from_with(&mut &data[..], &strict), Err(err));
+    }
+}
+
+#[test]
+fn test_connect_decode_with() {
+    let connect = Connect::new("c".into(), 60);
+    let data = Packet::from(connect.clone()).encode().unwrap();
+    // The body after the fixed header, with the reserved flag set
+    let mut body = data.as_ref()[2..].to_vec();
+    body[7] |= 1;
+    let lenient = DecodeOptions::default().with_lenient(Leniencies {
+        connect_reserved_flag: true,
+        ..Default::default()
+    });
+
+    let err = Error::InvalidConnectFlags(0b11);
+    assert_eq!(Connect::decode(&body, &mut 0), Err(err.clone()));
+    assert_eq!(
+        block_on(Connect::decode_async(&mut &body[..])),
+        Err(err.clone())
+    );
+    let mut offset = 0;
+    let protocol = Protocol::decode(&body, &mut offset).unwrap();
+    assert_eq!(
+        Connect::decode_buffer_with_protocol(&body, &mut offset, protocol),
+        Err(err)
+    );
+
+    let mut offset = 0;
+    assert_eq!(
+        Connect::decode_with(&body, &mut offset, &lenient),
+        Ok(connect.clone())
+    );
+    assert_eq!(offset, body.len());
+    assert_eq!(
+        block_on(Connect::decode_async_with(&mut &body[..], &lenient)),
+        Ok(connect.clone())
+    );
+    let mut offset = 0;
+    let protocol = Protocol::decode(&body, &mut offset).unwrap();
+    assert_eq!(
+        Connect::decode_buffer_with_options(&body, &mut offset, protocol, &lenient),
+        Ok(connect)
+    );
+}
+
+#[test]
+fn test_lenient() {
+    let report = Arc::new(LenientReport::new());
+    let options = |lenient| {
+        DecodeOptions::default()
+            .with_lenient(lenient)
+            .with_report(report.clone())
+    };
+    let connect_reserved_flag = options(Leniencies {
+        connect_reserved_flag: true,
+        ..Default::default()
+    });
+    let reserved_header_flags = options(Leniencies {
+        reserved_header_flags: true,
+        ..Default::default()
+    });
+
+    // Connect with the reserved flag set
+    let mut connect = Packet::from(Connect::new("c".into(), 60))
+        .encode()
+        .unwrap()
+        .as_ref()
+        .to_vec();
+    connect[9] |= 1;
+    assert_eq!(
+        Packet::decode(&connect),
+        Err(Error::InvalidConnectFlags(0b11))
+    );
+    assert_eq!(
+        Packet::decode_with(&connect, &reserved_header_flags),
+        Err(Error::InvalidConnectFlags(0b11))
+    );
+    let expected = Packet::from(Connect::new("c".into(), 60));
+    assert_eq!(
+        Packet::decode_with(&connect, &connect_reserved_flag),
+        Ok(Some(expected.clone()))
+    );
+    assert_eq!(
+        Packet::decode_from_slice_with(&connect, &connect_reserved_flag),
+        Ok(Some((expected, connect.len())))
+    );
+    assert_eq!(report.count(Leniency::ConnectReservedFlag), 2);
+
+    // Subscribe with the reserved flags 0000 instead of 0010
+    let pid = Pid::try_from(1).unwrap();
+    let subscribe = Packet::from(Subscribe::new(
+        pid,
+        alloc::vec![(TopicFilter::try_from("a/+").unwrap(), QoS::Level1)],
+    ));
+    let mut data = subscribe.encode().unwrap().as_ref().to_vec();
+    data[0] = 0x80;
+    assert_eq!(Packet::decode(&data), Err(Error::InvalidHeader));
+    assert_eq!(
+        Packet::decode_with(&data, &connect_reserved_flag),
+        Err(Error::InvalidHeader)
+    );
+    assert_eq!(
+        Packet::read_from_with(&mut &data[..], &reserved_header_flags),
+        Ok(subscribe.clone())
+    );
+    assert_eq!(
+        block_on(
+            PollPacket::new(
+                &mut Default::default(),
+                &mut &data[..],
+                &mut BufferPool::default()
+            )
+            .with_options(reserved_header_flags.clone())
+        )
+        .unwrap()
+        .2,
+        subscribe
+    );
+    assert_eq!(report.count(Leniency::ReservedHeaderFlags), 2);
+    assert_eq!(report.total(), 4);
+}
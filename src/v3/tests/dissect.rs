@@ -0,0 +1,235 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use bytes::Bytes;
+
+use crate::v3::*;
+use crate::*;
+
+fn encode(packets: &[Packet]) -> Vec<u8> {
+    packets
+        .iter()
+        .flat_map(|packet| packet.encode().unwrap().as_ref().to_vec())
+        .collect()
+}
+
+fn values(fields: &[Field]) -> Vec<(&'static str, &str)> {
+    fields
+        .iter()
+        .map(|field| (field.name, field.value.as_str()))
+        .collect()
+}
+
+#[test]
+fn test_dissect_packets() {
+    let mut connect = Connect::new("client".into(), 30);
+    connect.last_will = Some(LastWill {
+        qos: QoS::Level1,
+        retain: true,
+        topic_name: TopicName::try_from("will").unwrap(),
+        message: Bytes::from_static(b"bye"),
+    });
+    connect.username = Some("user".into());
+    connect.password = Some(Bytes::from_static(&[0, 1]));
+    let publish = Publish::new(
+        QosPid::Level2(Pid::try_from(9).unwrap()),
+        TopicName::try_from("a/b").unwrap(),
+        Bytes::from_static(b"payload"),
+    );
+    let subscribe = Subscribe::new(
+        Pid::try_from(3).unwrap(),
+        alloc::vec![(TopicFilter::try_from("a/+").unwrap(), QoS::Level1)],
+    );
+    let suback = Suback::new(
+        Pid::try_from(3).unwrap(),
+        alloc::vec![
+            SubscribeReturnCode::MaxLevel1,
+            SubscribeReturnCode::MaxLevel2
+        ],
+    );
+    let packets = [
+        connect.into(),
+        publish.into(),
+        Packet::Pubrel(Pid::try_from(9).unwrap()),
+        subscribe.into(),
+        suback.into(),
+        Packet::Pingreq,
+    ];
+    let data = encode(&packets);
+    let dissection = dissect(&data);
+    assert_eq!(dissection.error, None);
+    assert_eq!(
+        values(&dissection.packets),
+        [
+            ("Packet", "CONNECT"),
+            ("Packet", "PUBLISH"),
+            ("Packet", "PUBREL"),
+            ("Packet", "SUBSCRIBE"),
+            ("Packet", "SUBACK"),
+            ("Packet", "PINGREQ"),
+        ]
+    );
+    let mut offset = 0;
+    for (field, packet) in dissection.packets.iter().zip(&packets) {
+        assert_eq!(field.offset, offset);
+        assert_eq!(field.len, packet.encode_len().unwrap());
+        offset += field.len;
+    }
+
+    let connect = &dissection.packets[0];
+    assert_eq!(
+        values(&connect.children[1..]),
+        [
+            ("Protocol name", "\"MQTT\""),
+            ("Protocol level", "4 (v3.1.1)"),
+            ("Connect flags", "0xee"),
+            ("Keep alive", "30"),
+            ("Client identifier", "\"client\""),
+            ("Will topic", "\"will\""),
+            ("Will message", "3 bytes [62 79 65] \"bye\""),
+            ("Username", "\"user\""),
+            ("Password", "2 bytes [00 01]"),
+        ]
+    );
+    assert_eq!(
+        values(&connect.children[3].children),
+        [
+            ("Username", "1"),
+            ("Password", "1"),
+            ("Will retain", "1"),
+            ("Will QoS", "1"),
+            ("Will", "1"),
+            ("Clean session", "1"),
+            ("Reserved", "0"),
+        ]
+    );
+
+    let publish = &dissection.packets[1];
+    assert_eq!(
+        values(&publish.children[0].children[1].children),
+        [("DUP", "0"), ("QoS", "2"), ("Retain", "0")]
+    );
+    assert_eq!(
+        values(&publish.children[1..]),
+        [
+            ("Topic name", "\"a/b\""),
+            ("Packet identifier", "9"),
+            ("Payload", "7 bytes [70 61 79 6c 6f 61 64] \"payload\""),
+        ]
+    );
+    let subscription = dissection.find("Subscription").unwrap();
+    assert_eq!(subscription.value, "\"a/+\"");
+    assert_eq!(subscription.children[1].children[0].value, "1");
+    let suback = &dissection.packets[4];
+    assert_eq!(
+        values(&suback.children[2..]),
+        [
+            ("Return code", "1 (MaxLevel1)"),
+            ("Return code", "2 (MaxLevel2)")
+        ]
+    );
+
+    assert_eq!(
+        dissect(&[0xc0, 0x00, 0x20, 0x02, 0x01, 0x05]).to_string(),
+        "0000 +2    Packet: PINGREQ
+0000 +2      Fixed header
+0000 +1        Packet type: 12 (PINGREQ)
+0000 +1        Flags: 0x00
+0001 +1        Remaining length: 0
+0002 +4    Packet: CONNACK
+0002 +2      Fixed header
+0002 +1        Packet type: 2 (CONNACK)
+0002 +1        Flags: 0x00
+0003 +1        Remaining length: 2
+0004 +1      Acknowledge flags: 0x01
+0004 +1        Session present: 1
+0005 +1      Return code: 5 (NotAuthorized)
+"
+    );
+}
+
+#[test]
+fn test_dissect_malformed() {
+    let long_payload = {
+        let mut data = alloc::vec![0x30, 0x16, 0, 1, b'a'];
+        data.extend_from_slice(&[0xff; 18]);
+        data
+    };
+    let cases: &[(&[u8], usize, &str)] = &[
+        (&[0x00, 0x00], 0, "invalid packet type 0"),
+        (&[0xf0, 0x00], 0, "invalid packet type 15"),
+        (&[0x36, 0x00], 0, "invalid QoS 3"),
+        (
+            &[0x60, 0x02, 0, 1],
+            0,
+            "invalid flags 0x0 of PUBREL, expected 0x2",
+        ),
+        (
+            &[0x30, 0xff, 0xff, 0xff, 0xff],
+            1,
+            "invalid variable byte integer in Remaining length",
+        ),
+        (
+            &[0x30, 0x80],
+            2,
+            "unexpected end of data, 1 more bytes needed",
+        ),
+        (
+            &[0x40, 0x03, 0, 1, 0],
+            4,
+            "1 unexpected bytes at the end of the packet",
+        ),
+        (
+            &[0x40, 0x01, 0, 1],
+            2,
+            "field of 2 bytes exceeds the remaining length by 1 bytes",
+        ),
+        (
+            &[0x30, 0x04, 0, 2, 0xc3, 0x28],
+            4,
+            "Topic name is not valid UTF-8",
+        ),
+        (&[0x20, 0x02, 0, 6], 3, "invalid connect return code: `6`"),
+        (&[0x90, 0x04, 0, 1, 0x80, 3], 5, "invalid qos: `3`"),
+        (
+            &[0x20, 0x02, 0x02, 0],
+            2,
+            "reserved bits set in Acknowledge flags: 0x02",
+        ),
+        (&[0x82, 0x02, 0, 1], 4, "empty subscription"),
+        (&[0x82, 0x06, 0, 1, 0, 1, b'a', 3], 7, "invalid QoS 3"),
+        (
+            &[0x10, 0x0a, 0, 4, b'M', b'Q', b'T', b'T', 4, 0x01, 0, 0],
+            9,
+            "reserved bits set in Connect flags: 0x01",
+        ),
+        (
+            &[0x10, 0x0a, 0, 4, b'M', b'Q', b'T', b'T', 9, 0x00, 0, 0],
+            8,
+            "invalid protocol: MQTT, 9",
+        ),
+        (
+            &long_payload,
+            23,
+            "unexpected end of data, 1 more bytes needed",
+        ),
+    ];
+    for (data, offset, message) in cases {
+        let err = dissect(data).error.unwrap_or_else(|| panic!("{data:02x?}"));
+        assert_eq!(
+            (err.offset, err.message.as_str()),
+            (*offset, *message),
+            "{data:02x?}"
+        );
+    }
+
+    // Fields before the error are kept
+    let dissection = dissect(&[0xc0, 0x00, 0x30, 0x05, 0, 3, b'a']);
+    assert_eq!(dissection.packets.len(), 2);
+    assert_eq!(dissection.packets[1].value, "PUBLISH");
+    assert_eq!(dissection.packets[1].len, 4);
+    assert_eq!(dissection.packets[1].children.len(), 1);
+    let err = dissection.error.unwrap();
+    assert_eq!(err.offset, 7);
+    assert_eq!(err.message, "unexpected end of data, 2 more bytes needed");
+}
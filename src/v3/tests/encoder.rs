@@ -17,7 +17,7 @@ fn assert_encode(pkt: Packet, len: usize) {
     assert_eq!(pkt, decoded_pkt);
 
     let mut data = &data_async[..];
-    let mut buffer = MockBuffer::default();
+    let mut buffer = BufferPool::default();
     let (total, buf, polled_pkt) = block_on(PollPacket::new(
         &mut Default::default(),
         &mut data,
@@ -163,3 +163,36 @@ fn test_encode_ping_resp() {
 fn test_encode_disconnect() {
     assert_encode(Packet::Disconnect, 2);
 }
+
+#[test]
+fn test_encode_streaming_publish() {
+    let payload: Vec<u8> = (0..=255).cycle().take(3000).collect();
+    let mut publish = Publish::new(
+        QosPid::Level2(Pid::try_from(9).unwrap()),
+        TopicName::try_from("fw/image").unwrap(),
+        Bytes::new(),
+    );
+    publish.retain = true;
+
+    let mut streamed = Vec::new();
+    block_on(publish.encode_streaming_async(3000, &mut payload.as_slice(), &mut streamed)).unwrap();
+
+    publish.payload = Bytes::from(payload.clone());
+    let expected = Packet::from(publish.clone()).encode().unwrap();
+    assert_eq!(streamed, expected.as_ref());
+    assert_eq!(
+        publish.encode_head(3000).unwrap(),
+        &streamed[..streamed.len() - 3000]
+    );
+
+    // Shorter payloads are rejected
+    let err =
+        block_on(publish.encode_streaming_async(3001, &mut payload.as_slice(), &mut Vec::new()))
+            .unwrap_err();
+    assert_eq!(err, Error::PayloadLengthMismatch(3001));
+
+    // Nothing after the payload is read
+    let mut reader = payload.as_slice();
+    block_on(publish.encode_streaming_async(2999, &mut reader, &mut Vec::new())).unwrap();
+    assert_eq!(reader, &payload[2999..]);
+}
@@ -1,2 +1,7 @@
+#[cfg(feature = "std")]
+mod conformance;
 mod decoder;
+mod dissect;
 mod encoder;
+#[cfg(feature = "serde")]
+mod serde;
@@ -0,0 +1,70 @@
+use alloc::vec::Vec;
+
+use bytes::Bytes;
+
+use crate::v3::*;
+use crate::*;
+
+fn assert_serde(pkt: Packet) {
+    let json = serde_json::to_string(&pkt).unwrap();
+    let decoded: Packet = serde_json::from_str(&json).unwrap();
+    assert_eq!(pkt, decoded, "{json}");
+}
+
+#[test]
+fn test_serde_roundtrip() {
+    let mut connect = Connect::new("sample".into(), 120);
+    connect.last_will = Some(LastWill {
+        qos: QoS::Level1,
+        retain: true,
+        topic_name: TopicName::try_from("abc").unwrap(),
+        message: Bytes::from("msg-content"),
+    });
+    connect.username = Some("username".into());
+    connect.password = Some(Bytes::from("password"));
+
+    let pid = Pid::try_from(10).unwrap();
+    let packets: Vec<Packet> = alloc::vec![
+        connect.into(),
+        Connack::new(true, ConnectReturnCode::NotAuthorized).into(),
+        Publish::new(
+            QosPid::Level2(pid),
+            TopicName::try_from("a/b").unwrap(),
+            Bytes::from_static(&[0, 1, 2, 0xff]),
+        )
+        .into(),
+        Packet::Puback(pid),
+        Packet::Pubrec(pid),
+        Packet::Pubrel(pid),
+        Packet::Pubcomp(pid),
+        Subscribe::new(
+            pid,
+            alloc::vec![(TopicFilter::try_from("$share/g/a/+").unwrap(), QoS::Level1)],
+        )
+        .into(),
+        Suback::new(pid, alloc::vec![SubscribeReturnCode::Failure]).into(),
+        Unsubscribe::new(pid, alloc::vec![TopicFilter::try_from("a/#").unwrap()]).into(),
+        Packet::Unsuback(pid),
+        Packet::Pingreq,
+        Packet::Pingresp,
+        Packet::Disconnect,
+    ];
+    for pkt in packets {
+        assert_serde(pkt);
+    }
+}
+
+#[test]
+fn test_serde_shared_filter() {
+    let filter: TopicFilter = serde_json::from_str(r#""$share/g/a/+""#).unwrap();
+    assert_eq!(filter.shared_info(), Some(("g", "a/+")));
+}
+
+#[test]
+fn test_serde_invalid_values() {
+    assert!(serde_json::from_str::<Pid>("0").is_err());
+    assert_eq!(serde_json::from_str::<Pid>("7").unwrap().value(), 7);
+    assert!(serde_json::from_str::<TopicName>(r#""a/+""#).is_err());
+    assert!(serde_json::from_str::<TopicFilter>(r#""a/#/b""#).is_err());
+    assert!(serde_json::from_str::<TopicFilter>(r#""""#).is_err());
+}
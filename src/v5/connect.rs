@@ -10,8 +10,8 @@ use tokio::io::AsyncReadExt;
 
 use crate::{
     read_bytes, read_bytes_async, read_string, read_string_async, read_u16, read_u16_async,
-    read_u8, read_u8_async, write_bytes, write_u16, write_u8, AsyncRead, ClientId, Encodable,
-    Error, Protocol, QoS, SyncWrite, ToError, TopicName, Username,
+    read_u8, read_u8_async, write_bytes, write_u16, write_u8, AsyncRead, ClientId, DecodeOptions,
+    Encodable, Error, Leniency, Protocol, QoS, SyncWrite, ToError, TopicName, Username,
 };
 
 use super::{
@@ -21,6 +21,7 @@ use super::{
 
 /// Body type of CONNECT packet.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Connect {
     /// The [protocol version](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901036).
     pub protocol: Protocol,
@@ -89,16 +90,33 @@ impl Connect {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, header: Header) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
         let protocol = Protocol::decode(buf, offset)?;
-        Self::decode_buffer_with_protocol(buf, offset, header, protocol)
+        Self::decode_buffer_with_options(buf, offset, header, protocol, options)
     }
 
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         header: Header,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let protocol = Protocol::decode_async(reader).await?;
-        Self::decode_stream_with_protocol(reader, header, protocol).await
+        Self::decode_stream_with_options(reader, header, protocol, options).await
     }
 
     #[inline]
@@ -107,31 +125,45 @@ impl Connect {
         offset: &mut usize,
         header: Header,
         protocol: Protocol,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_buffer_with_options(buf, offset, header, protocol, &DecodeOptions::default())
+    }
+
+    /// [`Connect::decode_buffer_with_protocol`] with the decode `options`.
+    pub fn decode_buffer_with_options(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        protocol: Protocol,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         if protocol != Protocol::V500 {
             return Err(Error::UnexpectedProtocol(protocol).into());
         }
         let connect_flags: u8 = read_u8(buf, offset)?;
-        if connect_flags & 1 != 0 {
+        if connect_flags & 1 != 0 && !options.tolerate(Leniency::ConnectReservedFlag) {
             return Err(Error::InvalidConnectFlags(connect_flags).into());
         }
         let keep_alive = read_u16(buf, offset)?;
 
         // FIXME: check remaining length
 
-        let properties = ConnectProperties::decode(buf, offset, header.typ)?;
-        let client_id = read_string(buf, offset)?.into();
+        let properties = ConnectProperties::decode_with(buf, offset, header.typ, options)?;
+        let client_id = read_string(buf, offset)?;
+        options.check_string(client_id)?;
         let last_will = if connect_flags & 0b100 != 0 {
             let qos = QoS::from_u8((connect_flags & 0b11000) >> 3)?;
             let retain = (connect_flags & 0b00100000) != 0;
-            Some(LastWill::decode(buf, offset, qos, retain)?)
+            Some(LastWill::decode_with(buf, offset, qos, retain, options)?)
         } else if connect_flags & 0b11000 != 0 {
             return Err(Error::InvalidConnectFlags(connect_flags).into());
         } else {
             None
         };
         let username = if connect_flags & 0b10000000 != 0 {
-            Some(read_string(buf, offset)?.into())
+            let username = read_string(buf, offset)?;
+            options.check_string(username)?;
+            Some(username.into())
         } else {
             None
         };
@@ -147,7 +179,7 @@ impl Connect {
             clean_start,
             properties,
             keep_alive,
-            client_id,
+            client_id: client_id.into(),
             last_will,
             username,
             password,
@@ -159,31 +191,44 @@ impl Connect {
         reader: &mut T,
         header: Header,
         protocol: Protocol,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_stream_with_options(reader, header, protocol, &DecodeOptions::default()).await
+    }
+
+    /// [`Connect::decode_stream_with_protocol`] with the decode `options`.
+    pub async fn decode_stream_with_options<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        protocol: Protocol,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         if protocol != Protocol::V500 {
             return Err(Error::UnexpectedProtocol(protocol).into());
         }
         let connect_flags: u8 = read_u8_async(reader).await?;
-        if connect_flags & 1 != 0 {
+        if connect_flags & 1 != 0 && !options.tolerate(Leniency::ConnectReservedFlag) {
             return Err(Error::InvalidConnectFlags(connect_flags).into());
         }
         let keep_alive = read_u16_async(reader).await?;
 
         // FIXME: check remaining length
 
-        let properties = ConnectProperties::decode_async(reader, header.typ).await?;
+        let properties = ConnectProperties::decode_async_with(reader, header.typ, options).await?;
         let client_id = read_string_async(reader).await?;
+        options.check_string(&client_id)?;
         let last_will = if connect_flags & 0b100 != 0 {
             let qos = QoS::from_u8((connect_flags & 0b11000) >> 3)?;
             let retain = (connect_flags & 0b00100000) != 0;
-            Some(LastWill::decode_async(reader, qos, retain).await?)
+            Some(LastWill::decode_async_with(reader, qos, retain, options).await?)
         } else if connect_flags & 0b11000 != 0 {
             return Err(Error::InvalidConnectFlags(connect_flags).into());
         } else {
             None
         };
         let username = if connect_flags & 0b10000000 != 0 {
-            Some(read_string_async(reader).await?)
+            let username = read_string_async(reader).await?;
+            options.check_string(&username)?;
+            Some(username)
         } else {
             None
         };
@@ -267,6 +312,7 @@ impl Encodable for Connect {
 
 /// Property list for CONNECT packet.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConnectProperties {
     /// Session Expiry Interval
     pub session_expiry_interval: Option<u32>,
@@ -310,10 +356,20 @@ impl ConnectProperties {
         buf: &[u8],
         offset: &mut usize,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, packet_type, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = ConnectProperties::default();
         decode_properties!(
             packet_type,
+            options,
             properties,
             buf,
             offset,
@@ -332,10 +388,19 @@ impl ConnectProperties {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, packet_type, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = ConnectProperties::default();
         decode_properties_async!(
             packet_type,
+            options,
             properties,
             reader,
             SessionExpiryInterval,
@@ -388,6 +453,7 @@ impl Encodable for ConnectProperties {
 
 /// The will message for CONNECT packet.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LastWill {
     pub qos: QoS,
     pub retain: bool,
@@ -421,8 +487,19 @@ impl LastWill {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, qos: QoS, retain: bool) -> Result<Self, ErrorV5> {
-        let properties = WillProperties::decode(buf, offset)?;
+        Self::decode_with(buf, offset, qos, retain, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        qos: QoS,
+        retain: bool,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
+        let properties = WillProperties::decode_with(buf, offset, options)?;
         let topic_name = TopicName::try_from(read_string(buf, offset)?)?;
+        options.check_string(&topic_name)?;
         let payload = read_bytes(buf, offset)?;
         if properties.payload_is_utf8 == Some(true) && from_utf8(payload).is_err() {
             return Err(ErrorV5::InvalidPayloadFormat);
@@ -441,8 +518,18 @@ impl LastWill {
         qos: QoS,
         retain: bool,
     ) -> Result<Self, ErrorV5> {
-        let properties = WillProperties::decode_async(reader).await?;
+        Self::decode_async_with(reader, qos, retain, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        qos: QoS,
+        retain: bool,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
+        let properties = WillProperties::decode_async_with(reader, options).await?;
         let topic_name = TopicName::try_from(read_string_async(reader).await?)?;
+        options.check_string(&topic_name)?;
         let payload = read_bytes_async(reader).await?;
         if properties.payload_is_utf8 == Some(true) && from_utf8(&payload).is_err() {
             return Err(ErrorV5::InvalidPayloadFormat);
@@ -476,6 +563,7 @@ impl Encodable for LastWill {
 
 /// Property list for will message.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WillProperties {
     pub delay_interval: Option<u32>,
     pub payload_is_utf8: Option<bool>,
@@ -503,9 +591,18 @@ impl<'a> arbitrary::Arbitrary<'a> for WillProperties {
 
 impl WillProperties {
     pub fn decode(buf: &[u8], offset: &mut usize) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
         let mut properties = WillProperties::default();
         decode_properties!(
             LastWill,
+            options,
             properties,
             buf,
             offset,
@@ -520,9 +617,17 @@ impl WillProperties {
     }
 
     pub async fn decode_async<T: AsyncRead + Unpin>(reader: &mut T) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
         let mut properties = WillProperties::default();
         decode_properties_async!(
             LastWill,
+            options,
             properties,
             reader,
             WillDelayInterval,
@@ -570,6 +675,7 @@ impl Encodable for WillProperties {
 /// Body type of CONNACK packet.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Connack {
     pub session_present: bool,
     pub reason_code: ConnectReasonCode,
@@ -586,6 +692,15 @@ impl Connack {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, header: Header) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
         let session_present = match read_u8(buf, offset)? {
             0 => false,
             1 => true,
@@ -594,7 +709,7 @@ impl Connack {
         let code = read_u8(buf, offset)?;
         let reason_code =
             ConnectReasonCode::from_u8(code).ok_or(ErrorV5::InvalidReasonCode(header.typ, code))?;
-        let properties = ConnackProperties::decode(buf, offset, header.typ)?;
+        let properties = ConnackProperties::decode_with(buf, offset, header.typ, options)?;
         Ok(Connack {
             session_present,
             reason_code,
@@ -605,6 +720,14 @@ impl Connack {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         header: Header,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut payload = [0u8; 2];
         reader
@@ -618,7 +741,7 @@ impl Connack {
         };
         let reason_code = ConnectReasonCode::from_u8(payload[1])
             .ok_or(ErrorV5::InvalidReasonCode(header.typ, payload[1]))?;
-        let properties = ConnackProperties::decode_async(reader, header.typ).await?;
+        let properties = ConnackProperties::decode_async_with(reader, header.typ, options).await?;
         Ok(Connack {
             session_present,
             reason_code,
@@ -669,6 +792,8 @@ impl Encodable for Connack {
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ConnectReasonCode {
     Success = 0x00,
     UnspecifiedError = 0x80,
@@ -727,6 +852,7 @@ impl ConnectReasonCode {
 
 /// Property list for CONNACK packet.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConnackProperties {
     pub session_expiry_interval: Option<u32>,
     pub receive_max: Option<u16>,
@@ -777,10 +903,20 @@ impl ConnackProperties {
         buf: &[u8],
         offset: &mut usize,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, packet_type, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = ConnackProperties::default();
         decode_properties!(
             packet_type,
+            options,
             properties,
             buf,
             offset,
@@ -807,10 +943,19 @@ impl ConnackProperties {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, packet_type, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = ConnackProperties::default();
         decode_properties_async!(
             packet_type,
+            options,
             properties,
             reader,
             SessionExpiryInterval,
@@ -888,6 +1033,7 @@ impl Encodable for ConnackProperties {
 /// Body type for DISCONNECT packet.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Disconnect {
     pub reason_code: DisconnectReasonCode,
     pub properties: DisconnectProperties,
@@ -906,6 +1052,15 @@ impl Disconnect {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, header: Header) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
         let (reason_code, properties) = if header.remaining_len == 0 {
             (DisconnectReasonCode::NormalDisconnect, Default::default())
         } else if header.remaining_len == 1 {
@@ -917,7 +1072,7 @@ impl Disconnect {
             let reason_byte = read_u8(buf, offset)?;
             let reason_code = DisconnectReasonCode::from_u8(reason_byte)
                 .ok_or(ErrorV5::InvalidReasonCode(header.typ, reason_byte))?;
-            let properties = DisconnectProperties::decode(buf, offset, header.typ)?;
+            let properties = DisconnectProperties::decode_with(buf, offset, header.typ, options)?;
             (reason_code, properties)
         };
         Ok(Disconnect {
@@ -929,6 +1084,14 @@ impl Disconnect {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         header: Header,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let (reason_code, properties) = if header.remaining_len == 0 {
             (DisconnectReasonCode::NormalDisconnect, Default::default())
@@ -941,7 +1104,8 @@ impl Disconnect {
             let reason_byte = read_u8_async(reader).await?;
             let reason_code = DisconnectReasonCode::from_u8(reason_byte)
                 .ok_or(ErrorV5::InvalidReasonCode(header.typ, reason_byte))?;
-            let properties = DisconnectProperties::decode_async(reader, header.typ).await?;
+            let properties =
+                DisconnectProperties::decode_async_with(reader, header.typ, options).await?;
             (reason_code, properties)
         };
         Ok(Disconnect {
@@ -1016,6 +1180,8 @@ impl Encodable for Disconnect {
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum DisconnectReasonCode {
     NormalDisconnect = 0x00,
     DisconnectWithWillMessage = 0x04,
@@ -1089,6 +1255,7 @@ impl DisconnectReasonCode {
 /// Property list for DISCONNECT packet.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DisconnectProperties {
     pub session_expiry_interval: Option<u32>,
     pub reason_string: Option<Arc<str>>,
@@ -1101,10 +1268,20 @@ impl DisconnectProperties {
         buf: &[u8],
         offset: &mut usize,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, packet_type, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = DisconnectProperties::default();
         decode_properties!(
             packet_type,
+            options,
             properties,
             buf,
             offset,
@@ -1118,10 +1295,19 @@ impl DisconnectProperties {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, packet_type, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = DisconnectProperties::default();
         decode_properties_async!(
             packet_type,
+            options,
             properties,
             reader,
             SessionExpiryInterval,
@@ -1160,6 +1346,7 @@ impl Encodable for DisconnectProperties {
 /// Body type of AUTH packet .
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Auth {
     pub reason_code: AuthReasonCode,
     pub properties: AuthProperties,
@@ -1178,6 +1365,15 @@ impl Auth {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, header: Header) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
         let auth = if header.remaining_len == 0 {
             Auth {
                 reason_code: AuthReasonCode::Success,
@@ -1187,7 +1383,7 @@ impl Auth {
             let reason_byte = read_u8(buf, offset)?;
             let reason_code = AuthReasonCode::from_u8(reason_byte)
                 .ok_or(ErrorV5::InvalidReasonCode(header.typ, reason_byte))?;
-            let properties = AuthProperties::decode(buf, offset, header.typ)?;
+            let properties = AuthProperties::decode_with(buf, offset, header.typ, options)?;
             Auth {
                 reason_code,
                 properties,
@@ -1199,6 +1395,14 @@ impl Auth {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         header: Header,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let auth = if header.remaining_len == 0 {
             Auth {
@@ -1209,7 +1413,7 @@ impl Auth {
             let reason_byte = read_u8_async(reader).await?;
             let reason_code = AuthReasonCode::from_u8(reason_byte)
                 .ok_or(ErrorV5::InvalidReasonCode(header.typ, reason_byte))?;
-            let properties = AuthProperties::decode_async(reader, header.typ).await?;
+            let properties = AuthProperties::decode_async_with(reader, header.typ, options).await?;
             Auth {
                 reason_code,
                 properties,
@@ -1251,6 +1455,8 @@ impl Encodable for Auth {
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum AuthReasonCode {
     Success = 0x00,
     ContinueAuthentication = 0x18,
@@ -1271,6 +1477,7 @@ impl AuthReasonCode {
 
 /// Property list for AUTH packet.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AuthProperties {
     pub auth_method: Option<Arc<str>>,
     pub auth_data: Option<Bytes>,
@@ -1295,10 +1502,20 @@ impl AuthProperties {
         buf: &[u8],
         offset: &mut usize,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, packet_type, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = AuthProperties::default();
         decode_properties!(
             packet_type,
+            options,
             properties,
             buf,
             offset,
@@ -1312,10 +1529,19 @@ impl AuthProperties {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, packet_type, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = AuthProperties::default();
         decode_properties_async!(
             packet_type,
+            options,
             properties,
             reader,
             AuthenticationMethod,
@@ -0,0 +1,234 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt::Debug;
+
+use crate::{
+    dissect_connect_flags, dissect_packets, dissect_protocol, DResult, Dissection, Dissector,
+};
+
+use super::{
+    AuthReasonCode, ConnectReasonCode, DisconnectReasonCode, ErrorV5, PacketType, PropertyId,
+    PubackReasonCode, PubcompReasonCode, PubrecReasonCode, PubrelReasonCode, RetainHandling,
+    SubscribeReasonCode, UnsubscribeReasonCode,
+};
+
+/// Dissect the MQTT v5.0 packets in `bytes` into an annotated field tree,
+/// for debugging. Properties are named by [`PropertyId`]. Dissecting stops
+/// at the first malformed field.
+///
+/// ```
+/// let dissection = mqtt_proto::v5::dissect(&[0xe0, 0x01, 0x04]);
+/// let reason = dissection.find("Reason code").unwrap();
+/// assert_eq!(reason.value, "4 (DisconnectWithWillMessage)");
+/// ```
+pub fn dissect(bytes: &[u8]) -> Dissection {
+    dissect_packets(bytes, 15, |d, first| match first >> 4 {
+        1 => connect(d),
+        2 => connack(d),
+        3 => publish(d, (first >> 1) & 0b11),
+        4 => ack(d, PacketType::Puback, PubackReasonCode::from_u8),
+        5 => ack(d, PacketType::Pubrec, PubrecReasonCode::from_u8),
+        6 => ack(d, PacketType::Pubrel, PubrelReasonCode::from_u8),
+        7 => ack(d, PacketType::Pubcomp, PubcompReasonCode::from_u8),
+        8 => subscribe(d),
+        9 => sub_ack(d, PacketType::Suback, SubscribeReasonCode::from_u8),
+        10 => unsubscribe(d),
+        11 => sub_ack(d, PacketType::Unsuback, UnsubscribeReasonCode::from_u8),
+        14 => reason_and_properties(d, PacketType::Disconnect, DisconnectReasonCode::from_u8),
+        15 => reason_and_properties(d, PacketType::Auth, AuthReasonCode::from_u8),
+        _ => Ok(()),
+    })
+}
+
+fn connect(d: &mut Dissector<'_>) -> DResult<()> {
+    dissect_protocol(d)?;
+    let flags = dissect_connect_flags(d)?;
+    d.u16("Keep alive")?;
+    properties(d, "Properties")?;
+    d.string("Client identifier")?;
+    if flags & 0x04 != 0 {
+        properties(d, "Will properties")?;
+        d.string("Will topic")?;
+        d.binary("Will payload")?;
+    }
+    if flags & 0x80 != 0 {
+        d.string("Username")?;
+    }
+    if flags & 0x40 != 0 {
+        d.binary("Password")?;
+    }
+    Ok(())
+}
+
+fn connack(d: &mut Dissector<'_>) -> DResult<()> {
+    d.flags("Acknowledge flags", &[("Session present", 0x01)], 0xFE)?;
+    reason_code(d, PacketType::Connack, ConnectReasonCode::from_u8)?;
+    properties(d, "Properties")
+}
+
+fn publish(d: &mut Dissector<'_>, qos: u8) -> DResult<()> {
+    d.string("Topic name")?;
+    if qos > 0 {
+        d.u16("Packet identifier")?;
+    }
+    properties(d, "Properties")?;
+    d.payload("Payload")?;
+    Ok(())
+}
+
+/// PUBACK, PUBREC, PUBREL and PUBCOMP, the reason code and the properties
+/// may be omitted.
+fn ack<C: Debug>(
+    d: &mut Dissector<'_>,
+    typ: PacketType,
+    from_u8: fn(u8) -> Option<C>,
+) -> DResult<()> {
+    d.u16("Packet identifier")?;
+    reason_and_properties(d, typ, from_u8)
+}
+
+fn reason_and_properties<C: Debug>(
+    d: &mut Dissector<'_>,
+    typ: PacketType,
+    from_u8: fn(u8) -> Option<C>,
+) -> DResult<()> {
+    if d.has_more() {
+        reason_code(d, typ, from_u8)?;
+    }
+    if d.has_more() {
+        properties(d, "Properties")?;
+    }
+    Ok(())
+}
+
+fn subscribe(d: &mut Dissector<'_>) -> DResult<()> {
+    d.u16("Packet identifier")?;
+    properties(d, "Properties")?;
+    if !d.has_more() {
+        return no_topics(d);
+    }
+    while d.has_more() {
+        d.group("Subscription", String::new(), |d| {
+            let filter = d.string("Topic filter")?;
+            d.set_value(format!("{filter:?}"));
+            let bits = [
+                ("QoS", 0x03),
+                ("No local", 0x04),
+                ("Retain as published", 0x08),
+                ("Retain handling", 0x30),
+            ];
+            let options = d.flags("Options", &bits, 0xC0)?;
+            if options & 0x03 == 0x03 || RetainHandling::from_u8((options & 0x30) >> 4).is_none() {
+                let offset = d.offset() - 1;
+                return d.fail(
+                    offset,
+                    ErrorV5::InvalidSubscriptionOption(options).to_string(),
+                );
+            }
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+fn unsubscribe(d: &mut Dissector<'_>) -> DResult<()> {
+    d.u16("Packet identifier")?;
+    properties(d, "Properties")?;
+    if !d.has_more() {
+        return no_topics(d);
+    }
+    while d.has_more() {
+        d.string("Topic filter")?;
+    }
+    Ok(())
+}
+
+/// SUBACK and UNSUBACK.
+fn sub_ack<C: Debug>(
+    d: &mut Dissector<'_>,
+    typ: PacketType,
+    from_u8: fn(u8) -> Option<C>,
+) -> DResult<()> {
+    d.u16("Packet identifier")?;
+    properties(d, "Properties")?;
+    while d.has_more() {
+        reason_code(d, typ, from_u8)?;
+    }
+    Ok(())
+}
+
+fn no_topics(d: &mut Dissector<'_>) -> DResult<()> {
+    let offset = d.offset();
+    d.fail(offset, crate::Error::EmptySubscription.to_string())
+}
+
+fn reason_code<C: Debug>(
+    d: &mut Dissector<'_>,
+    typ: PacketType,
+    from_u8: fn(u8) -> Option<C>,
+) -> DResult<()> {
+    d.u8_with("Reason code", |value| {
+        from_u8(value)
+            .map(|code| Some(format!("{code:?}")))
+            .ok_or_else(|| ErrorV5::InvalidReasonCode(typ, value).to_string())
+    })?;
+    Ok(())
+}
+
+fn properties(d: &mut Dissector<'_>, name: &'static str) -> DResult<()> {
+    d.group(name, String::new(), |d| {
+        let len = d.var_int("Length")?;
+        d.limit(len as usize, "property length", |d| {
+            while d.has_more() {
+                property(d)?;
+            }
+            Ok(())
+        })
+    })
+}
+
+fn property(d: &mut Dissector<'_>) -> DResult<()> {
+    d.group("Property", String::new(), |d| {
+        let mut id = None;
+        d.u8_with("Identifier", |value| {
+            let property_id = PropertyId::from_u8(value).map_err(|err| err.to_string())?;
+            id = Some(property_id);
+            Ok(Some(property_id.to_string()))
+        })?;
+        let id = id.expect("property id checked");
+        d.set_value(id.to_string());
+        match id {
+            PropertyId::PayloadFormatIndicator
+            | PropertyId::RequestProblemInformation
+            | PropertyId::RequestResponseInformation
+            | PropertyId::MaximumQoS
+            | PropertyId::RetainAvailable
+            | PropertyId::WildcardSubscriptionAvailable
+            | PropertyId::SubscriptionIdentifierAvailable
+            | PropertyId::SharedSubscriptionAvailable => d.u8("Value").map(drop),
+            PropertyId::ServerKeepAlive
+            | PropertyId::ReceiveMaximum
+            | PropertyId::TopicAliasMaximum
+            | PropertyId::TopicAlias => d.u16("Value").map(drop),
+            PropertyId::MessageExpiryInterval
+            | PropertyId::SessionExpiryInterval
+            | PropertyId::WillDelayInterval
+            | PropertyId::MaximumPacketSize => d.u32("Value").map(drop),
+            PropertyId::SubscriptionIdentifier => d.var_int("Value").map(drop),
+            PropertyId::ContentType
+            | PropertyId::ResponseTopic
+            | PropertyId::AssignedClientIdentifier
+            | PropertyId::AuthenticationMethod
+            | PropertyId::ResponseInformation
+            | PropertyId::ServerReference
+            | PropertyId::ReasonString => d.string("Value").map(drop),
+            PropertyId::CorrelationData | PropertyId::AuthenticationData => {
+                d.binary("Value").map(drop)
+            }
+            PropertyId::UserProperty => {
+                d.string("Name")?;
+                d.string("Value").map(drop)
+            }
+        }
+    })
+}
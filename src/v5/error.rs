@@ -4,6 +4,7 @@ use super::{PacketType, PropertyId};
 
 /// MQTT v5.0 errors returned by encoding and decoding process.
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ErrorV5 {
     /// Common error of MQTT v3 and v5.
     #[error("common error of v3/v5: {0}")]
@@ -3,19 +3,22 @@
 //! [v5.0]: https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html
 
 mod connect;
+mod dissect;
 mod error;
 mod packet;
 mod poll;
 mod publish;
 mod subscribe;
 mod types;
+mod will;
 
 #[cfg(test)]
 mod tests;
 
 pub(crate) use types::{
-    decode_properties, decode_properties_async, decode_property, decode_property_async,
-    encode_properties, encode_properties_len, encode_property, encode_property_len, PropertyValue,
+    check_string_property, decode_properties, decode_properties_async, decode_property,
+    decode_property_async, encode_properties, encode_properties_len, encode_property,
+    encode_property_len, tolerate_duplicate, PropertyValue,
 };
 
 pub use connect::{
@@ -23,9 +26,10 @@ pub use connect::{
     ConnectReasonCode, Disconnect, DisconnectProperties, DisconnectReasonCode, LastWill,
     WillProperties,
 };
+pub use dissect::dissect;
 pub use error::ErrorV5;
-pub use packet::{Header, Packet, PacketType};
-pub use poll::{PollPacket, PollPacketState};
+pub use packet::{Header, Packet, PacketType, StreamPacket};
+pub use poll::{DecodeBatch, PollPacket, PollPacketState};
 pub use publish::{
     Puback, PubackProperties, PubackReasonCode, Pubcomp, PubcompProperties, PubcompReasonCode,
     Publish, PublishProperties, Pubrec, PubrecProperties, PubrecReasonCode, Pubrel,
@@ -37,3 +41,4 @@ pub use subscribe::{
     UnsubscribeReasonCode,
 };
 pub use types::{PropertyId, UserProperty, VarByteInt};
+pub use will::WillScheduler;
@@ -1,4 +1,9 @@
-use crate::{AsyncRead, GenericPollPacket, GenericPollPacketState, PollHeader};
+use bytes::Bytes;
+
+use crate::{
+    AsyncRead, DecodeOptions, GenericDecodeBatch, GenericPollPacket, GenericPollPacketState,
+    PollHeader,
+};
 
 use super::{
     Auth, Connack, Connect, Disconnect, ErrorV5, Header, Packet, PacketType, Puback, Pubcomp,
@@ -16,6 +21,20 @@ impl PollHeader for Header {
         Header::new_with(hd, remaining_len, total_len)
     }
 
+    fn new_with_options(
+        hd: u8,
+        remaining_len: u32,
+        total_len: u32,
+        options: &DecodeOptions,
+    ) -> Result<Self, Self::Error> {
+        Header::new_with_options(hd, remaining_len, total_len, options)
+    }
+
+    /// v5.0 requires the minimal encoding [MQTT-1.5.5-1], strict by default.
+    fn strict_var_int(&self, options: &DecodeOptions) -> bool {
+        options.strict_var_int(true)
+    }
+
     fn build_empty_packet(&self) -> Option<Self::Packet> {
         let packet = match self.typ {
             PacketType::Pingreq => Packet::Pingreq,
@@ -28,43 +47,80 @@ impl PollHeader for Header {
     }
 
     fn decode_buffer(self, buf: &[u8], offset: &mut usize) -> Result<Self::Packet, Self::Error> {
+        self.decode_buffer_with(buf, offset, &DecodeOptions::default())
+    }
+
+    fn decode_bytes(self, buf: &Bytes, offset: &mut usize) -> Result<Self::Packet, Self::Error> {
+        self.decode_bytes_with(buf, offset, &DecodeOptions::default())
+    }
+
+    async fn decode_stream<T: AsyncRead + Unpin>(
+        self,
+        reader: &mut T,
+    ) -> Result<Self::Packet, Self::Error> {
+        self.decode_stream_with(reader, &DecodeOptions::default())
+            .await
+    }
+
+    #[rustfmt::skip]
+    fn decode_buffer_with(
+        self,
+        buf: &[u8],
+        offset: &mut usize,
+        options: &DecodeOptions,
+    ) -> Result<Self::Packet, Self::Error> {
         match self.typ {
-            PacketType::Connect => Connect::decode(buf, offset, self).map(Into::into),
-            PacketType::Connack => Connack::decode(buf, offset, self).map(Into::into),
-            PacketType::Publish => Publish::decode(buf, offset, self).map(Into::into),
-            PacketType::Puback => Puback::decode(buf, offset, self).map(Into::into),
-            PacketType::Pubrec => Pubrec::decode(buf, offset, self).map(Into::into),
-            PacketType::Pubrel => Pubrel::decode(buf, offset, self).map(Into::into),
-            PacketType::Pubcomp => Pubcomp::decode(buf, offset, self).map(Into::into),
-            PacketType::Subscribe => Subscribe::decode(buf, offset, self).map(Into::into),
-            PacketType::Suback => Suback::decode(buf, offset, self).map(Into::into),
-            PacketType::Unsubscribe => Unsubscribe::decode(buf, offset, self).map(Into::into),
-            PacketType::Unsuback => Unsuback::decode(buf, offset, self).map(Into::into),
-            PacketType::Disconnect => Disconnect::decode(buf, offset, self).map(Into::into),
-            PacketType::Auth => Auth::decode(buf, offset, self).map(Into::into),
+            PacketType::Connect => Connect::decode_with(buf, offset, self, options).map(Into::into),
+            PacketType::Connack => Connack::decode_with(buf, offset, self, options).map(Into::into),
+            PacketType::Publish => Publish::decode_with(buf, offset, self, options).map(Into::into),
+            PacketType::Puback => Puback::decode_with(buf, offset, self, options).map(Into::into),
+            PacketType::Pubrec => Pubrec::decode_with(buf, offset, self, options).map(Into::into),
+            PacketType::Pubrel => Pubrel::decode_with(buf, offset, self, options).map(Into::into),
+            PacketType::Pubcomp => Pubcomp::decode_with(buf, offset, self, options).map(Into::into),
+            PacketType::Subscribe => Subscribe::decode_with(buf, offset, self, options).map(Into::into),
+            PacketType::Suback => Suback::decode_with(buf, offset, self, options).map(Into::into),
+            PacketType::Unsubscribe => Unsubscribe::decode_with(buf, offset, self, options).map(Into::into),
+            PacketType::Unsuback => Unsuback::decode_with(buf, offset, self, options).map(Into::into),
+            PacketType::Disconnect => Disconnect::decode_with(buf, offset, self, options).map(Into::into),
+            PacketType::Auth => Auth::decode_with(buf, offset, self, options).map(Into::into),
             PacketType::Pingreq | PacketType::Pingresp => unreachable!(),
         }
     }
 
+    fn decode_bytes_with(
+        self,
+        buf: &Bytes,
+        offset: &mut usize,
+        options: &DecodeOptions,
+    ) -> Result<Self::Packet, Self::Error> {
+        match self.typ {
+            PacketType::Publish => {
+                Publish::decode_bytes_with(buf, offset, self, options).map(Into::into)
+            }
+            _ => self.decode_buffer_with(buf, offset, options),
+        }
+    }
+
     #[rustfmt::skip]
-    async fn decode_stream<T: AsyncRead + Unpin>(
+    async fn decode_stream_with<T: AsyncRead + Unpin>(
         self,
         reader: &mut T,
+        options: &DecodeOptions,
     ) -> Result<Self::Packet, Self::Error> {
         match self.typ {
-            PacketType::Connect => Connect::decode_async(reader, self).await.map(Into::into),
-            PacketType::Connack => Connack::decode_async(reader, self).await.map(Into::into),
-            PacketType::Publish => Publish::decode_async(reader, self).await.map(Into::into),
-            PacketType::Puback => Puback::decode_async(reader, self).await.map(Into::into),
-            PacketType::Pubrec => Pubrec::decode_async(reader, self).await.map(Into::into),
-            PacketType::Pubrel => Pubrel::decode_async(reader, self).await.map(Into::into),
-            PacketType::Pubcomp => Pubcomp::decode_async(reader, self).await.map(Into::into),
-            PacketType::Subscribe => Subscribe::decode_async(reader, self).await.map(Into::into),
-            PacketType::Suback => Suback::decode_async(reader, self).await.map(Into::into),
-            PacketType::Unsubscribe => Unsubscribe::decode_async(reader, self).await.map(Into::into),
-            PacketType::Unsuback => Unsuback::decode_async(reader, self).await.map(Into::into),
-            PacketType::Disconnect => Disconnect::decode_async(reader, self).await.map(Into::into),
-            PacketType::Auth => Auth::decode_async(reader, self).await.map(Into::into),
+            PacketType::Connect => Connect::decode_async_with(reader, self, options).await.map(Into::into),
+            PacketType::Connack => Connack::decode_async_with(reader, self, options).await.map(Into::into),
+            PacketType::Publish => Publish::decode_async_with(reader, self, options).await.map(Into::into),
+            PacketType::Puback => Puback::decode_async_with(reader, self, options).await.map(Into::into),
+            PacketType::Pubrec => Pubrec::decode_async_with(reader, self, options).await.map(Into::into),
+            PacketType::Pubrel => Pubrel::decode_async_with(reader, self, options).await.map(Into::into),
+            PacketType::Pubcomp => Pubcomp::decode_async_with(reader, self, options).await.map(Into::into),
+            PacketType::Subscribe => Subscribe::decode_async_with(reader, self, options).await.map(Into::into),
+            PacketType::Suback => Suback::decode_async_with(reader, self, options).await.map(Into::into),
+            PacketType::Unsubscribe => Unsubscribe::decode_async_with(reader, self, options).await.map(Into::into),
+            PacketType::Unsuback => Unsuback::decode_async_with(reader, self, options).await.map(Into::into),
+            PacketType::Disconnect => Disconnect::decode_async_with(reader, self, options).await.map(Into::into),
+            PacketType::Auth => Auth::decode_async_with(reader, self, options).await.map(Into::into),
             PacketType::Pingreq | PacketType::Pingresp => unreachable!(),
         }
     }
@@ -77,10 +133,15 @@ impl PollHeader for Header {
         self.total_len as usize
     }
 
+    fn packet_type(&self) -> u8 {
+        self.typ as u8 + 1
+    }
+
     fn is_eof_error(err: &Self::Error) -> bool {
         err.is_eof()
     }
 }
 
 pub type PollPacket<'a, T, B> = GenericPollPacket<'a, T, Header, B>;
-pub type PollPacketState = GenericPollPacketState<Header>;
+pub type DecodeBatch<'a> = GenericDecodeBatch<'a, Header>;
+pub type PollPacketState<BH> = GenericPollPacketState<Header, BH>;
@@ -6,12 +6,12 @@ use alloc::vec::Vec;
 use bytes::Bytes;
 use simdutf8::basic::from_utf8;
 #[cfg(feature = "tokio")]
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 use crate::{
-    read_raw_bytes, read_string, read_string_async, read_u16, read_u16_async, read_u8,
-    read_u8_async, write_bytes, write_u16, write_u8, AsyncRead, Encodable, Error, Pid, QoS, QosPid,
-    SyncWrite, ToError, TopicName,
+    copy_payload, read_raw_bytes, read_string, read_string_async, read_u16, read_u16_async,
+    read_u8, read_u8_async, total_len, write_bytes, write_u16, write_u8, write_var_int, AsyncRead,
+    AsyncWrite, DecodeOptions, Encodable, Error, Pid, QoS, QosPid, SyncWrite, ToError, TopicName,
 };
 
 use super::{
@@ -21,6 +21,7 @@ use super::{
 
 /// Body type of PUBLISH packet.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Publish {
     pub dup: bool,
     pub retain: bool,
@@ -57,8 +58,42 @@ impl Publish {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, header: Header) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_payload_with(buf, offset, header, options, Bytes::copy_from_slice)
+    }
+
+    /// Decode the packet body, the payload is sliced from `buf` without copy.
+    pub fn decode_bytes(buf: &Bytes, offset: &mut usize, header: Header) -> Result<Self, ErrorV5> {
+        Self::decode_bytes_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_bytes_with(
+        buf: &Bytes,
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_payload_with(buf, offset, header, options, |data| buf.slice_ref(data))
+    }
+
+    fn decode_payload_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+        payload: impl FnOnce(&[u8]) -> Bytes,
+    ) -> Result<Self, ErrorV5> {
         let mut remaining_len = header.remaining_len as usize;
         let topic_name = read_string(buf, offset)?;
+        options.check_string(topic_name)?;
         remaining_len = remaining_len
             .checked_sub(2 + topic_name.len())
             .ok_or(Error::InvalidRemainingLength)?;
@@ -77,18 +112,19 @@ impl Publish {
                 QosPid::Level2(Pid::try_from(read_u16(buf, offset)?)?)
             }
         };
-        let properties = PublishProperties::decode(buf, offset, header.typ)?;
+        let (properties, properties_len) =
+            PublishProperties::decode_with_len(buf, offset, header.typ, options)?;
         remaining_len = remaining_len
-            .checked_sub(properties.encode_len())
+            .checked_sub(properties_len)
             .ok_or(Error::InvalidRemainingLength)?;
         let payload = if remaining_len > 0 {
             let data = read_raw_bytes(buf, offset, remaining_len)?;
             if properties.payload_is_utf8 == Some(true) && from_utf8(data).is_err() {
                 return Err(ErrorV5::InvalidPayloadFormat);
             }
-            data.to_vec()
+            payload(data)
         } else {
-            Vec::new()
+            Bytes::new()
         };
         Ok(Publish {
             dup: header.dup,
@@ -96,7 +132,7 @@ impl Publish {
             retain: header.retain,
             topic_name: TopicName::try_from(topic_name)?,
             properties,
-            payload: Bytes::from(payload),
+            payload,
         })
     }
 
@@ -104,8 +140,47 @@ impl Publish {
         reader: &mut T,
         header: Header,
     ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
+        let (mut publish, payload_len) =
+            Self::decode_head_async_with(reader, header, options).await?;
+        if payload_len > 0 {
+            let mut data = alloc::vec![0u8; payload_len];
+            reader
+                .read_exact(&mut data)
+                .await
+                .map_err(ToError::to_error)?;
+            if publish.properties.payload_is_utf8 == Some(true) && from_utf8(&data).is_err() {
+                return Err(ErrorV5::InvalidPayloadFormat);
+            }
+            publish.payload = Bytes::from(data);
+        }
+        Ok(publish)
+    }
+
+    /// Decode everything before the payload, return the packet with an empty
+    /// payload and the payload length. The payload format is not checked.
+    pub async fn decode_head_async<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+    ) -> Result<(Self, usize), ErrorV5> {
+        Self::decode_head_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_head_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<(Self, usize), ErrorV5> {
         let mut remaining_len = header.remaining_len as usize;
         let topic_name = read_string_async(reader).await?;
+        options.check_string(&topic_name)?;
         remaining_len = remaining_len
             .checked_sub(2 + topic_name.len())
             .ok_or(Error::InvalidRemainingLength)?;
@@ -124,36 +199,76 @@ impl Publish {
                 QosPid::Level2(Pid::try_from(read_u16_async(reader).await?)?)
             }
         };
-        let properties = PublishProperties::decode_async(reader, header.typ).await?;
+        let (properties, properties_len) =
+            PublishProperties::decode_with_len_async(reader, header.typ, options).await?;
         remaining_len = remaining_len
-            .checked_sub(properties.encode_len())
+            .checked_sub(properties_len)
             .ok_or(Error::InvalidRemainingLength)?;
-        let payload = if remaining_len > 0 {
-            let mut data = alloc::vec![0u8; remaining_len];
-            reader
-                .read_exact(&mut data)
-                .await
-                .map_err(ToError::to_error)?;
-            if properties.payload_is_utf8 == Some(true) && from_utf8(&data).is_err() {
-                return Err(ErrorV5::InvalidPayloadFormat);
-            }
-            data
-        } else {
-            Vec::new()
-        };
-        Ok(Publish {
+        let publish = Publish {
             dup: header.dup,
             qos_pid,
             retain: header.retain,
             topic_name: TopicName::try_from(topic_name)?,
             properties,
-            payload: Bytes::from(payload),
-        })
+            payload: Bytes::new(),
+        };
+        Ok((publish, remaining_len))
     }
 }
 
-impl Encodable for Publish {
-    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+impl Publish {
+    /// The first byte of the fixed header.
+    pub(crate) fn control_byte(&self) -> u8 {
+        let mut control_byte: u8 = match self.qos_pid {
+            QosPid::Level0 => 0b00110000,
+            QosPid::Level1(_) => 0b00110010,
+            QosPid::Level2(_) => 0b00110100,
+        };
+        if self.dup {
+            control_byte |= 0b00001000;
+        }
+        if self.retain {
+            control_byte |= 0b00000001;
+        }
+        control_byte
+    }
+
+    /// Encode the fixed header and the variable header for a payload of
+    /// `payload_len` bytes written afterwards, `self.payload` is ignored.
+    pub fn encode_head(&self, payload_len: usize) -> Result<Vec<u8>, Error> {
+        let head_len = self.encode_len() - self.payload.len();
+        let remaining_len = head_len + payload_len;
+        let total = total_len(remaining_len)?;
+        let mut buf = Vec::with_capacity(total - payload_len);
+        buf.push(self.control_byte());
+        write_var_int(&mut buf, remaining_len)?;
+        self.encode_variable_header(&mut buf)?;
+        debug_assert_eq!(buf.len(), total - payload_len);
+        Ok(buf)
+    }
+
+    /// Write the packet with `payload_len` bytes copied from `payload`
+    /// instead of `self.payload`. `payload` must supply exactly `payload_len`
+    /// bytes, e.g. a reader wrapped in `take(payload_len)`: bytes after the
+    /// payload are left unread, and a `payload` ending early fails after the
+    /// head was written.
+    /// The payload format indicator is not checked.
+    pub async fn encode_streaming_async<R, W>(
+        &self,
+        payload_len: usize,
+        payload: &mut R,
+        writer: &mut W,
+    ) -> Result<(), ErrorV5>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        writer.write_all(&self.encode_head(payload_len)?).await?;
+        copy_payload(payload, writer, payload_len).await?;
+        Ok(())
+    }
+
+    fn encode_variable_header<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
         write_bytes(writer, self.topic_name.as_bytes())?;
         match self.qos_pid {
             QosPid::Level0 => {}
@@ -162,6 +277,13 @@ impl Encodable for Publish {
             }
         }
         self.properties.encode(writer)?;
+        Ok(())
+    }
+}
+
+impl Encodable for Publish {
+    fn encode<W: SyncWrite>(&self, writer: &mut W) -> Result<(), Error> {
+        self.encode_variable_header(writer)?;
         writer.write_all(self.payload.as_ref())?;
         Ok(())
     }
@@ -182,6 +304,7 @@ impl Encodable for Publish {
 
 /// Property list for PUBLISH packet.
 #[derive(Debug, Clone, PartialEq, Eq, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PublishProperties {
     pub payload_is_utf8: Option<bool>,
     pub message_expiry_interval: Option<u32>,
@@ -216,9 +339,29 @@ impl PublishProperties {
         offset: &mut usize,
         packet_type: PacketType,
     ) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, packet_type, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with_len(buf, offset, packet_type, options).map(|(properties, _)| properties)
+    }
+
+    /// Decode the properties and their encoded length.
+    pub(crate) fn decode_with_len(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
+    ) -> Result<(Self, usize), ErrorV5> {
         let mut properties = PublishProperties::default();
-        decode_properties!(
+        let len = decode_properties!(
             packet_type,
+            options,
             properties,
             buf,
             offset,
@@ -230,16 +373,36 @@ impl PublishProperties {
             SubscriptionIdentifier,
             ContentType,
         );
-        Ok(properties)
+        Ok((properties, len))
     }
 
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         packet_type: PacketType,
     ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, packet_type, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with_len_async(reader, packet_type, options)
+            .await
+            .map(|(properties, _)| properties)
+    }
+
+    /// Decode the properties and their encoded length.
+    pub(crate) async fn decode_with_len_async<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
+    ) -> Result<(Self, usize), ErrorV5> {
         let mut properties = PublishProperties::default();
-        decode_properties_async!(
+        let len = decode_properties_async!(
             packet_type,
+            options,
             properties,
             reader,
             PayloadFormatIndicator,
@@ -250,7 +413,7 @@ impl PublishProperties {
             SubscriptionIdentifier,
             ContentType,
         );
-        Ok(properties)
+        Ok((properties, len))
     }
 }
 
@@ -289,6 +452,7 @@ impl Encodable for PublishProperties {
 /// Body type for PUBACK packet.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Puback {
     pub pid: Pid,
     pub reason_code: PubackReasonCode,
@@ -309,6 +473,15 @@ impl Puback {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, header: Header) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
         let pid = Pid::try_from(read_u16(buf, offset)?)?;
         let (reason_code, properties) = if header.remaining_len == 2 {
             (PubackReasonCode::Success, PubackProperties::default())
@@ -321,7 +494,7 @@ impl Puback {
             let reason_byte = read_u8(buf, offset)?;
             let reason_code = PubackReasonCode::from_u8(reason_byte)
                 .ok_or(ErrorV5::InvalidReasonCode(header.typ, reason_byte))?;
-            let properties = PubackProperties::decode(buf, offset, header.typ)?;
+            let properties = PubackProperties::decode_with(buf, offset, header.typ, options)?;
             (reason_code, properties)
         };
         Ok(Puback {
@@ -334,6 +507,14 @@ impl Puback {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         header: Header,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let pid = Pid::try_from(read_u16_async(reader).await?)?;
         let (reason_code, properties) = if header.remaining_len == 2 {
@@ -347,7 +528,8 @@ impl Puback {
             let reason_byte = read_u8_async(reader).await?;
             let reason_code = PubackReasonCode::from_u8(reason_byte)
                 .ok_or(ErrorV5::InvalidReasonCode(header.typ, reason_byte))?;
-            let properties = PubackProperties::decode_async(reader, header.typ).await?;
+            let properties =
+                PubackProperties::decode_async_with(reader, header.typ, options).await?;
             (reason_code, properties)
         };
         Ok(Puback {
@@ -388,6 +570,7 @@ impl Encodable for Puback {
 /// Property list for PUBACK packet.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PubackProperties {
     pub reason_string: Option<Arc<str>>,
     pub user_properties: Vec<UserProperty>,
@@ -398,18 +581,35 @@ impl PubackProperties {
         buf: &[u8],
         offset: &mut usize,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, packet_type, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = PubackProperties::default();
-        decode_properties!(packet_type, properties, buf, offset, ReasonString,);
+        decode_properties!(packet_type, options, properties, buf, offset, ReasonString,);
         Ok(properties)
     }
 
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, packet_type, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = PubackProperties::default();
-        decode_properties_async!(packet_type, properties, reader, ReasonString,);
+        decode_properties_async!(packet_type, options, properties, reader, ReasonString,);
         Ok(properties)
     }
 }
@@ -445,6 +645,8 @@ impl Encodable for PubackProperties {
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum PubackReasonCode {
     Success = 0x00,
     NoMatchingSubscribers = 0x10,
@@ -478,6 +680,7 @@ impl PubackReasonCode {
 /// Body type for PUBREC packet.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pubrec {
     pub pid: Pid,
     pub reason_code: PubrecReasonCode,
@@ -498,6 +701,15 @@ impl Pubrec {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, header: Header) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
         let pid = Pid::try_from(read_u16(buf, offset)?)?;
         let (reason_code, properties) = if header.remaining_len == 2 {
             (PubrecReasonCode::Success, PubrecProperties::default())
@@ -510,7 +722,7 @@ impl Pubrec {
             let reason_byte = read_u8(buf, offset)?;
             let reason_code = PubrecReasonCode::from_u8(reason_byte)
                 .ok_or(ErrorV5::InvalidReasonCode(header.typ, reason_byte))?;
-            let properties = PubrecProperties::decode(buf, offset, header.typ)?;
+            let properties = PubrecProperties::decode_with(buf, offset, header.typ, options)?;
             (reason_code, properties)
         };
         Ok(Pubrec {
@@ -523,6 +735,14 @@ impl Pubrec {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         header: Header,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let pid = Pid::try_from(read_u16_async(reader).await?)?;
         let (reason_code, properties) = if header.remaining_len == 2 {
@@ -536,7 +756,8 @@ impl Pubrec {
             let reason_byte = read_u8_async(reader).await?;
             let reason_code = PubrecReasonCode::from_u8(reason_byte)
                 .ok_or(ErrorV5::InvalidReasonCode(header.typ, reason_byte))?;
-            let properties = PubrecProperties::decode_async(reader, header.typ).await?;
+            let properties =
+                PubrecProperties::decode_async_with(reader, header.typ, options).await?;
             (reason_code, properties)
         };
         Ok(Pubrec {
@@ -577,6 +798,7 @@ impl Encodable for Pubrec {
 /// Property list for PUBREC packet.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PubrecProperties {
     pub reason_string: Option<Arc<str>>,
     pub user_properties: Vec<UserProperty>,
@@ -587,18 +809,35 @@ impl PubrecProperties {
         buf: &[u8],
         offset: &mut usize,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, packet_type, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = PubrecProperties::default();
-        decode_properties!(packet_type, properties, buf, offset, ReasonString,);
+        decode_properties!(packet_type, options, properties, buf, offset, ReasonString,);
         Ok(properties)
     }
 
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, packet_type, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = PubrecProperties::default();
-        decode_properties_async!(packet_type, properties, reader, ReasonString,);
+        decode_properties_async!(packet_type, options, properties, reader, ReasonString,);
         Ok(properties)
     }
 }
@@ -634,6 +873,8 @@ impl Encodable for PubrecProperties {
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum PubrecReasonCode {
     Success = 0x00,
     NoMatchingSubscribers = 0x10,
@@ -667,6 +908,7 @@ impl PubrecReasonCode {
 /// Body type for PUBREL packet.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pubrel {
     pub pid: Pid,
     pub reason_code: PubrelReasonCode,
@@ -687,6 +929,15 @@ impl Pubrel {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, header: Header) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
         let pid = Pid::try_from(read_u16(buf, offset)?)?;
         let (reason_code, properties) = if header.remaining_len == 2 {
             (PubrelReasonCode::Success, PubrelProperties::default())
@@ -699,7 +950,7 @@ impl Pubrel {
             let reason_byte = read_u8(buf, offset)?;
             let reason_code = PubrelReasonCode::from_u8(reason_byte)
                 .ok_or(ErrorV5::InvalidReasonCode(header.typ, reason_byte))?;
-            let properties = PubrelProperties::decode(buf, offset, header.typ)?;
+            let properties = PubrelProperties::decode_with(buf, offset, header.typ, options)?;
             (reason_code, properties)
         };
         Ok(Pubrel {
@@ -712,6 +963,14 @@ impl Pubrel {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         header: Header,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let pid = Pid::try_from(read_u16_async(reader).await?)?;
         let (reason_code, properties) = if header.remaining_len == 2 {
@@ -725,7 +984,8 @@ impl Pubrel {
             let reason_byte = read_u8_async(reader).await?;
             let reason_code = PubrelReasonCode::from_u8(reason_byte)
                 .ok_or(ErrorV5::InvalidReasonCode(header.typ, reason_byte))?;
-            let properties = PubrelProperties::decode_async(reader, header.typ).await?;
+            let properties =
+                PubrelProperties::decode_async_with(reader, header.typ, options).await?;
             (reason_code, properties)
         };
         Ok(Pubrel {
@@ -766,6 +1026,7 @@ impl Encodable for Pubrel {
 /// Property list for PUBREL packet.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PubrelProperties {
     pub reason_string: Option<Arc<str>>,
     pub user_properties: Vec<UserProperty>,
@@ -776,18 +1037,35 @@ impl PubrelProperties {
         buf: &[u8],
         offset: &mut usize,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, packet_type, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = PubrelProperties::default();
-        decode_properties!(packet_type, properties, buf, offset, ReasonString,);
+        decode_properties!(packet_type, options, properties, buf, offset, ReasonString,);
         Ok(properties)
     }
 
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, packet_type, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = PubrelProperties::default();
-        decode_properties_async!(packet_type, properties, reader, ReasonString,);
+        decode_properties_async!(packet_type, options, properties, reader, ReasonString,);
         Ok(properties)
     }
 }
@@ -814,6 +1092,8 @@ impl Encodable for PubrelProperties {
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum PubrelReasonCode {
     Success = 0x00,
     PacketIdentifierNotFound = 0x92,
@@ -833,6 +1113,7 @@ impl PubrelReasonCode {
 /// Body type for PUBCOMP packet.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pubcomp {
     pub pid: Pid,
     pub reason_code: PubcompReasonCode,
@@ -853,6 +1134,15 @@ impl Pubcomp {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, header: Header) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
         let pid = Pid::try_from(read_u16(buf, offset)?)?;
         let (reason_code, properties) = if header.remaining_len == 2 {
             (PubcompReasonCode::Success, PubcompProperties::default())
@@ -865,7 +1155,7 @@ impl Pubcomp {
             let reason_byte = read_u8(buf, offset)?;
             let reason_code = PubcompReasonCode::from_u8(reason_byte)
                 .ok_or(ErrorV5::InvalidReasonCode(header.typ, reason_byte))?;
-            let properties = PubcompProperties::decode(buf, offset, header.typ)?;
+            let properties = PubcompProperties::decode_with(buf, offset, header.typ, options)?;
             (reason_code, properties)
         };
         Ok(Pubcomp {
@@ -878,6 +1168,14 @@ impl Pubcomp {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         header: Header,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let pid = Pid::try_from(read_u16_async(reader).await?)?;
         let (reason_code, properties) = if header.remaining_len == 2 {
@@ -891,7 +1189,8 @@ impl Pubcomp {
             let reason_byte = read_u8_async(reader).await?;
             let reason_code = PubcompReasonCode::from_u8(reason_byte)
                 .ok_or(ErrorV5::InvalidReasonCode(header.typ, reason_byte))?;
-            let properties = PubcompProperties::decode_async(reader, header.typ).await?;
+            let properties =
+                PubcompProperties::decode_async_with(reader, header.typ, options).await?;
             (reason_code, properties)
         };
         Ok(Pubcomp {
@@ -932,6 +1231,7 @@ impl Encodable for Pubcomp {
 /// Property list for PUBCOMP packet.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PubcompProperties {
     pub reason_string: Option<Arc<str>>,
     pub user_properties: Vec<UserProperty>,
@@ -942,18 +1242,35 @@ impl PubcompProperties {
         buf: &[u8],
         offset: &mut usize,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, packet_type, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = PubcompProperties::default();
-        decode_properties!(packet_type, properties, buf, offset, ReasonString,);
+        decode_properties!(packet_type, options, properties, buf, offset, ReasonString,);
         Ok(properties)
     }
 
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, packet_type, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = PubcompProperties::default();
-        decode_properties_async!(packet_type, properties, reader, ReasonString,);
+        decode_properties_async!(packet_type, options, properties, reader, ReasonString,);
         Ok(properties)
     }
 }
@@ -980,6 +1297,8 @@ impl Encodable for PubcompProperties {
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum PubcompReasonCode {
     Success = 0x00,
     PacketIdentifierNotFound = 0x92,
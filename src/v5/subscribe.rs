@@ -4,9 +4,9 @@ use alloc::sync::Arc;
 use alloc::vec::Vec;
 
 use crate::{
-    decode_var_int, decode_var_int_async, read_string, read_string_async, read_u16, read_u16_async,
-    read_u8, read_u8_async, write_bytes, write_u16, write_u8, AsyncRead, Encodable, Error, Pid,
-    QoS, SyncWrite, TopicFilter,
+    check_var_int, decode_var_int, decode_var_int_async, read_string, read_string_async, read_u16,
+    read_u16_async, read_u8, read_u8_async, write_bytes, write_u16, write_u8, AsyncRead,
+    DecodeOptions, Encodable, Error, Pid, QoS, SyncWrite, TopicFilter,
 };
 
 use super::{
@@ -17,6 +17,7 @@ use super::{
 /// Body type for SUBSCRIBE packet.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Subscribe {
     pub pid: Pid,
     pub properties: SubscribeProperties,
@@ -33,11 +34,21 @@ impl Subscribe {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, header: Header) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
         let mut remaining_len = header.remaining_len as usize;
         let pid = Pid::try_from(read_u16(buf, offset)?)?;
-        let properties = SubscribeProperties::decode(buf, offset, header.typ)?;
+        let (properties, properties_len) =
+            SubscribeProperties::decode_with_len(buf, offset, header.typ, options)?;
         remaining_len = remaining_len
-            .checked_sub(2 + properties.encode_len())
+            .checked_sub(2 + properties_len)
             .ok_or(Error::InvalidRemainingLength)?;
         if remaining_len == 0 {
             return Err(Error::EmptySubscription.into());
@@ -45,6 +56,7 @@ impl Subscribe {
         let mut topics = Vec::new();
         while remaining_len > 0 {
             let topic_filter = TopicFilter::try_from(read_string(buf, offset)?)?;
+            options.check_string(&topic_filter)?;
             let options = {
                 let opt_byte = read_u8(buf, offset)?;
                 if opt_byte & 0b11000000 > 0 {
@@ -78,12 +90,21 @@ impl Subscribe {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         header: Header,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut remaining_len = header.remaining_len as usize;
         let pid = Pid::try_from(read_u16_async(reader).await?)?;
-        let properties = SubscribeProperties::decode_async(reader, header.typ).await?;
+        let (properties, properties_len) =
+            SubscribeProperties::decode_with_len_async(reader, header.typ, options).await?;
         remaining_len = remaining_len
-            .checked_sub(2 + properties.encode_len())
+            .checked_sub(2 + properties_len)
             .ok_or(Error::InvalidRemainingLength)?;
         if remaining_len == 0 {
             return Err(Error::EmptySubscription.into());
@@ -91,6 +112,7 @@ impl Subscribe {
         let mut topics = Vec::new();
         while remaining_len > 0 {
             let topic_filter = TopicFilter::try_from(read_string_async(reader).await?)?;
+            options.check_string(&topic_filter)?;
             let options = {
                 let opt_byte = read_u8_async(reader).await?;
                 if opt_byte & 0b11000000 > 0 {
@@ -146,6 +168,7 @@ impl Encodable for Subscribe {
 /// Property list for SUBSCRIBE packet.
 #[derive(Debug, Clone, PartialEq, Eq, Default, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SubscribeProperties {
     pub subscription_id: Option<VarByteInt>,
     pub user_properties: Vec<UserProperty>,
@@ -157,18 +180,69 @@ impl SubscribeProperties {
         offset: &mut usize,
         packet_type: PacketType,
     ) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, packet_type, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with_len(buf, offset, packet_type, options).map(|(properties, _)| properties)
+    }
+
+    /// Decode the properties and their encoded length.
+    pub(crate) fn decode_with_len(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
+    ) -> Result<(Self, usize), ErrorV5> {
         let mut properties = SubscribeProperties::default();
-        decode_properties!(packet_type, properties, buf, offset, SubscriptionIdentifier,);
-        Ok(properties)
+        let len = decode_properties!(
+            packet_type,
+            options,
+            properties,
+            buf,
+            offset,
+            SubscriptionIdentifier,
+        );
+        Ok((properties, len))
     }
 
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         packet_type: PacketType,
     ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, packet_type, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with_len_async(reader, packet_type, options)
+            .await
+            .map(|(properties, _)| properties)
+    }
+
+    /// Decode the properties and their encoded length.
+    pub(crate) async fn decode_with_len_async<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
+    ) -> Result<(Self, usize), ErrorV5> {
         let mut properties = SubscribeProperties::default();
-        decode_properties_async!(packet_type, properties, reader, SubscriptionIdentifier,);
-        Ok(properties)
+        let len = decode_properties_async!(
+            packet_type,
+            options,
+            properties,
+            reader,
+            SubscriptionIdentifier,
+        );
+        Ok((properties, len))
     }
 }
 
@@ -187,6 +261,7 @@ impl Encodable for SubscribeProperties {
 /// Subscription options.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SubscriptionOptions {
     pub max_qos: QoS,
     pub no_local: bool,
@@ -221,6 +296,7 @@ impl SubscriptionOptions {
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RetainHandling {
     SendAtSubscribe = 0,
     SendAtSubscribeIfNotExist = 1,
@@ -242,6 +318,7 @@ impl RetainHandling {
 /// Body type for SUBACK packet.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Suback {
     pub pid: Pid,
     pub properties: SubackProperties,
@@ -258,11 +335,21 @@ impl Suback {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, header: Header) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
         let mut remaining_len = header.remaining_len as usize;
         let pid = Pid::try_from(read_u16(buf, offset)?)?;
-        let properties = SubackProperties::decode(buf, offset, header.typ)?;
+        let (properties, properties_len) =
+            SubackProperties::decode_with_len(buf, offset, header.typ, options)?;
         remaining_len = remaining_len
-            .checked_sub(2 + properties.encode_len())
+            .checked_sub(2 + properties_len)
             .ok_or(Error::InvalidRemainingLength)?;
         let mut topics = Vec::new();
         while remaining_len > 0 {
@@ -282,12 +369,21 @@ impl Suback {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         header: Header,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut remaining_len = header.remaining_len as usize;
         let pid = Pid::try_from(read_u16_async(reader).await?)?;
-        let properties = SubackProperties::decode_async(reader, header.typ).await?;
+        let (properties, properties_len) =
+            SubackProperties::decode_with_len_async(reader, header.typ, options).await?;
         remaining_len = remaining_len
-            .checked_sub(2 + properties.encode_len())
+            .checked_sub(2 + properties_len)
             .ok_or(Error::InvalidRemainingLength)?;
         let mut topics = Vec::new();
         while remaining_len > 0 {
@@ -323,6 +419,7 @@ impl Encodable for Suback {
 /// Property list for SUBACK packet.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SubackProperties {
     pub reason_string: Option<Arc<str>>,
     pub user_properties: Vec<UserProperty>,
@@ -334,18 +431,56 @@ impl SubackProperties {
         offset: &mut usize,
         packet_type: PacketType,
     ) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, packet_type, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with_len(buf, offset, packet_type, options).map(|(properties, _)| properties)
+    }
+
+    /// Decode the properties and their encoded length.
+    pub(crate) fn decode_with_len(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
+    ) -> Result<(Self, usize), ErrorV5> {
         let mut properties = SubackProperties::default();
-        decode_properties!(packet_type, properties, buf, offset, ReasonString,);
-        Ok(properties)
+        let len = decode_properties!(packet_type, options, properties, buf, offset, ReasonString,);
+        Ok((properties, len))
     }
 
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         packet_type: PacketType,
     ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, packet_type, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with_len_async(reader, packet_type, options)
+            .await
+            .map(|(properties, _)| properties)
+    }
+
+    /// Decode the properties and their encoded length.
+    pub(crate) async fn decode_with_len_async<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
+    ) -> Result<(Self, usize), ErrorV5> {
         let mut properties = SubackProperties::default();
-        decode_properties_async!(packet_type, properties, reader, ReasonString,);
-        Ok(properties)
+        let len = decode_properties_async!(packet_type, options, properties, reader, ReasonString,);
+        Ok((properties, len))
     }
 }
 
@@ -381,6 +516,8 @@ impl Encodable for SubackProperties {
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum SubscribeReasonCode {
     GrantedQoS0 = 0x00,
     GrantedQoS1 = 0x01,
@@ -420,6 +557,7 @@ impl SubscribeReasonCode {
 /// Body type for UNSUBSCRIBE packet.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Unsubscribe {
     pub pid: Pid,
     pub properties: UnsubscribeProperties,
@@ -436,9 +574,21 @@ impl Unsubscribe {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, header: Header) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
         let mut remaining_len = header.remaining_len as usize;
         let pid = Pid::try_from(read_u16(buf, offset)?)?;
         let (property_len, property_len_bytes) = decode_var_int(buf, offset)?;
+        if options.strict_var_int(true) {
+            check_var_int(property_len, property_len_bytes)?;
+        }
         let mut properties = UnsubscribeProperties::default();
         let mut len = 0;
         while property_len as usize > len {
@@ -446,6 +596,8 @@ impl Unsubscribe {
             match property_id {
                 PropertyId::UserProperty => {
                     let property = PropertyValue::decode_user_property(buf, offset)?;
+                    options.check_string(&property.name)?;
+                    options.check_string(&property.value)?;
                     len += 1 + 4 + property.name.len() + property.value.len();
                     properties.user_properties.push(property);
                 }
@@ -464,6 +616,7 @@ impl Unsubscribe {
         let mut topics = Vec::new();
         while remaining_len > 0 {
             let topic_filter = TopicFilter::try_from(read_string(buf, offset)?)?;
+            options.check_string(&topic_filter)?;
             remaining_len = remaining_len
                 .checked_sub(2 + topic_filter.len())
                 .ok_or(Error::InvalidRemainingLength)?;
@@ -479,10 +632,21 @@ impl Unsubscribe {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         header: Header,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut remaining_len = header.remaining_len as usize;
         let pid = Pid::try_from(read_u16_async(reader).await?)?;
         let (property_len, property_len_bytes) = decode_var_int_async(reader).await?;
+        if options.strict_var_int(true) {
+            check_var_int(property_len, property_len_bytes)?;
+        }
         let mut properties = UnsubscribeProperties::default();
         let mut len = 0;
         while property_len as usize > len {
@@ -490,6 +654,8 @@ impl Unsubscribe {
             match property_id {
                 PropertyId::UserProperty => {
                     let property = PropertyValue::decode_user_property_async(reader).await?;
+                    options.check_string(&property.name)?;
+                    options.check_string(&property.value)?;
                     len += 1 + 4 + property.name.len() + property.value.len();
                     properties.user_properties.push(property);
                 }
@@ -508,6 +674,7 @@ impl Unsubscribe {
         let mut topics = Vec::new();
         while remaining_len > 0 {
             let topic_filter = TopicFilter::try_from(read_string_async(reader).await?)?;
+            options.check_string(&topic_filter)?;
             remaining_len = remaining_len
                 .checked_sub(2 + topic_filter.len())
                 .ok_or(Error::InvalidRemainingLength)?;
@@ -546,6 +713,7 @@ impl Encodable for Unsubscribe {
 /// Property list for UNSUBSCRIBE packet.
 #[derive(Debug, Clone, PartialEq, Eq, Default, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnsubscribeProperties {
     pub user_properties: Vec<UserProperty>,
 }
@@ -555,18 +723,35 @@ impl UnsubscribeProperties {
         buf: &[u8],
         offset: &mut usize,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, packet_type, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = UnsubscribeProperties::default();
-        decode_properties!(packet_type, properties, buf, offset,);
+        decode_properties!(packet_type, options, properties, buf, offset,);
         Ok(properties)
     }
 
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         packet_type: PacketType,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, packet_type, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut properties = UnsubscribeProperties::default();
-        decode_properties_async!(packet_type, properties, reader,);
+        decode_properties_async!(packet_type, options, properties, reader,);
         Ok(properties)
     }
 }
@@ -592,6 +777,7 @@ impl From<Vec<UserProperty>> for UnsubscribeProperties {
 /// Body type for UNSUBACK packet.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Unsuback {
     pub pid: Pid,
     pub properties: UnsubackProperties,
@@ -608,11 +794,21 @@ impl Unsuback {
     }
 
     pub fn decode(buf: &[u8], offset: &mut usize, header: Header) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, header, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        header: Header,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
         let mut remaining_len = header.remaining_len as usize;
         let pid = Pid::try_from(read_u16(buf, offset)?)?;
-        let properties = UnsubackProperties::decode(buf, offset, header.typ)?;
+        let (properties, properties_len) =
+            UnsubackProperties::decode_with_len(buf, offset, header.typ, options)?;
         remaining_len = remaining_len
-            .checked_sub(2 + properties.encode_len())
+            .checked_sub(2 + properties_len)
             .ok_or(Error::InvalidRemainingLength)?;
         let mut topics = Vec::new();
         while remaining_len > 0 {
@@ -632,12 +828,21 @@ impl Unsuback {
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         header: Header,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, header, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        header: Header,
+        options: &DecodeOptions,
     ) -> Result<Self, ErrorV5> {
         let mut remaining_len = header.remaining_len as usize;
         let pid = Pid::try_from(read_u16_async(reader).await?)?;
-        let properties = UnsubackProperties::decode_async(reader, header.typ).await?;
+        let (properties, properties_len) =
+            UnsubackProperties::decode_with_len_async(reader, header.typ, options).await?;
         remaining_len = remaining_len
-            .checked_sub(2 + properties.encode_len())
+            .checked_sub(2 + properties_len)
             .ok_or(Error::InvalidRemainingLength)?;
         let mut topics = Vec::new();
         while remaining_len > 0 {
@@ -673,6 +878,7 @@ impl Encodable for Unsuback {
 /// Property list for UNSUBACK packet.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnsubackProperties {
     pub reason_string: Option<Arc<str>>,
     pub user_properties: Vec<UserProperty>,
@@ -684,18 +890,56 @@ impl UnsubackProperties {
         offset: &mut usize,
         packet_type: PacketType,
     ) -> Result<Self, ErrorV5> {
+        Self::decode_with(buf, offset, packet_type, &DecodeOptions::default())
+    }
+
+    pub fn decode_with(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with_len(buf, offset, packet_type, options).map(|(properties, _)| properties)
+    }
+
+    /// Decode the properties and their encoded length.
+    pub(crate) fn decode_with_len(
+        buf: &[u8],
+        offset: &mut usize,
+        packet_type: PacketType,
+        options: &DecodeOptions,
+    ) -> Result<(Self, usize), ErrorV5> {
         let mut properties = UnsubackProperties::default();
-        decode_properties!(packet_type, properties, buf, offset, ReasonString,);
-        Ok(properties)
+        let len = decode_properties!(packet_type, options, properties, buf, offset, ReasonString,);
+        Ok((properties, len))
     }
 
     pub async fn decode_async<T: AsyncRead + Unpin>(
         reader: &mut T,
         packet_type: PacketType,
     ) -> Result<Self, ErrorV5> {
+        Self::decode_async_with(reader, packet_type, &DecodeOptions::default()).await
+    }
+
+    pub async fn decode_async_with<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
+    ) -> Result<Self, ErrorV5> {
+        Self::decode_with_len_async(reader, packet_type, options)
+            .await
+            .map(|(properties, _)| properties)
+    }
+
+    /// Decode the properties and their encoded length.
+    pub(crate) async fn decode_with_len_async<T: AsyncRead + Unpin>(
+        reader: &mut T,
+        packet_type: PacketType,
+        options: &DecodeOptions,
+    ) -> Result<(Self, usize), ErrorV5> {
         let mut properties = UnsubackProperties::default();
-        decode_properties_async!(packet_type, properties, reader, ReasonString,);
-        Ok(properties)
+        let len = decode_properties_async!(packet_type, options, properties, reader, ReasonString,);
+        Ok((properties, len))
     }
 }
 
@@ -726,6 +970,8 @@ impl Encodable for UnsubackProperties {
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum UnsubscribeReasonCode {
     Success = 0x00,
     NoSubscriptionExisted = 0x11,
@@ -0,0 +1,534 @@
+//! The normative statements of MQTT v5.0 about the encoding of packets, one
+//! test per statement checking the status listed in `CONFORMANCE.md`.
+
+use alloc::vec::Vec;
+
+use bytes::Bytes;
+
+use crate::v5::*;
+use crate::*;
+
+use Status::{Application, Deviation, Enforced};
+
+#[rustfmt::skip]
+static V5: Conformance = Conformance {
+    title: "MQTT v5.0",
+    statements: &[
+        Statement { id: "MQTT-1.5.4-1", summary: "UTF-8 encoded strings are well-formed and exclude the surrogates U+D800 to U+DFFF", status: Enforced },
+        Statement { id: "MQTT-1.5.4-2", summary: "UTF-8 encoded strings do not include the null character U+0000", status: Application },
+        Statement { id: "MQTT-1.5.4-3", summary: "A leading 0xEF 0xBB 0xBF is decoded as U+FEFF, not stripped", status: Enforced },
+        Statement { id: "MQTT-1.5.5-1", summary: "Variable Byte Integers use the minimum number of bytes", status: Enforced },
+        Statement { id: "MQTT-1.5.7-1", summary: "Both strings of a UTF-8 String Pair are well-formed UTF-8", status: Enforced },
+        Statement { id: "MQTT-2.1.3-1", summary: "Reserved fixed header flags are set to the listed values", status: Enforced },
+        Statement { id: "MQTT-2.2.1-3", summary: "SUBSCRIBE, UNSUBSCRIBE and PUBLISH (QoS > 0) carry a non-zero Packet Identifier", status: Enforced },
+        Statement { id: "MQTT-2.2.2-1", summary: "No properties are encoded as a Property Length of zero", status: Enforced },
+        Statement { id: "2.2.2.2", summary: "A property identifier is valid for the packet it is in", status: Enforced },
+        Statement { id: "2.4", summary: "Reason codes are one of the values listed for the packet", status: Enforced },
+        Statement { id: "MQTT-3.1.2-1", summary: "The protocol name is \"MQTT\"", status: Enforced },
+        Statement { id: "MQTT-3.1.2-2", summary: "An unsupported protocol version is answered with CONNACK reason code 0x84", status: Application },
+        Statement { id: "MQTT-3.1.2-3", summary: "The reserved CONNECT flag is zero", status: Enforced },
+        Statement { id: "MQTT-3.1.2-11", summary: "Will QoS is 0 when the Will Flag is 0", status: Enforced },
+        Statement { id: "MQTT-3.1.2-12", summary: "Will QoS is not 3", status: Enforced },
+        Statement { id: "MQTT-3.1.2-13", summary: "Will Retain is 0 when the Will Flag is 0", status: Application },
+        Statement { id: "3.1.2.11.2", summary: "Properties other than User Property and Subscription Identifier appear at most once", status: Enforced },
+        Statement { id: "3.1.2.11.3", summary: "Receive Maximum is not zero", status: Application },
+        Statement { id: "3.1.2.11.4", summary: "Maximum Packet Size is not zero", status: Application },
+        Statement { id: "3.1.2.11.7", summary: "Request Problem Information and Request Response Information are 0 or 1", status: Enforced },
+        Statement { id: "3.1.2.11.8", summary: "The User Property may appear multiple times", status: Enforced },
+        Statement { id: "3.1.2.11.10", summary: "Authentication Data is only sent with an Authentication Method", status: Application },
+        Statement { id: "3.2.2.1", summary: "The reserved CONNACK acknowledge flags are zero", status: Enforced },
+        Statement { id: "MQTT-3.2.2-6", summary: "Session Present is 0 with a non-zero CONNACK reason code", status: Application },
+        Statement { id: "3.2.2.3.4", summary: "Maximum QoS is 0 or 1", status: Enforced },
+        Statement { id: "MQTT-3.3.1-2", summary: "DUP is 0 for QoS 0 messages", status: Application },
+        Statement { id: "MQTT-3.3.1-4", summary: "A PUBLISH does not have both QoS bits set", status: Enforced },
+        Statement { id: "MQTT-3.3.2-2", summary: "The PUBLISH Topic Name has no wildcard characters", status: Enforced },
+        Statement { id: "3.3.2.3.2", summary: "Payload Format Indicator is 0 or 1, a payload marked 1 is UTF-8", status: Enforced },
+        Statement { id: "MQTT-3.3.2-8", summary: "Topic Alias is not zero", status: Application },
+        Statement { id: "3.3.2.3.5", summary: "The Response Topic has no wildcard characters", status: Enforced },
+        Statement { id: "3.3.2.3.8", summary: "A PUBLISH may carry multiple Subscription Identifiers", status: Deviation },
+        Statement { id: "MQTT-3.6.1-1", summary: "PUBREL fixed header flags are 0010", status: Enforced },
+        Statement { id: "MQTT-3.8.1-1", summary: "SUBSCRIBE fixed header flags are 0010", status: Enforced },
+        Statement { id: "3.8.2.1.2", summary: "Subscription Identifier is not zero", status: Application },
+        Statement { id: "MQTT-3.8.3-2", summary: "A SUBSCRIBE has at least one Topic Filter", status: Enforced },
+        Statement { id: "MQTT-3.8.3-4", summary: "No Local is not set on a Shared Subscription", status: Application },
+        Statement { id: "MQTT-3.8.3-5", summary: "The reserved bits of the Subscription Options are zero", status: Enforced },
+        Statement { id: "3.8.3.1", summary: "Retain Handling is not 3 and Maximum QoS is not 3", status: Enforced },
+        Statement { id: "MQTT-3.10.1-1", summary: "UNSUBSCRIBE fixed header flags are 0010", status: Enforced },
+        Statement { id: "MQTT-3.10.3-2", summary: "An UNSUBSCRIBE has at least one Topic Filter", status: Enforced },
+        Statement { id: "MQTT-4.7.1-1", summary: "'#' is alone or after a '/', and is the last character", status: Enforced },
+        Statement { id: "MQTT-4.7.1-2", summary: "'+' occupies an entire level of the filter", status: Enforced },
+        Statement { id: "MQTT-4.7.3-1", summary: "Topic Names are at least one character long (empty Topic Filters are rejected)", status: Application },
+        Statement { id: "MQTT-4.7.3-2", summary: "Topic Names and Topic Filters do not include U+0000", status: Enforced },
+        Statement { id: "MQTT-4.7.3-3", summary: "Topic Names and Topic Filters are at most 65535 bytes", status: Enforced },
+        Statement { id: "MQTT-4.8.2-1", summary: "A Shared Subscription is $share/{ShareName}/{filter} with a non-empty ShareName", status: Enforced },
+        Statement { id: "MQTT-4.8.2-2", summary: "The ShareName has no '/', '+' or '#'", status: Enforced },
+    ],
+};
+
+raw_packets!(ErrorV5, 5);
+
+fn connack(flags: u8, code: u8, props: &[u8]) -> Vec<u8> {
+    let mut body = alloc::vec![flags, code];
+    body.extend(properties(props));
+    packet(0x20, &body)
+}
+
+fn filter(value: &str) -> TopicFilter {
+    TopicFilter::try_from(value).unwrap()
+}
+
+#[test]
+fn mqtt_1_5_4_1() {
+    let surrogate = publish(&[0xed, 0xa0, 0x80], &[], b"x");
+    let invalid = connect(0x02, &[], &string(&[0xff]));
+    assert!(decode(&publish("é".as_bytes(), &[], b"x")).is_ok());
+    V5.check(
+        "MQTT-1.5.4-1",
+        Status::observed(rejected(&surrogate) && rejected(&invalid)),
+    );
+}
+
+#[test]
+fn mqtt_1_5_4_2() {
+    let client_id = connect(0x02, &[], &string(b"a\0b"));
+    let reason_string = connack(0, 0, &[0x1f, 0, 2, b'a', 0]);
+    V5.check(
+        "MQTT-1.5.4-2",
+        Status::observed(rejected(&client_id) && rejected(&reason_string)),
+    );
+}
+
+#[test]
+fn mqtt_1_5_4_3() {
+    let packet = decode(&publish("\u{feff}a".as_bytes(), &[], b"x")).unwrap();
+    let Packet::Publish(publish) = packet else {
+        panic!("not a PUBLISH: {packet:?}");
+    };
+    V5.check(
+        "MQTT-1.5.4-3",
+        Status::observed(&*publish.topic_name == "\u{feff}a"),
+    );
+}
+
+#[test]
+fn mqtt_1_5_5_1() {
+    let remaining_length = [0xc0, 0x80, 0x00];
+    let mut property_length = string(b"a");
+    property_length.extend_from_slice(&[0x80, 0x00, b'x']);
+    let property_length = packet(0x30, &property_length);
+    V5.check(
+        "MQTT-1.5.5-1",
+        Status::observed(rejected(&remaining_length) && rejected(&property_length)),
+    );
+}
+
+#[test]
+fn mqtt_1_5_7_1() {
+    let name = connect(0x02, &[0x26, 0, 1, 0xff, 0, 1, b'v'], &string(b"c"));
+    let value = connect(0x02, &[0x26, 0, 1, b'n', 0, 1, 0xff], &string(b"c"));
+    assert!(decode(&connect(
+        0x02,
+        &[0x26, 0, 1, b'n', 0, 1, b'v'],
+        &string(b"c")
+    ))
+    .is_ok());
+    V5.check(
+        "MQTT-1.5.7-1",
+        Status::observed(rejected(&name) && rejected(&value)),
+    );
+}
+
+#[test]
+fn mqtt_2_1_3_1() {
+    let pid = Pid::try_from(1).unwrap();
+    let packets: [Packet; 15] = [
+        Connect::new("c".into(), 60).into(),
+        Connack::new(false, ConnectReasonCode::Success).into(),
+        Puback::new_success(pid).into(),
+        Pubrec::new_success(pid).into(),
+        Pubrel::new_success(pid).into(),
+        Pubcomp::new_success(pid).into(),
+        Subscribe::new(
+            pid,
+            alloc::vec![(filter("a"), SubscriptionOptions::new(QoS::Level0))],
+        )
+        .into(),
+        Suback::new(pid, alloc::vec![SubscribeReasonCode::GrantedQoS0]).into(),
+        Unsubscribe::new(pid, alloc::vec![filter("a")]).into(),
+        Unsuback::new(pid, alloc::vec![UnsubscribeReasonCode::Success]).into(),
+        Packet::Pingreq,
+        Packet::Pingresp,
+        Disconnect::new_normal().into(),
+        Disconnect::new(DisconnectReasonCode::ServerBusy).into(),
+        Auth::new(AuthReasonCode::ContinueAuthentication).into(),
+    ];
+    let enforced = packets.into_iter().all(|packet| {
+        let mut bytes = encode(packet);
+        assert!(decode(&bytes).is_ok());
+        bytes[0] ^= 0x01;
+        rejected(&bytes)
+    });
+    V5.check("MQTT-2.1.3-1", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_2_2_1_3() {
+    let mut qos1 = string(b"a");
+    qos1.extend_from_slice(&[0, 0, 0, b'x']);
+    let enforced = rejected(&packet(0x32, &qos1))
+        && rejected(&packet(0x82, &[0, 0, 0, 0, 1, b'a', 0]))
+        && rejected(&packet(0xa2, &[0, 0, 0, 0, 1, b'a']))
+        && Pid::try_from(0).is_err();
+    V5.check("MQTT-2.2.1-3", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_2_2_2_1() {
+    let topic_name = TopicName::try_from("a").unwrap();
+    let publish = Publish::new(QosPid::Level0, topic_name, Bytes::from_static(b"x"));
+    let enforced = encode(publish.into()) == [0x30, 5, 0, 1, b'a', 0, b'x'];
+    V5.check("MQTT-2.2.2-1", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_2_2_2_2() {
+    assert!(decode(&publish(b"a", &[0x23, 0, 1], b"x")).is_ok());
+    let topic_alias = connect(0x02, &[0x23, 0, 1], &string(b"c"));
+    let will_delay = publish(b"a", &[0x18, 0, 0, 0, 1], b"x");
+    V5.check(
+        "2.2.2.2",
+        Status::observed(rejected(&topic_alias) && rejected(&will_delay)),
+    );
+}
+
+#[test]
+fn mqtt_2_4() {
+    assert!(decode(&[0x40, 3, 0, 1, 0x10]).is_ok());
+    let enforced = rejected(&[0x40, 3, 0, 1, 0x01])
+        && rejected(&connack(0, 0x01, &[]))
+        && rejected(&[0xe0, 1, 0x03]);
+    V5.check("2.4", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_3_1_2_1() {
+    let mut body = string(b"MQTX");
+    body.extend_from_slice(&[5, 0x02, 0, 60, 0]);
+    body.extend(string(b"c"));
+    V5.check(
+        "MQTT-3.1.2-1",
+        Status::observed(rejected(&packet(0x10, &body))),
+    );
+}
+
+#[test]
+fn mqtt_3_1_2_2() {
+    let mut body = string(b"MQTT");
+    body.extend_from_slice(&[6, 0x02, 0, 60, 0]);
+    body.extend(string(b"c"));
+    // The version is reported for the application to send CONNACK 0x84.
+    let err = decode(&packet(0x10, &body)).unwrap_err();
+    assert!(
+        matches!(err, ErrorV5::Common(Error::InvalidProtocol(ref name, 6)) if &**name == "MQTT"),
+        "{err:?}"
+    );
+    V5.check("MQTT-3.1.2-2", Application);
+}
+
+#[test]
+fn mqtt_3_1_2_3() {
+    assert!(decode(&connect(0x02, &[], &string(b"c"))).is_ok());
+    let packet = connect(0x03, &[], &string(b"c"));
+    V5.check("MQTT-3.1.2-3", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_1_2_11() {
+    let packet = connect(0x02 | 0x08, &[], &string(b"c"));
+    V5.check("MQTT-3.1.2-11", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_1_2_12() {
+    let mut payload = string(b"c");
+    payload.push(0);
+    payload.extend(string(b"t"));
+    payload.extend(string(b"m"));
+    assert!(decode(&connect(0x02 | 0x04 | 0x10, &[], &payload)).is_ok());
+    let packet = connect(0x02 | 0x04 | 0x18, &[], &payload);
+    V5.check("MQTT-3.1.2-12", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_1_2_13() {
+    let packet = connect(0x02 | 0x20, &[], &string(b"c"));
+    V5.check("MQTT-3.1.2-13", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_1_2_11_2() {
+    let expiry = [0x11, 0, 0, 0, 1];
+    let connect = self::connect(0x02, &[expiry, expiry].concat(), &string(b"c"));
+    let alias = [0x23, 0, 1];
+    let publish = self::publish(b"a", &[alias, alias].concat(), b"x");
+    V5.check(
+        "3.1.2.11.2",
+        Status::observed(rejected(&connect) && rejected(&publish)),
+    );
+}
+
+#[test]
+fn mqtt_3_1_2_11_3() {
+    let packet = connect(0x02, &[0x21, 0, 0], &string(b"c"));
+    V5.check("3.1.2.11.3", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_1_2_11_4() {
+    let packet = connect(0x02, &[0x27, 0, 0, 0, 0], &string(b"c"));
+    V5.check("3.1.2.11.4", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_1_2_11_7() {
+    assert!(decode(&connect(0x02, &[0x17, 1, 0x19, 1], &string(b"c"))).is_ok());
+    let problem = connect(0x02, &[0x17, 2], &string(b"c"));
+    let response = connect(0x02, &[0x19, 2], &string(b"c"));
+    V5.check(
+        "3.1.2.11.7",
+        Status::observed(rejected(&problem) && rejected(&response)),
+    );
+}
+
+#[test]
+fn mqtt_3_1_2_11_8() {
+    let user = [0x26, 0, 1, b'n', 0, 1, b'v'];
+    let packet = decode(&connect(0x02, &[user, user].concat(), &string(b"c"))).unwrap();
+    let Packet::Connect(connect) = packet else {
+        panic!("not a CONNECT: {packet:?}");
+    };
+    V5.check(
+        "3.1.2.11.8",
+        Status::observed(connect.properties.user_properties.len() == 2),
+    );
+}
+
+#[test]
+fn mqtt_3_1_2_11_10() {
+    let packet = connect(0x02, &[0x16, 0, 1, b'x'], &string(b"c"));
+    V5.check("3.1.2.11.10", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_2_2_1() {
+    assert!(decode(&connack(0x01, 0, &[])).is_ok());
+    V5.check(
+        "3.2.2.1",
+        Status::observed(rejected(&connack(0x02, 0, &[]))),
+    );
+}
+
+#[test]
+fn mqtt_3_2_2_6() {
+    let packet = connack(0x01, 0x80, &[]);
+    V5.check("MQTT-3.2.2-6", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_2_2_3_4() {
+    assert!(decode(&connack(0, 0, &[0x24, 1])).is_ok());
+    V5.check(
+        "3.2.2.3.4",
+        Status::observed(rejected(&connack(0, 0, &[0x24, 2]))),
+    );
+}
+
+#[test]
+fn mqtt_3_3_1_2() {
+    let mut bytes = publish(b"a", &[], b"x");
+    bytes[0] |= 0x08;
+    V5.check("MQTT-3.3.1-2", Status::observed(rejected(&bytes)));
+}
+
+#[test]
+fn mqtt_3_3_1_4() {
+    let mut body = string(b"a");
+    body.extend_from_slice(&[0, 1, 0, b'x']);
+    V5.check(
+        "MQTT-3.3.1-4",
+        Status::observed(rejected(&packet(0x36, &body))),
+    );
+}
+
+#[test]
+fn mqtt_3_3_2_2() {
+    let enforced = rejected(&publish(b"a/+", &[], b"x")) && rejected(&publish(b"a/#", &[], b"x"));
+    V5.check("MQTT-3.3.2-2", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_3_3_2_3_2() {
+    assert!(decode(&publish(b"a", &[0x01, 1], b"x")).is_ok());
+    let enforced =
+        rejected(&publish(b"a", &[0x01, 2], b"x")) && rejected(&publish(b"a", &[0x01, 1], &[0xff]));
+    V5.check("3.3.2.3.2", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_3_3_2_8() {
+    let packet = publish(b"a", &[0x23, 0, 0], b"x");
+    V5.check("MQTT-3.3.2-8", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_3_2_3_5() {
+    assert!(decode(&publish(b"a", &[0x08, 0, 1, b'r'], b"x")).is_ok());
+    let packet = publish(b"a", &[0x08, 0, 3, b'r', b'/', b'+'], b"x");
+    V5.check("3.3.2.3.5", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_3_2_3_8() {
+    assert!(decode(&publish(b"a", &[0x0b, 1], b"x")).is_ok());
+    let err = decode(&publish(b"a", &[0x0b, 1, 0x0b, 2], b"x")).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            ErrorV5::DuplicatedProperty(PropertyId::SubscriptionIdentifier)
+        ),
+        "{err:?}"
+    );
+    V5.check("3.3.2.3.8", Deviation);
+}
+
+#[test]
+fn mqtt_3_6_1_1() {
+    assert!(decode(&[0x62, 2, 0, 1]).is_ok());
+    let enforced = [0x60, 0x61, 0x63, 0x66, 0x6a]
+        .into_iter()
+        .all(|first| rejected(&[first, 2, 0, 1]));
+    V5.check("MQTT-3.6.1-1", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_3_8_1_1() {
+    let mut bytes = subscribe(b"a", 0);
+    assert!(decode(&bytes).is_ok());
+    bytes[0] = 0x80;
+    V5.check("MQTT-3.8.1-1", Status::observed(rejected(&bytes)));
+}
+
+#[test]
+fn mqtt_3_8_2_1_2() {
+    assert!(decode(&subscribe_with(&[0x0b, 1], b"a", 0)).is_ok());
+    let packet = subscribe_with(&[0x0b, 0], b"a", 0);
+    V5.check("3.8.2.1.2", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_8_3_2() {
+    let packet = [0x82, 3, 0, 1, 0];
+    V5.check("MQTT-3.8.3-2", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_8_3_4() {
+    let packet = subscribe(b"$share/g/a", 0x04);
+    V5.check("MQTT-3.8.3-4", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_3_8_3_5() {
+    assert!(decode(&subscribe(b"a", 0x2e)).is_ok());
+    let enforced = rejected(&subscribe(b"a", 0x40)) && rejected(&subscribe(b"a", 0x80));
+    V5.check("MQTT-3.8.3-5", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_3_8_3_1() {
+    let enforced = rejected(&subscribe(b"a", 0x30)) && rejected(&subscribe(b"a", 0x03));
+    V5.check("3.8.3.1", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_3_10_1_1() {
+    let mut bytes = unsubscribe(b"a");
+    assert!(decode(&bytes).is_ok());
+    bytes[0] = 0xa0;
+    V5.check("MQTT-3.10.1-1", Status::observed(rejected(&bytes)));
+}
+
+#[test]
+fn mqtt_3_10_3_2() {
+    let packet = [0xa2, 3, 0, 1, 0];
+    V5.check("MQTT-3.10.3-2", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_4_7_1_1() {
+    assert!(decode(&subscribe(b"a/#", 0)).is_ok());
+    let enforced = ["a#", "a/#/b", "#/"].into_iter().all(|filter| {
+        TopicFilter::try_from(filter).is_err() && rejected(&subscribe(filter.as_bytes(), 0))
+    });
+    V5.check("MQTT-4.7.1-1", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_4_7_1_2() {
+    assert!(decode(&subscribe(b"+/a/+", 0)).is_ok());
+    let enforced = ["a+", "a/+b", "+a/b"].into_iter().all(|filter| {
+        TopicFilter::try_from(filter).is_err() && rejected(&subscribe(filter.as_bytes(), 0))
+    });
+    V5.check("MQTT-4.7.1-2", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_4_7_3_1() {
+    assert!(rejected(&subscribe(b"", 0)));
+    assert!(rejected(&unsubscribe(b"")));
+    // Empty Topic Names are valid with a Topic Alias, and accepted without.
+    assert!(decode(&publish(b"", &[0x23, 0, 1], b"x")).is_ok());
+    let packet = publish(b"", &[], b"x");
+    V5.check("MQTT-4.7.3-1", Status::observed(rejected(&packet)));
+}
+
+#[test]
+fn mqtt_4_7_3_2() {
+    let enforced = rejected(&publish(b"a\0b", &[], b"x"))
+        && rejected(&subscribe(b"a\0b", 0))
+        && rejected(&unsubscribe(b"a\0b"))
+        && TopicName::try_from("a\0b").is_err()
+        && TopicFilter::try_from("a\0b").is_err();
+    V5.check("MQTT-4.7.3-2", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_4_7_3_3() {
+    let long = "a".repeat(u16::MAX as usize + 1);
+    assert!(TopicName::try_from(&long[1..]).is_ok());
+    let enforced = TopicName::try_from(long.as_str()).is_err()
+        && TopicFilter::try_from(long.as_str()).is_err();
+    V5.check("MQTT-4.7.3-3", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_4_8_2_1() {
+    assert!(decode(&subscribe(b"$share/g/a/#", 0)).is_ok());
+    let enforced = ["$share//a", "$share/g", "$share/g/"]
+        .into_iter()
+        .all(|filter| rejected(&subscribe(filter.as_bytes(), 0)));
+    V5.check("MQTT-4.8.2-1", Status::observed(enforced));
+}
+
+#[test]
+fn mqtt_4_8_2_2() {
+    let enforced = ["$share/g+/a", "$share/g#/a", "$share/+/a"]
+        .into_iter()
+        .all(|filter| rejected(&subscribe(filter.as_bytes(), 0)));
+    V5.check("MQTT-4.8.2-2", Status::observed(enforced));
+}
+
+#[test]
+fn report() {
+    V5.check_report();
+}
@@ -1,3 +1,4 @@
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 
 use bytes::Bytes;
@@ -66,8 +67,8 @@ fn test_v5_header_len() {
         ),
         (
             alloc::vec![1 << 4, 0x80, 0],
-            Ok(Header::new(Connect, false, Level0, false, 0, 2)),
-        ), //Weird encoding for "0" buf matches spec
+            Err(Error::NonMinimalVarByteInt(0, 2).into()),
+        ), // "0" must be encoded in one byte [MQTT-1.5.5-1]
         (
             alloc::vec![1 << 4, 0x80, 1],
             Ok(Header::new(Connect, false, Level0, false, 128, 2)),
@@ -104,7 +105,7 @@ fn test_v5_non_utf8_string() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -138,7 +139,7 @@ fn test_v5_decode_connect() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -157,7 +158,7 @@ fn test_v5_decode_connect() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err(),
         Error::InvalidRemainingLength.into()
@@ -172,7 +173,7 @@ fn test_v5_decode_connect() {
     assert!(block_on(PollPacket::new(
         &mut Default::default(),
         &mut data,
-        &mut MockBuffer::default()
+        &mut BufferPool::default()
     ))
     .unwrap_err()
     .is_eof());
@@ -192,7 +193,7 @@ fn test_v5_decode_connect() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -212,7 +213,7 @@ fn test_v5_decode_connect() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -231,7 +232,7 @@ fn test_v5_decode_connect() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -259,7 +260,7 @@ fn test_v5_decode_connect() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -282,7 +283,7 @@ fn test_v5_decode_connack() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -314,7 +315,7 @@ fn test_v5_decode_connack() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -331,7 +332,7 @@ fn test_v5_decode_connack() {
             block_on(PollPacket::new(
                 &mut Default::default(),
                 &mut data,
-                &mut MockBuffer::default()
+                &mut BufferPool::default()
             ))
             .unwrap_err()
         );
@@ -355,7 +356,7 @@ fn test_v5_decode_connack() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -377,7 +378,7 @@ fn test_v5_decode_connack() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -411,7 +412,7 @@ fn test_v5_decode_disconnect() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -435,7 +436,7 @@ fn test_v5_decode_disconnect() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -458,7 +459,7 @@ fn test_v5_decode_disconnect() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -480,7 +481,7 @@ fn test_v5_decode_disconnect() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -514,7 +515,7 @@ fn test_v5_decode_auth() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -538,7 +539,7 @@ fn test_v5_decode_auth() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -560,7 +561,7 @@ fn test_v5_decode_auth() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -581,7 +582,7 @@ fn test_v5_decode_auth() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -616,7 +617,7 @@ fn test_v5_decode_publish() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -655,7 +656,7 @@ fn test_v5_decode_publish() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -695,7 +696,7 @@ fn test_v5_decode_publish() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -726,7 +727,7 @@ fn test_v5_decode_publish() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -759,7 +760,7 @@ fn test_v5_decode_publish() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -784,7 +785,7 @@ fn test_v5_decode_publish() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -805,7 +806,7 @@ fn test_v5_decode_publish() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -831,7 +832,7 @@ fn test_v5_decode_publish() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -859,7 +860,7 @@ fn test_v5_decode_publish() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -895,7 +896,7 @@ fn test_v5_decode_puback() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -920,7 +921,7 @@ fn test_v5_decode_puback() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -946,7 +947,7 @@ fn test_v5_decode_puback() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -983,7 +984,7 @@ fn test_v5_decode_pubrec() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1008,7 +1009,7 @@ fn test_v5_decode_pubrec() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1034,7 +1035,7 @@ fn test_v5_decode_pubrec() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1070,7 +1071,7 @@ fn test_v5_decode_pubrel() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1095,7 +1096,7 @@ fn test_v5_decode_pubrel() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1121,7 +1122,7 @@ fn test_v5_decode_pubrel() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1157,7 +1158,7 @@ fn test_v5_decode_pubcomp() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1182,7 +1183,7 @@ fn test_v5_decode_pubcomp() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1208,7 +1209,7 @@ fn test_v5_decode_pubcomp() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1256,7 +1257,7 @@ fn test_v5_decode_subscribe() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1295,7 +1296,7 @@ fn test_v5_decode_subscribe() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1327,7 +1328,7 @@ fn test_v5_decode_subscribe() {
             block_on(PollPacket::new(
                 &mut Default::default(),
                 &mut data,
-                &mut MockBuffer::default()
+                &mut BufferPool::default()
             ))
             .unwrap_err()
         );
@@ -1349,7 +1350,7 @@ fn test_v5_decode_subscribe() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -1389,7 +1390,7 @@ fn test_v5_decode_suback() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1412,7 +1413,7 @@ fn test_v5_decode_suback() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -1477,7 +1478,7 @@ fn test_v5_decode_unsubscribe() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1500,7 +1501,7 @@ fn test_v5_decode_unsubscribe() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -1522,7 +1523,7 @@ fn test_v5_decode_unsubscribe() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -1562,7 +1563,7 @@ fn test_v5_decode_unsuback() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1585,7 +1586,7 @@ fn test_v5_decode_unsuback() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap_err()
     );
@@ -1600,7 +1601,7 @@ fn test_v5_decode_pingreq() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1613,7 +1614,7 @@ fn test_v5_decode_pingreq() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1629,7 +1630,7 @@ fn test_v5_decode_pingresp() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1642,7 +1643,7 @@ fn test_v5_decode_pingresp() {
         block_on(PollPacket::new(
             &mut Default::default(),
             &mut data,
-            &mut MockBuffer::default()
+            &mut BufferPool::default()
         ))
         .unwrap()
         .2,
@@ -1674,7 +1675,7 @@ async fn poll_actor_model_simulation_v5() {
     }
 
     for size in (0..15).map(|i| 1 << i) {
-        let payload = vec![b'x'; size];
+        let payload = alloc::vec![b'x'; size];
         let pkt = Packet::Publish(Publish {
             dup: false,
             qos_pid: QosPid::Level1(Pid::try_from(1).unwrap()),
@@ -1690,7 +1691,7 @@ async fn poll_actor_model_simulation_v5() {
         let pkt = Packet::Subscribe(Subscribe {
             pid: Pid::try_from(10).unwrap(),
             properties: Default::default(),
-            topics: vec![(
+            topics: alloc::vec![(
                 TopicFilter::try_from("a/+").unwrap(),
                 SubscriptionOptions {
                     max_qos: qos,
@@ -1707,7 +1708,7 @@ async fn poll_actor_model_simulation_v5() {
         let pkt = Packet::Unsubscribe(Unsubscribe {
             pid: Pid::try_from(20).unwrap(),
             properties: Default::default(),
-            topics: vec![TopicFilter::try_from("b/#").unwrap()],
+            topics: alloc::vec![TopicFilter::try_from("b/#").unwrap()],
         });
         packets.push(pkt.encode().unwrap());
     }
@@ -1787,3 +1788,450 @@ async fn poll_actor_model_simulation_v5() {
 
     println!("--- End Report ---");
 }
+
+#[test]
+fn test_v5_decode_publish_shared_payload() {
+    let mut data: &[u8] = &[
+        0b00110000, 9, // type=Publish, qos=0, remaining_len=9
+        0x00, 0x03, b'a', b'/', b'b', // topic
+        0x00, // properties
+        b'h', b'e', b'y', // payload
+    ];
+    let mut buffer = BytesBuffer::default();
+    let (_, body, packet) = block_on(PollPacket::new(
+        &mut Default::default(),
+        &mut data,
+        &mut buffer,
+    ))
+    .unwrap();
+    let body = body.into_bytes();
+    let Packet::Publish(publish) = packet else {
+        panic!("unexpected packet");
+    };
+    assert_eq!(publish.payload.as_ref(), b"hey");
+    assert_eq!(publish.payload.as_ptr(), body[6..].as_ptr());
+
+    let header = Header::new(PacketType::Publish, false, QoS::Level0, false, 9, 11);
+    let decoded = Publish::decode_bytes(&body, &mut 0, header).unwrap();
+    assert_eq!(decoded, publish);
+}
+
+#[test]
+fn test_v5_decode_streaming_publish() {
+    let mut publish = Publish::new(
+        QosPid::Level0,
+        TopicName::try_from("fw").unwrap(),
+        Bytes::from(alloc::vec![1u8; 500]),
+    );
+    publish.properties.message_expiry_interval = Some(60);
+    let data = Packet::from(publish.clone()).encode().unwrap();
+    let mut reader = data.as_ref();
+
+    let StreamPacket::Publish(head, mut payload) =
+        block_on(Packet::decode_streaming(&mut reader)).unwrap()
+    else {
+        panic!("expected a streamed publish");
+    };
+    assert_eq!(head.properties, publish.properties);
+    assert_eq!(payload.remaining(), 500);
+    block_on(payload.skip()).unwrap();
+    assert_eq!(payload.remaining(), 0);
+    assert!(reader.is_empty());
+}
+
+#[test]
+fn test_v5_decode_from_slice() {
+    let pid = Pid::try_from(5).unwrap();
+    let mut publish = Publish::new(
+        QosPid::Level2(pid),
+        TopicName::try_from("a/b").unwrap(),
+        Bytes::from_static(b"hello"),
+    );
+    publish.properties.topic_alias = Some(3);
+    let packets = [
+        Packet::from(publish),
+        Puback::new_success(pid).into(),
+        Disconnect::new_normal().into(),
+        Packet::Pingresp,
+        Auth::new_success().into(),
+    ];
+    let mut data = Vec::new();
+    for packet in &packets {
+        data.extend_from_slice(packet.encode().unwrap().as_ref());
+    }
+    let mut offset = 0;
+    for packet in &packets {
+        let (decoded, used) = Packet::decode_from_slice(&data[offset..]).unwrap().unwrap();
+        assert_eq!(&decoded, packet);
+        offset += used;
+    }
+    assert_eq!(offset, data.len());
+    assert_eq!(Packet::decode_from_slice(&data[..3]), Ok(None));
+}
+
+#[test]
+fn test_v5_read_from_write_to() {
+    let pid = Pid::try_from(5).unwrap();
+    let packets = [
+        Packet::from(Publish::new(
+            QosPid::Level1(pid),
+            TopicName::try_from("a/b").unwrap(),
+            Bytes::from_static(b"hello"),
+        )),
+        Puback::new_success(pid).into(),
+        Disconnect::new_normal().into(),
+        Packet::Pingreq,
+    ];
+    let mut data = Vec::new();
+    for packet in &packets {
+        packet.write_to(&mut data).unwrap();
+    }
+    let mut reader = &data[..];
+    for packet in &packets {
+        assert_eq!(&Packet::read_from(&mut reader).unwrap(), packet);
+    }
+    assert!(reader.is_empty());
+
+    let mut reader = &data[..3];
+    assert_eq!(
+        Packet::read_from(&mut reader),
+        Err(ErrorV5::Common(Error::IoError(IoErrorKind::UnexpectedEof)))
+    );
+}
+
+#[test]
+fn test_v5_poll_packet_resumable() {
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::task::{Context, Poll, Waker};
+
+    let publish = Packet::from(Publish::new(
+        QosPid::Level0,
+        TopicName::try_from("a/b").unwrap(),
+        Bytes::from(alloc::vec![7u8; 100]),
+    ));
+    let data = publish.encode().unwrap();
+
+    let budget = MemoryBudget::new(MemoryBudgetConfig::default());
+    let small = BufferPool::new(BufferPoolConfig {
+        size_classes: alloc::vec![16],
+        max_memory: 64,
+        chunk_size: 8,
+    });
+    let mut buffer = BudgetedBuffer::new(small, budget.connection());
+    let mut reader = trickle_reader(data.as_ref());
+    let mut state = PollPacketState::default();
+    let mut cx = Context::from_waker(Waker::noop());
+
+    // Drop the future after every poll, the body stays charged meanwhile
+    let mut polls = 0;
+    let (_, _, decoded) = loop {
+        let mut poll_packet = PollPacket::new(&mut state, &mut reader, &mut buffer);
+        if let Poll::Ready(result) = Pin::new(&mut poll_packet).poll(&mut cx) {
+            break result.unwrap();
+        }
+        polls += 1;
+        if polls == 20 {
+            assert!(matches!(
+                state,
+                GenericPollPacketState::Chunk { idx: 17, .. }
+            ));
+            assert_eq!(budget.stats().in_use, data.as_ref().len() - 2);
+        }
+    };
+    assert_eq!(decoded, publish);
+    assert_eq!(budget.stats().in_use, 0);
+    assert!(matches!(state, GenericPollPacketState::Header { .. }));
+    #[cfg(feature = "tokio")]
+    assert!(reader.is_empty());
+    #[cfg(not(feature = "tokio"))]
+    assert!(reader.inner().is_empty());
+}
+
+#[tokio::test]
+async fn test_v5_poll_packet_budget_exhausted() {
+    let budget = MemoryBudget::new(MemoryBudgetConfig {
+        limit: 100,
+        connection_limit: 100,
+        wait: true,
+    });
+    let holder = budget.connection();
+    let permit = holder.reserve(100).await.unwrap();
+
+    let publish = Packet::from(Publish::new(
+        QosPid::Level0,
+        TopicName::try_from("a/b").unwrap(),
+        Bytes::from_static(b"payload"),
+    ));
+    let data = publish.encode().unwrap().as_ref().to_vec();
+    let mut buffer = BudgetedBuffer::new(BufferPool::default(), budget.connection());
+    let decoder = tokio::spawn(async move {
+        let mut reader: &[u8] = &data;
+        let mut state = PollPacketState::default();
+        // The future is re-created on every poll, the queued reservation
+        // must survive it
+        PollPacket::new(&mut state, &mut reader, &mut buffer)
+            .await
+            .map(|(_, _, packet)| packet)
+    });
+    while budget.stats().throttled == 0 {
+        tokio::task::yield_now().await;
+    }
+    tokio::task::yield_now().await;
+    assert_eq!(budget.stats().waiting, 1);
+
+    drop(permit);
+    let decoded = tokio::time::timeout(core::time::Duration::from_secs(2), decoder)
+        .await
+        .expect("the decoder is woken when memory is released")
+        .unwrap();
+    assert_eq!(decoded, Ok(publish));
+    assert_eq!(budget.stats().in_use, 0);
+}
+
+#[test]
+fn test_v5_decode_batch() {
+    let mut data = Vec::new();
+    for pid in 1..=20 {
+        let packet = Packet::from(Puback::new_success(Pid::try_from(pid).unwrap()));
+        data.extend_from_slice(packet.encode().unwrap().as_ref());
+    }
+    let disconnect = Packet::from(Disconnect::new_normal());
+    data.extend_from_slice(&disconnect.encode().unwrap().as_ref()[..1]);
+
+    let mut batch = Packet::decode_batch(&data);
+    for pid in 1..=20 {
+        let expected = Packet::from(Puback::new_success(Pid::try_from(pid).unwrap()));
+        assert_eq!(batch.next(), Some(Ok(expected)));
+    }
+    assert!(batch.next().is_none());
+    assert_eq!(batch.leftover(), 1);
+    assert_eq!(batch.consumed(), data.len() - 1);
+}
+
+#[test]
+fn test_v5_strict_var_int() {
+    // Pingreq with the remaining length 0 in two bytes
+    let remaining_len: &[u8] = &[0xc0, 0x80, 0x00];
+    // Publish with the property length 0 in two bytes
+    let property_len: &[u8] = &[0x30, 0x06, 0x00, 0x01, b'a', 0x80, 0x00, b'x'];
+    // Subscribe with the subscription identifier 1 in two bytes
+    let subscription_id: &[u8] = &[
+        0x82, 0x0a, 0x00, 0x01, 0x03, 0x0b, 0x81, 0x00, 0x00, 0x01, b'a', 0x00,
+    ];
+    let lenient = DecodeOptions::default().with_strict_var_int(false);
+    for (data, value) in [(remaining_len, 0), (property_len, 0), (subscription_id, 1)] {
+        let err = ErrorV5::Common(Error::NonMinimalVarByteInt(value, 2));
+        assert_eq!(Packet::decode(data), Err(err.clone()));
+        assert_eq!(Packet::decode_from_slice(data), Err(err.clone()));
+        assert_eq!(Packet::read_from(&mut &data[..]), Err(err.clone()));
+        assert_eq!(Packet::decode_batch(data).next(), Some(Err(err.clone())));
+        assert_eq!(
+            block_on(PollPacket::new(
+                &mut Default::default(),
+                &mut &data[..],
+                &mut BufferPool::default(),
+            ))
+            .unwrap_err(),
+            err
+        );
+
+        let expected = Packet::decode_with(data, &lenient).unwrap().unwrap();
+        assert_eq!(
+            Packet::decode_from_slice_with(data, &lenient),
+            Ok(Some((expected.clone(), data.len())))
+        );
+        assert_eq!(
+            Packet::read_from_with(&mut &data[..], &lenient),
+            Ok(expected.clone())
+        );
+        assert_eq!(
+            DecodeBatch::new(data).with_options(lenient.clone()).next(),
+            Some(Ok(expected.clone()))
+        );
+        assert_eq!(
+            block_on(
+                PollPacket::new(
+                    &mut Default::default(),
+                    &mut &data[..],
+                    &mut BufferPool::default(),
+                )
+                .with_options(lenient.clone())
+            )
+            .unwrap()
+            .2,
+            expected
+        );
+    }
+
+    let Packet::Publish(publish) = Packet::decode_with(property_len, &lenient)
+        .unwrap()
+        .unwrap()
+    else {
+        panic!("not a publish packet");
+    };
+    assert_eq!(publish.payload.as_ref(), b"x");
+    let Packet::Subscribe(subscribe) = Packet::decode_with(subscription_id, &lenient)
+        .unwrap()
+        .unwrap()
+    else {
+        panic!("not a subscribe packet");
+    };
+    assert_eq!(
+        subscribe.properties.subscription_id,
+        Some(VarByteInt::try_from(1).unwrap())
+    );
+    assert_eq!(subscribe.topics.len(), 1);
+}
+
+#[test]
+fn test_v5_string_policy() {
+    let strict = DecodeOptions::default().with_string_policy(StringPolicy::Strict);
+    let user_property = |name: &str, value: &str| UserProperty {
+        name: name.into(),
+        value: value.into(),
+    };
+
+    let client_id = Connect::new("c\0".into(), 60);
+    let mut username = Connect::new("c".into(), 60);
+    username.username = Some("user\u{7}".into());
+    let mut connect_property = Connect::new("c".into(), 60);
+    connect_property
+        .properties
+        .user_properties
+        .push(user_property("k", "v\u{85}"));
+    let mut will_topic = Connect::new("c".into(), 60);
+    will_topic.last_will = Some(LastWill::new(
+        QoS::Level0,
+        TopicName::try_from("will\u{1}").unwrap(),
+        Bytes::new(),
+    ));
+    let mut content_type = Publish::new(
+        QosPid::Level0,
+        TopicName::try_from("a/b").unwrap(),
+        Bytes::from_static(b"hello"),
+    );
+    content_type.properties.content_type = Some("text/\u{fdd0}".into());
+    let topic_name = Publish::new(
+        QosPid::Level0,
+        TopicName::try_from("a/\u{ffff}").unwrap(),
+        Bytes::new(),
+    );
+    let pid = Pid::try_from(1).unwrap();
+    let topic_filter = Subscribe::new(
+        pid,
+        alloc::vec![(
+            TopicFilter::try_from("a/\u{1f}").unwrap(),
+            SubscriptionOptions::new(QoS::Level0)
+        )],
+    );
+    let mut unsubscribe = Unsubscribe::new(pid, alloc::vec![TopicFilter::try_from("a").unwrap()]);
+    unsubscribe
+        .properties
+        .user_properties
+        .push(user_property("\u{9f}", "v"));
+    let mut reason_string = Disconnect::new_normal();
+    reason_string.properties.reason_string = Some("bye\u{fffe}".into());
+
+    for (packet, code) in [
+        (Packet::from(client_id), 0x00),
+        (username.into(), 0x07),
+        (connect_property.into(), 0x85),
+        (will_topic.into(), 0x01),
+        (content_type.into(), 0xfdd0),
+        (topic_name.into(), 0xffff),
+        (topic_filter.into(), 0x1f),
+        (unsubscribe.into(), 0x9f),
+        (reason_string.into(), 0xfffe),
+    ] {
+        let data = packet.encode().unwrap();
+        let data = data.as_ref();
+        assert_eq!(Packet::decode(data), Ok(Some(packet.clone())));
+
+        let err = ErrorV5::Common(Error::DisallowedChar(code));
+        assert_eq!(Packet::decode_with(data, &strict), Err(err.clone()));
+        assert_eq!(
+            Packet::decode_from_slice_with(data, &strict),
+            Err(err.clone())
+        );
+        assert_eq!(
+            block_on(
+                PollPacket::new(
+                    &mut Default::default(),
+                    &mut &data[..],
+                    &mut BufferPool::default()
+                )
+                .with_options(strict.clone())
+            )
+            .unwrap_err(),
+            err
+        );
+    }
+}
+
+#[test]
+fn test_v5_lenient_duplicated_property() {
+    let report = Arc::new(LenientReport::new());
+    let lenient = DecodeOptions::default()
+        .with_lenient(Leniencies {
+            duplicated_property: true,
+            ..Default::default()
+        })
+        .with_report(report.clone());
+    // Publish with the message expiry interval and the content type twice
+    let data: &[u8] = &[
+        0x30, 0x1c, 0x00, 0x01, b'a', // topic name
+        0x13, // property length
+        0x02, 0x00, 0x00, 0x00, 0x0a, // message expiry interval 10
+        0x03, 0x00, 0x01, b'x', // content type "x"
+        0x02, 0x00, 0x00, 0x00, 0x14, // message expiry interval 20
+        0x03, 0x00, 0x02, b'y', b'z', // content type "yz"
+        b'h', b'e', b'l', b'l', b'o', // payload
+    ];
+    let err = ErrorV5::DuplicatedProperty(PropertyId::MessageExpiryInterval);
+    assert_eq!(Packet::decode(data), Err(err.clone()));
+    assert_eq!(Packet::decode_from_slice(data), Err(err));
+
+    let mut publish = Publish::new(
+        QosPid::Level0,
+        TopicName::try_from("a").unwrap(),
+        Bytes::from_static(b"hello"),
+    );
+    publish.properties.message_expiry_interval = Some(10);
+    publish.properties.content_type = Some("x".into());
+    let expected = Packet::from(publish);
+    assert_eq!(
+        Packet::decode_with(data, &lenient),
+        Ok(Some(expected.clone()))
+    );
+    assert_eq!(
+        Packet::decode_from_slice_with(data, &lenient),
+        Ok(Some((expected, data.len())))
+    );
+    assert_eq!(report.count(Leniency::DuplicatedProperty), 4);
+    assert_eq!(report.total(), 4);
+
+    // Publish with two subscription identifiers [3.3.2.3.8], not reported
+    let data: &[u8] = &[
+        0x30, 0x0a, 0x00, 0x01, b'a', // topic name
+        0x04, // property length
+        0x0b, 0x01, // subscription identifier 1
+        0x0b, 0x02, // subscription identifier 2
+        b'h', b'i', // payload
+    ];
+    let err = ErrorV5::DuplicatedProperty(PropertyId::SubscriptionIdentifier);
+    assert_eq!(Packet::decode(data), Err(err));
+
+    let mut publish = Publish::new(
+        QosPid::Level0,
+        TopicName::try_from("a").unwrap(),
+        Bytes::from_static(b"hi"),
+    );
+    publish.properties.subscription_id = Some(VarByteInt::try_from(1).unwrap());
+    let expected = Packet::from(publish);
+    assert_eq!(
+        Packet::decode_from_slice_with(data, &lenient),
+        Ok(Some((expected, data.len())))
+    );
+    assert_eq!(report.count(Leniency::DuplicatedProperty), 4);
+}
@@ -0,0 +1,202 @@
+use alloc::vec::Vec;
+
+use bytes::Bytes;
+
+use crate::v5::*;
+use crate::*;
+
+fn encode(packets: &[Packet]) -> Vec<u8> {
+    packets
+        .iter()
+        .flat_map(|packet| packet.encode().unwrap().as_ref().to_vec())
+        .collect()
+}
+
+fn values(fields: &[Field]) -> Vec<(&'static str, &str)> {
+    fields
+        .iter()
+        .map(|field| (field.name, field.value.as_str()))
+        .collect()
+}
+
+#[test]
+fn test_dissect_packets() {
+    let mut connect = Connect::new("client".into(), 30);
+    connect.properties.session_expiry_interval = Some(60);
+    connect.properties.user_properties.push(UserProperty {
+        name: "k".into(),
+        value: "v".into(),
+    });
+    connect.last_will = Some(LastWill {
+        qos: QoS::Level0,
+        retain: false,
+        topic_name: TopicName::try_from("will").unwrap(),
+        payload: Bytes::from_static(b"bye"),
+        properties: WillProperties {
+            delay_interval: Some(5),
+            ..Default::default()
+        },
+    });
+    let mut publish = Publish::new(
+        QosPid::Level1(Pid::try_from(7).unwrap()),
+        TopicName::try_from("a/b").unwrap(),
+        Bytes::from_static(b"hello world, this is long"),
+    );
+    publish.properties.topic_alias = Some(2);
+    let subscribe = Subscribe::new(
+        Pid::try_from(1).unwrap(),
+        alloc::vec![(
+            TopicFilter::try_from("a/#").unwrap(),
+            SubscriptionOptions {
+                max_qos: QoS::Level2,
+                no_local: true,
+                retain_as_published: false,
+                retain_handling: RetainHandling::SendAtSubscribe,
+            }
+        )],
+    );
+    let packets = [
+        connect.into(),
+        publish.into(),
+        Puback::new(Pid::try_from(7).unwrap(), PubackReasonCode::Success).into(),
+        subscribe.into(),
+        Disconnect::new(DisconnectReasonCode::NormalDisconnect).into(),
+    ];
+    let data = encode(&packets);
+    let dissection = dissect(&data);
+    assert_eq!(dissection.error, None);
+    let mut offset = 0;
+    for (field, packet) in dissection.packets.iter().zip(&packets) {
+        assert_eq!(field.offset, offset);
+        assert_eq!(field.len, packet.encode_len().unwrap());
+        offset += field.len;
+    }
+    assert_eq!(offset, data.len());
+
+    let connect = &dissection.packets[0];
+    let names: Vec<_> = connect.children.iter().map(|field| field.name).collect();
+    assert_eq!(
+        names,
+        [
+            "Fixed header",
+            "Protocol name",
+            "Protocol level",
+            "Connect flags",
+            "Keep alive",
+            "Properties",
+            "Client identifier",
+            "Will properties",
+            "Will topic",
+            "Will payload",
+        ]
+    );
+    assert_eq!(connect.children[2].value, "5 (v5.0)");
+    let properties = &connect.children[5];
+    assert_eq!(
+        values(&properties.children),
+        [
+            ("Length", "12"),
+            ("Property", "SessionExpiryInterval"),
+            ("Property", "UserProperty"),
+        ]
+    );
+    assert_eq!(
+        values(&properties.children[2].children),
+        [
+            ("Identifier", "38 (UserProperty)"),
+            ("Name", "\"k\""),
+            ("Value", "\"v\""),
+        ]
+    );
+    assert_eq!(
+        values(&connect.children[7].children[1].children),
+        [("Identifier", "24 (WillDelayInterval)"), ("Value", "5")]
+    );
+
+    let publish = &dissection.packets[1];
+    assert_eq!(
+        publish.children[4].value,
+        "25 bytes [68 65 6c 6c 6f 20 77 6f 72 6c 64 2c 20 74 68 69 ..] \"hello world, thi\"..",
+    );
+    assert_eq!(
+        values(&dissection.packets[2].children[1..]),
+        [("Packet identifier", "7")]
+    );
+    let subscription = dissection.find("Subscription").unwrap();
+    assert_eq!(subscription.value, "\"a/#\"");
+    assert_eq!(
+        values(&subscription.children[1].children),
+        [
+            ("QoS", "2"),
+            ("No local", "1"),
+            ("Retain as published", "0"),
+            ("Retain handling", "0"),
+        ]
+    );
+    // Normal disconnect without properties is encoded as the fixed header only
+    assert_eq!(dissection.packets[4].children.len(), 1);
+
+    let dissection = dissect(&[0xe0, 0x02, 0x8e, 0x00]);
+    assert_eq!(dissection.error, None);
+    assert_eq!(
+        dissection.find("Reason code").unwrap().value,
+        "142 (SessionTakenOver)"
+    );
+}
+
+#[test]
+fn test_dissect_malformed() {
+    let cases: &[(&[u8], usize, &str)] = &[
+        (&[0x00, 0x00], 0, "invalid packet type 0"),
+        (
+            &[0x60, 0x02, 0, 1],
+            0,
+            "invalid flags 0x0 of PUBREL, expected 0x2",
+        ),
+        (
+            &[0x40, 0x03, 0, 1, 0x01],
+            4,
+            "invalid reason code `1` for packet `Puback`",
+        ),
+        (
+            &[0x40, 0x05, 0, 1, 0, 1, 0x00],
+            6,
+            "invalid property identifier: `0`",
+        ),
+        (
+            &[0x40, 0x06, 0, 1, 0, 2, 0x23, 0],
+            7,
+            "field of 2 bytes exceeds the property length by 1 bytes",
+        ),
+        (
+            &[0x40, 0x05, 0, 1, 0, 3, 0x23],
+            6,
+            "property length exceeds the remaining length by 2 bytes",
+        ),
+        (
+            &[0x82, 0x06, 0, 1, 0, 0, 1, b'a'],
+            8,
+            "field of 1 bytes exceeds the remaining length by 1 bytes",
+        ),
+        (
+            &[0x82, 0x07, 0, 1, 0, 0, 1, b'a', 0x30],
+            8,
+            "invalid subscription option: `48`",
+        ),
+        (&[0x82, 0x03, 0, 1, 0], 5, "empty subscription"),
+        (
+            &[0xe0, 0x01, 0x05],
+            2,
+            "invalid reason code `5` for packet `Disconnect`",
+        ),
+        (&[0xf0, 0x00, 0xe0, 0x00, 0x00], 4, "invalid packet type 0"),
+    ];
+    for (data, offset, message) in cases {
+        let err = dissect(data).error.unwrap_or_else(|| panic!("{data:02x?}"));
+        assert_eq!(
+            (err.offset, err.message.as_str()),
+            (*offset, *message),
+            "{data:02x?}"
+        );
+    }
+}
@@ -17,7 +17,7 @@ fn assert_encode(pkt: Packet, len: usize) {
     assert_eq!(pkt, decoded_pkt);
 
     let mut data = &data_async[..];
-    let mut buffer = MockBuffer::default();
+    let mut buffer = BufferPool::default();
     let (total, buf, polled_pkt) = block_on(PollPacket::new(
         &mut Default::default(),
         &mut data,
@@ -767,3 +767,27 @@ fn test_v5_encode_unsuback() {
     .sum();
     assert_encode(packet.into(), len);
 }
+
+#[test]
+fn test_v5_encode_streaming_publish() {
+    let payload = alloc::vec![7u8; 200];
+    let mut publish = Publish::new(
+        QosPid::Level1(Pid::try_from(2).unwrap()),
+        TopicName::try_from("big").unwrap(),
+        Bytes::new(),
+    );
+    publish.properties.content_type = Some("bin".into());
+
+    let mut streamed = Vec::new();
+    block_on(publish.encode_streaming_async(200, &mut payload.as_slice(), &mut streamed)).unwrap();
+    publish.payload = Bytes::from(payload.clone());
+    assert_eq!(
+        streamed,
+        Packet::from(publish.clone()).encode().unwrap().as_ref()
+    );
+
+    let err =
+        block_on(publish.encode_streaming_async(201, &mut payload.as_slice(), &mut Vec::new()))
+            .unwrap_err();
+    assert_eq!(err, ErrorV5::Common(Error::PayloadLengthMismatch(201)));
+}
@@ -0,0 +1,90 @@
+use alloc::vec::Vec;
+
+use bytes::Bytes;
+
+use crate::v5::*;
+use crate::*;
+
+fn assert_serde(pkt: Packet) {
+    let json = serde_json::to_string(&pkt).unwrap();
+    let decoded: Packet = serde_json::from_str(&json).unwrap();
+    assert_eq!(pkt, decoded, "{json}");
+}
+
+#[test]
+fn test_v5_serde_roundtrip() {
+    let user_properties = alloc::vec![UserProperty {
+        name: "k".into(),
+        value: "v".into(),
+    }];
+
+    let mut connect = Connect::new("sample".into(), 120);
+    connect.properties.session_expiry_interval = Some(60);
+    connect.properties.auth_data = Some(Bytes::from_static(b"\x00\x01"));
+    connect.properties.user_properties = user_properties.clone();
+    let mut last_will = LastWill::new(
+        QoS::Level1,
+        TopicName::try_from("will").unwrap(),
+        Bytes::from_static(b"bye"),
+    );
+    last_will.properties.delay_interval = Some(10);
+    last_will.properties.response_topic = Some(TopicName::try_from("reply").unwrap());
+    connect.last_will = Some(last_will);
+
+    let mut connack = Connack::new(false, ConnectReasonCode::Success);
+    connack.properties.max_qos = Some(QoS::Level1);
+    connack.properties.assigned_client_id = Some("assigned".into());
+
+    let pid = Pid::try_from(10).unwrap();
+    let mut publish = Publish::new(
+        QosPid::Level1(pid),
+        TopicName::try_from("a/b").unwrap(),
+        Bytes::from_static(b"payload"),
+    );
+    publish.properties.subscription_id = Some(VarByteInt::try_from(3344).unwrap());
+    publish.properties.correlation_data = Some(Bytes::from_static(b"id"));
+    publish.properties.user_properties = user_properties.clone();
+
+    let mut subscribe = Subscribe::new(
+        pid,
+        alloc::vec![(
+            TopicFilter::try_from("a/+").unwrap(),
+            SubscriptionOptions::new(QoS::Level2),
+        )],
+    );
+    subscribe.properties.subscription_id = Some(VarByteInt::try_from(1).unwrap());
+
+    let mut disconnect = Disconnect::new(DisconnectReasonCode::ServerMoved);
+    disconnect.properties.server_reference = Some("other".into());
+
+    let packets: Vec<Packet> = alloc::vec![
+        connect.into(),
+        connack.into(),
+        publish.into(),
+        Puback::new(pid, PubackReasonCode::NoMatchingSubscribers).into(),
+        Pubrec::new_success(pid).into(),
+        Pubrel::new_success(pid).into(),
+        Pubcomp::new_success(pid).into(),
+        subscribe.into(),
+        Suback::new(pid, alloc::vec![SubscribeReasonCode::GrantedQoS1]).into(),
+        Unsubscribe::new(pid, alloc::vec![TopicFilter::try_from("a/#").unwrap()]).into(),
+        Unsuback::new(pid, alloc::vec![UnsubscribeReasonCode::Success]).into(),
+        Packet::Pingreq,
+        Packet::Pingresp,
+        disconnect.into(),
+        Auth::new_success().into(),
+    ];
+    for pkt in packets {
+        assert_serde(pkt);
+    }
+}
+
+#[test]
+fn test_v5_serde_invalid_values() {
+    assert!(serde_json::from_str::<VarByteInt>("268435456").is_err());
+    assert_eq!(
+        serde_json::from_str::<VarByteInt>("268435455").unwrap(),
+        VarByteInt::try_from(268435455).unwrap()
+    );
+    assert!(serde_json::from_str::<UserProperty>(r#"{"name":"k"}"#).is_err());
+}
@@ -6,10 +6,11 @@ use bytes::Bytes;
 
 use crate::{
     read_bytes, read_bytes_async, read_string, read_string_async, read_u16, read_u16_async,
-    read_u32, read_u32_async, read_u8, read_u8_async, AsyncRead, Error, TopicName,
+    read_u32, read_u32_async, read_u8, read_u8_async, AsyncRead, DecodeOptions, Error, Leniency,
+    TopicName,
 };
 
-use super::ErrorV5;
+use super::{ErrorV5, PacketType};
 
 /// [Property identifier](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901027)
 ///
@@ -47,6 +48,8 @@ use super::ErrorV5;
 /// |  42 | 0x2A | Shared Subscription Available     | Byte                  | CONNACK                                         |
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum PropertyId {
     PayloadFormatIndicator = 0x01,
     MessageExpiryInterval = 0x02,
@@ -319,6 +322,7 @@ impl PropertyValue {
 /// User Property is a UTF-8 String Pair.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UserProperty {
     /// The name of the user property.
     pub name: Arc<str>,
@@ -328,6 +332,11 @@ pub struct UserProperty {
 
 /// Variable Byte Integer
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u32", into = "u32")
+)]
 pub struct VarByteInt(u32);
 
 #[cfg(feature = "arbitrary")]
@@ -344,6 +353,12 @@ impl VarByteInt {
     }
 }
 
+impl From<VarByteInt> for u32 {
+    fn from(value: VarByteInt) -> u32 {
+        value.0
+    }
+}
+
 impl TryFrom<u32> for VarByteInt {
     type Error = ErrorV5;
     fn try_from(value: u32) -> Result<Self, ErrorV5> {
@@ -356,6 +371,22 @@ impl TryFrom<u32> for VarByteInt {
 }
 
 macro_rules! decode_property {
+    (SubscriptionIdentifier, $properties:expr, $buf:expr, $offset:expr, $property_id:expr, $options:expr, $len:expr) => {
+        if $properties.subscription_id.is_some() {
+            return Err(crate::v5::ErrorV5::DuplicatedProperty($property_id));
+        }
+        let (value, bytes) = crate::decode_var_int($buf, $offset)?;
+        if $options.strict_var_int(true) {
+            crate::check_var_int(value, bytes)?;
+        }
+        $properties.subscription_id = Some(crate::v5::VarByteInt::try_from(value)?);
+        // `encode_property_len!` only counts the minimal encoding
+        $len += bytes - crate::var_int_len(value as usize)?;
+    };
+    ($t:ident, $properties:expr, $buf:expr, $offset:expr, $property_id:expr, $options:expr, $len:expr) => {
+        crate::v5::decode_property!($t, $properties, $buf, $offset, $property_id);
+        crate::v5::check_string_property!($t, $properties, $options);
+    };
     (PayloadFormatIndicator, $properties:expr, $buf:expr, $offset:expr, $property_id:expr) => {
         crate::v5::PropertyValue::decode_bool(
             $buf,
@@ -579,6 +610,22 @@ macro_rules! decode_property {
 }
 
 macro_rules! decode_property_async {
+    (SubscriptionIdentifier, $properties:expr, $reader:expr, $property_id:expr, $options:expr, $len:expr) => {
+        if $properties.subscription_id.is_some() {
+            return Err(crate::v5::ErrorV5::DuplicatedProperty($property_id));
+        }
+        let (value, bytes) = crate::decode_var_int_async($reader).await?;
+        if $options.strict_var_int(true) {
+            crate::check_var_int(value, bytes)?;
+        }
+        $properties.subscription_id = Some(crate::v5::VarByteInt::try_from(value)?);
+        // `encode_property_len!` only counts the minimal encoding
+        $len += bytes - crate::var_int_len(value as usize)?;
+    };
+    ($t:ident, $properties:expr, $reader:expr, $property_id:expr, $options:expr, $len:expr) => {
+        crate::v5::decode_property_async!($t, $properties, $reader, $property_id);
+        crate::v5::check_string_property!($t, $properties, $options);
+    };
     (PayloadFormatIndicator, $properties:expr, $reader:expr, $property_id:expr) => {
         crate::v5::PropertyValue::decode_bool_async(
             $reader,
@@ -801,22 +848,91 @@ macro_rules! decode_property_async {
     };
 }
 
+// Check a decoded string property against the string policy of the options.
+macro_rules! check_string_property {
+    (ContentType, $properties:expr, $options:expr) => {
+        crate::v5::check_string_property!($properties.content_type, $options);
+    };
+    (ResponseTopic, $properties:expr, $options:expr) => {
+        crate::v5::check_string_property!($properties.response_topic, $options);
+    };
+    (AssignedClientIdentifier, $properties:expr, $options:expr) => {
+        crate::v5::check_string_property!($properties.assigned_client_id, $options);
+    };
+    (AuthenticationMethod, $properties:expr, $options:expr) => {
+        crate::v5::check_string_property!($properties.auth_method, $options);
+    };
+    (ResponseInformation, $properties:expr, $options:expr) => {
+        crate::v5::check_string_property!($properties.response_info, $options);
+    };
+    (ServerReference, $properties:expr, $options:expr) => {
+        crate::v5::check_string_property!($properties.server_reference, $options);
+    };
+    (ReasonString, $properties:expr, $options:expr) => {
+        crate::v5::check_string_property!($properties.reason_string, $options);
+    };
+    ($t:ident, $properties:expr, $options:expr) => {};
+    ($value:expr, $options:expr) => {
+        if let Some(value) = &$value {
+            $options.check_string(value)?;
+        }
+    };
+}
+
+/// Whether a repeated `property_id` is accepted, the first value is kept.
+pub(crate) fn tolerate_duplicate(
+    packet_type: PacketType,
+    property_id: PropertyId,
+    options: &DecodeOptions,
+) -> bool {
+    if packet_type == PacketType::Publish && property_id == PropertyId::SubscriptionIdentifier {
+        // A PUBLISH may carry multiple Subscription Identifiers [3.3.2.3.8],
+        // only the first is kept but the others are not a violation
+        options.lenient.allows(Leniency::DuplicatedProperty)
+    } else {
+        options.tolerate(Leniency::DuplicatedProperty)
+    }
+}
+
+// Decode a property list, evaluates to its encoded length which is longer
+// than `encode_len` for non-minimal variable byte integers.
 macro_rules! decode_properties {
-    (LastWill, $properties:expr, $buf:expr, $offset:expr, $($t:ident,)*) => {
-        let (property_len, _bytes) = crate::decode_var_int($buf, $offset)?;
+    (LastWill, $options:expr, $properties:expr, $buf:expr, $offset:expr, $($t:ident,)*) => {{
+        let (property_len, bytes) = crate::decode_var_int($buf, $offset)?;
+        if $options.strict_var_int(true) {
+            crate::check_var_int(property_len, bytes)?;
+        }
         let mut len = 0;
+        // Property ids already decoded, the list of some packets is empty
+        #[allow(unused_mut, unused_variables)]
+        let mut seen = 0u64;
         while property_len as usize > len {
             let property_id = crate::v5::PropertyId::from_u8(crate::read_u8($buf, $offset)?)?;
             match property_id {
                 $(
                     crate::v5::PropertyId::$t => {
-                        crate::v5::decode_property!($t, $properties, $buf, $offset, property_id);
+                        let bit = 1u64 << property_id as u8;
+                        let first = if seen & bit != 0
+                            && $options.tolerate(crate::Leniency::DuplicatedProperty)
+                        {
+                            Some(core::mem::take(&mut $properties))
+                        } else {
+                            None
+                        };
+                        seen |= bit;
+                        crate::v5::decode_property!($t, $properties, $buf, $offset, property_id, $options, len);
                         crate::v5::encode_property_len!($t, $properties, len);
+                        // The duplicate is dropped, the first value is kept
+                        if let Some(first) = first {
+                            $properties = first;
+                        }
                     }
                 )*
                 crate::v5::PropertyId::UserProperty => {
                     crate::v5::decode_property!(UserProperty, $properties, $buf, $offset, property_id);
                     let last = $properties.user_properties.last().expect("user property exists");
+                    $options.check_string(&last.name)?;
+                    $options.check_string(&last.value)?;
                     len += 1 + 4 + last.name.len() + last.value.len();
                 }
                 _ => return Err(crate::v5::ErrorV5::InvalidWillProperty(property_id)),
@@ -825,22 +941,45 @@ macro_rules! decode_properties {
         if property_len as usize != len {
             return Err(crate::v5::ErrorV5::InvalidPropertyLength(property_len));
         }
-    };
-    ($packet_type:expr, $properties:expr, $buf:expr, $offset:expr, $($t:ident,)*) => {
-        let (property_len, _bytes) = crate::decode_var_int($buf, $offset)?;
+        let encoded_len = bytes + property_len as usize;
+        encoded_len
+    }};
+    ($packet_type:expr, $options:expr, $properties:expr, $buf:expr, $offset:expr, $($t:ident,)*) => {{
+        let (property_len, bytes) = crate::decode_var_int($buf, $offset)?;
+        if $options.strict_var_int(true) {
+            crate::check_var_int(property_len, bytes)?;
+        }
         let mut len = 0;
+        // Property ids already decoded, the list of some packets is empty
+        #[allow(unused_mut, unused_variables)]
+        let mut seen = 0u64;
         while property_len as usize > len {
             let property_id = crate::v5::PropertyId::from_u8(crate::read_u8($buf, $offset)?)?;
             match property_id {
                 $(
                     crate::v5::PropertyId::$t => {
-                        crate::v5::decode_property!($t, $properties, $buf, $offset, property_id);
+                        let bit = 1u64 << property_id as u8;
+                        let first = if seen & bit != 0
+                            && crate::v5::tolerate_duplicate($packet_type, property_id, $options)
+                        {
+                            Some(core::mem::take(&mut $properties))
+                        } else {
+                            None
+                        };
+                        seen |= bit;
+                        crate::v5::decode_property!($t, $properties, $buf, $offset, property_id, $options, len);
                         crate::v5::encode_property_len!($t, $properties, len);
+                        // The duplicate is dropped, the first value is kept
+                        if let Some(first) = first {
+                            $properties = first;
+                        }
                     }
                 )*
                 crate::v5::PropertyId::UserProperty => {
                     crate::v5::decode_property!(UserProperty, $properties, $buf, $offset, property_id);
                     let last = $properties.user_properties.last().expect("user property exists");
+                    $options.check_string(&last.name)?;
+                    $options.check_string(&last.value)?;
                     len += 1 + 4 + last.name.len() + last.value.len();
                 }
                 _ => return Err(crate::v5::ErrorV5::InvalidProperty($packet_type, property_id)),
@@ -849,25 +988,48 @@ macro_rules! decode_properties {
         if property_len as usize != len {
             return Err(crate::v5::ErrorV5::InvalidPropertyLength(property_len));
         }
-    };
+        let encoded_len = bytes + property_len as usize;
+        encoded_len
+    }};
 }
 
 macro_rules! decode_properties_async {
-    (LastWill, $properties:expr, $reader:expr, $($t:ident,)*) => {
-        let (property_len, _bytes) = crate::decode_var_int_async($reader).await?;
+    (LastWill, $options:expr, $properties:expr, $reader:expr, $($t:ident,)*) => {{
+        let (property_len, bytes) = crate::decode_var_int_async($reader).await?;
+        if $options.strict_var_int(true) {
+            crate::check_var_int(property_len, bytes)?;
+        }
         let mut len = 0;
+        // Property ids already decoded, the list of some packets is empty
+        #[allow(unused_mut, unused_variables)]
+        let mut seen = 0u64;
         while property_len as usize > len {
             let property_id = crate::v5::PropertyId::from_u8(crate::read_u8_async($reader).await?)?;
             match property_id {
                 $(
                     crate::v5::PropertyId::$t => {
-                        crate::v5::decode_property_async!($t, $properties, $reader, property_id);
+                        let bit = 1u64 << property_id as u8;
+                        let first = if seen & bit != 0
+                            && $options.tolerate(crate::Leniency::DuplicatedProperty)
+                        {
+                            Some(core::mem::take(&mut $properties))
+                        } else {
+                            None
+                        };
+                        seen |= bit;
+                        crate::v5::decode_property_async!($t, $properties, $reader, property_id, $options, len);
                         crate::v5::encode_property_len!($t, $properties, len);
+                        // The duplicate is dropped, the first value is kept
+                        if let Some(first) = first {
+                            $properties = first;
+                        }
                     }
                 )*
                     crate::v5::PropertyId::UserProperty => {
                         crate::v5::decode_property_async!(UserProperty, $properties, $reader, property_id);
                         let last = $properties.user_properties.last().expect("user property exists");
+                        $options.check_string(&last.name)?;
+                        $options.check_string(&last.value)?;
                         len += 1 + 4 + last.name.len() + last.value.len();
                     }
                     _ => return Err(crate::v5::ErrorV5::InvalidWillProperty(property_id)),
@@ -876,22 +1038,45 @@ macro_rules! decode_properties_async {
         if property_len as usize != len {
             return Err(crate::v5::ErrorV5::InvalidPropertyLength(property_len));
         }
-    };
-    ($packet_type:expr, $properties:expr, $reader:expr, $($t:ident,)*) => {
-        let (property_len, _bytes) = crate::decode_var_int_async($reader).await?;
+        let encoded_len = bytes + property_len as usize;
+        encoded_len
+    }};
+    ($packet_type:expr, $options:expr, $properties:expr, $reader:expr, $($t:ident,)*) => {{
+        let (property_len, bytes) = crate::decode_var_int_async($reader).await?;
+        if $options.strict_var_int(true) {
+            crate::check_var_int(property_len, bytes)?;
+        }
         let mut len = 0;
+        // Property ids already decoded, the list of some packets is empty
+        #[allow(unused_mut, unused_variables)]
+        let mut seen = 0u64;
         while property_len as usize > len {
             let property_id = crate::v5::PropertyId::from_u8(crate::read_u8_async($reader).await?)?;
             match property_id {
                 $(
                     crate::v5::PropertyId::$t => {
-                        crate::v5::decode_property_async!($t, $properties, $reader, property_id);
+                        let bit = 1u64 << property_id as u8;
+                        let first = if seen & bit != 0
+                            && crate::v5::tolerate_duplicate($packet_type, property_id, $options)
+                        {
+                            Some(core::mem::take(&mut $properties))
+                        } else {
+                            None
+                        };
+                        seen |= bit;
+                        crate::v5::decode_property_async!($t, $properties, $reader, property_id, $options, len);
                         crate::v5::encode_property_len!($t, $properties, len);
+                        // The duplicate is dropped, the first value is kept
+                        if let Some(first) = first {
+                            $properties = first;
+                        }
                     }
                 )*
                     crate::v5::PropertyId::UserProperty => {
                         crate::v5::decode_property_async!(UserProperty, $properties, $reader, property_id);
                         let last = $properties.user_properties.last().expect("user property exists");
+                        $options.check_string(&last.name)?;
+                        $options.check_string(&last.value)?;
                         len += 1 + 4 + last.name.len() + last.value.len();
                     }
                 _ => return Err(crate::v5::ErrorV5::InvalidProperty($packet_type, property_id)),
@@ -900,9 +1085,12 @@ macro_rules! decode_properties_async {
         if property_len as usize != len {
             return Err(crate::v5::ErrorV5::InvalidPropertyLength(property_len));
         }
-    };
+        let encoded_len = bytes + property_len as usize;
+        encoded_len
+    }};
 }
 
+pub(crate) use check_string_property;
 pub(crate) use decode_properties;
 pub(crate) use decode_properties_async;
 pub(crate) use decode_property;
@@ -0,0 +1,310 @@
+use core::ops::Add;
+use core::time::Duration;
+
+use alloc::collections::BTreeMap;
+
+use crate::{ClientId, Pid, QoS, QosPid};
+
+use super::{LastWill, Publish, PublishProperties, WillProperties};
+
+/// Scheduler for delayed [will messages].
+///
+/// A will message is published once the [Will Delay Interval] has passed or
+/// the session ends, whichever happens first. Reconnecting with the same
+/// client identifier before that instant cancels the will.
+///
+/// The scheduler does not read any clock itself, the caller passes the current
+/// instant to every method. `T` can be `std::time::Instant`,
+/// `embassy_time::Instant`, or a plain [`Duration`] measured from any fixed
+/// origin.
+///
+/// [will messages]: https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901060
+/// [Will Delay Interval]: https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901062
+#[derive(Debug, Clone)]
+pub struct WillScheduler<T> {
+    // Ordered by (publish instant, insertion sequence)
+    queue: BTreeMap<(T, u64), ClientId>,
+    wills: BTreeMap<ClientId, ScheduledWill<T>>,
+    next_seq: u64,
+}
+
+#[derive(Debug, Clone)]
+struct ScheduledWill<T> {
+    publish_at: T,
+    seq: u64,
+    will: LastWill,
+}
+
+impl<T> Default for WillScheduler<T> {
+    fn default() -> Self {
+        WillScheduler {
+            queue: BTreeMap::new(),
+            wills: BTreeMap::new(),
+            next_seq: 0,
+        }
+    }
+}
+
+impl<T> WillScheduler<T>
+where
+    T: Ord + Copy + Add<Duration, Output = T>,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Schedule the will message of a disconnected client, return the instant
+    /// when it should be published.
+    ///
+    /// `session_expiry_interval` is the value in effect at disconnect time
+    /// (from CONNECT, possibly overridden by DISCONNECT). An absent value
+    /// means the session ends together with the network connection. A will
+    /// already scheduled for the same client is replaced.
+    pub fn schedule(
+        &mut self,
+        client_id: ClientId,
+        will: impl Into<LastWill>,
+        session_expiry_interval: Option<u32>,
+        now: T,
+    ) -> T {
+        let will = will.into();
+        let delay = effective_delay(&will.properties, session_expiry_interval);
+        let publish_at = now + Duration::from_secs(u64::from(delay));
+
+        self.cancel(&client_id);
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.queue.insert((publish_at, seq), client_id.clone());
+        self.wills.insert(
+            client_id,
+            ScheduledWill {
+                publish_at,
+                seq,
+                will,
+            },
+        );
+        publish_at
+    }
+
+    /// Cancel the pending will of a client (for example when it reconnects),
+    /// return the will message if there was one.
+    pub fn cancel(&mut self, client_id: &str) -> Option<LastWill> {
+        let scheduled = self.wills.remove(client_id)?;
+        self.queue.remove(&(scheduled.publish_at, scheduled.seq));
+        Some(scheduled.will)
+    }
+
+    /// The instant when the will of a client is going to be published.
+    pub fn publish_at(&self, client_id: &str) -> Option<T> {
+        self.wills
+            .get(client_id)
+            .map(|scheduled| scheduled.publish_at)
+    }
+
+    /// The earliest instant a will message is due, useful to arm a timer.
+    pub fn next_deadline(&self) -> Option<T> {
+        self.queue.keys().next().map(|(publish_at, _)| *publish_at)
+    }
+
+    /// Remove and return the earliest will message that is due at `now`.
+    pub fn pop_due(&mut self, now: T) -> Option<(ClientId, Publish)> {
+        let (publish_at, seq) = *self.queue.keys().next()?;
+        if publish_at > now {
+            return None;
+        }
+        let client_id = self.queue.remove(&(publish_at, seq))?;
+        let scheduled = self.wills.remove(&client_id)?;
+        Some((client_id, scheduled.will.into()))
+    }
+
+    /// Number of pending will messages.
+    pub fn len(&self) -> usize {
+        self.wills.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.wills.is_empty()
+    }
+}
+
+/// The will is published after the delay interval or when the session ends,
+/// whichever happens first.
+#[inline]
+fn effective_delay(properties: &WillProperties, session_expiry_interval: Option<u32>) -> u32 {
+    let will_delay = properties.delay_interval.unwrap_or(0);
+    let session_expiry = session_expiry_interval.unwrap_or(0);
+    will_delay.min(session_expiry)
+}
+
+impl From<WillProperties> for PublishProperties {
+    fn from(properties: WillProperties) -> Self {
+        PublishProperties {
+            payload_is_utf8: properties.payload_is_utf8,
+            message_expiry_interval: properties.message_expiry_interval,
+            topic_alias: None,
+            response_topic: properties.response_topic,
+            correlation_data: properties.correlation_data,
+            user_properties: properties.user_properties,
+            subscription_id: None,
+            content_type: properties.content_type,
+        }
+    }
+}
+
+/// Convert the will message into a PUBLISH packet ready to route.
+///
+/// The packet identifier is a placeholder, it must be assigned per receiver
+/// when the message is forwarded with QoS > 0.
+impl From<LastWill> for Publish {
+    fn from(will: LastWill) -> Self {
+        let qos_pid = match will.qos {
+            QoS::Level0 => QosPid::Level0,
+            QoS::Level1 => QosPid::Level1(Pid::default()),
+            QoS::Level2 => QosPid::Level2(Pid::default()),
+        };
+        Publish {
+            dup: false,
+            retain: will.retain,
+            qos_pid,
+            topic_name: will.topic_name,
+            payload: will.payload,
+            properties: will.properties.into(),
+        }
+    }
+}
+
+/// A v3.x will has no properties, it is published as soon as the client
+/// disconnects.
+#[cfg(feature = "v3")]
+impl From<crate::v3::LastWill> for LastWill {
+    fn from(will: crate::v3::LastWill) -> Self {
+        LastWill {
+            qos: will.qos,
+            retain: will.retain,
+            topic_name: will.topic_name,
+            payload: will.message,
+            properties: WillProperties::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::TryFrom;
+
+    use bytes::Bytes;
+
+    use crate::TopicName;
+
+    use super::*;
+
+    fn will(delay_interval: Option<u32>) -> LastWill {
+        let mut will = LastWill::new(
+            QoS::Level1,
+            TopicName::try_from("will/topic").unwrap(),
+            Bytes::from_static(b"gone"),
+        );
+        will.properties.delay_interval = delay_interval;
+        will
+    }
+
+    fn secs(n: u64) -> Duration {
+        Duration::from_secs(n)
+    }
+
+    #[test]
+    fn test_will_effective_delay() {
+        for (delay, session_expiry, expected) in [
+            (None, None, 0),
+            (Some(30), None, 0),
+            (None, Some(60), 0),
+            (Some(30), Some(60), 30),
+            (Some(90), Some(60), 60),
+            (Some(30), Some(u32::MAX), 30),
+        ] {
+            let mut scheduler = WillScheduler::new();
+            let at = scheduler.schedule("c1".into(), will(delay), session_expiry, secs(100));
+            assert_eq!(at, secs(100 + expected), "{delay:?} {session_expiry:?}");
+        }
+    }
+
+    #[test]
+    fn test_will_pop_due_and_cancel() {
+        let mut scheduler = WillScheduler::new();
+        scheduler.schedule("c1".into(), will(Some(20)), Some(100), secs(0));
+        scheduler.schedule("c2".into(), will(Some(10)), Some(100), secs(0));
+        scheduler.schedule("c3".into(), will(Some(30)), Some(100), secs(0));
+        assert_eq!(scheduler.len(), 3);
+        assert_eq!(scheduler.next_deadline(), Some(secs(10)));
+
+        // c3 reconnects before the delay
+        assert!(scheduler.cancel("c3").is_some());
+        assert!(scheduler.cancel("c3").is_none());
+
+        assert!(scheduler.pop_due(secs(9)).is_none());
+        let (client_id, publish) = scheduler.pop_due(secs(25)).unwrap();
+        assert_eq!(&*client_id, "c2");
+        assert_eq!(publish.qos_pid, QosPid::Level1(Pid::default()));
+        assert_eq!(&*publish.topic_name, "will/topic");
+        let (client_id, _) = scheduler.pop_due(secs(25)).unwrap();
+        assert_eq!(&*client_id, "c1");
+        assert!(scheduler.pop_due(secs(1000)).is_none());
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_will_reschedule_replaces() {
+        let mut scheduler = WillScheduler::new();
+        scheduler.schedule("c1".into(), will(Some(10)), Some(100), secs(0));
+        scheduler.schedule("c1".into(), will(Some(50)), Some(100), secs(0));
+        assert_eq!(scheduler.len(), 1);
+        assert_eq!(scheduler.publish_at("c1"), Some(secs(50)));
+        assert!(scheduler.pop_due(secs(10)).is_none());
+    }
+
+    #[test]
+    fn test_will_into_publish() {
+        let mut will = will(Some(10));
+        will.retain = true;
+        will.properties.payload_is_utf8 = Some(true);
+        will.properties.message_expiry_interval = Some(3600);
+        will.properties.content_type = Some("text/plain".into());
+        will.properties.response_topic = Some(TopicName::try_from("reply").unwrap());
+        will.properties.correlation_data = Some(Bytes::from_static(b"id"));
+        let publish = Publish::from(will.clone());
+        assert!(publish.retain);
+        assert!(!publish.dup);
+        assert_eq!(publish.payload, will.payload);
+        assert_eq!(publish.properties.payload_is_utf8, Some(true));
+        assert_eq!(publish.properties.message_expiry_interval, Some(3600));
+        assert_eq!(
+            publish.properties.content_type.as_deref(),
+            Some("text/plain")
+        );
+        assert_eq!(
+            publish.properties.response_topic,
+            will.properties.response_topic
+        );
+        assert_eq!(
+            publish.properties.correlation_data,
+            will.properties.correlation_data
+        );
+        assert_eq!(publish.properties.topic_alias, None);
+    }
+
+    #[cfg(feature = "v3")]
+    #[test]
+    fn test_will_from_v3() {
+        let v3_will = crate::v3::LastWill::new(
+            QoS::Level0,
+            TopicName::try_from("will/v3").unwrap(),
+            Bytes::from_static(b"bye"),
+        );
+        let mut scheduler = WillScheduler::new();
+        let at = scheduler.schedule("v3".into(), v3_will, None, secs(5));
+        assert_eq!(at, secs(5));
+        let (_, publish) = scheduler.pop_due(secs(5)).unwrap();
+        assert_eq!(publish.qos_pid, QosPid::Level0);
+        assert_eq!(&publish.payload[..], b"bye");
+    }
+}
@@ -0,0 +1,71 @@
+use thiserror::Error;
+
+/// Errors of the WebSocket transport.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Common error of MQTT v3 and v5, also used for io errors.
+    #[error("common error of v3/v5: {0}")]
+    Common(#[from] crate::Error),
+
+    /// The HTTP upgrade request or response is malformed or rejected.
+    #[error("invalid handshake: {0}")]
+    InvalidHandshake(&'static str),
+
+    /// The peer did not agree on the `mqtt` subprotocol.
+    #[error("subprotocol `mqtt` not negotiated")]
+    SubprotocolNotNegotiated,
+
+    /// A frame sets reserved bits, no extension is negotiated.
+    #[error("reserved bits set in frame header")]
+    ReservedBits,
+
+    /// Unknown frame opcode.
+    #[error("invalid opcode: `{0}`")]
+    InvalidOpcode(u8),
+
+    /// The most significant bit of a 64-bit payload length is set.
+    #[error("invalid payload length")]
+    InvalidPayloadLength,
+
+    /// Control frame fragmented or longer than 125 bytes.
+    #[error("invalid control frame")]
+    InvalidControlFrame,
+
+    /// Frame masking does not match the role of the peer.
+    #[error("invalid frame masking")]
+    InvalidMasking,
+
+    /// Text frame, MQTT must be carried in binary frames.
+    #[error("text frame not allowed")]
+    TextFrame,
+
+    /// Continuation frame without a started message, or a new message
+    /// before the last one is finished.
+    #[error("unexpected continuation frame")]
+    UnexpectedContinuation,
+}
+
+impl Error {
+    pub fn is_eof(&self) -> bool {
+        if let Error::Common(e) = self {
+            e.is_eof()
+        } else {
+            false
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Common(err.into())
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> std::io::Error {
+        match err {
+            Error::Common(err) => err.into(),
+            err => std::io::Error::new(std::io::ErrorKind::InvalidData, err),
+        }
+    }
+}
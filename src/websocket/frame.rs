@@ -0,0 +1,160 @@
+use super::Error;
+
+/// Longest frame header: 2 bytes, 8 bytes extended length and the mask.
+pub const MAX_HEADER_LEN: usize = 14;
+
+/// Longest payload of a control frame.
+pub const MAX_CONTROL_LEN: usize = 125;
+
+/// WebSocket frame opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl Opcode {
+    pub fn from_u8(value: u8) -> Result<Self, Error> {
+        Ok(match value {
+            0x0 => Opcode::Continuation,
+            0x1 => Opcode::Text,
+            0x2 => Opcode::Binary,
+            0x8 => Opcode::Close,
+            0x9 => Opcode::Ping,
+            0xA => Opcode::Pong,
+            _ => return Err(Error::InvalidOpcode(value)),
+        })
+    }
+
+    pub fn as_u8(self) -> u8 {
+        match self {
+            Opcode::Continuation => 0x0,
+            Opcode::Text => 0x1,
+            Opcode::Binary => 0x2,
+            Opcode::Close => 0x8,
+            Opcode::Ping => 0x9,
+            Opcode::Pong => 0xA,
+        }
+    }
+
+    pub fn is_control(self) -> bool {
+        matches!(self, Opcode::Close | Opcode::Ping | Opcode::Pong)
+    }
+}
+
+/// Header of a WebSocket frame ([RFC 6455 5.2]).
+///
+/// [RFC 6455 5.2]: https://www.rfc-editor.org/rfc/rfc6455#section-5.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub fin: bool,
+    pub opcode: Opcode,
+    pub mask: Option<[u8; 4]>,
+    pub payload_len: u64,
+}
+
+impl FrameHeader {
+    pub fn new(opcode: Opcode, mask: Option<[u8; 4]>, payload_len: u64) -> Self {
+        FrameHeader {
+            fin: true,
+            opcode,
+            mask,
+            payload_len,
+        }
+    }
+
+    /// Decode the header from the start of `buf`, return the header and its
+    /// length, or `Ok(None)` if `buf` ends before the header does.
+    pub fn decode(buf: &[u8]) -> Result<Option<(Self, usize)>, Error> {
+        if buf.len() < 2 {
+            return Ok(None);
+        }
+        let (b0, b1) = (buf[0], buf[1]);
+        if b0 & 0x70 != 0 {
+            return Err(Error::ReservedBits);
+        }
+        let fin = b0 & 0x80 != 0;
+        let opcode = Opcode::from_u8(b0 & 0x0F)?;
+        let masked = b1 & 0x80 != 0;
+
+        let mut len = 2;
+        let payload_len = match b1 & 0x7F {
+            126 => {
+                let Some(bytes) = buf.get(2..4) else {
+                    return Ok(None);
+                };
+                len += 2;
+                u64::from(u16::from_be_bytes([bytes[0], bytes[1]]))
+            }
+            127 => {
+                let Some(bytes) = buf.get(2..10) else {
+                    return Ok(None);
+                };
+                len += 8;
+                let mut value = [0u8; 8];
+                value.copy_from_slice(bytes);
+                let value = u64::from_be_bytes(value);
+                if value >> 63 != 0 {
+                    return Err(Error::InvalidPayloadLength);
+                }
+                value
+            }
+            value => u64::from(value),
+        };
+        let mask = if masked {
+            let Some(bytes) = buf.get(len..len + 4) else {
+                return Ok(None);
+            };
+            len += 4;
+            Some([bytes[0], bytes[1], bytes[2], bytes[3]])
+        } else {
+            None
+        };
+
+        if opcode.is_control() && (!fin || payload_len > MAX_CONTROL_LEN as u64) {
+            return Err(Error::InvalidControlFrame);
+        }
+        let header = FrameHeader {
+            fin,
+            opcode,
+            mask,
+            payload_len,
+        };
+        Ok(Some((header, len)))
+    }
+
+    /// Encode the header into `buf`, return the encoded length.
+    pub fn encode(&self, buf: &mut [u8; MAX_HEADER_LEN]) -> usize {
+        buf[0] = (u8::from(self.fin) << 7) | self.opcode.as_u8();
+        let mask_bit = if self.mask.is_some() { 0x80 } else { 0 };
+        let mut len = 2;
+        if self.payload_len < 126 {
+            buf[1] = mask_bit | self.payload_len as u8;
+        } else if self.payload_len <= u64::from(u16::MAX) {
+            buf[1] = mask_bit | 126;
+            buf[2..4].copy_from_slice(&(self.payload_len as u16).to_be_bytes());
+            len += 2;
+        } else {
+            buf[1] = mask_bit | 127;
+            buf[2..10].copy_from_slice(&self.payload_len.to_be_bytes());
+            len += 8;
+        }
+        if let Some(mask) = self.mask {
+            buf[len..len + 4].copy_from_slice(&mask);
+            len += 4;
+        }
+        len
+    }
+}
+
+/// Mask or unmask `data` in place, `offset` is the position of `data` in
+/// the frame payload.
+pub fn apply_mask(data: &mut [u8], mask: [u8; 4], offset: usize) {
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte ^= mask[(offset + i) & 3];
+    }
+}
@@ -0,0 +1,194 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use super::{Error, Role, WsStream};
+use crate::IoErrorKind;
+
+/// The WebSocket subprotocol of MQTT.
+pub const SUBPROTOCOL: &str = "mqtt";
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Upper bound of the HTTP request or response head.
+const MAX_HEAD_LEN: usize = 8192;
+
+const BAD_REQUEST: &[u8] =
+    b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+/// The `Sec-WebSocket-Accept` value for a `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    let mut sha1 = sha1_smol::Sha1::new();
+    sha1.update(key.as_bytes());
+    sha1.update(ACCEPT_GUID.as_bytes());
+    STANDARD.encode(sha1.digest().bytes())
+}
+
+/// Accept the WebSocket upgrade request of a client, the `mqtt`
+/// subprotocol is required.
+///
+/// A `400 Bad Request` response is sent when the request is rejected.
+pub async fn accept<S>(mut stream: S) -> Result<WsStream<S>, Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (head, leftover) = read_head(&mut stream).await?;
+    let response = match Head::parse(&head).and_then(|head| server_response(&head)) {
+        Ok(response) => response,
+        Err(err) => {
+            // The connection is dropped anyway, the response is best effort
+            let _ = stream.write_all(BAD_REQUEST).await;
+            return Err(err);
+        }
+    };
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(WsStream::with_leftover(stream, Role::Server, leftover))
+}
+
+/// Send the WebSocket upgrade request for `path` on `host` with the `mqtt`
+/// subprotocol, and check the response of the server.
+pub async fn connect<S>(mut stream: S, host: &str, path: &str) -> Result<WsStream<S>, Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut nonce = [0u8; 16];
+    getrandom::fill(&mut nonce).map_err(std::io::Error::from)?;
+    let key = STANDARD.encode(nonce);
+    let request = format!(
+        "GET {path} HTTP/1.1\r\n\
+         Host: {host}\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Key: {key}\r\n\
+         Sec-WebSocket-Version: 13\r\n\
+         Sec-WebSocket-Protocol: {SUBPROTOCOL}\r\n\r\n"
+    );
+    stream.write_all(request.as_bytes()).await?;
+    stream.flush().await?;
+
+    let (head, leftover) = read_head(&mut stream).await?;
+    let head = Head::parse(&head)?;
+    let mut status = head.first_line.split(' ');
+    if !status.next().is_some_and(|v| v.starts_with("HTTP/1.")) || status.next() != Some("101") {
+        return Err(Error::InvalidHandshake("status is not 101"));
+    }
+    check_upgrade(&head)?;
+    if head.get("sec-websocket-accept") != Some(accept_key(&key).as_str()) {
+        return Err(Error::InvalidHandshake("wrong Sec-WebSocket-Accept"));
+    }
+    if head.get("sec-websocket-protocol") != Some(SUBPROTOCOL) {
+        return Err(Error::SubprotocolNotNegotiated);
+    }
+    Ok(WsStream::with_leftover(stream, Role::Client, leftover))
+}
+
+fn server_response(head: &Head<'_>) -> Result<String, Error> {
+    let mut request = head.first_line.split(' ');
+    if request.next() != Some("GET") {
+        return Err(Error::InvalidHandshake("method is not GET"));
+    }
+    if !request.nth(1).is_some_and(|v| v.starts_with("HTTP/1.")) {
+        return Err(Error::InvalidHandshake("invalid HTTP version"));
+    }
+    check_upgrade(head)?;
+    if head.get("sec-websocket-version") != Some("13") {
+        return Err(Error::InvalidHandshake("Sec-WebSocket-Version is not 13"));
+    }
+    let key = head
+        .get("sec-websocket-key")
+        .ok_or(Error::InvalidHandshake("missing Sec-WebSocket-Key"))?;
+    if STANDARD.decode(key).map(|v| v.len()) != Ok(16) {
+        return Err(Error::InvalidHandshake("invalid Sec-WebSocket-Key"));
+    }
+    if !head.has_token("sec-websocket-protocol", SUBPROTOCOL, false) {
+        return Err(Error::SubprotocolNotNegotiated);
+    }
+    Ok(format!(
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\
+         Sec-WebSocket-Protocol: {SUBPROTOCOL}\r\n\r\n",
+        accept_key(key)
+    ))
+}
+
+fn check_upgrade(head: &Head<'_>) -> Result<(), Error> {
+    if !head.has_token("upgrade", "websocket", true) {
+        return Err(Error::InvalidHandshake("missing Upgrade: websocket"));
+    }
+    if !head.has_token("connection", "upgrade", true) {
+        return Err(Error::InvalidHandshake("missing Connection: Upgrade"));
+    }
+    Ok(())
+}
+
+/// Read the HTTP head, return it and the bytes read after it.
+async fn read_head<S: AsyncRead + Unpin>(stream: &mut S) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    loop {
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            let leftover = buf.split_off(end + 4);
+            return Ok((buf, leftover));
+        }
+        if buf.len() > MAX_HEAD_LEN {
+            return Err(Error::InvalidHandshake("head too long"));
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(crate::Error::IoError(IoErrorKind::UnexpectedEof).into());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+}
+
+/// HTTP request or response head.
+struct Head<'a> {
+    first_line: &'a str,
+    headers: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Head<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        let text =
+            core::str::from_utf8(data).map_err(|_| Error::InvalidHandshake("head is not utf-8"))?;
+        let mut lines = text.split("\r\n").filter(|line| !line.is_empty());
+        let first_line = lines.next().ok_or(Error::InvalidHandshake("empty head"))?;
+        let headers = lines
+            .map(|line| {
+                line.split_once(':')
+                    .map(|(name, value)| (name.trim(), value.trim()))
+                    .ok_or(Error::InvalidHandshake("malformed header"))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Head {
+            first_line,
+            headers,
+        })
+    }
+
+    fn get(&self, name: &str) -> Option<&'a str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+    }
+
+    /// Whether a comma separated header contains `token`.
+    fn has_token(&self, name: &str, token: &str, ignore_case: bool) -> bool {
+        self.headers
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .flat_map(|(_, value)| value.split(','))
+            .any(|value| {
+                let value = value.trim();
+                if ignore_case {
+                    value.eq_ignore_ascii_case(token)
+                } else {
+                    value == token
+                }
+            })
+    }
+}
@@ -0,0 +1,22 @@
+//! MQTT over [WebSocket]
+//!
+//! [`accept`] and [`connect`] run the HTTP upgrade with the `mqtt`
+//! subprotocol and return a [`WsStream`]. It implements the tokio
+//! `AsyncRead`/`AsyncWrite` over the payload of the binary frames, so
+//! packets may be split across or combined within frames, and works with
+//! `PollPacket` and `Packet::encode_async` like a TCP stream does.
+//!
+//! [WebSocket]: https://www.rfc-editor.org/rfc/rfc6455
+
+mod error;
+mod frame;
+mod handshake;
+mod stream;
+
+#[cfg(test)]
+mod tests;
+
+pub use error::Error;
+pub use frame::{apply_mask, FrameHeader, Opcode, MAX_CONTROL_LEN, MAX_HEADER_LEN};
+pub use handshake::{accept, accept_key, connect, SUBPROTOCOL};
+pub use stream::{Role, WsStream};
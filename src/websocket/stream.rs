@@ -0,0 +1,351 @@
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::frame::{apply_mask, FrameHeader, Opcode, MAX_HEADER_LEN};
+use super::Error;
+
+/// Bytes read from the inner stream at once while looking for a frame header.
+const READ_CHUNK: usize = 4096;
+
+/// Longest data frame sent by one `poll_write`.
+const MAX_WRITE_FRAME: usize = 64 * 1024;
+
+/// Close status code of a normal closure.
+const CLOSE_NORMAL: u16 = 1000;
+
+/// Side of the connection, a client masks the frames it sends and a server
+/// requires them masked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+/// Masks fetched from the OS random number generator at once.
+const MASK_BATCH: usize = 16;
+
+/// Masking keys for a client, taken from the OS random number generator as
+/// required by RFC 6455 section 5.3.
+#[derive(Debug)]
+struct MaskGen {
+    masks: [[u8; 4]; MASK_BATCH],
+    next: usize,
+}
+
+impl MaskGen {
+    fn new() -> Self {
+        MaskGen {
+            masks: [[0; 4]; MASK_BATCH],
+            next: MASK_BATCH,
+        }
+    }
+
+    fn next_mask(&mut self) -> io::Result<[u8; 4]> {
+        if self.next == MASK_BATCH {
+            getrandom::fill(self.masks.as_flattened_mut())?;
+            self.next = 0;
+        }
+        let mask = self.masks[self.next];
+        self.next += 1;
+        Ok(mask)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DataFrame {
+    remaining: u64,
+    mask: Option<[u8; 4]>,
+    offset: usize,
+}
+
+/// MQTT byte stream over a WebSocket connection.
+///
+/// Reading returns the payload of the binary frames, writing sends every
+/// write as one binary frame. Pings are answered with pongs while reading,
+/// a close frame from the peer is answered and ends the stream, and
+/// `shutdown` sends a close frame before shutting down the inner stream.
+///
+/// Written frames are buffered when the inner stream is busy, call `flush`
+/// to make sure they are sent.
+#[derive(Debug)]
+pub struct WsStream<S> {
+    inner: S,
+    role: Role,
+    mask_gen: Option<MaskGen>,
+
+    rbuf: Vec<u8>,
+    rpos: usize,
+    frame: Option<DataFrame>,
+    in_message: bool,
+    read_closed: bool,
+
+    wbuf: Vec<u8>,
+    wpos: usize,
+    close_sent: bool,
+}
+
+impl<S> WsStream<S> {
+    /// Wrap a stream on which the WebSocket handshake is already done.
+    pub fn from_raw(inner: S, role: Role) -> Self {
+        Self::with_leftover(inner, role, Vec::new())
+    }
+
+    /// `leftover` holds the bytes read after the handshake.
+    pub(crate) fn with_leftover(inner: S, role: Role, leftover: Vec<u8>) -> Self {
+        WsStream {
+            inner,
+            role,
+            mask_gen: (role == Role::Client).then(MaskGen::new),
+            rbuf: leftover,
+            rpos: 0,
+            frame: None,
+            in_message: false,
+            read_closed: false,
+            wbuf: Vec::new(),
+            wpos: 0,
+            close_sent: false,
+        }
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn queue_frame(&mut self, opcode: Opcode, payload: &[u8]) -> io::Result<()> {
+        let mask = self.mask_gen.as_mut().map(MaskGen::next_mask).transpose()?;
+        let header = FrameHeader::new(opcode, mask, payload.len() as u64);
+        let mut header_buf = [0u8; MAX_HEADER_LEN];
+        let header_len = header.encode(&mut header_buf);
+        self.wbuf.extend_from_slice(&header_buf[..header_len]);
+        let start = self.wbuf.len();
+        self.wbuf.extend_from_slice(payload);
+        if let Some(mask) = mask {
+            apply_mask(&mut self.wbuf[start..], mask, 0);
+        }
+        Ok(())
+    }
+
+    fn queue_close(&mut self, code: u16) -> io::Result<()> {
+        if !self.close_sent {
+            self.queue_frame(Opcode::Close, &code.to_be_bytes())?;
+            self.close_sent = true;
+        }
+        Ok(())
+    }
+
+    /// Handle a control frame whose payload is unmasked in `payload`.
+    fn handle_control(&mut self, opcode: Opcode, payload: &[u8]) -> io::Result<()> {
+        match opcode {
+            Opcode::Ping if !self.close_sent => self.queue_frame(Opcode::Pong, payload),
+            Opcode::Close => {
+                let code = match payload {
+                    [a, b, ..] => u16::from_be_bytes([*a, *b]),
+                    _ => CLOSE_NORMAL,
+                };
+                self.queue_close(code)?;
+                self.read_closed = true;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_header(&self, header: &FrameHeader) -> Result<(), Error> {
+        let expect_masked = self.role == Role::Server;
+        if header.mask.is_some() != expect_masked {
+            return Err(Error::InvalidMasking);
+        }
+        match header.opcode {
+            Opcode::Text => Err(Error::TextFrame),
+            Opcode::Binary if self.in_message => Err(Error::UnexpectedContinuation),
+            Opcode::Continuation if !self.in_message => Err(Error::UnexpectedContinuation),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> WsStream<S> {
+    /// Write the buffered frames to the inner stream.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.wpos < self.wbuf.len() {
+            let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.wbuf[self.wpos..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.wpos += n;
+        }
+        self.wbuf.clear();
+        self.wpos = 0;
+        Poll::Ready(Ok(()))
+    }
+
+    /// Read more bytes from the inner stream into the read buffer.
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        if self.rpos > 0 {
+            self.rbuf.drain(..self.rpos);
+            self.rpos = 0;
+        }
+        let len = self.rbuf.len();
+        self.rbuf.resize(len + READ_CHUNK, 0);
+        let mut read_buf = ReadBuf::new(&mut self.rbuf[len..]);
+        let result = Pin::new(&mut self.inner).poll_read(cx, &mut read_buf);
+        let n = read_buf.filled().len();
+        self.rbuf.truncate(len + n);
+        match result {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(n)),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    /// Copy the payload of the current data frame into `buf`.
+    fn poll_read_payload(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+        mut frame: DataFrame,
+    ) -> Poll<io::Result<()>> {
+        let max = (buf.remaining() as u64).min(frame.remaining) as usize;
+        let n = if self.rpos < self.rbuf.len() {
+            let n = max.min(self.rbuf.len() - self.rpos);
+            let data = &mut self.rbuf[self.rpos..self.rpos + n];
+            if let Some(mask) = frame.mask {
+                apply_mask(data, mask, frame.offset);
+            }
+            buf.put_slice(data);
+            self.rpos += n;
+            n
+        } else {
+            let mut limited = buf.take(max);
+            ready!(Pin::new(&mut self.inner).poll_read(cx, &mut limited))?;
+            let n = limited.filled().len();
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            if let Some(mask) = frame.mask {
+                apply_mask(limited.filled_mut(), mask, frame.offset);
+            }
+            // SAFETY: the bytes were filled by the inner reader.
+            unsafe { buf.assume_init(n) };
+            buf.advance(n);
+            n
+        };
+        frame.remaining -= n as u64;
+        frame.offset += n;
+        self.frame = (frame.remaining > 0).then_some(frame);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for WsStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            // Pongs and the close reply go out while reading, a busy or
+            // failed inner writer is retried and reported by the next write
+            let _ = this.poll_drain(cx);
+            if this.read_closed || buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+            if let Some(frame) = this.frame {
+                return this.poll_read_payload(cx, buf, frame);
+            }
+
+            if let Some((header, header_len)) = FrameHeader::decode(&this.rbuf[this.rpos..])? {
+                this.check_header(&header)?;
+                if header.opcode.is_control() {
+                    let start = this.rpos + header_len;
+                    let end = start + header.payload_len as usize;
+                    if this.rbuf.len() >= end {
+                        let mut payload = [0u8; super::frame::MAX_CONTROL_LEN];
+                        let payload = &mut payload[..end - start];
+                        payload.copy_from_slice(&this.rbuf[start..end]);
+                        if let Some(mask) = header.mask {
+                            apply_mask(payload, mask, 0);
+                        }
+                        this.rpos = end;
+                        this.handle_control(header.opcode, payload)?;
+                        continue;
+                    }
+                } else {
+                    this.rpos += header_len;
+                    this.in_message = !header.fin;
+                    if header.payload_len > 0 {
+                        this.frame = Some(DataFrame {
+                            remaining: header.payload_len,
+                            mask: header.mask,
+                            offset: 0,
+                        });
+                    }
+                    continue;
+                }
+            }
+
+            let buffered = this.rbuf.len() - this.rpos;
+            if ready!(this.poll_fill(cx))? == 0 {
+                if buffered == 0 && !this.in_message {
+                    // Closed without a close frame
+                    this.read_closed = true;
+                    return Poll::Ready(Ok(()));
+                }
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for WsStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        if this.close_sent {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        let n = buf.len().min(MAX_WRITE_FRAME);
+        this.queue_frame(Opcode::Binary, &buf[..n])?;
+        // The frame stays buffered if the inner writer is busy
+        if let Poll::Ready(Err(err)) = this.poll_drain(cx) {
+            return Poll::Ready(Err(err));
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.queue_close(CLOSE_NORMAL)?;
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
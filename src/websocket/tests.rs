@@ -0,0 +1,233 @@
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+
+use super::*;
+
+fn raw_frame(opcode: Opcode, fin: bool, mask: Option<[u8; 4]>, payload: &[u8]) -> Vec<u8> {
+    let header = FrameHeader {
+        fin,
+        opcode,
+        mask,
+        payload_len: payload.len() as u64,
+    };
+    let mut buf = [0u8; MAX_HEADER_LEN];
+    let len = header.encode(&mut buf);
+    let mut data = buf[..len].to_vec();
+    let start = data.len();
+    data.extend_from_slice(payload);
+    if let Some(mask) = mask {
+        apply_mask(&mut data[start..], mask, 0);
+    }
+    data
+}
+
+#[test]
+fn test_accept_key() {
+    // Example of RFC 6455 section 1.3
+    assert_eq!(
+        accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+        "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+    );
+}
+
+#[test]
+fn test_frame_header() {
+    for len in [0, 1, 125, 126, 65535, 65536, 1 << 40] {
+        for mask in [None, Some([1, 2, 3, 4])] {
+            let header = FrameHeader::new(Opcode::Binary, mask, len);
+            let mut buf = [0u8; MAX_HEADER_LEN];
+            let n = header.encode(&mut buf);
+            assert_eq!(FrameHeader::decode(&buf[..n]), Ok(Some((header, n))));
+            assert_eq!(FrameHeader::decode(&buf[..n - 1]), Ok(None));
+        }
+    }
+    assert_eq!(
+        FrameHeader::decode(&[0x82, 0x7e, 0x01, 0x00]).unwrap(),
+        Some((FrameHeader::new(Opcode::Binary, None, 256), 4))
+    );
+    assert_eq!(FrameHeader::decode(&[0xc2, 0]), Err(Error::ReservedBits));
+    assert_eq!(
+        FrameHeader::decode(&[0x83, 0]),
+        Err(Error::InvalidOpcode(3))
+    );
+    assert_eq!(
+        FrameHeader::decode(&[0x09, 0]),
+        Err(Error::InvalidControlFrame)
+    );
+    assert_eq!(
+        FrameHeader::decode(&[0x89, 126, 0, 126]),
+        Err(Error::InvalidControlFrame)
+    );
+}
+
+#[test]
+fn test_apply_mask() {
+    let mask = [0x37, 0xfa, 0x21, 0x3d];
+    let mut data = *b"Hello";
+    apply_mask(&mut data, mask, 0);
+    assert_eq!(data, [0x7f, 0x9f, 0x4d, 0x51, 0x58]);
+    // Unmask in two parts
+    apply_mask(&mut data[..2], mask, 0);
+    apply_mask(&mut data[2..], mask, 2);
+    assert_eq!(&data, b"Hello");
+}
+
+#[cfg(feature = "v3")]
+#[tokio::test]
+async fn test_mqtt_over_websocket() {
+    use crate::v3::{Connack, Connect, ConnectReturnCode, Packet, PollPacket, Publish};
+    use crate::{BufferPool, QosPid, TopicName};
+
+    // A small pipe splits the frames on the way
+    let (client, server) = duplex(64);
+    let (client, server) = tokio::join!(connect(client, "localhost", "/mqtt"), accept(server));
+    let (mut client, mut server) = (client.unwrap(), server.unwrap());
+    assert_eq!(client.role(), Role::Client);
+    assert_eq!(server.role(), Role::Server);
+
+    let packets = [
+        Packet::from(Connect::new("client".into(), 30)),
+        Publish::new(
+            QosPid::Level0,
+            TopicName::try_from("a/b").unwrap(),
+            bytes::Bytes::from(vec![5u8; 1000]),
+        )
+        .into(),
+        Packet::Pingreq,
+    ];
+    let sent = packets.clone();
+    let writer = tokio::spawn(async move {
+        for packet in &sent {
+            packet.encode_async(&mut client).await.unwrap();
+        }
+        client.flush().await.unwrap();
+        let connack = Packet::decode_async(&mut client).await.unwrap();
+        client.shutdown().await.unwrap();
+        connack
+    });
+
+    let mut state = Default::default();
+    let mut buffer = BufferPool::default();
+    for packet in &packets {
+        let (_, _, decoded) = PollPacket::new(&mut state, &mut server, &mut buffer)
+            .await
+            .unwrap();
+        assert_eq!(&decoded, packet);
+    }
+    let connack = Packet::from(Connack::new(false, ConnectReturnCode::Accepted));
+    connack.encode_async(&mut server).await.unwrap();
+    server.flush().await.unwrap();
+
+    assert_eq!(writer.await.unwrap(), connack);
+    // Close frame of the client
+    let mut buf = [0u8; 8];
+    assert_eq!(server.read(&mut buf).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_frames_split_and_combined() {
+    let (mut client, server) = duplex(1024);
+    let mut server = WsStream::from_raw(server, Role::Server);
+    let mask = Some([9, 8, 7, 6]);
+
+    let mut data = raw_frame(Opcode::Binary, true, mask, &[0x40, 2, 0, 1, 0x40, 2]);
+    data.extend(raw_frame(Opcode::Binary, false, mask, &[0, 2]));
+    // Control frames may come between the fragments
+    data.extend(raw_frame(Opcode::Ping, true, mask, b"ping"));
+    data.extend(raw_frame(Opcode::Continuation, false, mask, &[]));
+    data.extend(raw_frame(Opcode::Continuation, true, mask, &[0xc0, 0]));
+    client.write_all(&data).await.unwrap();
+
+    let mut buf = [0u8; 10];
+    server.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, [0x40, 2, 0, 1, 0x40, 2, 0, 2, 0xc0, 0]);
+
+    // Pong replied unmasked
+    let pong = raw_frame(Opcode::Pong, true, None, b"ping");
+    let mut reply = vec![0u8; pong.len()];
+    client.read_exact(&mut reply).await.unwrap();
+    assert_eq!(reply, pong);
+
+    // Close is echoed and ends the stream
+    client
+        .write_all(&raw_frame(
+            Opcode::Close,
+            true,
+            mask,
+            &1001u16.to_be_bytes(),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(server.read(&mut buf).await.unwrap(), 0);
+    let close = raw_frame(Opcode::Close, true, None, &1001u16.to_be_bytes());
+    let mut reply = vec![0u8; close.len()];
+    client.read_exact(&mut reply).await.unwrap();
+    assert_eq!(reply, close);
+    assert!(server.write_all(b"data").await.is_err());
+}
+
+#[tokio::test]
+async fn test_invalid_frames() {
+    let cases = [
+        raw_frame(Opcode::Binary, true, None, &[0xc0, 0]),
+        raw_frame(Opcode::Text, true, Some([1, 1, 1, 1]), b"text"),
+        raw_frame(Opcode::Continuation, true, Some([1, 1, 1, 1]), &[0]),
+    ];
+    for data in cases {
+        let (mut client, server) = duplex(1024);
+        let mut server = WsStream::from_raw(server, Role::Server);
+        client.write_all(&data).await.unwrap();
+        let err = server.read(&mut [0u8; 8]).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    // A server must not mask
+    let (client, mut server) = duplex(1024);
+    let mut client = WsStream::from_raw(client, Role::Client);
+    server
+        .write_all(&raw_frame(Opcode::Binary, true, Some([1, 2, 3, 4]), &[0]))
+        .await
+        .unwrap();
+    let err = client.read(&mut [0u8; 8]).await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[tokio::test]
+async fn test_handshake_rejected() {
+    let (mut client, server) = duplex(4096);
+    let request = "GET /mqtt HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+                   Connection: keep-alive, Upgrade\r\nSec-WebSocket-Version: 13\r\n\
+                   Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                   Sec-WebSocket-Protocol: chat\r\n\r\n";
+    client.write_all(request.as_bytes()).await.unwrap();
+    assert_eq!(
+        accept(server).await.unwrap_err(),
+        Error::SubprotocolNotNegotiated
+    );
+    let mut response = String::new();
+    client.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 400 "));
+
+    // The server does not agree on `mqtt`
+    let (client, mut server) = duplex(4096);
+    let server_task = tokio::spawn(async move {
+        let mut buf = [0u8; 1024];
+        let n = server.read(&mut buf).await.unwrap();
+        let request = core::str::from_utf8(&buf[..n]).unwrap();
+        let key = request
+            .lines()
+            .find_map(|line| line.strip_prefix("Sec-WebSocket-Key: "))
+            .unwrap();
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+             Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(key)
+        );
+        server.write_all(response.as_bytes()).await.unwrap();
+        server
+    });
+    assert_eq!(
+        connect(client, "localhost", "/").await.unwrap_err(),
+        Error::SubprotocolNotNegotiated
+    );
+    drop(server_task.await.unwrap());
+}